@@ -0,0 +1,20 @@
+BEGIN;
+    ALTER TABLE newsletter_issues ADD COLUMN slug TEXT NULL;
+    ALTER TABLE newsletter_issues
+        ADD COLUMN hidden_from_archive BOOLEAN NOT NULL DEFAULT false;
+    UPDATE newsletter_issues
+        SET slug = concat_ws(
+            '-',
+            NULLIF(
+                trim(BOTH '-' FROM lower(
+                    regexp_replace(title, '[^a-zA-Z0-9]+', '-', 'g')
+                )),
+                ''
+            ),
+            left(replace(newsletter_issue_id::text, '-', ''), 8)
+        )
+        WHERE slug IS NULL;
+    ALTER TABLE newsletter_issues ALTER COLUMN slug SET NOT NULL;
+    ALTER TABLE newsletter_issues ADD CONSTRAINT newsletter_issues_slug_key
+        UNIQUE (slug);
+COMMIT;
@@ -0,0 +1,15 @@
+BEGIN;
+    ALTER TABLE newsletter_issues
+        ALTER COLUMN published_at TYPE timestamptz
+        USING published_at::timestamptz;
+    -- Issues published before this migration have no known author
+    ALTER TABLE newsletter_issues
+        ADD COLUMN created_by uuid NULL REFERENCES users (user_id);
+    ALTER TABLE newsletter_issues ADD COLUMN updated_at timestamptz NULL;
+    UPDATE newsletter_issues
+        SET updated_at = published_at
+        WHERE updated_at IS NULL;
+    ALTER TABLE newsletter_issues ALTER COLUMN updated_at SET NOT NULL;
+    CREATE INDEX newsletter_issues_published_at_idx
+        ON newsletter_issues (published_at DESC);
+COMMIT;
@@ -0,0 +1,24 @@
+BEGIN;
+    ALTER TABLE newsletter_issues
+        ADD COLUMN delivery_status TEXT NOT NULL DEFAULT 'active';
+    ALTER TABLE newsletter_issues
+        ADD COLUMN n_recipients INTEGER NOT NULL DEFAULT 0;
+    -- Best effort for issues still being delivered, already delivered
+    -- issues have no record of their recipients
+    UPDATE newsletter_issues
+        SET n_recipients = (
+            SELECT count(*)
+            FROM issue_delivery_queue
+            WHERE issue_delivery_queue.newsletter_issue_id =
+                newsletter_issues.newsletter_issue_id
+        );
+COMMIT;
+
+CREATE TABLE issue_delivery_log (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_email TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    attempted_at timestamptz NOT NULL,
+    PRIMARY KEY(newsletter_issue_id, subscriber_email)
+);
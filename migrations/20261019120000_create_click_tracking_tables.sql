@@ -0,0 +1,20 @@
+ALTER TABLE newsletter_issues
+    ADD COLUMN track_clicks BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE issue_links (
+    link_id uuid NOT NULL,
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    url TEXT NOT NULL,
+    PRIMARY KEY(link_id),
+    UNIQUE(newsletter_issue_id, url)
+);
+
+CREATE TABLE link_clicks (
+    link_id uuid NOT NULL
+        REFERENCES issue_links (link_id),
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id),
+    clicked_at timestamptz NOT NULL
+);
+CREATE INDEX link_clicks_link_id_idx ON link_clicks (link_id);
@@ -0,0 +1,16 @@
+ALTER TABLE newsletter_issues
+    ADD COLUMN track_opens BOOLEAN NOT NULL DEFAULT false;
+
+ALTER TABLE subscriptions
+    ADD COLUMN do_not_track BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE issue_opens (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id),
+    first_opened_at timestamptz NOT NULL,
+    last_opened_at timestamptz NOT NULL,
+    n_opens INTEGER NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, subscriber_id)
+);
@@ -0,0 +1,9 @@
+-- Links are tagged only when all three parameters are set
+ALTER TABLE newsletter_issues
+    ADD COLUMN utm_source TEXT NULL,
+    ADD COLUMN utm_medium TEXT NULL,
+    ADD COLUMN utm_campaign TEXT NULL,
+    ADD CONSTRAINT newsletter_issues_utm_parameters_check CHECK (
+        (utm_source IS NULL) = (utm_medium IS NULL) AND
+        (utm_source IS NULL) = (utm_campaign IS NULL)
+    );
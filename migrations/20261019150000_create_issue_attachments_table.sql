@@ -0,0 +1,18 @@
+-- Attachments are staged by their uploader until the next issue they
+-- publish claims them.
+CREATE TABLE issue_attachments (
+    attachment_id uuid NOT NULL,
+    newsletter_issue_id uuid NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    uploaded_by uuid NOT NULL
+        REFERENCES users (user_id),
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    content_id TEXT NULL,
+    content BYTEA NOT NULL,
+    size INTEGER NOT NULL,
+    uploaded_at timestamptz NOT NULL,
+    PRIMARY KEY (attachment_id)
+);
+CREATE INDEX issue_attachments_newsletter_issue_id_idx
+    ON issue_attachments (newsletter_issue_id);
@@ -0,0 +1,41 @@
+-- Drafts are issues that have not been published yet
+ALTER TABLE newsletter_issues
+    ADD COLUMN state TEXT NOT NULL DEFAULT 'published';
+ALTER TABLE newsletter_issues ALTER COLUMN state DROP DEFAULT;
+ALTER TABLE newsletter_issues ALTER COLUMN published_at DROP NOT NULL;
+ALTER TABLE newsletter_issues
+    ADD CONSTRAINT newsletter_issues_published_at_check
+    CHECK ((state = 'published') = (published_at IS NOT NULL));
+
+CREATE TABLE issue_revisions (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    revision_number INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    created_by uuid NULL
+        REFERENCES users (user_id),
+    created_at timestamptz NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, revision_number)
+);
+
+-- The content of existing issues becomes their first revision
+INSERT INTO issue_revisions (
+    newsletter_issue_id,
+    revision_number,
+    title,
+    text_content,
+    html_content,
+    created_by,
+    created_at
+)
+SELECT
+    newsletter_issue_id,
+    1,
+    title,
+    text_content,
+    html_content,
+    created_by,
+    published_at
+FROM newsletter_issues;
@@ -0,0 +1,27 @@
+CREATE TABLE issue_transitions (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    from_state TEXT NULL,
+    to_state TEXT NOT NULL,
+    user_id uuid NULL
+        REFERENCES users (user_id),
+    transitioned_at timestamptz NOT NULL
+);
+CREATE INDEX issue_transitions_newsletter_issue_id_idx
+    ON issue_transitions (newsletter_issue_id);
+
+-- The creation of existing issues is their first transition
+INSERT INTO issue_transitions (
+    newsletter_issue_id,
+    from_state,
+    to_state,
+    user_id,
+    transitioned_at
+)
+SELECT
+    newsletter_issue_id,
+    NULL,
+    state,
+    created_by,
+    coalesce(published_at, updated_at)
+FROM newsletter_issues;
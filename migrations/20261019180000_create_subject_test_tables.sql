@@ -0,0 +1,31 @@
+-- Variant 0 is the title of the issue, alternatives are numbered from 1
+CREATE TABLE subject_tests (
+    newsletter_issue_id uuid PRIMARY KEY
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    sample_percent SMALLINT NOT NULL
+        CHECK (sample_percent BETWEEN 1 AND 100),
+    winning_metric TEXT NOT NULL,
+    wait_minutes INTEGER NOT NULL CHECK (wait_minutes > 0),
+    decide_after timestamptz NULL,
+    winning_variant SMALLINT NULL,
+    decided_at timestamptz NULL
+);
+
+CREATE TABLE subject_variants (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES subject_tests (newsletter_issue_id),
+    variant SMALLINT NOT NULL CHECK (variant > 0),
+    subject TEXT NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, variant)
+);
+
+-- Recipients outside of the sample wait here for the winning subject
+CREATE TABLE subject_test_holdouts (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES subject_tests (newsletter_issue_id),
+    subscriber_email TEXT NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, subscriber_email)
+);
+
+ALTER TABLE issue_delivery_queue ADD COLUMN subject_variant SMALLINT NULL;
+ALTER TABLE issue_delivery_log ADD COLUMN subject_variant SMALLINT NULL;
@@ -0,0 +1,6 @@
+ALTER TABLE subscriptions ADD COLUMN time_zone TEXT NULL;
+ALTER TABLE newsletter_issues ADD COLUMN deliver_at_local_time TIME NULL;
+ALTER TABLE issue_delivery_queue
+    ADD COLUMN execute_after timestamptz NOT NULL DEFAULT now();
+CREATE INDEX issue_delivery_queue_execute_after_idx
+    ON issue_delivery_queue (execute_after);
@@ -0,0 +1,29 @@
+-- Admin users can be disabled, and are asked to pick their own password
+-- the first time they log in.
+ALTER TABLE users
+    ADD COLUMN created_at timestamptz NOT NULL DEFAULT now(),
+    ADD COLUMN disabled_at timestamptz NULL,
+    ADD COLUMN must_change_password BOOLEAN NOT NULL DEFAULT false;
+
+-- Deleting a user keeps the issues they worked on, without their authorship.
+ALTER TABLE idempotency
+    DROP CONSTRAINT idempotency_user_id_fkey,
+    ADD CONSTRAINT idempotency_user_id_fkey
+        FOREIGN KEY (user_id) REFERENCES users (user_id) ON DELETE CASCADE;
+ALTER TABLE newsletter_issues
+    DROP CONSTRAINT newsletter_issues_created_by_fkey,
+    ADD CONSTRAINT newsletter_issues_created_by_fkey
+        FOREIGN KEY (created_by) REFERENCES users (user_id) ON DELETE SET NULL;
+ALTER TABLE issue_revisions
+    DROP CONSTRAINT issue_revisions_created_by_fkey,
+    ADD CONSTRAINT issue_revisions_created_by_fkey
+        FOREIGN KEY (created_by) REFERENCES users (user_id) ON DELETE SET NULL;
+ALTER TABLE issue_transitions
+    DROP CONSTRAINT issue_transitions_user_id_fkey,
+    ADD CONSTRAINT issue_transitions_user_id_fkey
+        FOREIGN KEY (user_id) REFERENCES users (user_id) ON DELETE SET NULL;
+ALTER TABLE issue_attachments ALTER COLUMN uploaded_by DROP NOT NULL;
+ALTER TABLE issue_attachments
+    DROP CONSTRAINT issue_attachments_uploaded_by_fkey,
+    ADD CONSTRAINT issue_attachments_uploaded_by_fkey
+        FOREIGN KEY (uploaded_by) REFERENCES users (user_id) ON DELETE SET NULL;
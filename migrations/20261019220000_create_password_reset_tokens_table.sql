@@ -0,0 +1,19 @@
+-- Password reset links are sent to the email address of the user.
+-- Sessions started before `sessions_revoked_at` are no longer valid.
+ALTER TABLE users
+    ADD COLUMN email TEXT NULL UNIQUE,
+    ADD COLUMN sessions_revoked_at timestamptz NULL;
+
+-- Only a hash of the token is stored, so a leaked table can't be used to
+-- reset passwords.
+CREATE TABLE password_reset_tokens (
+    token_hash TEXT NOT NULL,
+    user_id uuid NOT NULL
+        REFERENCES users (user_id) ON DELETE CASCADE,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL,
+    used_at timestamptz NULL,
+    PRIMARY KEY (token_hash)
+);
+CREATE INDEX password_reset_tokens_user_id_idx
+    ON password_reset_tokens (user_id);
@@ -0,0 +1,22 @@
+-- The TOTP secret is encrypted with the application key. It is stored as
+-- soon as enrolment starts, but only required once `totp_enabled_at` is set.
+ALTER TABLE users
+    ADD COLUMN totp_secret BYTEA NULL,
+    ADD COLUMN totp_enabled_at timestamptz NULL,
+    ADD COLUMN totp_last_used_step BIGINT NULL;
+
+-- Recovery codes are hashed like reset tokens, and can only be used once.
+CREATE TABLE recovery_codes (
+    user_id uuid NOT NULL
+        REFERENCES users (user_id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    used_at timestamptz NULL,
+    PRIMARY KEY (user_id, code_hash)
+);
+
+-- Settings that owners can change at runtime. There is a single row.
+CREATE TABLE security_settings (
+    id BOOLEAN PRIMARY KEY DEFAULT true CHECK (id),
+    require_two_factor BOOLEAN NOT NULL
+);
+INSERT INTO security_settings (require_two_factor) VALUES (false);
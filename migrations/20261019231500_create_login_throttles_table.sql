@@ -0,0 +1,11 @@
+-- Failed logins, counted per username and per client IP address.
+-- Usernames are tracked whether or not they exist, so that lockouts don't
+-- reveal which accounts exist.
+CREATE TABLE login_throttles (
+    kind TEXT NOT NULL,
+    key TEXT NOT NULL,
+    failed_attempts INT NOT NULL,
+    last_failed_at timestamptz NOT NULL,
+    locked_until timestamptz NULL,
+    PRIMARY KEY (kind, key)
+);
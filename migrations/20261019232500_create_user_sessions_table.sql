@@ -0,0 +1,14 @@
+-- One row per logged in session, so that users can see where they are
+-- logged in and end those sessions remotely. Sessions without a row here
+-- are rejected.
+CREATE TABLE user_sessions (
+    session_id uuid NOT NULL,
+    user_id uuid NOT NULL
+        REFERENCES users (user_id) ON DELETE CASCADE,
+    user_agent TEXT NOT NULL,
+    ip_address TEXT NOT NULL,
+    created_at timestamptz NOT NULL,
+    last_seen_at timestamptz NOT NULL,
+    PRIMARY KEY (session_id)
+);
+CREATE INDEX user_sessions_user_id_idx ON user_sessions (user_id);
@@ -0,0 +1,8 @@
+-- Session state, when sessions are not stored in Redis
+CREATE TABLE http_sessions (
+    session_key TEXT NOT NULL,
+    state TEXT NOT NULL,
+    expires_at timestamptz NOT NULL,
+    PRIMARY KEY (session_key)
+);
+CREATE INDEX http_sessions_expires_at_idx ON http_sessions (expires_at);
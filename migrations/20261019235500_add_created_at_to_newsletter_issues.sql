@@ -0,0 +1,22 @@
+-- Lists of issues are paginated by creation time, which can't change,
+-- unlike `updated_at`. Existing issues were created with their first
+-- revision.
+ALTER TABLE newsletter_issues ADD COLUMN created_at timestamptz NULL;
+UPDATE newsletter_issues i
+SET created_at = COALESCE(
+    (
+        SELECT min(r.created_at)
+        FROM issue_revisions r
+        WHERE r.newsletter_issue_id = i.newsletter_issue_id
+    ),
+    i.published_at,
+    i.updated_at
+);
+ALTER TABLE newsletter_issues
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN created_at SET DEFAULT now();
+CREATE INDEX newsletter_issues_created_at_idx
+    ON newsletter_issues (created_at, newsletter_issue_id);
+
+CREATE INDEX subscriptions_subscribed_at_idx
+    ON subscriptions (subscribed_at, id);
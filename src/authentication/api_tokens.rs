@@ -0,0 +1,177 @@
+//! Personal API tokens let scripts act on behalf of a user, without going
+//! through the login form. Only a hash of each token is stored: the token
+//! itself is shown once, when it is created.
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{ApiScope, ApiToken};
+
+pub struct ApiTokenSummary {
+    pub api_token_id: Uuid,
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Added to the request extensions when it was authenticated with an API
+/// token rather than a session.
+#[derive(Clone, Debug)]
+pub struct ApiTokenAuthentication {
+    pub api_token_id: Uuid,
+    pub user_id: Uuid,
+    pub scopes: Vec<ApiScope>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiTokenAuthentication {
+    pub fn has_scope(&self, scope: ApiScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn create_api_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    name: &str,
+    scopes: &[ApiScope],
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<ApiToken, anyhow::Error> {
+    let token = ApiToken::generate();
+    let scopes: Vec<String> =
+        scopes.iter().map(|s| s.as_str().to_string()).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO api_tokens (
+            api_token_id,
+            user_id,
+            name,
+            token_hash,
+            scopes,
+            created_at,
+            expires_at
+        )
+        VALUES ($1, $2, $3, $4, $5, now(), $6)
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        name,
+        token.hash(),
+        &scopes,
+        expires_at
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store an API token")?;
+    Ok(token)
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn list_api_tokens(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<ApiTokenSummary>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            api_token_id,
+            name,
+            scopes,
+            created_at,
+            expires_at,
+            last_used_at
+        FROM api_tokens
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the API tokens of a user")?;
+    rows.into_iter()
+        .map(|row| {
+            Ok(ApiTokenSummary {
+                api_token_id: row.api_token_id,
+                name: row.name,
+                scopes: parse_scopes(row.scopes)?,
+                created_at: row.created_at,
+                expires_at: row.expires_at,
+                last_used_at: row.last_used_at,
+            })
+        })
+        .collect()
+}
+
+/// Returns whether a token of the user was found.
+#[tracing::instrument(skip(pool))]
+pub async fn revoke_api_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    api_token_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM api_tokens WHERE api_token_id = $1 AND user_id = $2",
+        api_token_id,
+        user_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to revoke an API token")?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// `None` unless the token exists and has not expired. Its last use is
+/// recorded at most once a minute.
+#[tracing::instrument(skip(pool, token))]
+pub async fn authenticate_api_token(
+    pool: &PgPool,
+    token: &ApiToken,
+) -> Result<Option<ApiTokenAuthentication>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT api_token_id, user_id, scopes, created_at, last_used_at
+        FROM api_tokens
+        WHERE
+            token_hash = $1 AND
+            (expires_at IS NULL OR expires_at > now())
+        "#,
+        token.hash()
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve an API token")?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    if row
+        .last_used_at
+        .is_none_or(|at| Utc::now() - at > chrono::Duration::minutes(1))
+    {
+        sqlx::query!(
+            "UPDATE api_tokens SET last_used_at = now() \
+            WHERE api_token_id = $1",
+            row.api_token_id
+        )
+        .execute(pool)
+        .await
+        .context("Failed to record the use of an API token")?;
+    }
+    Ok(Some(ApiTokenAuthentication {
+        api_token_id: row.api_token_id,
+        user_id: row.user_id,
+        scopes: parse_scopes(row.scopes)?,
+        created_at: row.created_at,
+    }))
+}
+
+fn parse_scopes(scopes: Vec<String>) -> Result<Vec<ApiScope>, anyhow::Error> {
+    scopes
+        .into_iter()
+        .map(|s| ApiScope::try_from(s).map_err(anyhow::Error::msg))
+        .collect()
+}
@@ -0,0 +1,182 @@
+//! Synchronizer tokens against cross-site request forgery: every session
+//! gets a random token, which forms must send back for a request that
+//! changes anything to be accepted.
+use std::future::Future;
+use std::pin::Pin;
+
+use actix_web::{
+    body::MessageBody,
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::PayloadError,
+    http::{
+        header::{HeaderName, HeaderValue},
+        uri::PathAndQuery,
+        Method, Uri,
+    },
+    middleware::Next,
+    web, FromRequest, HttpMessage, HttpRequest,
+};
+use futures_util::Stream;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+
+use super::api_tokens::ApiTokenAuthentication;
+use crate::{
+    session_state::TypedSession,
+    utils::{constant_time_eq, e403, e500},
+};
+
+const CSRF_TOKEN_SIZE: usize = 32;
+/// The name of the form field, or of the query parameter for forms that
+/// upload files, carrying the token.
+const CSRF_TOKEN_FIELD: &str = "csrf_token";
+/// Where clients that can set headers send the token. Tokens found in the
+/// query string are moved there.
+const CSRF_TOKEN_HEADER: &str = "x-csrf-token";
+
+/// The token of the current session, created on first use.
+pub struct CsrfToken(String);
+
+impl CsrfToken {
+    fn generate() -> Self {
+        let mut rng = thread_rng();
+        let token = std::iter::repeat_with(|| rng.sample(Alphanumeric))
+            .map(char::from)
+            .take(CSRF_TOKEN_SIZE)
+            .collect();
+        Self(token)
+    }
+
+    /// The hidden input to add to every form.
+    pub fn form_field(&self) -> String {
+        format!(
+            r#"<input type="hidden" name="{}" value="{}">"#,
+            CSRF_TOKEN_FIELD, self.0
+        )
+    }
+
+    /// To append to the action of forms that are not URL encoded, whose
+    /// fields the middleware can't read.
+    pub fn query(&self) -> String {
+        format!("{}={}", CSRF_TOKEN_FIELD, self.0)
+    }
+}
+
+impl AsRef<str> for CsrfToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromRequest for CsrfToken {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let session = TypedSession::from_request(req, payload);
+        Box::pin(async move {
+            let session = session.await?;
+            if let Some(token) = session.get_csrf_token().map_err(e500)? {
+                return Ok(Self(token));
+            }
+            let token = Self::generate();
+            session.insert_csrf_token(&token.0).map_err(e500)?;
+            Ok(token)
+        })
+    }
+}
+
+/// Rejects requests that change state unless they carry the token of the
+/// session.
+pub async fn verify_csrf_token(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    // Browsers don't add API tokens to requests by themselves
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS)
+        || req.extensions().contains::<ApiTokenAuthentication>()
+    {
+        return next.call(req).await;
+    }
+    let (http_request, payload) = req.parts_mut();
+    let session = TypedSession::from_request(http_request, payload)
+        .await
+        .map_err(e500)?;
+    let expected = session.get_csrf_token().map_err(e500)?;
+
+    let mut submitted = req
+        .headers()
+        .get(CSRF_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    if submitted.is_none()
+        && req.content_type() == "application/x-www-form-urlencoded"
+    {
+        // Read the body, then put it back for the handler
+        let body = req.extract::<web::Bytes>().await?;
+        submitted = find_csrf_token(&body);
+        req.set_payload(bytes_to_payload(body));
+    }
+
+    match (expected, submitted) {
+        (Some(expected), Some(submitted))
+            if constant_time_eq(&expected, &submitted) =>
+        {
+            next.call(req).await
+        }
+        _ => Err(e403(
+            "The form has expired or was not sent from this site: \
+            reload the page and try again.",
+        )),
+    }
+}
+
+/// Takes the token out of the query string, before the URL gets logged,
+/// and puts it in the header `verify_csrf_token` reads.
+pub async fn move_csrf_token_to_header(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let Ok(mut parameters) =
+        serde_urlencoded::from_str::<Vec<(String, String)>>(req.query_string())
+    else {
+        return next.call(req).await;
+    };
+    let Some(position) = parameters
+        .iter()
+        .position(|(name, _)| name == CSRF_TOKEN_FIELD)
+    else {
+        return next.call(req).await;
+    };
+    let (_, token) = parameters.remove(position);
+    let query = serde_urlencoded::to_string(&parameters).map_err(e500)?;
+    let path_and_query = if query.is_empty() {
+        req.path().to_string()
+    } else {
+        format!("{}?{}", req.path(), query)
+    };
+    let mut parts = req.uri().clone().into_parts();
+    parts.path_and_query =
+        Some(PathAndQuery::try_from(path_and_query).map_err(e500)?);
+    let uri = Uri::from_parts(parts).map_err(e500)?;
+    req.match_info_mut().get_mut().update(&uri);
+    req.head_mut().uri = uri;
+    if let Ok(value) = HeaderValue::from_str(&token) {
+        req.headers_mut()
+            .insert(HeaderName::from_static(CSRF_TOKEN_HEADER), value);
+    }
+    next.call(req).await
+}
+
+fn find_csrf_token(form: &[u8]) -> Option<String> {
+    serde_urlencoded::from_bytes::<Vec<(String, String)>>(form)
+        .ok()?
+        .into_iter()
+        .find_map(|(name, value)| (name == CSRF_TOKEN_FIELD).then_some(value))
+}
+
+fn bytes_to_payload(body: web::Bytes) -> Payload {
+    let stream: Pin<Box<dyn Stream<Item = Result<web::Bytes, PayloadError>>>> =
+        Box::pin(futures_util::stream::once(async { Ok(body) }));
+    Payload::from(stream)
+}
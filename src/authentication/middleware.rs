@@ -1,16 +1,27 @@
 use std::ops::Deref;
 
+use super::api_tokens::authenticate_api_token;
+use super::sessions::touch_session;
 use crate::{
+    domain::{ApiScope, ApiToken, Role},
     session_state::TypedSession,
-    utils::{e500, see_other},
+    utils::{e403, e500, see_other},
 };
 use actix_web::{
-    body::MessageBody,
+    body::{EitherBody, MessageBody},
     dev::{ServiceRequest, ServiceResponse},
     error::InternalError,
+    http::{
+        header::{AUTHORIZATION, WWW_AUTHENTICATE},
+        Method,
+    },
     middleware::Next,
-    FromRequest, HttpMessage,
+    web, FromRequest, HttpMessage, HttpResponse,
 };
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
 use uuid::Uuid;
 
 #[derive(Copy, Clone, Debug)]
@@ -33,22 +44,327 @@ impl Deref for UserId {
 pub async fn reject_anonymous_users(
     mut req: ServiceRequest,
     next: Next<impl MessageBody>,
-) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    if req.headers().contains_key(AUTHORIZATION) {
+        authenticate_api_request(&req).await?;
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    }
+
     // Extract the session from the request
     let (http_request, payload) = req.parts_mut();
     let session = TypedSession::from_request(http_request, payload)
         .await
         .map_err(e500)?;
 
-    match session.get_user_id().map_err(e500)? {
-        Some(user_id) => {
-            req.extensions_mut().insert(UserId(user_id));
-            next.call(req).await
+    let Some(user_id) = session.get_user_id().map_err(e500)? else {
+        let response = see_other("/login");
+        let e = anyhow::anyhow!("The user has not logged in");
+        return Err(InternalError::from_response(e, response).into());
+    };
+
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .ok_or_else(|| e500("The database pool is not configured"))?;
+    // Revoked sessions, and sessions started before they were indexed,
+    // are ended
+    let session_active = match session.get_session_id().map_err(e500)? {
+        Some(session_id) => touch_session(pool, user_id, session_id)
+            .await
+            .map_err(e500)?,
+        None => false,
+    };
+    if !session_active {
+        session.logout();
+        let response = see_other("/login");
+        let e = anyhow::anyhow!("The session has been revoked");
+        return Err(InternalError::from_response(e, response).into());
+    }
+    let logged_in_at = session.get_logged_in_at().map_err(e500)?;
+    let role = match get_account_status(user_id, logged_in_at, pool)
+        .await
+        .map_err(e500)?
+    {
+        AccountStatus::Active(role) => role,
+        AccountStatus::MustChangePassword(role)
+            if PASSWORD_CHANGE_PATHS.contains(&routed_path(&req)) =>
+        {
+            role
+        }
+        AccountStatus::MustChangePassword(_) => {
+            FlashMessage::info(
+                "You must choose a new password before continuing.",
+            )
+            .send();
+            return Ok(req
+                .into_response(see_other("/admin/password"))
+                .map_into_right_body());
+        }
+        AccountStatus::MustEnrolTwoFactor(role)
+            if is_two_factor_setup_path(routed_path(&req)) =>
+        {
+            role
         }
-        None => {
+        AccountStatus::MustEnrolTwoFactor(_) => {
+            FlashMessage::info(
+                "You must set up two-factor authentication before continuing.",
+            )
+            .send();
+            return Ok(req
+                .into_response(see_other("/admin/two-factor"))
+                .map_into_right_body());
+        }
+        AccountStatus::Disabled => {
+            // Disabling or deleting an account, or resetting its password,
+            // ends its sessions
+            session.logout();
             let response = see_other("/login");
-            let e = anyhow::anyhow!("The user has not logged in");
-            Err(InternalError::from_response(e, response).into())
+            let e = anyhow::anyhow!("The user account has been disabled");
+            return Err(InternalError::from_response(e, response).into());
         }
+    };
+
+    req.extensions_mut().insert(UserId(user_id));
+    req.extensions_mut().insert(role);
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+/// For routes that only accept API tokens, which don't need to defend
+/// against forged requests like the pages used from a browser.
+pub async fn require_api_token(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if !req.headers().contains_key(AUTHORIZATION) {
+        return Err(unauthorized("An API token is required."));
+    }
+    authenticate_api_request(&req).await?;
+    next.call(req).await
+}
+
+/// Requests carrying `Authorization: Bearer <API token>` act on behalf of
+/// the owner of the token, within its scopes. They get the same extensions
+/// as requests of logged in users, plus an `ApiTokenAuthentication`.
+async fn authenticate_api_request(
+    req: &ServiceRequest,
+) -> Result<(), actix_web::Error> {
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .and_then(|token| ApiToken::parse(token.trim().to_string()).ok())
+        .ok_or_else(|| {
+            unauthorized("The API token is missing or malformed.")
+        })?;
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .ok_or_else(|| e500("The database pool is not configured"))?;
+    let authentication = authenticate_api_token(pool, &token)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| unauthorized("The API token is not valid."))?;
+    // Tokens created before the sessions of the user were revoked, for
+    // example by a password reset, are revoked too
+    let role = match get_account_status(
+        authentication.user_id,
+        Some(authentication.created_at),
+        pool,
+    )
+    .await
+    .map_err(e500)?
+    {
+        AccountStatus::Active(role) => role,
+        AccountStatus::MustChangePassword(_)
+        | AccountStatus::MustEnrolTwoFactor(_) => {
+            return Err(e403(
+                "Log in from a browser to finish setting up the account \
+                before using API tokens.",
+            ));
+        }
+        AccountStatus::Disabled => {
+            return Err(unauthorized("The API token is not valid."));
+        }
+    };
+    let scope =
+        required_scope(req.method(), routed_path(req)).ok_or_else(|| {
+            e403("API tokens can't be used to manage accounts or credentials.")
+        })?;
+    if !authentication.has_scope(scope) {
+        return Err(e403(format!(
+            "This request needs an API token with the {} scope.",
+            scope.as_str()
+        )));
+    }
+
+    req.extensions_mut().insert(UserId(authentication.user_id));
+    req.extensions_mut().insert(role);
+    req.extensions_mut().insert(authentication);
+    Ok(())
+}
+
+fn unauthorized(message: &'static str) -> actix_web::Error {
+    let response = HttpResponse::Unauthorized()
+        .insert_header((WWW_AUTHENTICATE, r#"Bearer realm="admin""#))
+        .body(message);
+    InternalError::from_response(message, response).into()
+}
+
+/// The path routes are matched against. Characters that didn't need to be
+/// percent-encoded are decoded in it, so `req.path()` may name the same
+/// route with a different string.
+fn routed_path(req: &ServiceRequest) -> &str {
+    req.match_info().as_str()
+}
+
+/// The scope an API token needs for a request, if API tokens can be used
+/// for it at all.
+fn required_scope(method: &Method, path: &str) -> Option<ApiScope> {
+    if is_credential_path(path) {
+        None
+    } else if matches!(*method, Method::GET | Method::HEAD) {
+        Some(ApiScope::Read)
+    } else if is_publish_path(path) {
+        Some(ApiScope::Publish)
+    } else {
+        Some(ApiScope::Write)
+    }
+}
+
+/// Pages managing accounts and credentials, which are reserved to users
+/// logged in from a browser.
+fn is_credential_path(path: &str) -> bool {
+    ["/admin/password", "/admin/logout"].contains(&path)
+        || [
+            "/admin/two-factor",
+            "/admin/sessions",
+            "/admin/api-tokens",
+            "/admin/users",
+        ]
+        .iter()
+        .any(|prefix| {
+            path == *prefix || path.starts_with(&format!("{}/", prefix))
+        })
+}
+
+/// Requests that send an issue to subscribers.
+fn is_publish_path(path: &str) -> bool {
+    path == "/admin/newsletters"
+        || ["/admin/drafts/", "/api/v1/issues/"].iter().any(|prefix| {
+            path.starts_with(prefix) && path.ends_with("/publish")
+        })
+}
+
+/// Only let editors and owners through.
+/// Must be wrapped by `reject_anonymous_users`.
+pub async fn require_editor(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    require_role(Role::Editor, req, next).await
+}
+
+/// Only let owners through.
+/// Must be wrapped by `reject_anonymous_users`.
+pub async fn require_owner(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    require_role(Role::Owner, req, next).await
+}
+
+async fn require_role(
+    required: Role,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let role = req
+        .extensions()
+        .get::<Role>()
+        .copied()
+        .ok_or_else(|| e500("The role of the user has not been loaded"))?;
+    if role < required {
+        return Err(e403(format!(
+            "This action requires the {} role.",
+            required.as_str()
+        )));
+    }
+    next.call(req).await
+}
+
+/// The pages a user who must change their password can still reach.
+const PASSWORD_CHANGE_PATHS: [&str; 2] = ["/admin/password", "/admin/logout"];
+
+/// The pages a user who must set up two-factor authentication can still
+/// reach.
+fn is_two_factor_setup_path(path: &str) -> bool {
+    path == "/admin/logout"
+        || path == "/admin/two-factor"
+        || path.starts_with("/admin/two-factor/")
+}
+
+enum AccountStatus {
+    Active(Role),
+    MustChangePassword(Role),
+    /// Two-factor authentication is required, but the user has not set it up.
+    MustEnrolTwoFactor(Role),
+    Disabled,
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_account_status(
+    user_id: Uuid,
+    logged_in_at: Option<DateTime<Utc>>,
+    pool: &PgPool,
+) -> Result<AccountStatus, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            disabled_at,
+            must_change_password,
+            role,
+            sessions_revoked_at,
+            totp_enabled_at,
+            (
+                SELECT require_two_factor FROM security_settings
+            ) AS "require_two_factor!"
+        FROM users
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the status of a user account")?;
+    Ok(match row {
+        Some(row)
+            if row.disabled_at.is_none()
+                && is_after(logged_in_at, row.sessions_revoked_at) =>
+        {
+            let role = row.role.try_into().map_err(anyhow::Error::msg)?;
+            if row.must_change_password {
+                AccountStatus::MustChangePassword(role)
+            } else if row.require_two_factor && row.totp_enabled_at.is_none() {
+                AccountStatus::MustEnrolTwoFactor(role)
+            } else {
+                AccountStatus::Active(role)
+            }
+        }
+        _ => AccountStatus::Disabled,
+    })
+}
+
+fn is_after(
+    logged_in_at: Option<DateTime<Utc>>,
+    sessions_revoked_at: Option<DateTime<Utc>>,
+) -> bool {
+    match (logged_in_at, sessions_revoked_at) {
+        (_, None) => true,
+        (Some(logged_in_at), Some(revoked_at)) => logged_in_at > revoked_at,
+        (None, Some(_)) => false,
     }
 }
@@ -1,8 +1,37 @@
+mod api_tokens;
+mod csrf;
 mod middleware;
 mod password;
+mod sessions;
+mod throttle;
+mod totp;
+mod two_factor;
 
-pub use middleware::reject_anonymous_users;
+pub use api_tokens::{
+    authenticate_api_token, create_api_token, list_api_tokens,
+    revoke_api_token, ApiTokenAuthentication, ApiTokenSummary,
+};
+pub use csrf::{move_csrf_token_to_header, verify_csrf_token, CsrfToken};
 pub use middleware::UserId;
+pub use middleware::{
+    reject_anonymous_users, require_api_token, require_editor, require_owner,
+};
 pub use password::{
-    change_password, validate_credentials, AuthError, Credentials,
+    change_password, create_user, reset_password, validate_credentials,
+    AuthError, Credentials,
+};
+pub use sessions::{
+    create_session, list_sessions, revoke_other_sessions, revoke_session,
+    touch_session, ActiveSession, SESSION_TTL,
+};
+pub use throttle::{
+    clear_failed_logins, get_lockouts, is_login_allowed, record_failed_login,
+    Lockout, ThrottleKey,
+};
+pub use totp::{TotpEncryptionKey, TotpSecret};
+pub use two_factor::{
+    disable_two_factor, enable_two_factor, get_totp_secret,
+    get_two_factor_status, is_two_factor_enabled, is_two_factor_required,
+    regenerate_recovery_codes, set_two_factor_required, start_enrolment,
+    verify_second_factor, TwoFactorStatus,
 };
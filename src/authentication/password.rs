@@ -5,10 +5,11 @@ use argon2::{
     Version,
 };
 use secrecy::{ExposeSecret, Secret};
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres, Transaction};
 use tokio::task::JoinHandle;
 
-use crate::domain::Password;
+use crate::configuration::PasswordHashingSettings;
+use crate::domain::{Password, Role, SubscriberEmail, Username};
 
 #[derive(thiserror::Error, Debug)]
 pub enum AuthError {
@@ -23,18 +24,25 @@ pub struct Credentials {
     pub password: Secret<String>,
 }
 
-#[tracing::instrument(name = "Validate credentials", skip(credentials, pool))]
+/// Hashes made with weaker parameters than the configured ones are
+/// replaced on success, so that raising the cost needs no password resets.
+#[tracing::instrument(
+    name = "Validate credentials",
+    skip(credentials, hashing, pool)
+)]
 pub async fn validate_credentials(
     credentials: Credentials,
+    hashing: &PasswordHashingSettings,
     pool: &PgPool,
 ) -> Result<uuid::Uuid, AuthError> {
     let mut user_id = None;
-    let mut expected_password_hash = Secret::new(
-        "$argon2id$v=19$m=15000,t=2,p=1$\
+    // Unknown usernames take as long to check as known ones
+    let mut expected_password_hash = Secret::new(format!(
+        "$argon2id$v=19$m={},t={},p={}$\
         gZiV/M1gPc22ElAH/Jh1Hw$\
-        CWOrkoo7oJBQ/iyh7uJ0LO2aLEfrHwTWllSAxT0zRno"
-            .to_string(),
-    );
+        CWOrkoo7oJBQ/iyh7uJ0LO2aLEfrHwTWllSAxT0zRno",
+        hashing.memory_kib, hashing.iterations, hashing.parallelism
+    ));
     if let Some((stored_user_id, stored_expected_password_hash)) =
         get_stored_credentials(credentials.username, pool)
             .await
@@ -44,16 +52,98 @@ pub async fn validate_credentials(
         expected_password_hash = stored_expected_password_hash;
     }
 
-    spawn_blocking_with_tracing(move || {
-        verify_password_hash(expected_password_hash, credentials.password)
+    let hashing_settings = hashing.clone();
+    let new_password_hash = spawn_blocking_with_tracing(move || {
+        verify_password_hash(&expected_password_hash, &credentials.password)?;
+        let new_password_hash = if needs_rehash(
+            expected_password_hash.expose_secret(),
+            &hashing_settings,
+        ) {
+            Some(hash_password(
+                credentials.password.expose_secret(),
+                &hashing_settings,
+            )?)
+        } else {
+            None
+        };
+        Ok::<_, AuthError>((expected_password_hash, new_password_hash))
     })
     .await
     .context("Failed to spawn blocking task")
     .map_err(AuthError::UnexpectedError)??;
 
-    user_id.ok_or_else(|| {
+    let user_id = user_id.ok_or_else(|| {
         AuthError::InvalidCredentials(anyhow::anyhow!("Unknown username"))
-    })
+    })?;
+    if let (old_password_hash, Some(new_password_hash)) = new_password_hash {
+        // The login goes ahead even if the new hash can't be stored
+        if let Err(e) = upgrade_password_hash(
+            user_id,
+            &old_password_hash,
+            &new_password_hash,
+            pool,
+        )
+        .await
+        {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to upgrade a password hash"
+            );
+        }
+    }
+    Ok(user_id)
+}
+
+/// Only replaces the hash if the password hasn't changed in the meantime.
+#[tracing::instrument(
+    name = "Upgrade password hash",
+    skip(old_password_hash, new_password_hash, pool)
+)]
+async fn upgrade_password_hash(
+    user_id: uuid::Uuid,
+    old_password_hash: &Secret<String>,
+    new_password_hash: &Secret<String>,
+    pool: &PgPool,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET password_hash = $1
+        WHERE user_id = $2 AND password_hash = $3
+        "#,
+        new_password_hash.expose_secret(),
+        user_id,
+        old_password_hash.expose_secret(),
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store an upgraded password hash")?;
+    Ok(())
+}
+
+/// Whether `password_hash` uses an older algorithm than Argon2id version
+/// 0x13, or any parameter weaker than `hashing`.
+fn needs_rehash(
+    password_hash: &str,
+    hashing: &PasswordHashingSettings,
+) -> bool {
+    let Ok(password_hash) = PasswordHash::new(password_hash) else {
+        return true;
+    };
+    if password_hash.algorithm != Algorithm::Argon2id.ident()
+        || password_hash.version != Some(Version::V0x13.into())
+    {
+        return true;
+    }
+    match Params::try_from(&password_hash) {
+        Ok(params) => {
+            params.m_cost() < hashing.memory_kib
+                || params.t_cost() < hashing.iterations
+                || params.p_cost() < hashing.parallelism
+        }
+        Err(_) => true,
+    }
 }
 
 #[tracing::instrument(name = "Get stored credentials", skip(username, pool))]
@@ -64,7 +154,9 @@ async fn get_stored_credentials(
     let row = sqlx::query!(
         "SELECT user_id, password_hash
         FROM users
-        WHERE username = $1
+        WHERE
+            username = $1 AND
+            disabled_at IS NULL
         ",
         username
     )
@@ -79,8 +171,8 @@ async fn get_stored_credentials(
     skip(expected_password_hash, password_candidate)
 )]
 fn verify_password_hash(
-    expected_password_hash: Secret<String>,
-    password_candidate: Secret<String>,
+    expected_password_hash: &Secret<String>,
+    password_candidate: &Secret<String>,
 ) -> Result<(), AuthError> {
     let expected_password_hash =
         PasswordHash::new(expected_password_hash.expose_secret())
@@ -96,21 +188,26 @@ fn verify_password_hash(
         .map_err(AuthError::InvalidCredentials)
 }
 
-#[tracing::instrument(name = "Change password", skip(password, pool))]
+#[tracing::instrument(name = "Change password", skip(password, hashing, pool))]
 pub async fn change_password(
     user_id: uuid::Uuid,
     password: Secret<Password>,
+    hashing: &PasswordHashingSettings,
     pool: &PgPool,
 ) -> Result<(), anyhow::Error> {
-    let password_hash =
-        spawn_blocking_with_tracing(move || compute_password_hash(&password))
-            .await
-            .context("Failed to hash password")?;
+    let hashing = hashing.clone();
+    let password_hash = spawn_blocking_with_tracing(move || {
+        hash_password(password.expose_secret().as_ref(), &hashing)
+    })
+    .await
+    .context("Failed to spawn blocking task")??;
 
     sqlx::query!(
         r#"
         UPDATE users
-        SET password_hash = $1
+        SET
+            password_hash = $1,
+            must_change_password = false
         WHERE user_id = $2
         "#,
         password_hash.expose_secret(),
@@ -123,18 +220,99 @@ pub async fn change_password(
     Ok(())
 }
 
-fn compute_password_hash(password: &Secret<Password>) -> Secret<String> {
-    let salt = SaltString::generate(&mut rand::thread_rng());
-    Secret::new(
-        Argon2::new(
-            Algorithm::Argon2id,
-            Version::V0x13,
-            Params::new(15000, 2, 1, None).unwrap(),
+/// Sessions started before the reset are revoked.
+#[tracing::instrument(
+    name = "Reset password",
+    skip(password, hashing, transaction)
+)]
+pub async fn reset_password(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: uuid::Uuid,
+    password: Secret<Password>,
+    hashing: &PasswordHashingSettings,
+) -> Result<(), anyhow::Error> {
+    let hashing = hashing.clone();
+    let password_hash = spawn_blocking_with_tracing(move || {
+        hash_password(password.expose_secret().as_ref(), &hashing)
+    })
+    .await
+    .context("Failed to spawn blocking task")??;
+
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET
+            password_hash = $1,
+            must_change_password = false,
+            sessions_revoked_at = now()
+        WHERE user_id = $2
+        "#,
+        password_hash.expose_secret(),
+        user_id,
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to reset password")?;
+
+    Ok(())
+}
+
+/// The new user has to change the password they were given the first time
+/// they log in.
+#[tracing::instrument(name = "Create user", skip(password, hashing, pool))]
+pub async fn create_user(
+    username: &Username,
+    email: Option<&SubscriberEmail>,
+    password: Secret<Password>,
+    role: Role,
+    hashing: &PasswordHashingSettings,
+    pool: &PgPool,
+) -> Result<uuid::Uuid, anyhow::Error> {
+    let hashing = hashing.clone();
+    let password_hash = spawn_blocking_with_tracing(move || {
+        hash_password(password.expose_secret().as_ref(), &hashing)
+    })
+    .await
+    .context("Failed to spawn blocking task")??;
+
+    let user_id = uuid::Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO users (
+            user_id,
+            username,
+            email,
+            password_hash,
+            role,
+            must_change_password
         )
-        .hash_password(password.expose_secret().as_ref().as_bytes(), &salt)
-        .unwrap()
-        .to_string(),
+        VALUES ($1, $2, $3, $4, $5, true)
+        "#,
+        user_id,
+        username.as_ref(),
+        email.map(|e| e.as_ref()),
+        password_hash.expose_secret(),
+        role.as_str(),
     )
+    .execute(pool)
+    .await
+    .context("Failed to create a user")?;
+
+    Ok(user_id)
+}
+
+fn hash_password(
+    password: &str,
+    hashing: &PasswordHashingSettings,
+) -> Result<Secret<String>, anyhow::Error> {
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    let params = hashing.params().map_err(anyhow::Error::msg)?;
+    let password_hash =
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password(password.as_bytes(), &salt)
+            .context("Failed to hash password")?
+            .to_string();
+    Ok(Secret::new(password_hash))
 }
 
 fn spawn_blocking_with_tracing<F, R>(f: F) -> JoinHandle<R>
@@ -145,3 +323,45 @@ where
     let current_span = tracing::Span::current();
     tokio::task::spawn_blocking(move || current_span.in_scope(f))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::needs_rehash;
+    use crate::configuration::PasswordHashingSettings;
+
+    const HASH: &str = "gZiV/M1gPc22ElAH/Jh1Hw$\
+        CWOrkoo7oJBQ/iyh7uJ0LO2aLEfrHwTWllSAxT0zRno";
+
+    fn hashing() -> PasswordHashingSettings {
+        PasswordHashingSettings {
+            memory_kib: 19456,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+
+    #[test]
+    fn hashes_with_the_configured_parameters_are_kept() {
+        let current = format!("$argon2id$v=19$m=19456,t=2,p=1${}", HASH);
+        let stronger = format!("$argon2id$v=19$m=65536,t=3,p=1${}", HASH);
+        assert!(!needs_rehash(&current, &hashing()));
+        assert!(!needs_rehash(&stronger, &hashing()));
+    }
+
+    #[test]
+    fn hashes_with_weaker_parameters_are_upgraded() {
+        for params in ["m=15000,t=2,p=1", "m=19456,t=1,p=1"] {
+            let hash = format!("$argon2id$v=19${}${}", params, HASH);
+            assert!(needs_rehash(&hash, &hashing()), "{}", params);
+        }
+    }
+
+    #[test]
+    fn hashes_from_older_algorithms_are_upgraded() {
+        let argon2i = format!("$argon2i$v=19$m=19456,t=2,p=1${}", HASH);
+        let old_version = format!("$argon2id$v=16$m=19456,t=2,p=1${}", HASH);
+        assert!(needs_rehash(&argon2i, &hashing()));
+        assert!(needs_rehash(&old_version, &hashing()));
+        assert!(needs_rehash("not a hash", &hashing()));
+    }
+}
@@ -0,0 +1,172 @@
+//! The sessions themselves live in the session store, which can't be
+//! searched by user: this index records who is logged in where.
+use actix_web::cookie::time::Duration;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// How long the session store keeps a session after its last change.
+/// Sessions not seen for as long have expired, and their row is stale.
+pub const SESSION_TTL: Duration = Duration::days(1);
+
+pub struct ActiveSession {
+    pub session_id: Uuid,
+    pub user_agent: String,
+    pub ip_address: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+/// Returns the id of the session, and when it was created: revocations
+/// are timed by the database clock too, so this is the login time to
+/// compare them with.
+#[tracing::instrument(skip(pool, user_agent))]
+pub async fn create_session(
+    pool: &PgPool,
+    user_id: Uuid,
+    user_agent: &str,
+    ip_address: &str,
+) -> Result<(Uuid, DateTime<Utc>), anyhow::Error> {
+    let session_id = Uuid::new_v4();
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO user_sessions (
+            session_id,
+            user_id,
+            user_agent,
+            ip_address,
+            created_at,
+            last_seen_at
+        )
+        VALUES ($1, $2, $3, $4, now(), now())
+        RETURNING created_at
+        "#,
+        session_id,
+        user_id,
+        user_agent,
+        ip_address
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to record a new session")?;
+    Ok((session_id, row.created_at))
+}
+
+/// Whether the session has not been revoked, nor expired. Its last seen
+/// time is updated at most once a minute.
+#[tracing::instrument(skip(pool))]
+pub async fn touch_session(
+    pool: &PgPool,
+    user_id: Uuid,
+    session_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            last_seen_at,
+            last_seen_at > now() - make_interval(secs => $3) AS "is_live!"
+        FROM user_sessions
+        WHERE session_id = $1 AND user_id = $2
+        "#,
+        session_id,
+        user_id,
+        SESSION_TTL.as_seconds_f64()
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve a session")?;
+    let Some(row) = row else {
+        return Ok(false);
+    };
+    if !row.is_live {
+        revoke_session(pool, user_id, session_id).await?;
+        return Ok(false);
+    }
+    if Utc::now() - row.last_seen_at > chrono::Duration::minutes(1) {
+        sqlx::query!(
+            "UPDATE user_sessions SET last_seen_at = now() \
+            WHERE session_id = $1",
+            session_id
+        )
+        .execute(pool)
+        .await
+        .context("Failed to update the last seen time of a session")?;
+    }
+    Ok(true)
+}
+
+/// The sessions that have not expired, the stale rows of the user are
+/// deleted on the way.
+#[tracing::instrument(skip(pool))]
+pub async fn list_sessions(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<ActiveSession>, anyhow::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM user_sessions
+        WHERE
+            user_id = $1 AND
+            last_seen_at <= now() - make_interval(secs => $2)
+        "#,
+        user_id,
+        SESSION_TTL.as_seconds_f64()
+    )
+    .execute(pool)
+    .await
+    .context("Failed to delete the expired sessions of a user")?;
+    let sessions = sqlx::query_as!(
+        ActiveSession,
+        r#"
+        SELECT session_id, user_agent, ip_address, created_at, last_seen_at
+        FROM user_sessions
+        WHERE user_id = $1
+        ORDER BY last_seen_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the sessions of a user")?;
+    Ok(sessions)
+}
+
+/// Returns whether a session of the user was found.
+#[tracing::instrument(skip(pool))]
+pub async fn revoke_session(
+    pool: &PgPool,
+    user_id: Uuid,
+    session_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM user_sessions WHERE session_id = $1 AND user_id = $2",
+        session_id,
+        user_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to revoke a session")?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Revoke every session of the user, but `except`.
+#[tracing::instrument(skip(pool))]
+pub async fn revoke_other_sessions(
+    pool: &PgPool,
+    user_id: Uuid,
+    except: Option<Uuid>,
+) -> Result<u64, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM user_sessions
+        WHERE user_id = $1 AND session_id IS DISTINCT FROM $2
+        "#,
+        user_id,
+        except
+    )
+    .execute(pool)
+    .await
+    .context("Failed to revoke the sessions of a user")?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,261 @@
+//! Slows down password guessing: every failed login is counted against
+//! the username and the client IP address. After a few failures each new
+//! attempt must wait longer, and too many failures lock them out for a while.
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+
+use crate::configuration::LoginThrottleSettings;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThrottleKey {
+    Username(String),
+    Ip(String),
+}
+
+impl ThrottleKey {
+    pub fn parse(kind: &str, key: String) -> Result<Self, String> {
+        match kind {
+            "username" => Ok(Self::Username(key)),
+            "ip" => Ok(Self::Ip(key)),
+            other => Err(format!("{} is not a login throttle kind.", other)),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Username(_) => "username",
+            Self::Ip(_) => "ip",
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Username(key) | Self::Ip(key) => key,
+        }
+    }
+
+    fn max_attempts(&self, settings: &LoginThrottleSettings) -> u32 {
+        match self {
+            Self::Username(_) => settings.max_attempts_per_username,
+            Self::Ip(_) => settings.max_attempts_per_ip,
+        }
+    }
+}
+
+pub struct Lockout {
+    pub key: ThrottleKey,
+    pub failed_attempts: i32,
+    pub locked_until: DateTime<Utc>,
+}
+
+/// Whether none of `keys` is locked out or still has to wait after its
+/// last failure.
+#[tracing::instrument(skip(pool, settings))]
+pub async fn is_login_allowed(
+    pool: &PgPool,
+    settings: &LoginThrottleSettings,
+    keys: &[ThrottleKey],
+) -> Result<bool, anyhow::Error> {
+    let now = Utc::now();
+    for key in keys {
+        let row = sqlx::query!(
+            r#"
+            SELECT failed_attempts, last_failed_at, locked_until
+            FROM login_throttles
+            WHERE kind = $1 AND key = $2
+            "#,
+            key.kind(),
+            key.key()
+        )
+        .fetch_optional(pool)
+        .await
+        .context("Failed to retrieve the failed logins")?;
+        let Some(row) = row else {
+            continue;
+        };
+        if row.locked_until.is_some_and(|until| until > now) {
+            return Ok(false);
+        }
+        let delay = retry_delay(settings, row.failed_attempts);
+        if delay.is_some_and(|delay| row.last_failed_at + delay > now) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[tracing::instrument(skip(pool, settings))]
+pub async fn record_failed_login(
+    pool: &PgPool,
+    settings: &LoginThrottleSettings,
+    keys: &[ThrottleKey],
+) -> Result<(), anyhow::Error> {
+    let lockout_minutes = settings.lockout_minutes as i32;
+    for key in keys {
+        sqlx::query!(
+            r#"
+            INSERT INTO login_throttles (
+                kind,
+                key,
+                failed_attempts,
+                last_failed_at,
+                locked_until
+            )
+            VALUES ($1, $2, 1, now(), NULL)
+            ON CONFLICT (kind, key) DO UPDATE
+            SET
+                failed_attempts = CASE
+                    WHEN login_throttles.last_failed_at
+                        < now() - make_interval(mins => $3)
+                    THEN 1
+                    ELSE login_throttles.failed_attempts + 1
+                END,
+                last_failed_at = now()
+            "#,
+            key.kind(),
+            key.key(),
+            lockout_minutes
+        )
+        .execute(pool)
+        .await
+        .context("Failed to record a failed login")?;
+        sqlx::query!(
+            r#"
+            UPDATE login_throttles
+            SET locked_until = now() + make_interval(mins => $3)
+            WHERE
+                kind = $1 AND
+                key = $2 AND
+                failed_attempts >= $4
+            "#,
+            key.kind(),
+            key.key(),
+            lockout_minutes,
+            key.max_attempts(settings) as i32
+        )
+        .execute(pool)
+        .await
+        .context("Failed to lock out a login")?;
+    }
+    // Forget failures that no longer count
+    sqlx::query!(
+        r#"
+        DELETE FROM login_throttles
+        WHERE
+            last_failed_at < now() - make_interval(mins => $1) AND
+            (locked_until IS NULL OR locked_until < now())
+        "#,
+        lockout_minutes
+    )
+    .execute(pool)
+    .await
+    .context("Failed to delete expired failed logins")?;
+    Ok(())
+}
+
+/// Forget the failures of `key`, after a successful login or when an owner
+/// unlocks it.
+#[tracing::instrument(skip(pool))]
+pub async fn clear_failed_logins(
+    pool: &PgPool,
+    key: &ThrottleKey,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        "DELETE FROM login_throttles WHERE kind = $1 AND key = $2",
+        key.kind(),
+        key.key()
+    )
+    .execute(pool)
+    .await
+    .context("Failed to clear failed logins")?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_lockouts(
+    pool: &PgPool,
+) -> Result<Vec<Lockout>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT kind, key, failed_attempts, locked_until AS "locked_until!"
+        FROM login_throttles
+        WHERE locked_until > now()
+        ORDER BY locked_until DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the lockouts")?;
+    rows.into_iter()
+        .map(|row| {
+            Ok(Lockout {
+                key: ThrottleKey::parse(&row.kind, row.key)
+                    .map_err(anyhow::Error::msg)?,
+                failed_attempts: row.failed_attempts,
+                locked_until: row.locked_until,
+            })
+        })
+        .collect()
+}
+
+/// How long to wait after the last of `failed_attempts` failures.
+fn retry_delay(
+    settings: &LoginThrottleSettings,
+    failed_attempts: i32,
+) -> Option<Duration> {
+    let delayed = u32::try_from(failed_attempts)
+        .ok()?
+        .checked_sub(settings.free_attempts)?;
+    let seconds = 2u32
+        .checked_pow(delayed)
+        .and_then(|factor| factor.checked_mul(settings.base_delay_seconds))
+        .map_or(settings.max_delay_seconds, |seconds| {
+            seconds.min(settings.max_delay_seconds)
+        });
+    Some(Duration::seconds(seconds.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{retry_delay, ThrottleKey};
+    use crate::configuration::LoginThrottleSettings;
+    use chrono::Duration;
+    use claims::{assert_err, assert_none, assert_some_eq};
+
+    fn settings() -> LoginThrottleSettings {
+        LoginThrottleSettings {
+            free_attempts: 3,
+            base_delay_seconds: 1,
+            max_delay_seconds: 60,
+            max_attempts_per_username: 10,
+            max_attempts_per_ip: 50,
+            lockout_minutes: 15,
+            client_ip_header: None,
+        }
+    }
+
+    #[test]
+    fn the_first_failures_are_not_delayed() {
+        assert_none!(retry_delay(&settings(), 0));
+        assert_none!(retry_delay(&settings(), 2));
+    }
+
+    #[test]
+    fn the_delay_doubles_up_to_the_maximum() {
+        assert_some_eq!(retry_delay(&settings(), 3), Duration::seconds(1));
+        assert_some_eq!(retry_delay(&settings(), 4), Duration::seconds(2));
+        assert_some_eq!(retry_delay(&settings(), 8), Duration::seconds(32));
+        assert_some_eq!(retry_delay(&settings(), 9), Duration::seconds(60));
+        assert_some_eq!(retry_delay(&settings(), 100), Duration::seconds(60));
+    }
+
+    #[test]
+    fn unknown_throttle_kinds_are_rejected() {
+        assert_err!(ThrottleKey::parse("email", "a@b.c".into()));
+        assert_eq!(
+            ThrottleKey::parse("ip", "127.0.0.1".into()),
+            Ok(ThrottleKey::Ip("127.0.0.1".into()))
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! Time-based one-time passwords (RFC 6238), as generated by authenticator
+//! apps: HMAC-SHA1, 30 second steps, 6 digits.
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use secrecy::{ExposeSecret, Secret};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::utils::constant_time_eq;
+
+const SECRET_SIZE: usize = 20;
+const STEP_SECONDS: i64 = 30;
+const DIGITS: u32 = 6;
+/// Codes from the previous and next steps are accepted too, to make up for
+/// clock drift and slow typing.
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+const NONCE_SIZE: usize = 12;
+
+pub struct TotpSecret(Secret<Vec<u8>>);
+
+impl std::fmt::Debug for TotpSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TotpSecret([REDACTED])")
+    }
+}
+
+impl TotpSecret {
+    pub fn generate() -> Self {
+        let mut bytes = vec![0; SECRET_SIZE];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self(Secret::new(bytes))
+    }
+
+    /// The form authenticator apps expect when the secret is typed in.
+    pub fn to_base32(&self) -> String {
+        base32_encode(self.0.expose_secret())
+    }
+
+    /// The `otpauth://` URI that QR codes for authenticator apps encode.
+    pub fn otpauth_uri(&self, issuer: &str, account: &str) -> String {
+        format!(
+            "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}\
+            &algorithm=SHA1&digits={DIGITS}&period={STEP_SECONDS}",
+            issuer = urlencoding::encode(issuer),
+            account = urlencoding::encode(account),
+            secret = self.to_base32(),
+        )
+    }
+
+    pub fn code_at(&self, step: i64) -> String {
+        let mut mac =
+            <Hmac<Sha1> as Mac>::new_from_slice(self.0.expose_secret())
+                .expect("HMAC can take a key of any size");
+        mac.update(&step.to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+        // Dynamic truncation
+        let offset = (hash[hash.len() - 1] & 0xf) as usize;
+        let binary = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+        format!(
+            "{:0width$}",
+            binary % 10u32.pow(DIGITS),
+            width = DIGITS as usize
+        )
+    }
+
+    /// The step matched by `code` at `unix_time`, if any.
+    /// Steps up to `last_used_step` are rejected, so that a code can't be
+    /// replayed.
+    pub fn verify(
+        &self,
+        code: &str,
+        unix_time: i64,
+        last_used_step: Option<i64>,
+    ) -> Option<i64> {
+        let code: String =
+            code.chars().filter(|c| !c.is_whitespace()).collect();
+        if code.len() != DIGITS as usize {
+            return None;
+        }
+        let current_step = unix_time.div_euclid(STEP_SECONDS);
+        (current_step - ALLOWED_DRIFT_STEPS
+            ..=current_step + ALLOWED_DRIFT_STEPS)
+            .filter(|step| last_used_step.is_none_or(|last| *step > last))
+            .find(|step| constant_time_eq(&self.code_at(*step), &code))
+    }
+}
+
+/// Encrypts TOTP secrets at rest, with AES-256-GCM.
+pub struct TotpEncryptionKey(Secret<[u8; 32]>);
+
+impl TotpEncryptionKey {
+    /// Any string works as a key: it is stretched to 256 bits with SHA-256.
+    pub fn new(key: &Secret<String>) -> Self {
+        Self(Secret::new(
+            Sha256::digest(key.expose_secret().as_bytes()).into(),
+        ))
+    }
+
+    /// The random nonce is stored in front of the ciphertext.
+    pub fn encrypt(&self, secret: &TotpSecret) -> Vec<u8> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher()
+            .encrypt(&nonce, secret.0.expose_secret().as_slice())
+            .expect("Failed to encrypt a TOTP secret");
+        let mut encrypted = nonce.to_vec();
+        encrypted.extend(ciphertext);
+        encrypted
+    }
+
+    pub fn decrypt(&self, encrypted: &[u8]) -> Result<TotpSecret, String> {
+        if encrypted.len() < NONCE_SIZE {
+            return Err("The encrypted TOTP secret is too short".into());
+        }
+        let (nonce, ciphertext) = encrypted.split_at(NONCE_SIZE);
+        self.cipher()
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map(|bytes| TotpSecret(Secret::new(bytes)))
+            .map_err(|_| "Failed to decrypt a TOTP secret".into())
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(self.0.expose_secret()))
+    }
+}
+
+/// RFC 4648 base32, without padding.
+fn base32_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut encoded = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded
+            .push(ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base32_encode, TotpEncryptionKey, TotpSecret};
+    use claims::{assert_err, assert_none, assert_some_eq};
+    use secrecy::{ExposeSecret, Secret};
+
+    /// The SHA1 secret of the RFC 6238 test vectors.
+    fn rfc_secret() -> TotpSecret {
+        TotpSecret(Secret::new(b"12345678901234567890".to_vec()))
+    }
+
+    #[test]
+    fn codes_match_the_rfc_6238_test_vectors() {
+        // The RFC uses 8 digits: these are their last 6
+        let secret = rfc_secret();
+        assert_eq!(secret.code_at(59 / 30), "287082");
+        assert_eq!(secret.code_at(1111111109 / 30), "081804");
+        assert_eq!(secret.code_at(1234567890 / 30), "005924");
+        assert_eq!(secret.code_at(2000000000 / 30), "279037");
+    }
+
+    #[test]
+    fn codes_from_adjacent_steps_are_accepted() {
+        let secret = rfc_secret();
+        let now = 1111111109;
+        let step = now / 30;
+        assert_some_eq!(secret.verify(&secret.code_at(step), now, None), step);
+        assert_some_eq!(
+            secret.verify(&secret.code_at(step - 1), now, None),
+            step - 1
+        );
+        assert_some_eq!(
+            secret.verify(&secret.code_at(step + 1), now, None),
+            step + 1
+        );
+        assert_none!(secret.verify(&secret.code_at(step - 2), now, None));
+    }
+
+    #[test]
+    fn codes_can_not_be_replayed() {
+        let secret = rfc_secret();
+        let now = 1111111109;
+        let step = now / 30;
+        assert_none!(secret.verify(&secret.code_at(step), now, Some(step)));
+        assert_some_eq!(
+            secret.verify(&secret.code_at(step + 1), now, Some(step)),
+            step + 1
+        );
+    }
+
+    #[test]
+    fn malformed_codes_are_rejected() {
+        let secret = rfc_secret();
+        assert_none!(secret.verify("", 59, None));
+        assert_none!(secret.verify("2870820", 59, None));
+        assert_none!(secret.verify("28708", 59, None));
+    }
+
+    #[test]
+    fn secrets_are_base32_encoded() {
+        assert_eq!(
+            rfc_secret().to_base32(),
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"
+        );
+        assert_eq!(base32_encode(b"f"), "MY");
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+    }
+
+    #[test]
+    fn secrets_survive_encryption() {
+        let key = TotpEncryptionKey::new(&Secret::new("key".to_string()));
+        let secret = TotpSecret::generate();
+
+        let encrypted = key.encrypt(&secret);
+
+        assert!(!encrypted
+            .windows(secret.0.expose_secret().len())
+            .any(|w| w == secret.0.expose_secret().as_slice()));
+        let decrypted = key.decrypt(&encrypted).unwrap();
+        assert_eq!(decrypted.to_base32(), secret.to_base32());
+    }
+
+    #[test]
+    fn secrets_can_not_be_decrypted_with_another_key() {
+        let key = TotpEncryptionKey::new(&Secret::new("key".to_string()));
+        let other_key =
+            TotpEncryptionKey::new(&Secret::new("other key".to_string()));
+
+        let encrypted = key.encrypt(&TotpSecret::generate());
+
+        assert_err!(other_key.decrypt(&encrypted));
+        assert_err!(key.decrypt(&encrypted[..8]));
+    }
+}
@@ -0,0 +1,392 @@
+use anyhow::Context;
+use rand::distributions::{Distribution, Uniform};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::totp::{TotpEncryptionKey, TotpSecret};
+use crate::domain::hash_token;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+pub enum TwoFactorStatus {
+    Disabled,
+    /// Enrolment has started, but the user has not confirmed a code yet.
+    Pending,
+    Enabled {
+        remaining_recovery_codes: i64,
+    },
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_two_factor_status(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<TwoFactorStatus, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            totp_secret IS NOT NULL AS "has_secret!",
+            totp_enabled_at IS NOT NULL AS "enabled!",
+            (
+                SELECT count(*)
+                FROM recovery_codes r
+                WHERE
+                    r.user_id = users.user_id AND
+                    r.used_at IS NULL
+            ) AS "remaining_recovery_codes!"
+        FROM users
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to retrieve the two-factor status of a user")?;
+    Ok(match (row.enabled, row.has_secret) {
+        (true, _) => TwoFactorStatus::Enabled {
+            remaining_recovery_codes: row.remaining_recovery_codes,
+        },
+        (false, true) => TwoFactorStatus::Pending,
+        (false, false) => TwoFactorStatus::Disabled,
+    })
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn is_two_factor_enabled(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let status = get_two_factor_status(pool, user_id).await?;
+    Ok(matches!(status, TwoFactorStatus::Enabled { .. }))
+}
+
+/// Replace any pending secret with a new one.
+#[tracing::instrument(skip(pool, key))]
+pub async fn start_enrolment(
+    pool: &PgPool,
+    key: &TotpEncryptionKey,
+    user_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    let secret = TotpSecret::generate();
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET
+            totp_secret = $1,
+            totp_last_used_step = NULL
+        WHERE
+            user_id = $2 AND
+            totp_enabled_at IS NULL
+        "#,
+        key.encrypt(&secret),
+        user_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store a new TOTP secret")?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(pool, key))]
+pub async fn get_totp_secret(
+    pool: &PgPool,
+    key: &TotpEncryptionKey,
+    user_id: Uuid,
+) -> Result<Option<TotpSecret>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT totp_secret
+        FROM users
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to retrieve the TOTP secret of a user")?;
+    row.totp_secret
+        .map(|encrypted| key.decrypt(&encrypted).map_err(anyhow::Error::msg))
+        .transpose()
+}
+
+/// Confirm the enrolment with a code from the pending secret.
+/// Returns the recovery codes, or `None` if the code is wrong.
+#[tracing::instrument(skip(pool, key, code))]
+pub async fn enable_two_factor(
+    pool: &PgPool,
+    key: &TotpEncryptionKey,
+    user_id: Uuid,
+    code: &str,
+) -> Result<Option<Vec<String>>, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    if !verify_totp(&mut transaction, key, user_id, code).await? {
+        return Ok(None);
+    }
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_enabled_at = now()
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to enable two-factor authentication")?;
+    let recovery_codes =
+        replace_recovery_codes(&mut transaction, user_id).await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the two-factor enrolment")?;
+    Ok(Some(recovery_codes))
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn disable_two_factor(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET
+            totp_secret = NULL,
+            totp_enabled_at = NULL,
+            totp_last_used_step = NULL
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to disable two-factor authentication")?;
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut transaction)
+        .await
+        .context("Failed to delete the recovery codes of a user")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit disabling two-factor authentication")?;
+    Ok(())
+}
+
+/// Check a code from the authenticator app, or else a recovery code.
+/// Either can only be used once.
+#[tracing::instrument(skip(pool, key, code))]
+pub async fn verify_second_factor(
+    pool: &PgPool,
+    key: &TotpEncryptionKey,
+    user_id: Uuid,
+    code: &str,
+) -> Result<bool, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let verified = verify_totp(&mut transaction, key, user_id, code).await?
+        || use_recovery_code(&mut transaction, user_id, code).await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the second factor verification")?;
+    Ok(verified)
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn regenerate_recovery_codes(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<String>, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let recovery_codes =
+        replace_recovery_codes(&mut transaction, user_id).await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the new recovery codes")?;
+    Ok(recovery_codes)
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn is_two_factor_required(
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!("SELECT require_two_factor FROM security_settings")
+        .fetch_one(pool)
+        .await
+        .context("Failed to retrieve the two-factor policy")?;
+    Ok(row.require_two_factor)
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn set_two_factor_required(
+    pool: &PgPool,
+    required: bool,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        "UPDATE security_settings SET require_two_factor = $1",
+        required
+    )
+    .execute(pool)
+    .await
+    .context("Failed to change the two-factor policy")?;
+    Ok(())
+}
+
+/// Lock the user row, so that the same code can't be used twice
+/// concurrently.
+async fn verify_totp(
+    transaction: &mut Transaction<'_, Postgres>,
+    key: &TotpEncryptionKey,
+    user_id: Uuid,
+    code: &str,
+) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT totp_secret, totp_last_used_step
+        FROM users
+        WHERE user_id = $1
+        FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .context("Failed to retrieve the TOTP secret of a user")?;
+    let Some(encrypted) = row.totp_secret else {
+        return Ok(false);
+    };
+    let secret = key.decrypt(&encrypted).map_err(anyhow::Error::msg)?;
+    let now = chrono::Utc::now().timestamp();
+    let Some(step) = secret.verify(code, now, row.totp_last_used_step) else {
+        return Ok(false);
+    };
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_last_used_step = $1
+        WHERE user_id = $2
+        "#,
+        step,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to record the last used TOTP step")?;
+    Ok(true)
+}
+
+async fn use_recovery_code(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    code: &str,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE recovery_codes
+        SET used_at = now()
+        WHERE
+            user_id = $1 AND
+            code_hash = $2 AND
+            used_at IS NULL
+        "#,
+        user_id,
+        hash_recovery_code(code)
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to use a recovery code")?;
+    Ok(result.rows_affected() == 1)
+}
+
+async fn replace_recovery_codes(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+) -> Result<Vec<String>, anyhow::Error> {
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to delete the recovery codes of a user")?;
+    let codes = generate_recovery_codes();
+    let hashes: Vec<String> =
+        codes.iter().map(|c| hash_recovery_code(c)).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO recovery_codes (user_id, code_hash)
+        SELECT $1, code_hash FROM UNNEST($2::text[]) AS code_hash
+        "#,
+        user_id,
+        &hashes
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to store the recovery codes of a user")?;
+    Ok(codes)
+}
+
+/// Codes look like `k7pq2-xm4ta`. Ambiguous characters are left out.
+fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    let alphabet = Uniform::from(0..RECOVERY_CODE_ALPHABET.len());
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let chars: String = (0..10)
+                .map(|_| {
+                    RECOVERY_CODE_ALPHABET[alphabet.sample(&mut rng)] as char
+                })
+                .collect();
+            format!("{}-{}", &chars[..5], &chars[5..])
+        })
+        .collect()
+}
+
+/// Case, dashes and spaces don't matter when a code is typed in.
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    hash_token(&normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_recovery_codes, hash_recovery_code};
+
+    #[test]
+    fn recovery_codes_are_distinct() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), 10);
+        let mut unique = codes.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), codes.len());
+        assert!(codes.iter().all(|c| c.len() == 11 && &c[5..6] == "-"));
+    }
+
+    #[test]
+    fn recovery_codes_are_compared_loosely() {
+        assert_eq!(
+            hash_recovery_code("k7pq2-xm4ta"),
+            hash_recovery_code(" K7PQ2 XM4TA ")
+        );
+        assert_ne!(
+            hash_recovery_code("k7pq2-xm4ta"),
+            hash_recovery_code("k7pq2-xm4tb")
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use anyhow::Context;
 use secrecy::{ExposeSecret, Secret};
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::{
@@ -5,14 +6,26 @@ use sqlx::{
     ConnectOptions,
 };
 
-use crate::{domain::SubscriberEmail, email_client::EmailClient};
+use crate::{
+    domain::{BreachedPasswords, PasswordPolicy, QuietHours, SubscriberEmail},
+    email_client::EmailClient,
+};
 
 #[derive(serde::Deserialize, Clone)]
 pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
     pub email_client: EmailClientSettings,
-    pub redis_uri: Secret<String>,
+    /// Only needed when sessions are stored in Redis.
+    pub redis_uri: Option<Secret<String>>,
+    pub session_store: SessionStoreSettings,
+    pub tracking: TrackingSettings,
+    pub attachments: AttachmentSettings,
+    pub publishing: PublishingSettings,
+    pub schedule: ScheduleSettings,
+    pub login_throttle: LoginThrottleSettings,
+    pub password_hashing: PasswordHashingSettings,
+    pub password_policy: PasswordPolicySettings,
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -33,6 +46,128 @@ pub struct ApplicationSettings {
     pub host: String,
     pub base_url: String,
     pub hmac_secret: Secret<String>,
+    /// Encrypts the TOTP secrets of admins in the database.
+    pub totp_encryption_key: Secret<String>,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SessionStoreSettings {
+    pub backend: SessionBackend,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionBackend {
+    Redis,
+    /// The application database, so that Redis is not needed.
+    Postgres,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct TrackingSettings {
+    pub click_tracking_enabled: bool,
+    pub open_tracking_enabled: bool,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct AttachmentSettings {
+    /// Largest accepted file, in bytes.
+    pub max_file_size: usize,
+    /// Largest combined size of the files attached to an issue, in bytes.
+    pub max_issue_size: usize,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct PublishingSettings {
+    /// Issues must be submitted and approved by another admin before they
+    /// can be published.
+    pub require_approval: bool,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct ScheduleSettings {
+    /// Quiet hours, as `HH:MM` in the time zone of each subscriber.
+    /// There are none unless both ends are set.
+    pub quiet_hours_start: Option<String>,
+    pub quiet_hours_end: Option<String>,
+}
+
+impl ScheduleSettings {
+    pub fn quiet_hours(&self) -> Result<Option<QuietHours>, String> {
+        match (&self.quiet_hours_start, &self.quiet_hours_end) {
+            (Some(start), Some(end)) => QuietHours::parse(start, end).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct LoginThrottleSettings {
+    /// Failed logins allowed before the next attempt must wait.
+    pub free_attempts: u32,
+    /// The wait after the first delayed attempt. It doubles with every
+    /// further failure, up to `max_delay_seconds`.
+    pub base_delay_seconds: u32,
+    pub max_delay_seconds: u32,
+    /// Failed logins that lock a username, or a client IP address, out.
+    pub max_attempts_per_username: u32,
+    pub max_attempts_per_ip: u32,
+    /// How long a lockout lasts. Failures older than this are forgotten.
+    pub lockout_minutes: u32,
+    /// The header a trusted reverse proxy puts the client IP address in.
+    /// Without it, the peer address of the connection is used.
+    pub client_ip_header: Option<String>,
+}
+
+/// Argon2id parameters for new password hashes. Stored hashes with weaker
+/// parameters are upgraded when their user logs in.
+#[derive(serde::Deserialize, Clone)]
+pub struct PasswordHashingSettings {
+    /// In KiB.
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl PasswordHashingSettings {
+    pub fn params(&self) -> Result<argon2::Params, String> {
+        argon2::Params::new(
+            self.memory_kib,
+            self.iterations,
+            self.parallelism,
+            None,
+        )
+        .map_err(|e| format!("Invalid password hashing parameters: {}", e))
+    }
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct PasswordPolicySettings {
+    pub min_length: usize,
+    pub max_length: usize,
+    /// Passwords estimated to be easier to guess are rejected.
+    pub min_strength_bits: u32,
+    /// A local list of leaked passwords, see `BreachedPasswords::parse`.
+    pub breached_passwords_path: String,
+}
+
+impl PasswordPolicySettings {
+    pub fn policy(&self) -> Result<PasswordPolicy, anyhow::Error> {
+        let list = std::fs::read_to_string(&self.breached_passwords_path)
+            .with_context(|| {
+                format!(
+                    "Failed to read the breached passwords from {}",
+                    self.breached_passwords_path
+                )
+            })?;
+        Ok(PasswordPolicy {
+            min_length: self.min_length,
+            max_length: self.max_length,
+            min_strength_bits: self.min_strength_bits,
+            breached_passwords: BreachedPasswords::parse(&list)
+                .map_err(anyhow::Error::msg)?,
+        })
+    }
 }
 
 #[derive(serde::Deserialize, Clone)]
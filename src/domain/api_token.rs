@@ -0,0 +1,127 @@
+use super::secret_token::{generate_token, hash_token, is_token_of_size};
+
+/// Makes tokens easy to recognise, for example by secret scanners.
+const API_TOKEN_PREFIX: &str = "z2p_";
+const API_TOKEN_SIZE: usize = 40;
+
+pub struct ApiToken(String);
+
+impl std::fmt::Debug for ApiToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ApiToken([REDACTED])")
+    }
+}
+
+impl ApiToken {
+    pub fn parse(token: String) -> Result<Self, String> {
+        let is_valid = token
+            .strip_prefix(API_TOKEN_PREFIX)
+            .is_some_and(|s| is_token_of_size(s, API_TOKEN_SIZE));
+        if is_valid {
+            Ok(Self(token))
+        } else {
+            Err("This is not a valid API token".into())
+        }
+    }
+
+    pub fn generate() -> Self {
+        Self(format!(
+            "{}{}",
+            API_TOKEN_PREFIX,
+            generate_token(API_TOKEN_SIZE)
+        ))
+    }
+
+    /// Stored like password reset tokens, as a hash.
+    pub fn hash(&self) -> String {
+        hash_token(&self.0)
+    }
+}
+
+impl AsRef<str> for ApiToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// What a request authenticated with an API token may do, on top of what
+/// the role of its owner allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiScope {
+    /// Read-only requests.
+    Read,
+    /// Requests that change anything but sending issues.
+    Write,
+    /// Sending issues to subscribers.
+    Publish,
+}
+
+impl ApiScope {
+    pub const ALL: [ApiScope; 3] =
+        [ApiScope::Read, ApiScope::Write, ApiScope::Publish];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Publish => "publish",
+        }
+    }
+}
+
+impl TryFrom<String> for ApiScope {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "read" => Ok(Self::Read),
+            "write" => Ok(Self::Write),
+            "publish" => Ok(Self::Publish),
+            other => Err(format!("{} is not a valid API token scope", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use crate::domain::{ApiScope, ApiToken};
+
+    #[test]
+    fn generated_tokens_are_valid() {
+        let token = ApiToken::generate();
+        assert!(token.as_ref().starts_with("z2p_"));
+        assert_ok!(ApiToken::parse(token.as_ref().to_string()));
+    }
+
+    #[test]
+    fn tokens_without_the_prefix_are_rejected() {
+        assert_err!(ApiToken::parse("a".repeat(40)));
+        assert_err!(ApiToken::parse(format!("abc_{}", "a".repeat(40))));
+    }
+
+    #[test]
+    fn tokens_of_the_wrong_size_or_alphabet_are_rejected() {
+        assert_err!(ApiToken::parse("z2p_".to_string()));
+        assert_err!(ApiToken::parse(format!("z2p_{}", "a".repeat(41))));
+        assert_err!(ApiToken::parse(format!("z2p_{}", "-".repeat(40))));
+    }
+
+    #[test]
+    fn debug_output_does_not_reveal_the_token() {
+        let token = ApiToken::generate();
+        assert!(!format!("{:?}", token).contains(token.as_ref()));
+    }
+
+    #[test]
+    fn scopes_round_trip_through_their_name() {
+        for scope in ApiScope::ALL {
+            assert_eq!(
+                ApiScope::try_from(scope.as_str().to_string()),
+                Ok(scope)
+            );
+        }
+        assert_err!(ApiScope::try_from("admin".to_string()));
+    }
+}
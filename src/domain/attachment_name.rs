@@ -0,0 +1,70 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Debug)]
+pub struct AttachmentName(String);
+
+impl AttachmentName {
+    /// Browsers may send the full path of the uploaded file, only its last
+    /// component is kept.
+    pub fn parse(s: &str) -> Result<AttachmentName, String> {
+        let name = s.rsplit(['/', '\\']).next().unwrap_or_default().trim();
+        let is_empty = name.is_empty() || name == "." || name == "..";
+        let is_too_long = name.graphemes(true).count() > 255;
+        let forbidden_characters = ['"', '<', '>', ':'];
+        let contains_forbidden_characters = name
+            .chars()
+            .any(|c| c.is_control() || forbidden_characters.contains(&c));
+
+        if is_empty || is_too_long || contains_forbidden_characters {
+            Err(format!("{} is not a valid file name", s))
+        } else {
+            Ok(Self(name.to_string()))
+        }
+    }
+}
+
+impl AsRef<str> for AttachmentName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::AttachmentName;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn a_plain_file_name_is_kept() {
+        let name = AttachmentName::parse("logo 2.png").unwrap();
+        assert_eq!(name.as_ref(), "logo 2.png");
+    }
+
+    #[test]
+    fn directories_are_stripped() {
+        for path in ["C:\\Users\\me\\logo.png", "/home/me/logo.png"] {
+            let name = AttachmentName::parse(path).unwrap();
+            assert_eq!(name.as_ref(), "logo.png");
+        }
+    }
+
+    #[test]
+    fn empty_names_are_rejected() {
+        for name in ["", "  ", "/tmp/", ".."] {
+            assert_err!(AttachmentName::parse(name));
+        }
+    }
+
+    #[test]
+    fn names_containing_invalid_characters_are_rejected() {
+        for name in ["a\"b", "a<b", "a>b", "a:b", "a\nb"] {
+            assert_err!(AttachmentName::parse(name));
+        }
+    }
+
+    #[test]
+    fn a_255_grapheme_long_name_is_valid() {
+        assert_ok!(AttachmentName::parse(&"ë".repeat(255)));
+        assert_err!(AttachmentName::parse(&"a".repeat(256)));
+    }
+}
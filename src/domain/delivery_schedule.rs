@@ -0,0 +1,214 @@
+use chrono::{
+    DateTime, Duration, LocalResult, NaiveDateTime, NaiveTime, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+
+/// A window of the day, in the subscriber's time zone, when no email is
+/// sent. It wraps around midnight when it ends before it starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    /// Both ends are formatted as `HH:MM`.
+    pub fn parse(start: &str, end: &str) -> Result<QuietHours, String> {
+        let start = parse_time_of_day(start)?;
+        let end = parse_time_of_day(end)?;
+        if start == end {
+            return Err(
+                "Quiet hours can't start and end at the same time".to_string()
+            );
+        }
+        Ok(Self { start, end })
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// Parse a `HH:MM` time of day, as sent by `<input type="time">`.
+pub fn parse_time_of_day(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s.trim(), "%H:%M")
+        .map_err(|_| format!("{} is not a valid time of day (HH:MM)", s))
+}
+
+/// The earliest time an issue can be sent to a subscriber living in
+/// `time_zone`: the next occurrence of `local_time` if the issue has one,
+/// pushed back to the end of the quiet hours if it falls within them.
+pub fn earliest_send_time(
+    now: DateTime<Utc>,
+    time_zone: Tz,
+    local_time: Option<NaiveTime>,
+    quiet_hours: Option<QuietHours>,
+) -> DateTime<Utc> {
+    let local_now = now.with_timezone(&time_zone).naive_local();
+    let mut send_at = match local_time {
+        Some(local_time) => {
+            let today = local_now.date().and_time(local_time);
+            if today >= local_now {
+                today
+            } else {
+                today + Duration::days(1)
+            }
+        }
+        None => local_now,
+    };
+    if let Some(quiet_hours) = quiet_hours {
+        if quiet_hours.contains(send_at.time()) {
+            let end = send_at.date().and_time(quiet_hours.end);
+            send_at = if end > send_at {
+                end
+            } else {
+                end + Duration::days(1)
+            };
+        }
+    }
+    to_utc(time_zone, send_at).max(now)
+}
+
+/// Ambiguous local times, when clocks go back, resolve to the earliest
+/// instant. Local times skipped when clocks go forward are moved past the
+/// gap.
+fn to_utc(time_zone: Tz, local: NaiveDateTime) -> DateTime<Utc> {
+    match time_zone.from_local_datetime(&local) {
+        LocalResult::Single(t) | LocalResult::Ambiguous(t, _) => {
+            t.with_timezone(&Utc)
+        }
+        LocalResult::None => to_utc(time_zone, local + Duration::minutes(30)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveTime, TimeZone, Utc};
+    use chrono_tz::Tz;
+    use claims::{assert_err, assert_ok};
+
+    use super::{earliest_send_time, parse_time_of_day, QuietHours};
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn time(s: &str) -> NaiveTime {
+        parse_time_of_day(s).unwrap()
+    }
+
+    fn night() -> QuietHours {
+        QuietHours::parse("22:00", "07:00").unwrap()
+    }
+
+    #[test]
+    fn times_of_day_are_hours_and_minutes() {
+        assert_ok!(parse_time_of_day("09:30"));
+        for s in ["9h30", "25:00", "", "09:30:00"] {
+            assert_err!(parse_time_of_day(s));
+        }
+    }
+
+    #[test]
+    fn quiet_hours_can_wrap_around_midnight() {
+        let quiet_hours = night();
+        for t in ["22:00", "23:59", "00:00", "06:59"] {
+            assert!(quiet_hours.contains(time(t)), "{}", t);
+        }
+        for t in ["07:00", "12:00", "21:59"] {
+            assert!(!quiet_hours.contains(time(t)), "{}", t);
+        }
+        let lunch = QuietHours::parse("12:00", "14:00").unwrap();
+        assert!(lunch.contains(time("13:00")));
+        assert!(!lunch.contains(time("23:00")));
+    }
+
+    #[test]
+    fn empty_quiet_hours_are_rejected() {
+        assert_err!(QuietHours::parse("10:00", "10:00"));
+    }
+
+    #[test]
+    fn without_constraints_issues_are_sent_right_away() {
+        let now = utc("2026-10-19T15:00:00Z");
+        assert_eq!(earliest_send_time(now, Tz::Asia__Tokyo, None, None), now);
+    }
+
+    #[test]
+    fn issues_are_sent_at_the_next_occurrence_of_the_local_time() {
+        // 8:00 in Lisbon (UTC+1 in summer time)
+        let now = utc("2026-10-19T07:00:00Z");
+        let send_at = earliest_send_time(
+            now,
+            Tz::Europe__Lisbon,
+            Some(time("09:00")),
+            None,
+        );
+        assert_eq!(send_at, utc("2026-10-19T08:00:00Z"));
+        // 10:00 in Lisbon, 9:00 has passed for today
+        let now = utc("2026-10-19T09:00:00Z");
+        let send_at = earliest_send_time(
+            now,
+            Tz::Europe__Lisbon,
+            Some(time("09:00")),
+            None,
+        );
+        assert_eq!(send_at, utc("2026-10-20T08:00:00Z"));
+    }
+
+    #[test]
+    fn the_local_time_depends_on_the_time_zone() {
+        let now = utc("2026-10-19T00:00:00Z");
+        let local_time = Some(time("09:00"));
+        let tokyo = earliest_send_time(now, Tz::Asia__Tokyo, local_time, None);
+        let new_york =
+            earliest_send_time(now, Tz::America__New_York, local_time, None);
+        assert_eq!(tokyo, utc("2026-10-19T00:00:00Z"));
+        assert_eq!(new_york, utc("2026-10-19T13:00:00Z"));
+    }
+
+    #[test]
+    fn deliveries_during_quiet_hours_wait_for_their_end() {
+        // 23:00 in UTC, quiet until 7:00 the next day
+        let now = utc("2026-10-19T23:00:00Z");
+        let send_at = earliest_send_time(now, Tz::UTC, None, Some(night()));
+        assert_eq!(send_at, utc("2026-10-20T07:00:00Z"));
+        // 3:00, quiet until 7:00 the same day
+        let now = utc("2026-10-20T03:00:00Z");
+        let send_at = earliest_send_time(now, Tz::UTC, None, Some(night()));
+        assert_eq!(send_at, utc("2026-10-20T07:00:00Z"));
+    }
+
+    #[test]
+    fn a_local_time_within_quiet_hours_is_pushed_back() {
+        let now = utc("2026-10-19T12:00:00Z");
+        let send_at = earliest_send_time(
+            now,
+            Tz::UTC,
+            Some(time("06:00")),
+            Some(night()),
+        );
+        assert_eq!(send_at, utc("2026-10-20T07:00:00Z"));
+    }
+
+    #[test]
+    fn local_times_skipped_by_daylight_saving_are_moved_past_the_gap() {
+        // Clocks in New York go from 2:00 to 3:00 on 2026-03-08
+        let now = utc("2026-03-08T05:00:00Z");
+        let send_at = earliest_send_time(
+            now,
+            Tz::America__New_York,
+            Some(time("02:30")),
+            None,
+        );
+        let expected = Tz::America__New_York
+            .with_ymd_and_hms(2026, 3, 8, 3, 0, 0)
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(send_at, expected);
+    }
+}
@@ -0,0 +1,154 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    Active,
+    Paused,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryAction {
+    Pause,
+    Resume,
+    Cancel,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Paused => "paused",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    /// The status reached by applying `action`, if the transition is allowed.
+    /// A cancelled delivery can't be restarted.
+    pub fn apply(self, action: DeliveryAction) -> Result<Self, String> {
+        match (self, action) {
+            (Self::Active, DeliveryAction::Pause) => Ok(Self::Paused),
+            (Self::Paused, DeliveryAction::Resume) => Ok(Self::Active),
+            (Self::Active | Self::Paused, DeliveryAction::Cancel) => {
+                Ok(Self::Cancelled)
+            }
+            (status, action) => Err(format!(
+                "A {} delivery can't be {}.",
+                status.as_str(),
+                action.past_participle()
+            )),
+        }
+    }
+
+    pub fn allowed_actions(self) -> &'static [DeliveryAction] {
+        match self {
+            Self::Active => &[DeliveryAction::Pause, DeliveryAction::Cancel],
+            Self::Paused => &[DeliveryAction::Resume, DeliveryAction::Cancel],
+            Self::Cancelled => &[],
+        }
+    }
+}
+
+impl TryFrom<String> for DeliveryStatus {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "active" => Ok(Self::Active),
+            "paused" => Ok(Self::Paused),
+            "cancelled" => Ok(Self::Cancelled),
+            other => Err(format!("{} is not a valid delivery status", other)),
+        }
+    }
+}
+
+impl DeliveryAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pause => "pause",
+            Self::Resume => "resume",
+            Self::Cancel => "cancel",
+        }
+    }
+
+    pub fn past_participle(&self) -> &'static str {
+        match self {
+            Self::Pause => "paused",
+            Self::Resume => "resumed",
+            Self::Cancel => "cancelled",
+        }
+    }
+}
+
+impl TryFrom<String> for DeliveryAction {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "pause" => Ok(Self::Pause),
+            "resume" => Ok(Self::Resume),
+            "cancel" => Ok(Self::Cancel),
+            other => Err(format!("{} is not a valid delivery action", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok_eq};
+
+    use crate::domain::{DeliveryAction, DeliveryStatus};
+
+    #[test]
+    fn an_active_delivery_can_be_paused_and_resumed() {
+        let status = DeliveryStatus::Active;
+        let status = status.apply(DeliveryAction::Pause).unwrap();
+        assert_eq!(status, DeliveryStatus::Paused);
+        assert_ok_eq!(
+            status.apply(DeliveryAction::Resume),
+            DeliveryStatus::Active
+        );
+    }
+
+    #[test]
+    fn active_and_paused_deliveries_can_be_cancelled() {
+        for status in [DeliveryStatus::Active, DeliveryStatus::Paused] {
+            assert_ok_eq!(
+                status.apply(DeliveryAction::Cancel),
+                DeliveryStatus::Cancelled
+            );
+        }
+    }
+
+    #[test]
+    fn a_cancelled_delivery_is_final() {
+        for action in [
+            DeliveryAction::Pause,
+            DeliveryAction::Resume,
+            DeliveryAction::Cancel,
+        ] {
+            assert_err!(DeliveryStatus::Cancelled.apply(action));
+        }
+    }
+
+    #[test]
+    fn an_active_delivery_can_not_be_resumed() {
+        assert_err!(DeliveryStatus::Active.apply(DeliveryAction::Resume));
+    }
+
+    #[test]
+    fn a_paused_delivery_can_not_be_paused_again() {
+        assert_err!(DeliveryStatus::Paused.apply(DeliveryAction::Pause));
+    }
+
+    #[test]
+    fn allowed_actions_are_consistent_with_transitions() {
+        for status in [
+            DeliveryStatus::Active,
+            DeliveryStatus::Paused,
+            DeliveryStatus::Cancelled,
+        ] {
+            for action in status.allowed_actions() {
+                assert!(status.apply(*action).is_ok());
+            }
+        }
+    }
+}
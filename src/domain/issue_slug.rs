@@ -0,0 +1,80 @@
+use uuid::Uuid;
+
+const MAX_TITLE_PART_LENGTH: usize = 64;
+
+#[derive(Debug)]
+pub struct IssueSlug(String);
+
+impl IssueSlug {
+    /// Build a URL-friendly slug from the issue title.
+    /// The first block of the issue id is appended to keep slugs unique
+    /// when two issues share the same title.
+    pub fn new(title: &str, issue_id: Uuid) -> Self {
+        let mut title_part = String::new();
+        for c in title.chars() {
+            if c.is_ascii_alphanumeric() {
+                title_part.push(c.to_ascii_lowercase());
+            } else if !title_part.is_empty() && !title_part.ends_with('-') {
+                title_part.push('-');
+            }
+            if title_part.len() >= MAX_TITLE_PART_LENGTH {
+                break;
+            }
+        }
+        let title_part = title_part.trim_end_matches('-');
+        let id_part = &issue_id.simple().to_string()[..8];
+
+        if title_part.is_empty() {
+            Self(id_part.to_string())
+        } else {
+            Self(format!("{}-{}", title_part, id_part))
+        }
+    }
+}
+
+impl AsRef<str> for IssueSlug {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for IssueSlug {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::domain::IssueSlug;
+
+    fn issue_id() -> Uuid {
+        Uuid::parse_str("1a2b3c4d-0000-4000-8000-000000000000").unwrap()
+    }
+
+    #[test]
+    fn title_is_lowercased_and_joined_with_dashes() {
+        let slug = IssueSlug::new("Our October Update", issue_id());
+        assert_eq!(slug.as_ref(), "our-october-update-1a2b3c4d");
+    }
+
+    #[test]
+    fn punctuation_is_collapsed_into_a_single_dash() {
+        let slug = IssueSlug::new("  What's new?! (v2.0)  ", issue_id());
+        assert_eq!(slug.as_ref(), "what-s-new-v2-0-1a2b3c4d");
+    }
+
+    #[test]
+    fn title_without_ascii_characters_falls_back_to_the_id() {
+        let slug = IssueSlug::new("ニュース", issue_id());
+        assert_eq!(slug.as_ref(), "1a2b3c4d");
+    }
+
+    #[test]
+    fn long_titles_are_truncated() {
+        let slug = IssueSlug::new(&"a".repeat(500), issue_id());
+        assert!(slug.as_ref().len() <= super::MAX_TITLE_PART_LENGTH + 9);
+    }
+}
@@ -0,0 +1,190 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueState {
+    Draft,
+    Submitted,
+    Approved,
+    Published,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueAction {
+    Submit,
+    Approve,
+    Reject,
+    Publish,
+}
+
+impl IssueState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Submitted => "submitted",
+            Self::Approved => "approved",
+            Self::Published => "published",
+        }
+    }
+
+    /// Only drafts can be edited: an issue has to go back to draft, and
+    /// through approval again, for its content to change.
+    pub fn is_editable(self) -> bool {
+        self == Self::Draft
+    }
+
+    /// The state reached by applying `action`, if the transition is allowed.
+    /// Rejecting a submitted or approved issue sends it back to draft.
+    pub fn apply(self, action: IssueAction) -> Result<Self, String> {
+        match (self, action) {
+            (Self::Draft, IssueAction::Submit) => Ok(Self::Submitted),
+            (Self::Submitted, IssueAction::Approve) => Ok(Self::Approved),
+            (Self::Submitted | Self::Approved, IssueAction::Reject) => {
+                Ok(Self::Draft)
+            }
+            (Self::Approved, IssueAction::Publish) => Ok(Self::Published),
+            (state, action) => Err(format!(
+                "A {} issue can't be {}.",
+                state.as_str(),
+                action.past_participle()
+            )),
+        }
+    }
+
+    pub fn allowed_actions(self) -> &'static [IssueAction] {
+        match self {
+            Self::Draft => &[IssueAction::Submit],
+            Self::Submitted => &[IssueAction::Approve, IssueAction::Reject],
+            Self::Approved => &[IssueAction::Publish, IssueAction::Reject],
+            Self::Published => &[],
+        }
+    }
+}
+
+impl TryFrom<String> for IssueState {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "draft" => Ok(Self::Draft),
+            "submitted" => Ok(Self::Submitted),
+            "approved" => Ok(Self::Approved),
+            "published" => Ok(Self::Published),
+            other => Err(format!("{} is not a valid issue state", other)),
+        }
+    }
+}
+
+impl IssueAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Submit => "submit",
+            Self::Approve => "approve",
+            Self::Reject => "reject",
+            Self::Publish => "publish",
+        }
+    }
+
+    pub fn past_participle(&self) -> &'static str {
+        match self {
+            Self::Submit => "submitted",
+            Self::Approve => "approved",
+            Self::Reject => "rejected",
+            Self::Publish => "published",
+        }
+    }
+}
+
+impl TryFrom<String> for IssueAction {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "submit" => Ok(Self::Submit),
+            "approve" => Ok(Self::Approve),
+            "reject" => Ok(Self::Reject),
+            "publish" => Ok(Self::Publish),
+            other => Err(format!("{} is not a valid issue action", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok_eq};
+
+    use crate::domain::{IssueAction, IssueState};
+
+    const STATES: [IssueState; 4] = [
+        IssueState::Draft,
+        IssueState::Submitted,
+        IssueState::Approved,
+        IssueState::Published,
+    ];
+
+    #[test]
+    fn states_round_trip_through_their_string_representation() {
+        for state in STATES {
+            assert_ok_eq!(
+                IssueState::try_from(state.as_str().to_string()),
+                state
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_states_are_rejected() {
+        assert_err!(IssueState::try_from("sent".to_string()));
+    }
+
+    #[test]
+    fn only_drafts_can_be_edited() {
+        for state in STATES {
+            assert_eq!(state.is_editable(), state == IssueState::Draft);
+        }
+    }
+
+    #[test]
+    fn an_issue_goes_through_approval_before_being_published() {
+        let state = IssueState::Draft.apply(IssueAction::Submit).unwrap();
+        let state = state.apply(IssueAction::Approve).unwrap();
+        assert_ok_eq!(state.apply(IssueAction::Publish), IssueState::Published);
+    }
+
+    #[test]
+    fn unapproved_issues_can_not_be_published() {
+        for state in [IssueState::Draft, IssueState::Submitted] {
+            assert_err!(state.apply(IssueAction::Publish));
+        }
+    }
+
+    #[test]
+    fn drafts_can_not_be_approved_without_being_submitted() {
+        assert_err!(IssueState::Draft.apply(IssueAction::Approve));
+    }
+
+    #[test]
+    fn rejected_issues_go_back_to_draft() {
+        for state in [IssueState::Submitted, IssueState::Approved] {
+            assert_ok_eq!(state.apply(IssueAction::Reject), IssueState::Draft);
+        }
+    }
+
+    #[test]
+    fn a_published_issue_is_final() {
+        for action in [
+            IssueAction::Submit,
+            IssueAction::Approve,
+            IssueAction::Reject,
+            IssueAction::Publish,
+        ] {
+            assert_err!(IssueState::Published.apply(action));
+        }
+    }
+
+    #[test]
+    fn allowed_actions_are_consistent_with_transitions() {
+        for state in STATES {
+            for action in state.allowed_actions() {
+                assert!(state.apply(*action).is_ok());
+            }
+        }
+    }
+}
@@ -1,12 +1,43 @@
+mod api_token;
+mod attachment_name;
+mod delivery_schedule;
+mod delivery_status;
+mod issue_slug;
+mod issue_state;
 mod new_subscriber;
+mod page_cursor;
 mod password;
+mod password_reset_token;
+mod role;
+mod secret_token;
+mod subject_test;
 mod subscriber_email;
 mod subscriber_name;
+mod subscriber_time_zone;
 mod subscription_token;
+mod username;
 
+pub use api_token::{ApiScope, ApiToken};
+pub use attachment_name::AttachmentName;
+pub use delivery_schedule::{
+    earliest_send_time, parse_time_of_day, QuietHours,
+};
+pub use delivery_status::{DeliveryAction, DeliveryStatus};
+pub use issue_slug::IssueSlug;
+pub use issue_state::{IssueAction, IssueState};
 pub use new_subscriber::NewSubscriber;
-pub use password::Password;
-pub use password::PasswordError;
+pub use page_cursor::PageCursor;
+pub use password::{
+    BreachedPasswords, Password, PasswordError, PasswordPolicy,
+};
+pub use password_reset_token::PasswordResetToken;
+pub use role::Role;
+pub(crate) use secret_token::hash_token;
+pub use subject_test::{
+    pick_winner, variant_label, SubjectTest, VariantResult, WinningMetric,
+};
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use subscriber_time_zone::SubscriberTimeZone;
 pub use subscription_token::SubscriptionToken;
+pub use username::Username;
@@ -1,7 +1,11 @@
 use crate::domain::SubscriberEmail;
 use crate::domain::SubscriberName;
+use crate::domain::SubscriberTimeZone;
 
 pub struct NewSubscriber {
     pub email: SubscriberEmail,
     pub name: SubscriberName,
+    /// Opt out of open and click tracking.
+    pub do_not_track: bool,
+    pub time_zone: Option<SubscriberTimeZone>,
 }
@@ -0,0 +1,77 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// Where the next page of a list ordered by creation time starts: the
+/// creation time and id of the last item of the previous page. Clients get
+/// it encoded, and should treat it as opaque.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageCursor {
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl PageCursor {
+    pub fn parse(cursor: &str) -> Result<Self, String> {
+        let error = || format!("{} is not a valid page cursor", cursor);
+        let decoded = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(error)?;
+        let (micros, id) = decoded.split_once(':').ok_or_else(error)?;
+        // Postgres keeps timestamps to the microsecond
+        let created_at = micros
+            .parse()
+            .ok()
+            .and_then(DateTime::from_timestamp_micros)
+            .ok_or_else(error)?;
+        let id = Uuid::parse_str(id).map_err(|_| error())?;
+        Ok(Self { created_at, id })
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!(
+            "{}:{}",
+            self.created_at.timestamp_micros(),
+            self.id
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use chrono::{DateTime, Utc};
+    use claims::assert_err;
+    use uuid::Uuid;
+
+    use crate::domain::PageCursor;
+
+    #[test]
+    fn cursors_round_trip_through_their_encoding() {
+        let cursor = PageCursor {
+            created_at: DateTime::<Utc>::from_timestamp_micros(
+                1_760_000_000_123_456,
+            )
+            .unwrap(),
+            id: Uuid::new_v4(),
+        };
+        assert_eq!(PageCursor::parse(&cursor.encode()), Ok(cursor));
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert_err!(PageCursor::parse(""));
+        assert_err!(PageCursor::parse("not a cursor!"));
+        assert_err!(PageCursor::parse(&URL_SAFE_NO_PAD.encode("123")));
+        assert_err!(PageCursor::parse(
+            &URL_SAFE_NO_PAD.encode("abc:not-a-uuid")
+        ));
+        assert_err!(PageCursor::parse(
+            &URL_SAFE_NO_PAD.encode(format!("abc:{}", Uuid::new_v4()))
+        ));
+    }
+}
@@ -1,19 +1,82 @@
 use secrecy::Zeroize;
+use sha1::{Digest, Sha1};
+use std::collections::{HashMap, HashSet};
+use unicode_segmentation::UnicodeSegmentation;
 
 pub struct Password(String);
 
+#[derive(Debug, PartialEq, Eq)]
 pub enum PasswordError {
-    TooShort,
-    TooLong,
+    TooShort { min_length: usize },
+    TooLong { max_length: usize },
+    ContainsUsername,
+    Breached,
+    TooWeak,
+}
+
+impl std::fmt::Display for PasswordError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooShort { min_length } => write!(
+                f,
+                "The new password must be at least {} characters long.",
+                min_length
+            ),
+            Self::TooLong { max_length } => write!(
+                f,
+                "The new password must be at most {} characters long.",
+                max_length
+            ),
+            Self::ContainsUsername => {
+                f.write_str("The new password must not contain the username.")
+            }
+            Self::Breached => f.write_str(
+                "The new password appears in a list of leaked passwords: \
+                please choose another one.",
+            ),
+            Self::TooWeak => f.write_str(
+                "The new password is too easy to guess: make it longer, \
+                or mix letters, digits and symbols.",
+            ),
+        }
+    }
+}
+
+pub struct PasswordPolicy {
+    /// In characters, as a user would count them.
+    pub min_length: usize,
+    pub max_length: usize,
+    /// See `estimate_strength_bits`.
+    pub min_strength_bits: u32,
+    pub breached_passwords: BreachedPasswords,
 }
 
 impl Password {
-    pub fn parse(s: String) -> Result<Password, PasswordError> {
-        let password_len = s.len();
-        if password_len < 12 {
-            return Err(PasswordError::TooShort);
-        } else if password_len > 128 {
-            return Err(PasswordError::TooLong);
+    pub fn parse(
+        s: String,
+        username: &str,
+        policy: &PasswordPolicy,
+    ) -> Result<Password, PasswordError> {
+        let password_len = s.graphemes(true).count();
+        if password_len < policy.min_length {
+            return Err(PasswordError::TooShort {
+                min_length: policy.min_length,
+            });
+        } else if password_len > policy.max_length {
+            return Err(PasswordError::TooLong {
+                max_length: policy.max_length,
+            });
+        }
+        if !username.is_empty()
+            && s.to_lowercase().contains(&username.to_lowercase())
+        {
+            return Err(PasswordError::ContainsUsername);
+        }
+        if policy.breached_passwords.contains(&s) {
+            return Err(PasswordError::Breached);
+        }
+        if estimate_strength_bits(&s) < f64::from(policy.min_strength_bits) {
+            return Err(PasswordError::TooWeak);
         }
         Ok(Password(s))
     }
@@ -31,3 +94,165 @@ impl Zeroize for Password {
         self.0 = "".to_string()
     }
 }
+
+/// SHA-1 hashes of leaked passwords, grouped by their first 5 hex digits
+/// like the k-anonymity ranges of Have I Been Pwned, so that the list can
+/// be kept locally instead of querying a remote service.
+#[derive(Debug, Default)]
+pub struct BreachedPasswords(HashMap<String, HashSet<String>>);
+
+impl BreachedPasswords {
+    /// One upper case hex SHA-1 hash per line, optionally followed by
+    /// `:<count>` as in Have I Been Pwned downloads. Lines starting with `#`
+    /// are ignored.
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut ranges: HashMap<String, HashSet<String>> = HashMap::new();
+        for line in list.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let hash = line.split(':').next().unwrap_or_default();
+            if hash.len() != 40 || !hash.chars().all(|c| c.is_ascii_hexdigit())
+            {
+                return Err(format!("{} is not a SHA-1 hash.", hash));
+            }
+            let hash = hash.to_ascii_uppercase();
+            let (prefix, suffix) = hash.split_at(5);
+            ranges
+                .entry(prefix.to_string())
+                .or_default()
+                .insert(suffix.to_string());
+        }
+        Ok(Self(ranges))
+    }
+
+    pub fn contains(&self, password: &str) -> bool {
+        let hash = format!("{:X}", Sha1::digest(password.as_bytes()));
+        let (prefix, suffix) = hash.split_at(5);
+        self.0
+            .get(prefix)
+            .is_some_and(|suffixes| suffixes.contains(suffix))
+    }
+}
+
+/// A rough estimate of how many guesses, as bits, it would take to find
+/// `password`. Each character is worth as many bits as the size of the
+/// character classes in use, but repeats and runs like `aaa` or `abc` are
+/// almost free and characters used before are worth less.
+fn estimate_strength_bits(password: &str) -> f64 {
+    let mut pool_size = 0;
+    if password.chars().any(|c| c.is_ascii_lowercase()) {
+        pool_size += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_uppercase()) {
+        pool_size += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_digit()) {
+        pool_size += 10;
+    }
+    if password
+        .chars()
+        .any(|c| c.is_ascii() && !c.is_ascii_alphanumeric())
+    {
+        pool_size += 33;
+    }
+    if !password.is_ascii() {
+        pool_size += 100;
+    }
+    if pool_size == 0 {
+        return 0.0;
+    }
+    let bits_per_character = f64::from(pool_size).log2();
+
+    let mut bits = 0.0;
+    let mut previous: Option<char> = None;
+    let mut seen = HashSet::new();
+    for c in password.chars() {
+        let lower = c.to_lowercase().to_string();
+        bits += match previous {
+            Some(p) if (p as i64 - c as i64).abs() <= 1 => 1.0,
+            _ if seen.contains(&lower) => bits_per_character / 4.0,
+            _ => bits_per_character,
+        };
+        seen.insert(lower);
+        previous = Some(c);
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        estimate_strength_bits, BreachedPasswords, Password, PasswordError,
+        PasswordPolicy,
+    };
+    use claims::{assert_err, assert_ok};
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 12,
+            max_length: 128,
+            min_strength_bits: 50,
+            breached_passwords: BreachedPasswords::parse(
+                // SHA-1 of "Password1234!", then of "qwertyuiop123"
+                "# Test list\n\
+                37804F97BD9984F61610A4D11B1D1FF312D8E15D\n\
+                D637E6EDAF4193FFCD807B5F60282A26FF72989B:42\n",
+            )
+            .unwrap(),
+        }
+    }
+
+    fn parse(password: &str) -> Result<Password, PasswordError> {
+        Password::parse(password.to_string(), "admin", &policy())
+    }
+
+    #[test]
+    fn length_is_counted_in_graphemes() {
+        // 22 bytes, but only 11 characters
+        assert_eq!(
+            parse(&"ë".repeat(11)).err(),
+            Some(PasswordError::TooShort { min_length: 12 })
+        );
+        assert_eq!(
+            parse(&"ë".repeat(129)).err(),
+            Some(PasswordError::TooLong { max_length: 128 })
+        );
+    }
+
+    #[test]
+    fn repeated_and_sequential_characters_are_too_weak() {
+        assert_eq!(parse("aaaaaaaaaaaa").err(), Some(PasswordError::TooWeak));
+        assert_eq!(parse("abcdefghijkl").err(), Some(PasswordError::TooWeak));
+        assert_eq!(parse("123456789012").err(), Some(PasswordError::TooWeak));
+    }
+
+    #[test]
+    fn varied_passwords_are_strong_enough() {
+        assert_ok!(parse("correct horse battery staple"));
+        assert_ok!(parse("2d640596-59cb-4fe0-b55b-8c357c9dabcb"));
+        assert_ok!(parse("ëlan vital à gogo"));
+        assert!(estimate_strength_bits("Tr0ub4dor&3xx") > 50.0);
+    }
+
+    #[test]
+    fn passwords_containing_the_username_are_rejected() {
+        assert_eq!(
+            parse("my-ADMIN-password-2026").err(),
+            Some(PasswordError::ContainsUsername)
+        );
+    }
+
+    #[test]
+    fn breached_passwords_are_rejected() {
+        let policy = policy();
+        assert!(policy.breached_passwords.contains("Password1234!"));
+        assert!(!policy.breached_passwords.contains("Password1234?"));
+        assert_eq!(parse("Password1234!").err(), Some(PasswordError::Breached));
+    }
+
+    #[test]
+    fn malformed_breached_password_lists_are_rejected() {
+        assert_err!(BreachedPasswords::parse("not-a-hash\n"));
+    }
+}
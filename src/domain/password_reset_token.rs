@@ -0,0 +1,76 @@
+use super::secret_token::{generate_token, hash_token, is_token_of_size};
+
+const PASSWORD_RESET_TOKEN_SIZE: usize = 40;
+
+pub struct PasswordResetToken(String);
+
+impl std::fmt::Debug for PasswordResetToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PasswordResetToken([REDACTED])")
+    }
+}
+
+impl PasswordResetToken {
+    pub fn parse(token: String) -> Result<Self, String> {
+        if is_token_of_size(&token, PASSWORD_RESET_TOKEN_SIZE) {
+            Ok(Self(token))
+        } else {
+            Err("This is not a valid password reset token".into())
+        }
+    }
+
+    pub fn generate() -> Self {
+        Self(generate_token(PASSWORD_RESET_TOKEN_SIZE))
+    }
+
+    /// What gets stored in the database, in place of the token itself.
+    pub fn hash(&self) -> String {
+        hash_token(&self.0)
+    }
+}
+
+impl AsRef<str> for PasswordResetToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use crate::domain::PasswordResetToken;
+
+    #[test]
+    fn generated_tokens_are_valid() {
+        let token = PasswordResetToken::generate();
+        assert_ok!(PasswordResetToken::parse(token.as_ref().to_string()));
+    }
+
+    #[test]
+    fn tokens_of_the_wrong_size_are_rejected() {
+        assert_err!(PasswordResetToken::parse("".to_string()));
+        assert_err!(PasswordResetToken::parse("a".repeat(41)));
+    }
+
+    #[test]
+    fn non_alphanumeric_tokens_are_rejected() {
+        assert_err!(PasswordResetToken::parse("é".repeat(20)));
+        assert_err!(PasswordResetToken::parse("-".repeat(40)));
+    }
+
+    #[test]
+    fn debug_output_does_not_reveal_the_token() {
+        let token = PasswordResetToken::generate();
+        assert!(!format!("{:?}", token).contains(token.as_ref()));
+    }
+
+    #[test]
+    fn the_hash_does_not_reveal_the_token() {
+        let token = PasswordResetToken::generate();
+        let hash = token.hash();
+        assert_eq!(hash.len(), 64);
+        assert!(!hash.contains(token.as_ref()));
+        assert_eq!(hash, token.hash());
+    }
+}
@@ -0,0 +1,60 @@
+/// Roles are ordered by privilege: each role can do everything the roles
+/// below it can.
+/// Viewers can browse issues and their reports, editors can also write,
+/// review and publish issues, owners can also manage users.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Viewer, Role::Editor, Role::Owner];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Viewer => "viewer",
+            Self::Editor => "editor",
+            Self::Owner => "owner",
+        }
+    }
+}
+
+impl TryFrom<String> for Role {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "viewer" => Ok(Self::Viewer),
+            "editor" => Ok(Self::Editor),
+            "owner" => Ok(Self::Owner),
+            other => Err(format!("{} is not a valid role", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::Role;
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn roles_round_trip_through_their_name() {
+        for role in Role::ALL {
+            assert_ok_eq!(Role::try_from(role.as_str().to_string()), role);
+        }
+    }
+
+    #[test]
+    fn unknown_roles_are_rejected() {
+        assert_err!(Role::try_from("admin".to_string()));
+        assert_err!(Role::try_from("Owner".to_string()));
+    }
+
+    #[test]
+    fn owners_can_do_what_editors_and_viewers_can() {
+        assert!(Role::Owner > Role::Editor);
+        assert!(Role::Editor > Role::Viewer);
+    }
+}
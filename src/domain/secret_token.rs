@@ -0,0 +1,25 @@
+//! Tokens handed out to users, which are only stored as a hash: leaking the
+//! database must not leak working tokens.
+use rand::distributions::Alphanumeric;
+use rand::thread_rng;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+/// `size` random alphanumeric characters.
+pub(crate) fn generate_token(size: usize) -> String {
+    let mut rng = thread_rng();
+    std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(size)
+        .collect()
+}
+
+/// Whether `token` looks like one made by `generate_token`.
+pub(crate) fn is_token_of_size(token: &str, size: usize) -> bool {
+    token.len() == size && token.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// What gets stored in the database, in place of the token itself.
+pub(crate) fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
@@ -0,0 +1,218 @@
+/// How the winning subject line of an A/B test is picked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WinningMetric {
+    Opens,
+    Clicks,
+}
+
+impl WinningMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Opens => "opens",
+            Self::Clicks => "clicks",
+        }
+    }
+}
+
+impl TryFrom<String> for WinningMetric {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "opens" => Ok(Self::Opens),
+            "clicks" => Ok(Self::Clicks),
+            other => Err(format!("{} is not a valid winning metric", other)),
+        }
+    }
+}
+
+/// Alternative subject lines sent to a sample of the audience before the
+/// best performing one goes to everyone else. The title of the issue is
+/// always variant 0, alternatives are numbered from 1.
+#[derive(Debug)]
+pub struct SubjectTest {
+    pub alternatives: Vec<String>,
+    pub sample_percent: i16,
+    pub winning_metric: WinningMetric,
+    pub wait_minutes: i32,
+}
+
+impl SubjectTest {
+    pub const MAX_ALTERNATIVES: usize = 4;
+    pub const MAX_WAIT_MINUTES: i32 = 7 * 24 * 60;
+
+    /// Parse the A/B test options of the publish form, one alternative
+    /// subject line per line. There is no test without alternatives.
+    pub fn parse(
+        alternatives: &str,
+        sample_percent: &str,
+        winning_metric: &str,
+        wait_minutes: &str,
+    ) -> Result<Option<Self>, String> {
+        let alternatives: Vec<String> = alternatives
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect();
+        if alternatives.is_empty() {
+            return Ok(None);
+        }
+        if alternatives.len() > Self::MAX_ALTERNATIVES {
+            return Err(format!(
+                "At most {} alternative subject lines can be tested",
+                Self::MAX_ALTERNATIVES
+            ));
+        }
+        let sample_percent = sample_percent
+            .trim()
+            .parse::<i16>()
+            .ok()
+            .filter(|p| (1..=100).contains(p))
+            .ok_or("The sample size must be a percentage between 1 and 100")?;
+        let wait_minutes = wait_minutes
+            .trim()
+            .parse::<i32>()
+            .ok()
+            .filter(|m| (1..=Self::MAX_WAIT_MINUTES).contains(m))
+            .ok_or(format!(
+                "The waiting period must be between 1 and {} minutes",
+                Self::MAX_WAIT_MINUTES
+            ))?;
+        let winning_metric =
+            WinningMetric::try_from(winning_metric.to_string())?;
+        Ok(Some(Self {
+            alternatives,
+            sample_percent,
+            winning_metric,
+            wait_minutes,
+        }))
+    }
+}
+
+/// A, B, C...
+pub fn variant_label(variant: i16) -> char {
+    char::from(b'A' + variant as u8)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct VariantResult {
+    pub variant: i16,
+    pub n_sent: i64,
+    pub n_converted: i64,
+}
+
+impl VariantResult {
+    pub fn rate(&self) -> f64 {
+        if self.n_sent > 0 {
+            self.n_converted as f64 / self.n_sent as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The variant with the best rate wins, ties go to the lowest variant so
+/// that the title of the issue wins when nothing could be measured.
+pub fn pick_winner(results: &[VariantResult]) -> i16 {
+    results
+        .iter()
+        .fold(None::<&VariantResult>, |best, result| match best {
+            Some(best)
+                if best.rate() > result.rate()
+                    || (best.rate() == result.rate()
+                        && best.variant < result.variant) =>
+            {
+                Some(best)
+            }
+            _ => Some(result),
+        })
+        .map(|r| r.variant)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_none, assert_ok};
+
+    use super::{
+        pick_winner, variant_label, SubjectTest, VariantResult, WinningMetric,
+    };
+
+    fn parse(alternatives: &str) -> Result<Option<SubjectTest>, String> {
+        SubjectTest::parse(alternatives, "20", "opens", "60")
+    }
+
+    #[test]
+    fn no_alternatives_means_no_test() {
+        assert_none!(parse(" \n\n ").unwrap());
+    }
+
+    #[test]
+    fn alternatives_are_read_one_per_line() {
+        let test = parse("First\r\n\n  Second  \n").unwrap().unwrap();
+        assert_eq!(test.alternatives, vec!["First", "Second"]);
+        assert_eq!(test.sample_percent, 20);
+        assert_eq!(test.winning_metric, WinningMetric::Opens);
+        assert_eq!(test.wait_minutes, 60);
+    }
+
+    #[test]
+    fn too_many_alternatives_are_rejected() {
+        assert_ok!(parse("1\n2\n3\n4"));
+        assert_err!(parse("1\n2\n3\n4\n5"));
+    }
+
+    #[test]
+    fn the_sample_must_be_a_percentage() {
+        for percent in ["0", "101", "-5", "", "half"] {
+            assert_err!(SubjectTest::parse("B", percent, "opens", "60"));
+        }
+        assert_ok!(SubjectTest::parse("B", "100", "clicks", "60"));
+    }
+
+    #[test]
+    fn the_waiting_period_is_bounded() {
+        for minutes in ["0", "10081", ""] {
+            assert_err!(SubjectTest::parse("B", "20", "opens", minutes));
+        }
+    }
+
+    #[test]
+    fn unknown_metrics_are_rejected() {
+        assert_err!(SubjectTest::parse("B", "20", "replies", "60"));
+    }
+
+    #[test]
+    fn variants_are_labelled_with_letters() {
+        assert_eq!(variant_label(0), 'A');
+        assert_eq!(variant_label(2), 'C');
+    }
+
+    fn result(variant: i16, n_sent: i64, n_converted: i64) -> VariantResult {
+        VariantResult {
+            variant,
+            n_sent,
+            n_converted,
+        }
+    }
+
+    #[test]
+    fn the_best_rate_wins() {
+        let results = [result(0, 10, 2), result(1, 9, 3), result(2, 10, 3)];
+        assert_eq!(pick_winner(&results), 1);
+    }
+
+    #[test]
+    fn ties_go_to_the_lowest_variant() {
+        let results = [result(0, 10, 0), result(1, 10, 0), result(2, 0, 0)];
+        assert_eq!(pick_winner(&results), 0);
+        let results = [result(0, 10, 1), result(1, 10, 5), result(2, 10, 5)];
+        assert_eq!(pick_winner(&results), 1);
+    }
+
+    #[test]
+    fn the_title_wins_without_results() {
+        assert_eq!(pick_winner(&[]), 0);
+    }
+}
@@ -0,0 +1,56 @@
+use chrono_tz::Tz;
+
+/// An IANA time zone name, such as `Europe/Lisbon`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriberTimeZone(Tz);
+
+impl SubscriberTimeZone {
+    pub fn parse(s: &str) -> Result<SubscriberTimeZone, String> {
+        s.trim()
+            .parse::<Tz>()
+            .map(Self)
+            .map_err(|_| format!("{} is not a valid time zone", s))
+    }
+
+    pub fn tz(&self) -> Tz {
+        self.0
+    }
+}
+
+impl AsRef<str> for SubscriberTimeZone {
+    fn as_ref(&self) -> &str {
+        self.0.name()
+    }
+}
+
+impl Default for SubscriberTimeZone {
+    fn default() -> Self {
+        Self(Tz::UTC)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::SubscriberTimeZone;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn iana_names_are_valid() {
+        for name in ["Europe/Lisbon", "America/New_York", "UTC", "Asia/Tokyo"] {
+            let time_zone = SubscriberTimeZone::parse(name).unwrap();
+            assert_eq!(time_zone.as_ref(), name);
+        }
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_ignored() {
+        assert_ok!(SubscriberTimeZone::parse(" Europe/Paris "));
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        for name in ["", "Europe/Atlantis", "GMT+25", "<script>"] {
+            assert_err!(SubscriberTimeZone::parse(name));
+        }
+    }
+}
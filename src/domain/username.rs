@@ -0,0 +1,71 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Allowed on top of letters and digits.
+const USERNAME_SYMBOLS: [char; 4] = ['.', '-', '_', '@'];
+
+#[derive(Debug)]
+pub struct Username(String);
+
+impl Username {
+    pub fn parse(s: String) -> Result<Username, String> {
+        let is_empty = s.is_empty();
+        let is_too_long = s.graphemes(true).count() > 64;
+        // Usernames are shown on many pages: keep out whitespace, control
+        // characters and anything with a meaning in HTML
+        let contains_forbidden_characters = s
+            .chars()
+            .any(|c| !(c.is_alphanumeric() || USERNAME_SYMBOLS.contains(&c)));
+
+        if is_empty || is_too_long || contains_forbidden_characters {
+            Err(format!("{} is not a valid username", s))
+        } else {
+            Ok(Self(s))
+        }
+    }
+}
+
+impl AsRef<str> for Username {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::Username;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn a_64_grapheme_long_username_is_valid() {
+        assert_ok!(Username::parse("ë".repeat(64)));
+    }
+
+    #[test]
+    fn a_username_longer_than_64_graphemes_is_rejected() {
+        assert_err!(Username::parse("a".repeat(65)));
+    }
+
+    #[test]
+    fn empty_string_is_rejected() {
+        assert_err!(Username::parse("".to_string()));
+    }
+
+    #[test]
+    fn usernames_containing_whitespace_are_rejected() {
+        for username in ["ursula le guin", " ursula", "ursula\t", "urs\nula"] {
+            assert_err!(Username::parse(username.to_string()));
+        }
+    }
+
+    #[test]
+    fn usernames_containing_markup_are_rejected() {
+        for username in ["<script>", "ursula&co", "\"ursula\"", "o'brien"] {
+            assert_err!(Username::parse(username.to_string()));
+        }
+    }
+
+    #[test]
+    fn a_valid_username_is_parsed_successfully() {
+        assert_ok!(Username::parse("ursula.le-guin".to_string()));
+    }
+}
@@ -1,7 +1,18 @@
 use crate::domain::SubscriberEmail;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use reqwest::Client;
 use secrecy::{ExposeSecret, Secret};
 
+/// A file sent along with an email.
+/// Inline images have a `content_id` and are referenced from the HTML body
+/// as `cid:<content_id>`.
+pub struct EmailAttachment {
+    pub name: String,
+    pub content_type: String,
+    pub content_id: Option<String>,
+    pub content: Vec<u8>,
+}
+
 pub struct EmailClient {
     http_client: Client,
     base_url: String,
@@ -30,6 +41,24 @@ impl EmailClient {
         subject: &str,
         html_content: &str,
         text_content: &str,
+    ) -> Result<(), reqwest::Error> {
+        self.send_email_with_attachments(
+            recipient,
+            subject,
+            html_content,
+            text_content,
+            &[],
+        )
+        .await
+    }
+
+    pub async fn send_email_with_attachments(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        attachments: &[EmailAttachment],
     ) -> Result<(), reqwest::Error> {
         let url = format!("{}/email", self.base_url);
         let request_body = SendEmailRequest {
@@ -38,6 +67,10 @@ impl EmailClient {
             subject,
             html_body: html_content,
             text_body: text_content,
+            attachments: attachments
+                .iter()
+                .map(AttachmentRequest::from)
+                .collect(),
         };
 
         self.http_client
@@ -62,6 +95,32 @@ struct SendEmailRequest<'a> {
     subject: &'a str,
     html_body: &'a str,
     text_body: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<AttachmentRequest<'a>>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct AttachmentRequest<'a> {
+    name: &'a str,
+    content: String,
+    content_type: &'a str,
+    #[serde(rename = "ContentID", skip_serializing_if = "Option::is_none")]
+    content_id: Option<String>,
+}
+
+impl<'a> From<&'a EmailAttachment> for AttachmentRequest<'a> {
+    fn from(attachment: &'a EmailAttachment) -> Self {
+        Self {
+            name: &attachment.name,
+            content: STANDARD.encode(&attachment.content),
+            content_type: &attachment.content_type,
+            content_id: attachment
+                .content_id
+                .as_ref()
+                .map(|content_id| format!("cid:{}", content_id)),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -80,7 +139,10 @@ mod tests {
         Mock, MockServer, ResponseTemplate,
     };
 
-    use crate::{domain::SubscriberEmail, email_client::EmailClient};
+    use crate::{
+        domain::SubscriberEmail,
+        email_client::{EmailAttachment, EmailClient},
+    };
 
     struct SendEmailBodyMatcher;
 
@@ -122,6 +184,63 @@ mod tests {
             .await;
     }
 
+    #[tokio::test]
+    async fn attachments_are_sent_base64_encoded() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let attachments = [
+            EmailAttachment {
+                name: "report.txt".into(),
+                content_type: "text/plain".into(),
+                content_id: None,
+                content: b"hello".to_vec(),
+            },
+            EmailAttachment {
+                name: "logo.png".into(),
+                content_type: "image/png".into(),
+                content_id: Some("logo.png".into()),
+                content: vec![0x89, 0x50],
+            },
+        ];
+        let outcome = email_client
+            .send_email_with_attachments(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                &attachments,
+            )
+            .await;
+
+        assert_ok!(outcome);
+        let request = &mock_server.received_requests().await.unwrap()[0];
+        let body: serde_json::Value =
+            serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(
+            body["Attachments"],
+            serde_json::json!([
+                {
+                    "Name": "report.txt",
+                    "Content": "aGVsbG8=",
+                    "ContentType": "text/plain",
+                },
+                {
+                    "Name": "logo.png",
+                    "Content": "iVA=",
+                    "ContentType": "image/png",
+                    "ContentID": "cid:logo.png",
+                },
+            ])
+        );
+    }
+
     #[tokio::test]
     async fn send_email_succeeds_if_the_server_returns_200() {
         let mock_server = MockServer::start().await;
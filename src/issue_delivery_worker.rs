@@ -1,19 +1,68 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Utc};
+use secrecy::Secret;
 use sqlx::{PgPool, Postgres, Transaction};
 use tracing::{field::display, Span};
 use uuid::Uuid;
 
 use crate::{
-    configuration::Settings, domain::SubscriberEmail,
-    email_client::EmailClient, startup::get_connection_pool,
+    configuration::{Settings, TrackingSettings},
+    domain::{
+        earliest_send_time, QuietHours, SubscriberEmail, SubscriberTimeZone,
+    },
+    email_client::{EmailAttachment, EmailClient},
+    startup::get_connection_pool,
+    subject_testing::{get_variant_subject, pick_subject_test_winners},
+    tracking::{
+        add_click_tracking, add_open_pixel, add_opt_out_link,
+        add_utm_parameters, OpenToken, UtmParameters,
+    },
 };
 
+/// What the worker needs to know to render an issue for a subscriber.
+pub struct DeliverySettings {
+    pub base_url: String,
+    pub hmac_secret: Secret<String>,
+    pub tracking: TrackingSettings,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl DeliverySettings {
+    pub fn new(configuration: &Settings) -> Self {
+        Self {
+            base_url: configuration.application.base_url.clone(),
+            hmac_secret: configuration.application.hmac_secret.clone(),
+            tracking: configuration.tracking.clone(),
+            quiet_hours: configuration
+                .schedule
+                .quiet_hours()
+                .expect("invalid quiet hours"),
+        }
+    }
+}
+
 pub enum ExecutionOutcome {
     TaskCompleted,
     EmptyQueue,
 }
 
+enum DeliveryOutcome {
+    Sent,
+    Failed,
+    InvalidAddress,
+}
+
+impl DeliveryOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sent => "sent",
+            Self::Failed => "failed",
+            Self::InvalidAddress => "invalid_address",
+        }
+    }
+}
+
 #[tracing::instrument(
     skip_all,
     fields(
@@ -24,33 +73,57 @@ pub enum ExecutionOutcome {
 pub async fn try_execute_task(
     pool: &PgPool,
     email_client: &EmailClient,
+    settings: &DeliverySettings,
 ) -> Result<ExecutionOutcome, anyhow::Error> {
-    if let Some((transaction, issue_id, email)) = dequeue_task(pool).await? {
+    if let Some((transaction, task)) = dequeue_task(pool).await? {
+        let DeliveryTask {
+            issue_id,
+            email,
+            subject_variant,
+        } = task;
         Span::current()
-            .record("newsletter_issue_id", &display(issue_id))
-            .record("subscriber_email", &display(&email));
-        match SubscriberEmail::parse(email.clone()) {
+            .record("newsletter_issue_id", display(issue_id))
+            .record("subscriber_email", display(&email));
+        let outcome = match SubscriberEmail::parse(email.clone()) {
             Ok(email) => {
                 let issue = get_issue(pool, issue_id).await?;
-                if let Err(e) = email_client
-                    .send_email(
+                let subject = match subject_variant {
+                    Some(variant) => {
+                        get_variant_subject(pool, issue_id, variant).await?
+                    }
+                    None => None,
+                }
+                .unwrap_or_else(|| issue.title.clone());
+                let attachments = get_attachments(pool, issue_id).await?;
+                let (html_content, text_content) =
+                    render_issue(pool, issue_id, &issue, &email, settings)
+                        .await?;
+                match email_client
+                    .send_email_with_attachments(
                         &email,
-                        &issue.title,
-                        &issue.html_content,
-                        &issue.text_content,
+                        &subject,
+                        &html_content,
+                        &text_content,
+                        &attachments,
                     )
                     .await
                 {
-                    // TODO: as an exercise implement retries logic
-                    // For example adding `n_retires` and `execute_after` colummns to `issue_delivery_type` table
-                    tracing::error!(error.cause_chain = ?e, error_message = %e, "Failed to deliver issue to a confirmed subscriber. Skipping.");
+                    Ok(()) => DeliveryOutcome::Sent,
+                    Err(e) => {
+                        // TODO: as an exercise implement retries logic
+                        // For example adding `n_retires` and `execute_after` colummns to `issue_delivery_type` table
+                        tracing::error!(error.cause_chain = ?e, error_message = %e, "Failed to deliver issue to a confirmed subscriber. Skipping.");
+                        DeliveryOutcome::Failed
+                    }
                 }
             }
             Err(e) => {
                 tracing::error!(error.cause_chain = ?e, error_message = %e, "Skipping a confirmed subscriber. Their stored contact details are invalid.");
+                DeliveryOutcome::InvalidAddress
             }
-        }
-        delete_task(transaction, issue_id, &email).await?;
+        };
+        complete_task(transaction, issue_id, &email, subject_variant, outcome)
+            .await?;
         Ok(ExecutionOutcome::TaskCompleted)
     } else {
         Ok(ExecutionOutcome::EmptyQueue)
@@ -59,38 +132,77 @@ pub async fn try_execute_task(
 
 type PgTransaction = Transaction<'static, Postgres>;
 
+struct DeliveryTask {
+    issue_id: Uuid,
+    email: String,
+    /// Only set for issues testing their subject line.
+    subject_variant: Option<i16>,
+}
+
+/// Only tasks of issues whose delivery is active and whose earliest send
+/// time has come are picked up.
+/// The issue row is share-locked until the task completes: pausing or
+/// cancelling a delivery waits for in-flight emails, and workers waiting on
+/// the lock re-check the status once the change is committed.
 #[tracing::instrument(skip_all)]
 async fn dequeue_task(
     pool: &PgPool,
-) -> Result<Option<(PgTransaction, Uuid, String)>, anyhow::Error> {
+) -> Result<Option<(PgTransaction, DeliveryTask)>, anyhow::Error> {
     let mut transaction = pool.begin().await?;
     let r = sqlx::query!(
         r#"
-        SELECT newsletter_issue_id, subscriber_email
-        FROM issue_delivery_queue
-        FOR UPDATE
-        SKIP LOCKED
+        SELECT q.newsletter_issue_id, q.subscriber_email, q.subject_variant
+        FROM issue_delivery_queue q
+        JOIN newsletter_issues i
+            ON i.newsletter_issue_id = q.newsletter_issue_id
+        WHERE
+            i.delivery_status = 'active' AND
+            q.execute_after <= now()
         LIMIT 1
+        FOR UPDATE OF q SKIP LOCKED
+        FOR SHARE OF i
         "#
     )
     .fetch_optional(&mut transaction)
     .await?;
     if let Some(r) = r {
-        Ok(Some((
-            transaction,
-            r.newsletter_issue_id,
-            r.subscriber_email,
-        )))
+        let task = DeliveryTask {
+            issue_id: r.newsletter_issue_id,
+            email: r.subscriber_email,
+            subject_variant: r.subject_variant,
+        };
+        Ok(Some((transaction, task)))
     } else {
         Ok(None)
     }
 }
 
-async fn delete_task(
+async fn complete_task(
     mut transaction: PgTransaction,
     issue_id: Uuid,
     email: &str,
+    subject_variant: Option<i16>,
+    outcome: DeliveryOutcome,
 ) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_log (
+            newsletter_issue_id,
+            subscriber_email,
+            outcome,
+            attempted_at,
+            subject_variant
+        )
+        VALUES ($1, $2, $3, now(), $4)
+        ON CONFLICT DO NOTHING
+        "#,
+        issue_id,
+        email,
+        outcome.as_str(),
+        subject_variant
+    )
+    .execute(&mut transaction)
+    .await?;
     sqlx::query!(
         r#"
         DELETE from issue_delivery_queue
@@ -107,10 +219,192 @@ async fn delete_task(
     Ok(())
 }
 
+/// Set the earliest send time of the tasks queued for an issue in the
+/// current transaction, following the local delivery time of the issue and
+/// the quiet hours in the time zone of each subscriber. Subscribers without
+/// a time zone are assumed to live in UTC.
+#[tracing::instrument(skip(transaction))]
+pub async fn schedule_deliveries(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    quiet_hours: Option<QuietHours>,
+) -> Result<(), sqlx::Error> {
+    let local_time = sqlx::query!(
+        r#"
+        SELECT deliver_at_local_time
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .deliver_at_local_time;
+    if local_time.is_none() && quiet_hours.is_none() {
+        return Ok(());
+    }
+    // Tasks queued in this transaction default to `now()`
+    let tasks = sqlx::query!(
+        r#"
+        SELECT q.subscriber_email, s.time_zone, now() AS "now!"
+        FROM issue_delivery_queue q
+        LEFT JOIN subscriptions s ON s.email = q.subscriber_email
+        WHERE
+            q.newsletter_issue_id = $1 AND
+            q.execute_after = now()
+        "#,
+        issue_id
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    let (emails, send_times): (Vec<String>, Vec<DateTime<Utc>>) = tasks
+        .into_iter()
+        .map(|task| {
+            let time_zone = task
+                .time_zone
+                .and_then(|tz| SubscriberTimeZone::parse(&tz).ok())
+                .unwrap_or_default();
+            let send_at = earliest_send_time(
+                task.now,
+                time_zone.tz(),
+                local_time,
+                quiet_hours,
+            );
+            (task.subscriber_email, send_at)
+        })
+        .unzip();
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue q
+        SET execute_after = t.execute_after
+        FROM UNNEST($2::text[], $3::timestamptz[])
+            AS t(subscriber_email, execute_after)
+        WHERE
+            q.newsletter_issue_id = $1 AND
+            q.subscriber_email = t.subscriber_email
+        "#,
+        issue_id,
+        &emails,
+        &send_times
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
 struct NewsletterIssue {
     title: String,
     text_content: String,
     html_content: String,
+    slug: String,
+    hidden_from_archive: bool,
+    track_clicks: bool,
+    track_opens: bool,
+    utm_source: Option<String>,
+    utm_medium: Option<String>,
+    utm_campaign: Option<String>,
+}
+
+impl NewsletterIssue {
+    fn utm_parameters(&self) -> Option<UtmParameters> {
+        Some(UtmParameters {
+            source: self.utm_source.clone()?,
+            medium: self.utm_medium.clone()?,
+            campaign: self.utm_campaign.clone()?,
+        })
+    }
+}
+
+struct Subscriber {
+    id: Uuid,
+    do_not_track: bool,
+}
+
+/// Personalise the issue content for a single subscriber.
+/// Subscribers who opted out of tracking receive untracked content.
+async fn render_issue(
+    pool: &PgPool,
+    issue_id: Uuid,
+    issue: &NewsletterIssue,
+    email: &SubscriberEmail,
+    settings: &DeliverySettings,
+) -> Result<(String, String), anyhow::Error> {
+    let (mut html_content, mut text_content) =
+        add_view_in_browser_link(issue, &settings.base_url);
+    // Tagged before click tracking hides the destination of the links
+    if let Some(utm) = issue.utm_parameters() {
+        (html_content, text_content) = add_utm_parameters(
+            &html_content,
+            &text_content,
+            &settings.base_url,
+            &utm,
+        );
+    }
+    let track_clicks =
+        settings.tracking.click_tracking_enabled && issue.track_clicks;
+    let track_opens =
+        settings.tracking.open_tracking_enabled && issue.track_opens;
+    if !track_clicks && !track_opens {
+        return Ok((html_content, text_content));
+    }
+    let subscriber = match get_subscriber(pool, email).await? {
+        Some(subscriber) if !subscriber.do_not_track => subscriber,
+        _ => return Ok((html_content, text_content)),
+    };
+
+    (html_content, text_content) = add_opt_out_link(
+        &html_content,
+        &text_content,
+        &settings.base_url,
+        subscriber.id,
+        &settings.hmac_secret,
+    );
+    if track_clicks {
+        html_content = add_click_tracking(
+            pool,
+            issue_id,
+            subscriber.id,
+            &html_content,
+            &settings.base_url,
+            &settings.hmac_secret,
+        )
+        .await?;
+    }
+    if track_opens {
+        let token = OpenToken {
+            issue_id,
+            subscriber_id: subscriber.id,
+        };
+        html_content = add_open_pixel(
+            &html_content,
+            &settings.base_url,
+            &token,
+            &settings.hmac_secret,
+        );
+    }
+    Ok((html_content, text_content))
+}
+
+/// Prepend a link to the archived copy of the issue to both bodies.
+/// Issues hidden from the archive are sent untouched, their archive page
+/// would return a 404.
+fn add_view_in_browser_link(
+    issue: &NewsletterIssue,
+    base_url: &str,
+) -> (String, String) {
+    if issue.hidden_from_archive {
+        return (issue.html_content.clone(), issue.text_content.clone());
+    }
+    let archive_url = format!("{}/archive/{}", base_url, issue.slug);
+    let html_content = format!(
+        "<p><a href=\"{}\">View this email in your browser</a></p>\n{}",
+        archive_url, issue.html_content
+    );
+    let text_content = format!(
+        "View this email in your browser: {}\n\n{}",
+        archive_url, issue.text_content
+    );
+    (html_content, text_content)
 }
 
 #[tracing::instrument(skip_all)]
@@ -121,7 +415,17 @@ async fn get_issue(
     let issue = sqlx::query_as!(
         NewsletterIssue,
         r#"
-        SELECT title, text_content, html_content
+        SELECT
+            title,
+            text_content,
+            html_content,
+            slug,
+            hidden_from_archive,
+            track_clicks,
+            track_opens,
+            utm_source,
+            utm_medium,
+            utm_campaign
         FROM newsletter_issues
         WHERE
             newsletter_issue_id = $1
@@ -133,14 +437,72 @@ async fn get_issue(
     Ok(issue)
 }
 
+#[tracing::instrument(skip_all)]
+async fn get_attachments(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<Vec<EmailAttachment>, anyhow::Error> {
+    let attachments = sqlx::query_as!(
+        EmailAttachment,
+        r#"
+        SELECT
+            file_name AS name,
+            content_type,
+            content_id,
+            content
+        FROM issue_attachments
+        WHERE newsletter_issue_id = $1
+        ORDER BY uploaded_at, file_name
+        "#,
+        issue_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(attachments)
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_subscriber(
+    pool: &PgPool,
+    email: &SubscriberEmail,
+) -> Result<Option<Subscriber>, anyhow::Error> {
+    let subscriber = sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT id, do_not_track
+        FROM subscriptions
+        WHERE email = $1
+        "#,
+        email.as_ref()
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(subscriber)
+}
+
+/// How often finished subject tests are looked for.
+const SUBJECT_TEST_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
 async fn worker_loop(
     pool: PgPool,
     email_client: EmailClient,
+    settings: DeliverySettings,
 ) -> Result<(), anyhow::Error> {
+    let mut last_winners_check: Option<Instant> = None;
     loop {
+        if last_winners_check
+            .is_none_or(|at| at.elapsed() >= SUBJECT_TEST_CHECK_INTERVAL)
+        {
+            if let Err(e) =
+                pick_subject_test_winners(&pool, settings.quiet_hours).await
+            {
+                tracing::error!(error.cause_chain = ?e, error_message = %e, "Failed to pick the winners of subject tests.");
+            }
+            last_winners_check = Some(Instant::now());
+        }
         // TODO: Differenciate between transient and fatal failures
         // wrong email format is fatal for example
-        match try_execute_task(&pool, &email_client).await {
+        match try_execute_task(&pool, &email_client, &settings).await {
             Ok(ExecutionOutcome::EmptyQueue) => {
                 tokio::time::sleep(Duration::from_secs(10)).await
             }
@@ -154,6 +516,7 @@ pub async fn run_worker_until_stopped(
     configuration: Settings,
 ) -> Result<(), anyhow::Error> {
     let connection_pool = get_connection_pool(&configuration.database);
+    let settings = DeliverySettings::new(&configuration);
     let email_client = configuration.email_client.client();
-    worker_loop(connection_pool, email_client).await
+    worker_loop(connection_pool, email_client, settings).await
 }
@@ -4,8 +4,12 @@ pub mod domain;
 pub mod email_client;
 pub mod idempotency;
 pub mod issue_delivery_worker;
+pub mod lint;
 pub mod routes;
 pub mod session_state;
+pub mod session_store;
 pub mod startup;
+pub mod subject_testing;
 pub mod telemetry;
+pub mod tracking;
 pub mod utils;
@@ -0,0 +1,317 @@
+//! Checks run on the content of an issue before it goes out.
+//! Errors block the issue, warnings are only reported to its author.
+
+use regex::Regex;
+use similar::TextDiff;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Gmail clips messages whose HTML is larger than this, in bytes.
+pub const GMAIL_CLIPPING_THRESHOLD: usize = 102 * 1024;
+
+/// Below this ratio the text version is unlikely to carry the same
+/// message as the HTML one.
+const MIN_TEXT_HTML_SIMILARITY: f32 = 0.5;
+
+/// Diffing two large and unrelated contents takes quadratic time, past
+/// this the diff gives up and reports whatever it found so far.
+const TEXT_HTML_DIFF_TIMEOUT: Duration = Duration::from_millis(200);
+
+const SPAM_PHRASES: [&str; 12] = [
+    "100% free",
+    "act now",
+    "buy now",
+    "cash bonus",
+    "click here",
+    "free money",
+    "guaranteed",
+    "limited time",
+    "no cost",
+    "risk-free",
+    "urgent",
+    "winner",
+];
+
+static IMG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)<img\b[^>]*>").unwrap());
+
+/// An empty `alt` marks a decorative image, only a missing one is reported.
+static ALT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\salt\s*=").unwrap());
+
+static LINK_TARGET: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)\s(href|src)\s*=\s*("([^"]*)"|'([^']*)')"#).unwrap()
+});
+
+static SCHEME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-zA-Z][a-zA-Z0-9+.-]*:").unwrap());
+
+static HIDDEN_BLOCK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)<(style|script|head)\b.*?</(style|script|head)>").unwrap()
+});
+
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+#[derive(Debug, Default)]
+pub struct LintReport {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+pub fn lint_issue(
+    title: &str,
+    html_content: &str,
+    text_content: &str,
+) -> LintReport {
+    let mut report = LintReport::default();
+    check_alt_text(html_content, &mut report);
+    check_relative_links(html_content, &mut report);
+    check_html_size(html_content, &mut report);
+    check_unsubscribe_link(html_content, text_content, &mut report);
+    check_title(title, &mut report);
+    check_text_html_divergence(html_content, text_content, &mut report);
+    report
+}
+
+fn check_alt_text(html_content: &str, report: &mut LintReport) {
+    let n_missing = IMG
+        .find_iter(html_content)
+        .filter(|img| !ALT.is_match(img.as_str()))
+        .count();
+    if n_missing > 0 {
+        report.warnings.push(format!(
+            "{} image(s) have no alt text, they will show as blank boxes \
+            when images are blocked.",
+            n_missing
+        ));
+    }
+}
+
+/// Emails have no base URL, relative links lead nowhere.
+fn check_relative_links(html_content: &str, report: &mut LintReport) {
+    for caps in LINK_TARGET.captures_iter(html_content) {
+        let target = caps
+            .get(3)
+            .or_else(|| caps.get(4))
+            .map(|m| m.as_str().trim())
+            .unwrap_or_default();
+        if target.is_empty() {
+            report.errors.push(format!(
+                "The {} attribute of a tag is empty.",
+                caps[1].to_lowercase()
+            ));
+        } else if !SCHEME.is_match(target)
+            && !target.starts_with('#')
+            && !target.starts_with("//")
+        {
+            report.errors.push(format!(
+                "The link to {} is relative, it won't work in an email. \
+                Use an absolute URL instead.",
+                htmlescape::encode_minimal(target)
+            ));
+        }
+    }
+}
+
+fn check_html_size(html_content: &str, report: &mut LintReport) {
+    if html_content.len() > GMAIL_CLIPPING_THRESHOLD {
+        report.warnings.push(format!(
+            "The HTML content is {} KB long, Gmail clips messages \
+            over {} KB.",
+            html_content.len().div_ceil(1024),
+            GMAIL_CLIPPING_THRESHOLD / 1024
+        ));
+    }
+}
+
+fn check_unsubscribe_link(
+    html_content: &str,
+    text_content: &str,
+    report: &mut LintReport,
+) {
+    let contains_unsubscribe =
+        |s: &str| s.to_lowercase().contains("unsubscribe");
+    if !contains_unsubscribe(html_content)
+        || !contains_unsubscribe(text_content)
+    {
+        report.warnings.push(
+            "Both the HTML and the text content should include an \
+            unsubscribe link."
+                .to_string(),
+        );
+    }
+}
+
+fn check_title(title: &str, report: &mut LintReport) {
+    let letters: Vec<char> =
+        title.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.len() >= 4 && letters.iter().all(|c| !c.is_lowercase()) {
+        report
+            .warnings
+            .push("The title is written in capital letters.".to_string());
+    }
+    if title.contains("!!") || title.contains("$$") {
+        report
+            .warnings
+            .push("The title repeats punctuation marks.".to_string());
+    }
+    let lowercase_title = title.to_lowercase();
+    for phrase in SPAM_PHRASES {
+        if lowercase_title.contains(phrase) {
+            report.warnings.push(format!(
+                "The title contains \"{}\", a phrase often flagged as spam.",
+                phrase
+            ));
+        }
+    }
+}
+
+fn check_text_html_divergence(
+    html_content: &str,
+    text_content: &str,
+    report: &mut LintReport,
+) {
+    let html_text = visible_text(html_content);
+    let text = normalize_whitespace(text_content);
+    if html_text.is_empty() && text.is_empty() {
+        return;
+    }
+    let similarity = TextDiff::configure()
+        .timeout(TEXT_HTML_DIFF_TIMEOUT)
+        .diff_words(&html_text, &text)
+        .ratio();
+    if similarity < MIN_TEXT_HTML_SIMILARITY {
+        report.warnings.push(format!(
+            "The text and HTML contents differ a lot, they are only {:.0}% \
+            similar.",
+            similarity * 100.
+        ));
+    }
+}
+
+/// The text a reader sees when the HTML is rendered, roughly.
+fn visible_text(html_content: &str) -> String {
+    let without_hidden = HIDDEN_BLOCK.replace_all(html_content, " ");
+    let without_tags = TAG.replace_all(&without_hidden, " ");
+    let decoded = htmlescape::decode_html(&without_tags)
+        .unwrap_or_else(|_| without_tags.into_owned());
+    normalize_whitespace(&decoded)
+}
+
+fn normalize_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::assert_none;
+    use std::time::{Duration, Instant};
+
+    use super::{lint_issue, LintReport, GMAIL_CLIPPING_THRESHOLD};
+
+    const TEXT: &str = "Hello there. Unsubscribe: https://example.com/u";
+    const HTML: &str = r#"<p>Hello there.</p><p><a href="https://example.com/u">Unsubscribe</a></p>"#;
+
+    fn contains(messages: &[String], fragment: &str) -> bool {
+        messages.iter().any(|m| m.contains(fragment))
+    }
+
+    fn lint_html(html: &str) -> LintReport {
+        lint_issue("Monthly news", html, TEXT)
+    }
+
+    #[test]
+    fn a_well_formed_issue_has_no_findings() {
+        let report = lint_issue("Monthly news", HTML, TEXT);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    }
+
+    #[test]
+    fn images_without_alt_text_are_reported() {
+        let html = format!(
+            r#"{HTML}<img src="https://example.com/a.png"><img alt="" src="https://example.com/b.png"><img alt="Logo" src="cid:logo.png">"#
+        );
+        let report = lint_html(&html);
+        assert!(contains(&report.warnings, "1 image(s) have no alt text"));
+        assert!(report.errors.is_empty());
+    }
+
+    #[test]
+    fn relative_links_are_errors() {
+        for target in ["/archive", "page.html", "../img.png", ""] {
+            let html = format!(r#"{HTML}<a href="{target}">Link</a>"#);
+            let report = lint_html(&html);
+            assert_eq!(report.errors.len(), 1, "{}", target);
+        }
+    }
+
+    #[test]
+    fn absolute_links_and_anchors_are_fine() {
+        for target in [
+            "https://example.com",
+            "mailto:me@example.com",
+            "cid:logo.png",
+            "#top",
+            "//example.com/img.png",
+        ] {
+            let html = format!(r#"{HTML}<a href='{target}'>Link</a>"#);
+            assert!(lint_html(&html).errors.is_empty(), "{}", target);
+        }
+    }
+
+    #[test]
+    fn html_over_the_gmail_clipping_threshold_is_reported() {
+        let padding = "<!-- -->".repeat(GMAIL_CLIPPING_THRESHOLD / 8);
+        let report = lint_html(&format!("{HTML}{padding}"));
+        assert!(contains(&report.warnings, "Gmail clips messages"));
+        assert!(!contains(&lint_html(HTML).warnings, "Gmail"));
+    }
+
+    #[test]
+    fn a_missing_unsubscribe_link_is_reported() {
+        let report = lint_issue("Monthly news", HTML, "Hello there.");
+        assert!(contains(&report.warnings, "unsubscribe link"));
+    }
+
+    #[test]
+    fn spammy_titles_are_reported() {
+        for title in ["MONTHLY NEWS", "Monthly news!!", "Act now: news"] {
+            let report = lint_issue(title, HTML, TEXT);
+            assert_eq!(report.warnings.len(), 1, "{}", title);
+        }
+    }
+
+    #[test]
+    fn short_acronyms_are_not_shouting() {
+        let report = lint_issue("AWS news", HTML, TEXT);
+        assert_none!(report.warnings.first());
+    }
+
+    #[test]
+    fn diverging_text_and_html_are_reported() {
+        let text = "Something completely different, with many other words \
+            nobody wrote in the HTML version. Unsubscribe";
+        let report = lint_issue("Monthly news", HTML, text);
+        assert!(contains(&report.warnings, "differ a lot"));
+    }
+
+    #[test]
+    fn markup_is_ignored_when_comparing_text_and_html() {
+        let html = format!(
+            "<html><head><style>p {{ color: red; }}</style></head><body>{HTML}</body></html>"
+        );
+        assert!(!contains(&lint_html(&html).warnings, "differ a lot"));
+    }
+
+    #[test]
+    fn large_diverging_contents_are_compared_in_bounded_time() {
+        let html: String =
+            (0..50_000).map(|i| format!("<p>html{i}</p>")).collect();
+        let text: String = (0..50_000).map(|i| format!("text{i} ")).collect();
+        let start = Instant::now();
+        let report = lint_issue("Monthly news", &html, &text);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(contains(&report.warnings, "differ a lot"));
+    }
+}
@@ -0,0 +1,164 @@
+use actix_web::{
+    http::header::ContentType,
+    web::{self, ReqData},
+    HttpResponse,
+};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::{
+    authentication::{list_api_tokens, CsrfToken, UserId},
+    domain::ApiScope,
+    utils::e500,
+};
+
+/// The lifetimes offered for new tokens, in days.
+pub const API_TOKEN_LIFETIMES_DAYS: [i64; 4] = [7, 30, 90, 365];
+
+#[tracing::instrument(
+    name = "List the API tokens of a user",
+    skip(user_id, pool, flash_messages, csrf_token)
+)]
+pub async fn api_tokens_list(
+    user_id: ReqData<UserId>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: CsrfToken,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let csrf_field = csrf_token.form_field();
+    let tokens = list_api_tokens(&pool, **user_id).await.map_err(e500)?;
+    let mut rows_html = String::new();
+    for token in &tokens {
+        let scopes: Vec<&str> =
+            token.scopes.iter().map(|s| s.as_str()).collect();
+        writeln!(
+            rows_html,
+            r#"<tr>
+                <td>{name}</td>
+                <td>{scopes}</td>
+                <td>{created_at}</td>
+                <td>{expires_at}</td>
+                <td>{last_used_at}</td>
+                <td>
+                    <form action="/admin/api-tokens/{api_token_id}/revoke" method="post">
+                        {csrf_field}
+                        <button type="submit">Revoke</button>
+                    </form>
+                </td>
+            </tr>"#,
+            name = htmlescape::encode_minimal(&token.name),
+            scopes = scopes.join(", "),
+            created_at = token.created_at.format("%Y-%m-%d %H:%M UTC"),
+            expires_at = token.expires_at.map_or("Never".to_string(), |at| {
+                at.format("%Y-%m-%d %H:%M UTC").to_string()
+            }),
+            last_used_at = token.last_used_at.map_or("Never".to_string(), |at| {
+                at.format("%Y-%m-%d %H:%M UTC").to_string()
+            }),
+            api_token_id = token.api_token_id,
+        )
+        .unwrap();
+    }
+    let tokens_html = if tokens.is_empty() {
+        "<p>You have no API tokens.</p>".to_string()
+    } else {
+        format!(
+            r#"<table>
+            <tr>
+                <th>Name</th>
+                <th>Scopes</th>
+                <th>Created at</th>
+                <th>Expires at</th>
+                <th>Last used at</th>
+                <th></th>
+            </tr>
+            {rows_html}
+        </table>"#
+        )
+    };
+
+    let mut scopes_html = String::new();
+    for scope in ApiScope::ALL {
+        writeln!(
+            scopes_html,
+            r#"<label><input type="checkbox" name="scope_{scope}" value="on"> {scope}</label>"#,
+            scope = scope.as_str(),
+        )
+        .unwrap();
+    }
+    let mut lifetimes_html = String::new();
+    for days in API_TOKEN_LIFETIMES_DAYS {
+        writeln!(
+            lifetimes_html,
+            r#"<option value="{days}"{selected}>{days} days</option>"#,
+            selected = if days == 30 { " selected" } else { "" },
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!doctype html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+        <title>API tokens</title>
+    </head>
+    <body>
+        {msg_html}
+        <p>Scripts can act on your behalf by sending an API token in an
+        <code>Authorization: Bearer</code> header. Tokens can't change your
+        account or credentials.</p>
+        {tokens_html}
+        <h2>New token</h2>
+        <form action="/admin/api-tokens" method="post">
+            {csrf_field}
+            <label>Name
+                <input type="text" placeholder="What the token is for" name="name">
+            </label>
+            <br>
+            <fieldset>
+                <legend>Scopes</legend>
+                {scopes_html}
+            </fieldset>
+            <label>Expires after
+                <select name="expires_in_days">
+                    {lifetimes_html}
+                    <option value="never">Never</option>
+                </select>
+            </label>
+            <br>
+            <button type="submit">Create token</button>
+        </form>
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>"#,
+        )))
+}
+
+/// Tokens are only shown once, right after they are created.
+pub fn new_api_token_page(token: &str) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!doctype html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+        <title>New API token</title>
+    </head>
+    <body>
+        <p>Copy your new API token now: it won't be shown again.</p>
+        <p><code>{token}</code></p>
+        <p><a href="/admin/api-tokens">Continue to your API tokens</a></p>
+    </body>
+</html>"#,
+        ))
+}
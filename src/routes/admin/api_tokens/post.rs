@@ -0,0 +1,99 @@
+use actix_web::{
+    web::{self, ReqData},
+    HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+use super::get::{new_api_token_page, API_TOKEN_LIFETIMES_DAYS};
+use crate::{
+    authentication::{create_api_token, revoke_api_token, UserId},
+    domain::ApiScope,
+    utils::{e404, e500, see_other},
+};
+
+const MAX_NAME_LENGTH: usize = 100;
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    name: String,
+    /// A number of days, or `never`.
+    expires_in_days: String,
+    // Checkboxes are only sent when checked
+    scope_read: Option<String>,
+    scope_write: Option<String>,
+    scope_publish: Option<String>,
+}
+
+#[tracing::instrument(
+    name = "Create an API token",
+    skip(user_id, form, pool),
+    fields(user_id = %*user_id)
+)]
+pub async fn create_user_api_token(
+    user_id: ReqData<UserId>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let form = form.0;
+    let name = form.name.trim();
+    if name.is_empty() || name.graphemes(true).count() > MAX_NAME_LENGTH {
+        return Ok(reject(&format!(
+            "The name of a token must be between 1 and {} characters long.",
+            MAX_NAME_LENGTH
+        )));
+    }
+    let scopes: Vec<ApiScope> = [
+        (ApiScope::Read, &form.scope_read),
+        (ApiScope::Write, &form.scope_write),
+        (ApiScope::Publish, &form.scope_publish),
+    ]
+    .into_iter()
+    .filter_map(|(scope, checked)| checked.as_ref().map(|_| scope))
+    .collect();
+    if scopes.is_empty() {
+        return Ok(reject("Choose at least one scope for the token."));
+    }
+    let expires_at = match form.expires_in_days.as_str() {
+        "never" => None,
+        days => match days.parse::<i64>() {
+            Ok(days) if API_TOKEN_LIFETIMES_DAYS.contains(&days) => {
+                Some(Utc::now() + Duration::days(days))
+            }
+            _ => return Ok(reject("This is not a valid token lifetime.")),
+        },
+    };
+
+    let token = create_api_token(&pool, **user_id, name, &scopes, expires_at)
+        .await
+        .map_err(e500)?;
+    Ok(new_api_token_page(token.as_ref()))
+}
+
+fn reject(message: &str) -> HttpResponse {
+    FlashMessage::error(message).send();
+    see_other("/admin/api-tokens")
+}
+
+#[tracing::instrument(
+    name = "Revoke an API token",
+    skip(user_id, pool),
+    fields(user_id = %*user_id)
+)]
+pub async fn revoke_user_api_token(
+    user_id: ReqData<UserId>,
+    api_token_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if !revoke_api_token(&pool, **user_id, api_token_id.into_inner())
+        .await
+        .map_err(e500)?
+    {
+        return Err(e404("The API token was not found."));
+    }
+    FlashMessage::info("The API token has been revoked.").send();
+    Ok(see_other("/admin/api-tokens"))
+}
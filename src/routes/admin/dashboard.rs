@@ -1,4 +1,4 @@
-use crate::authentication::UserId;
+use crate::authentication::{CsrfToken, UserId};
 use actix_web::{
     http::header::ContentType,
     web::{self, ReqData},
@@ -8,15 +8,31 @@ use anyhow::Context;
 use sqlx::PgPool;
 use uuid::Uuid;
 
+use crate::domain::Role;
 use crate::utils::e500;
 
 pub async fn admin_dashboard(
     user_id: ReqData<UserId>,
+    role: ReqData<Role>,
     pool: web::Data<PgPool>,
+    csrf_token: CsrfToken,
 ) -> Result<HttpResponse, actix_web::Error> {
     let username = get_username(*user_id.into_inner(), &pool)
         .await
         .map_err(e500)?;
+    let role = role.into_inner();
+    let mut actions_html = String::new();
+    if role >= Role::Editor {
+        actions_html.push_str(
+            r#"<li><a href="/admin/newsletters">Send a newsletter</a></li>"#,
+        );
+    }
+    actions_html
+        .push_str(r#"<li><a href="/admin/issues">Newsletter issues</a></li>"#);
+    if role >= Role::Owner {
+        actions_html
+            .push_str(r#"<li><a href="/admin/users">Manage users</a></li>"#);
+    }
     Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
         .body(format!(
@@ -30,14 +46,20 @@ pub async fn admin_dashboard(
     <p>Welcome {username}!</p>
     <p>Available actions:</p>
     <ol>
-    <li><a href="/admin/newsletters">Send a newsletter</a></li>
+    {actions_html}
     <li><a href="/admin/password">Change password</a></li>
+    <li><a href="/admin/two-factor">Two-factor authentication</a></li>
+    <li><a href="/admin/sessions">Active sessions</a></li>
+    <li><a href="/admin/api-tokens">API tokens</a></li>
     <form name="logoutForm" action="/admin/logout" method="post">
+        {csrf_field}
         <input type="submit" value="logout">
     </form>
     </ol>
     </body>
 </html>"#,
+            username = htmlescape::encode_minimal(&username),
+            csrf_field = csrf_token.form_field(),
         )))
 }
 
@@ -0,0 +1,166 @@
+use actix_web::{
+    http::header::ContentType,
+    web::{self, ReqData},
+    HttpResponse,
+};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::authentication::CsrfToken;
+use crate::configuration::PublishingSettings;
+use crate::domain::{IssueAction, IssueState, Role};
+use crate::routes::admin::issues::{
+    get_revisions, get_transitions, render_revisions, render_transitions,
+};
+use crate::utils::{capitalize, e404, e500, see_other};
+
+struct Draft {
+    title: String,
+    html_content: String,
+    text_content: String,
+    state: String,
+}
+
+/// Issues waiting for approval are shown read-only, along with the
+/// actions their reviewers can take. Viewers only get the read-only page.
+#[tracing::instrument(
+    name = "Show a draft",
+    skip(role, pool, publishing, flash_messages, csrf_token)
+)]
+pub async fn edit_draft_form(
+    issue_id: web::Path<Uuid>,
+    role: ReqData<Role>,
+    pool: web::Data<PgPool>,
+    publishing: web::Data<PublishingSettings>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: CsrfToken,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let can_edit = *role >= Role::Editor;
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let csrf_field = csrf_token.form_field();
+    let draft = get_draft(&pool, issue_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("This newsletter issue does not exist."))?;
+    let state: IssueState = draft.state.try_into().map_err(e500)?;
+    if state == IssueState::Published {
+        return Ok(see_other(&format!("/admin/issues/{}", issue_id)));
+    }
+    let editable = can_edit && state.is_editable();
+    let (readonly, save_html) = if editable {
+        ("", r#"<button type="submit">Save draft</button>"#)
+    } else {
+        (" readonly", "")
+    };
+    let mut actions_html = String::new();
+    if can_edit && !publishing.require_approval && state == IssueState::Draft {
+        writeln!(actions_html, "{}", publish_button(issue_id, &csrf_field))
+            .unwrap();
+    }
+    let allowed_actions = if can_edit {
+        state.allowed_actions()
+    } else {
+        &[]
+    };
+    for action in allowed_actions {
+        let button_html = match action {
+            IssueAction::Publish => publish_button(issue_id, &csrf_field),
+            action => format!(
+                r#"<form action="/admin/drafts/{issue_id}/review" method="post">
+                {csrf_field}
+                <input hidden type="text" name="action" value="{action}">
+                <button type="submit">{label}</button>
+            </form>"#,
+                action = action.as_str(),
+                label = capitalize(action.as_str()),
+            ),
+        };
+        writeln!(actions_html, "{}", button_html).unwrap();
+    }
+    let revisions = get_revisions(&pool, issue_id).await.map_err(e500)?;
+    let revisions_html =
+        render_revisions(issue_id, &revisions, editable, &csrf_field);
+    let transitions = get_transitions(&pool, issue_id).await.map_err(e500)?;
+    let transitions_html = render_transitions(&transitions);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!doctype html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+        <title>Edit a draft</title>
+    </head>
+    <body>
+        {msg_html}
+        <p>State: {state}</p>
+        <form action="/admin/drafts/{issue_id}" method="post">
+            {csrf_field}
+            <label
+                >Title
+                <input type="text" name="title" value="{title}"{readonly} />
+            </label>
+            <label
+                >HTML Content
+                <textarea name="html_content"{readonly}>{html_content}</textarea>
+            </label>
+            <label
+                >Text Content
+                <textarea name="text_content"{readonly}>{text_content}</textarea>
+            </label>
+            {save_html}
+        </form>
+        {actions_html}
+        <form action="/admin/issues/{issue_id}/duplicate" method="post">
+            {csrf_field}
+            <button type="submit">Duplicate</button>
+        </form>
+        {revisions_html}
+        {transitions_html}
+        <p><a href="/admin/issues">&lt;- Back</a></p>
+    </body>
+</html>"#,
+            state = capitalize(state.as_str()),
+            title = htmlescape::encode_minimal(&draft.title),
+            html_content = htmlescape::encode_minimal(&draft.html_content),
+            text_content = htmlescape::encode_minimal(&draft.text_content),
+        )))
+}
+
+fn publish_button(issue_id: Uuid, csrf_field: &str) -> String {
+    format!(
+        r#"<form action="/admin/drafts/{issue_id}/publish" method="post">
+                {csrf_field}
+                <button type="submit">Publish</button>
+            </form>"#
+    )
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_draft(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<Option<Draft>, anyhow::Error> {
+    let draft = sqlx::query_as!(
+        Draft,
+        r#"
+        SELECT title, html_content, text_content, state
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve a draft")?;
+    Ok(draft)
+}
@@ -0,0 +1,9 @@
+mod get;
+mod post;
+mod publish;
+mod review;
+
+pub use get::edit_draft_form;
+pub use post::{restore_revision, save_draft};
+pub use publish::{publish_draft, publish_issue, PublishOutcome};
+pub use review::review_issue;
@@ -0,0 +1,235 @@
+use actix_web::{
+    web::{self, ReqData},
+    HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    authentication::UserId,
+    domain::{IssueSlug, IssueState},
+    routes::admin::{
+        issues::record_revision,
+        newsletter::{send_warnings, validate_newsletter_issue},
+    },
+    utils::{e404, e500, see_other},
+};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    title: String,
+    html_content: String,
+    text_content: String,
+}
+
+#[tracing::instrument(
+    name = "Save a draft",
+    skip(form, pool, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn save_draft(
+    issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let draft_url = format!("/admin/drafts/{}", issue_id);
+    let warnings = match validate_newsletter_issue(
+        &form.title,
+        &form.html_content,
+        &form.text_content,
+    ) {
+        Ok(warnings) => warnings,
+        Err(violation_messages) => {
+            for message in violation_messages {
+                FlashMessage::error(message).send();
+            }
+            return Ok(see_other(&draft_url));
+        }
+    };
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let state = lock_issue(&mut transaction, issue_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("This newsletter issue does not exist."))?;
+    if !state.is_editable() {
+        FlashMessage::error(format!(
+            "A {} issue can't be edited.",
+            state.as_str()
+        ))
+        .send();
+        return Ok(see_other(&issue_url(issue_id, state)));
+    }
+    update_content(
+        &mut transaction,
+        issue_id,
+        &form.title,
+        &form.html_content,
+        &form.text_content,
+    )
+    .await
+    .map_err(e500)?;
+    record_revision(&mut transaction, issue_id, **user_id)
+        .await
+        .context("Failed to record a revision of the draft")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to save the draft")
+        .map_err(e500)?;
+
+    FlashMessage::info("The draft has been saved.").send();
+    send_warnings(warnings);
+    Ok(see_other(&draft_url))
+}
+
+/// Restoring a revision records it again as the latest one, history is
+/// never rewritten.
+#[tracing::instrument(
+    name = "Restore a revision",
+    skip(pool, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn restore_revision(
+    path: web::Path<(Uuid, i32)>,
+    pool: web::Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (issue_id, revision_number) = path.into_inner();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let state = lock_issue(&mut transaction, issue_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("This newsletter issue does not exist."))?;
+    if !state.is_editable() {
+        FlashMessage::error(format!(
+            "A {} issue can't be edited.",
+            state.as_str()
+        ))
+        .send();
+        return Ok(see_other(&issue_url(issue_id, state)));
+    }
+    let revision = sqlx::query!(
+        r#"
+        SELECT title, html_content, text_content
+        FROM issue_revisions
+        WHERE
+            newsletter_issue_id = $1 AND
+            revision_number = $2
+        "#,
+        issue_id,
+        revision_number
+    )
+    .fetch_optional(&mut transaction)
+    .await
+    .context("Failed to retrieve a revision")
+    .map_err(e500)?
+    .ok_or_else(|| e404("This revision does not exist."))?;
+    update_content(
+        &mut transaction,
+        issue_id,
+        &revision.title,
+        &revision.html_content,
+        &revision.text_content,
+    )
+    .await
+    .map_err(e500)?;
+    record_revision(&mut transaction, issue_id, **user_id)
+        .await
+        .context("Failed to record a revision of the draft")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to restore a revision")
+        .map_err(e500)?;
+
+    FlashMessage::info(format!(
+        "Revision {} has been restored.",
+        revision_number
+    ))
+    .send();
+    Ok(see_other(&format!("/admin/drafts/{}", issue_id)))
+}
+
+/// Published issues only have a report, the others are shown as drafts.
+fn issue_url(issue_id: Uuid, state: IssueState) -> String {
+    match state {
+        IssueState::Published => format!("/admin/issues/{}", issue_id),
+        _ => format!("/admin/drafts/{}", issue_id),
+    }
+}
+
+/// Lock the issue row for the rest of the transaction, so that a draft
+/// can't be published while it is being edited.
+#[tracing::instrument(skip(transaction))]
+pub async fn lock_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+) -> Result<Option<IssueState>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT state
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        FOR UPDATE
+        "#,
+        issue_id
+    )
+    .fetch_optional(transaction)
+    .await
+    .context("Failed to retrieve the state of an issue")?;
+    row.map(|r| IssueState::try_from(r.state))
+        .transpose()
+        .map_err(anyhow::Error::msg)
+}
+
+/// The slug follows the title until the issue is published.
+#[tracing::instrument(skip_all)]
+async fn update_content(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    title: &str,
+    html_content: &str,
+    text_content: &str,
+) -> Result<(), anyhow::Error> {
+    let slug = IssueSlug::new(title, issue_id);
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            title = $1,
+            html_content = $2,
+            text_content = $3,
+            slug = $4,
+            utm_campaign = CASE
+                WHEN utm_campaign = slug THEN $4
+                ELSE utm_campaign
+            END,
+            updated_at = now()
+        WHERE newsletter_issue_id = $5
+        "#,
+        title,
+        html_content,
+        text_content,
+        slug.as_ref(),
+        issue_id
+    )
+    .execute(transaction)
+    .await
+    .context("Failed to update the content of a draft")?;
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use actix_web::{
+    web::{self, ReqData},
+    HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{post::lock_issue, review::change_state};
+use crate::{
+    authentication::UserId,
+    configuration::{PublishingSettings, ScheduleSettings},
+    domain::{IssueAction, IssueState},
+    routes::admin::newsletter::{
+        enqueue_delivery_tasks, send_warnings, success_message,
+        validate_newsletter_issue,
+    },
+    utils::{e404, e500, see_other},
+};
+
+/// What came of an attempt to publish an existing issue.
+pub enum PublishOutcome {
+    /// Emails will go out, the lint warnings of the issue aside.
+    Published(Vec<String>),
+    NotFound,
+    AlreadyPublished,
+    NotApproved,
+    /// The content of the issue doesn't pass the checks.
+    Invalid(Vec<String>),
+}
+
+#[tracing::instrument(
+    name = "Publish a draft",
+    skip(pool, publishing, schedule, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn publish_draft(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    publishing: web::Data<PublishingSettings>,
+    schedule: web::Data<ScheduleSettings>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    match publish_issue(&pool, &publishing, &schedule, issue_id, **user_id)
+        .await
+        .map_err(e500)?
+    {
+        PublishOutcome::Published(warnings) => {
+            success_message().send();
+            send_warnings(warnings);
+            Ok(see_other(&format!("/admin/issues/{}", issue_id)))
+        }
+        PublishOutcome::NotFound => {
+            Err(e404("This newsletter issue does not exist."))
+        }
+        PublishOutcome::AlreadyPublished => {
+            FlashMessage::error("This issue has already been published.")
+                .send();
+            Ok(see_other(&format!("/admin/issues/{}", issue_id)))
+        }
+        PublishOutcome::NotApproved => {
+            FlashMessage::error("Only approved issues can be published.")
+                .send();
+            Ok(see_other(&format!("/admin/drafts/{}", issue_id)))
+        }
+        PublishOutcome::Invalid(violation_messages) => {
+            for message in violation_messages {
+                FlashMessage::error(message).send();
+            }
+            Ok(see_other(&format!("/admin/drafts/{}", issue_id)))
+        }
+    }
+}
+
+/// Publish an issue that was saved before, following the approval
+/// workflow when it is enabled.
+#[tracing::instrument(skip(pool, publishing, schedule))]
+pub async fn publish_issue(
+    pool: &PgPool,
+    publishing: &PublishingSettings,
+    schedule: &ScheduleSettings,
+    issue_id: Uuid,
+    user_id: Uuid,
+) -> Result<PublishOutcome, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let Some(state) = lock_issue(&mut transaction, issue_id).await? else {
+        return Ok(PublishOutcome::NotFound);
+    };
+    // Publishing twice must not send the issue twice
+    if state == IssueState::Published {
+        return Ok(PublishOutcome::AlreadyPublished);
+    }
+    if publishing.require_approval && state != IssueState::Approved {
+        return Ok(PublishOutcome::NotApproved);
+    }
+    // The content may predate the checks, or come from a restored revision
+    let content = sqlx::query!(
+        r#"
+        SELECT title, html_content, text_content
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_one(&mut transaction)
+    .await
+    .context("Failed to retrieve the content of an issue")?;
+    let warnings = match validate_newsletter_issue(
+        &content.title,
+        &content.html_content,
+        &content.text_content,
+    ) {
+        Ok(warnings) => warnings,
+        Err(violation_messages) => {
+            return Ok(PublishOutcome::Invalid(violation_messages));
+        }
+    };
+    // Without approval a draft is published directly
+    let next_state = match state {
+        IssueState::Draft => IssueState::Published,
+        state => state
+            .apply(IssueAction::Publish)
+            .map_err(anyhow::Error::msg)?,
+    };
+    change_state(&mut transaction, issue_id, state, next_state, user_id)
+        .await?;
+    let quiet_hours = schedule.quiet_hours().map_err(anyhow::Error::msg)?;
+    enqueue_delivery_tasks(&mut transaction, issue_id, quiet_hours)
+        .await
+        .context("Failed to enqueue delivery tasks")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to publish a draft")?;
+    Ok(PublishOutcome::Published(warnings))
+}
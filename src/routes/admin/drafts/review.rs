@@ -0,0 +1,149 @@
+use actix_web::{
+    web::{self, ReqData},
+    HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::post::lock_issue;
+use crate::{
+    authentication::UserId,
+    domain::{IssueAction, IssueState},
+    routes::admin::issues::record_transition,
+    utils::{e400, e404, e500, see_other},
+};
+
+#[derive(serde::Deserialize, Debug)]
+pub struct FormData {
+    action: String,
+}
+
+/// Move an issue through the approval workflow. Publishing has its own
+/// route, it is the only transition that sends emails.
+#[tracing::instrument(
+    name = "Review an issue",
+    skip(pool, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn review_issue(
+    issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let draft_url = format!("/admin/drafts/{}", issue_id);
+    let action =
+        IssueAction::try_from(form.into_inner().action).map_err(e400)?;
+    if action == IssueAction::Publish {
+        return Err(e400("Issues are published from their own route."));
+    }
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let state = lock_issue(&mut transaction, issue_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("This newsletter issue does not exist."))?;
+    let next_state = match state.apply(action) {
+        Ok(next_state) => next_state,
+        Err(message) => {
+            FlashMessage::error(message).send();
+            return Ok(see_other(&draft_url));
+        }
+    };
+    if action == IssueAction::Approve
+        && is_author(&mut transaction, issue_id, **user_id)
+            .await
+            .map_err(e500)?
+    {
+        FlashMessage::error(
+            "Issues must be approved by someone who did not write them.",
+        )
+        .send();
+        return Ok(see_other(&draft_url));
+    }
+    change_state(&mut transaction, issue_id, state, next_state, **user_id)
+        .await
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to change the state of an issue")
+        .map_err(e500)?;
+
+    FlashMessage::info(format!(
+        "The issue has been {}.",
+        action.past_participle()
+    ))
+    .send();
+    Ok(see_other(&draft_url))
+}
+
+/// Anyone who wrote a revision of the issue counts as one of its authors.
+#[tracing::instrument(skip(transaction))]
+async fn is_author(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    user_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM newsletter_issues
+            WHERE newsletter_issue_id = $1 AND created_by = $2
+            UNION ALL
+            SELECT 1
+            FROM issue_revisions
+            WHERE newsletter_issue_id = $1 AND created_by = $2
+        ) AS "is_author!"
+        "#,
+        issue_id,
+        user_id
+    )
+    .fetch_one(transaction)
+    .await
+    .context("Failed to check the authors of an issue")?;
+    Ok(row.is_author)
+}
+
+#[tracing::instrument(skip(transaction))]
+pub async fn change_state(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    from_state: IssueState,
+    to_state: IssueState,
+    user_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            state = $1,
+            published_at = CASE WHEN $1 = 'published' THEN now() END,
+            updated_at = now()
+        WHERE newsletter_issue_id = $2
+        "#,
+        to_state.as_str(),
+        issue_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to change the state of an issue")?;
+    record_transition(
+        transaction,
+        issue_id,
+        Some(from_state),
+        to_state,
+        user_id,
+    )
+    .await
+    .context("Failed to record the transition of an issue")?;
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::utils::{e404, e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    hidden: bool,
+}
+
+#[tracing::instrument(
+    name = "Change the archive visibility of an issue",
+    skip(form, pool)
+)]
+pub async fn set_archive_visibility(
+    issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            hidden_from_archive = $1,
+            updated_at = now()
+        WHERE newsletter_issue_id = $2
+        "#,
+        form.hidden,
+        *issue_id
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to update the archive visibility of an issue")
+    .map_err(e500)?
+    .rows_affected();
+    if n_updated_rows == 0 {
+        return Err(e404("This newsletter issue does not exist."));
+    }
+
+    if form.hidden {
+        FlashMessage::info("The issue has been hidden from the archive.")
+            .send();
+    } else {
+        FlashMessage::info("The issue is now visible in the archive.").send();
+    }
+    Ok(see_other("/admin/issues"))
+}
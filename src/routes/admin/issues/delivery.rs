@@ -0,0 +1,124 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::{DeliveryAction, DeliveryStatus};
+use crate::utils::{e400, e404, e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    action: String,
+}
+
+#[tracing::instrument(
+    name = "Change the delivery status of an issue",
+    skip(form, pool),
+    fields(action = %form.action)
+)]
+pub async fn change_delivery_status(
+    issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let action: DeliveryAction = form.0.action.try_into().map_err(e400)?;
+    let report_url = format!("/admin/issues/{}", issue_id);
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let current_status = get_delivery_status(&mut transaction, issue_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("This newsletter issue does not exist."))?;
+    let new_status = match current_status.apply(action) {
+        Ok(status) => status,
+        Err(message) => {
+            FlashMessage::error(message).send();
+            return Ok(see_other(&report_url));
+        }
+    };
+    update_delivery_status(&mut transaction, issue_id, new_status)
+        .await
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the delivery status change")
+        .map_err(e500)?;
+
+    FlashMessage::info(format!(
+        "Delivery of the issue has been {}.",
+        action.past_participle()
+    ))
+    .send();
+    Ok(see_other(&report_url))
+}
+
+/// Lock the issue row: the lock is granted once in-flight deliveries of the
+/// issue are done, so the report is accurate as soon as the change commits.
+#[tracing::instrument(skip(transaction))]
+async fn get_delivery_status(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+) -> Result<Option<DeliveryStatus>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT delivery_status
+        FROM newsletter_issues
+        WHERE
+            newsletter_issue_id = $1 AND
+            state = 'published'
+        FOR UPDATE
+        "#,
+        issue_id
+    )
+    .fetch_optional(transaction)
+    .await
+    .context("Failed to retrieve the delivery status of an issue")?;
+    match row {
+        Some(row) => Ok(Some(
+            row.delivery_status.try_into().map_err(anyhow::Error::msg)?,
+        )),
+        None => Ok(None),
+    }
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn update_delivery_status(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    status: DeliveryStatus,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            delivery_status = $1,
+            updated_at = now()
+        WHERE newsletter_issue_id = $2
+        "#,
+        status.as_str(),
+        issue_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to update the delivery status of an issue")?;
+    if status == DeliveryStatus::Cancelled {
+        sqlx::query!(
+            r#"
+            DELETE FROM issue_delivery_queue
+            WHERE newsletter_issue_id = $1
+            "#,
+            issue_id
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to drop the pending deliveries of an issue")?;
+    }
+    Ok(())
+}
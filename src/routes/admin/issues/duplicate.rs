@@ -0,0 +1,170 @@
+use actix_web::{
+    web::{self, ReqData},
+    HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::revisions::record_revision;
+use super::transitions::record_transition;
+use crate::{
+    authentication::UserId,
+    domain::{IssueSlug, IssueState},
+    subject_testing::copy_subject_test,
+    utils::{e404, e500, see_other},
+};
+
+/// Start a new draft from the content, options and attachments of any
+/// issue. A UTM campaign defaulting to the slug of the original issue
+/// follows the slug of the copy.
+#[tracing::instrument(
+    name = "Duplicate an issue",
+    skip(pool, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn duplicate_issue(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let copy_id = copy_issue(&mut transaction, *issue_id, **user_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("This newsletter issue does not exist."))?;
+    record_revision(&mut transaction, copy_id, **user_id)
+        .await
+        .context("Failed to record the first revision of the copy")
+        .map_err(e500)?;
+    record_transition(
+        &mut transaction,
+        copy_id,
+        None,
+        IssueState::Draft,
+        **user_id,
+    )
+    .await
+    .context("Failed to record the creation of the copy")
+    .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to store the copy of an issue")
+        .map_err(e500)?;
+
+    FlashMessage::info("A new draft has been created from the issue.").send();
+    Ok(see_other(&format!("/admin/drafts/{}", copy_id)))
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn copy_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    author_id: Uuid,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    let title = sqlx::query!(
+        "SELECT title FROM newsletter_issues WHERE newsletter_issue_id = $1",
+        issue_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to retrieve the issue to duplicate")?;
+    let title = match title {
+        Some(row) => row.title,
+        None => return Ok(None),
+    };
+    let copy_id = Uuid::new_v4();
+    let slug = IssueSlug::new(&title, copy_id);
+
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id,
+            title,
+            text_content,
+            html_content,
+            published_at,
+            updated_at,
+            slug,
+            hidden_from_archive,
+            created_by,
+            track_clicks,
+            track_opens,
+            utm_source,
+            utm_medium,
+            utm_campaign,
+            state,
+            deliver_at_local_time
+        )
+        SELECT
+            $1,
+            title,
+            text_content,
+            html_content,
+            NULL,
+            now(),
+            $2,
+            hidden_from_archive,
+            $3,
+            track_clicks,
+            track_opens,
+            utm_source,
+            utm_medium,
+            CASE WHEN utm_campaign = slug THEN $2 ELSE utm_campaign END,
+            'draft',
+            deliver_at_local_time
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $4
+        "#,
+        copy_id,
+        slug.as_ref(),
+        author_id,
+        issue_id,
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to copy an issue")?;
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_attachments (
+            attachment_id,
+            newsletter_issue_id,
+            uploaded_by,
+            file_name,
+            content_type,
+            content_id,
+            content,
+            size,
+            uploaded_at
+        )
+        SELECT
+            gen_random_uuid(),
+            $1,
+            $2,
+            file_name,
+            content_type,
+            content_id,
+            content,
+            size,
+            now()
+        FROM issue_attachments
+        WHERE newsletter_issue_id = $3
+        "#,
+        copy_id,
+        author_id,
+        issue_id,
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to copy the attachments of an issue")?;
+    copy_subject_test(transaction, issue_id, copy_id)
+        .await
+        .context("Failed to copy the subject test of an issue")?;
+    Ok(Some(copy_id))
+}
@@ -0,0 +1,122 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::authentication::CsrfToken;
+use crate::utils::{capitalize, e500};
+
+struct IssueSummary {
+    newsletter_issue_id: Uuid,
+    title: String,
+    published_at: Option<DateTime<Utc>>,
+    state: String,
+    hidden_from_archive: bool,
+    author: Option<String>,
+}
+
+pub async fn issues_list(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: CsrfToken,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let csrf_field = csrf_token.form_field();
+    let issues = get_issues(&pool).await.map_err(e500)?;
+    let mut rows_html = String::new();
+    for issue in issues {
+        let (visibility, action_label, hidden) = if issue.hidden_from_archive {
+            ("Hidden", "Show in archive", false)
+        } else {
+            ("Public", "Hide from archive", true)
+        };
+        let (issue_url, published_at) = match issue.published_at {
+            Some(published_at) => (
+                format!("/admin/issues/{}", issue.newsletter_issue_id),
+                published_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+            ),
+            None => (
+                format!("/admin/drafts/{}", issue.newsletter_issue_id),
+                capitalize(&issue.state),
+            ),
+        };
+        writeln!(
+            rows_html,
+            r#"<tr>
+                <td><a href="{issue_url}">{title}</a></td>
+                <td>{published_at}</td>
+                <td>{author}</td>
+                <td>{visibility}</td>
+                <td>
+                    <form action="/admin/issues/{issue_id}/archive" method="post">
+                        {csrf_field}
+                        <input hidden type="text" name="hidden" value="{hidden}">
+                        <button type="submit">{action_label}</button>
+                    </form>
+                </td>
+            </tr>"#,
+            title = htmlescape::encode_minimal(&issue.title),
+            author = htmlescape::encode_minimal(
+                issue.author.as_deref().unwrap_or("-")
+            ),
+            issue_id = issue.newsletter_issue_id,
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!doctype html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+        <title>Newsletter issues</title>
+    </head>
+    <body>
+        {msg_html}
+        <table>
+            <tr>
+                <th>Title</th>
+                <th>Published at</th>
+                <th>Author</th>
+                <th>Archive</th>
+                <th></th>
+            </tr>
+            {rows_html}
+        </table>
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>"#,
+        )))
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_issues(pool: &PgPool) -> Result<Vec<IssueSummary>, anyhow::Error> {
+    let issues = sqlx::query_as!(
+        IssueSummary,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            published_at,
+            state,
+            hidden_from_archive,
+            users.username AS "author?"
+        FROM newsletter_issues
+        LEFT JOIN users ON users.user_id = newsletter_issues.created_by
+        ORDER BY published_at DESC NULLS FIRST, updated_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve newsletter issues")?;
+    Ok(issues)
+}
@@ -0,0 +1,17 @@
+mod archive;
+mod delivery;
+mod duplicate;
+mod get;
+mod report;
+mod revisions;
+mod transitions;
+
+pub use archive::set_archive_visibility;
+pub use delivery::change_delivery_status;
+pub use duplicate::duplicate_issue;
+pub use get::issues_list;
+pub use report::issue_report;
+pub use revisions::{
+    get_revisions, record_revision, render_revisions, revision_diff,
+};
+pub use transitions::{get_transitions, record_transition, render_transitions};
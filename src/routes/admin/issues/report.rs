@@ -0,0 +1,364 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use super::revisions::{get_revisions, render_revisions};
+use super::transitions::{get_transitions, render_transitions};
+use crate::authentication::CsrfToken;
+use crate::domain::{variant_label, DeliveryStatus};
+use crate::subject_testing::{get_subject_test, SubjectTestSummary};
+use crate::utils::{capitalize, e404, e500};
+
+struct IssueReport {
+    title: String,
+    slug: String,
+    published_at: DateTime<Utc>,
+    delivery_status: String,
+    n_recipients: i32,
+    n_sent: i64,
+    n_failed: i64,
+    n_pending: i64,
+    track_clicks: bool,
+    track_opens: bool,
+}
+
+struct OpenStatistics {
+    n_unique_opens: i64,
+    n_opens: i64,
+}
+
+struct LinkStatistics {
+    url: String,
+    n_clicks: i64,
+    n_unique_clicks: i64,
+}
+
+#[tracing::instrument(
+    name = "Show an issue report",
+    skip(pool, flash_messages, csrf_token)
+)]
+pub async fn issue_report(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: CsrfToken,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let csrf_field = csrf_token.form_field();
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let report = get_issue_report(&pool, issue_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("This newsletter issue does not exist."))?;
+    let status: DeliveryStatus =
+        report.delivery_status.try_into().map_err(e500)?;
+    let subject_test = get_subject_test(&pool, issue_id).await.map_err(e500)?;
+    // Recipients held back by a running subject test are still to be sent
+    let n_waiting = match &subject_test {
+        Some(t) if t.winning_variant.is_none() => t.n_held,
+        _ => 0,
+    };
+    let n_not_sent = i64::from(report.n_recipients)
+        - report.n_sent
+        - report.n_failed
+        - report.n_pending
+        - n_waiting;
+    let subject_test_html = subject_test
+        .as_ref()
+        .map(render_subject_test)
+        .unwrap_or_default();
+
+    let clicks_html = if report.track_clicks {
+        let links = get_link_statistics(&pool, issue_id).await.map_err(e500)?;
+        let n_clickers =
+            count_unique_clickers(&pool, issue_id).await.map_err(e500)?;
+        render_click_statistics(&links, n_clickers, report.n_sent)
+    } else {
+        "<p>Click tracking is disabled for this issue.</p>".to_string()
+    };
+
+    let opens_html = if report.track_opens {
+        let opens = get_open_statistics(&pool, issue_id).await.map_err(e500)?;
+        render_open_statistics(&opens, report.n_sent)
+    } else {
+        "<p>Open tracking is disabled for this issue.</p>".to_string()
+    };
+
+    let revisions = get_revisions(&pool, issue_id).await.map_err(e500)?;
+    let revisions_html =
+        render_revisions(issue_id, &revisions, false, &csrf_field);
+    let transitions = get_transitions(&pool, issue_id).await.map_err(e500)?;
+    let transitions_html = render_transitions(&transitions);
+
+    let mut actions_html = String::new();
+    for action in status.allowed_actions() {
+        writeln!(
+            actions_html,
+            r#"<form action="/admin/issues/{issue_id}/delivery" method="post">
+                {csrf_field}
+                <input hidden type="text" name="action" value="{action}">
+                <button type="submit">{label}</button>
+            </form>"#,
+            action = action.as_str(),
+            label = capitalize(action.as_str()),
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(format!(
+        r#"<!doctype html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+        <title>Issue report</title>
+    </head>
+    <body>
+        {msg_html}
+        <h1>{title}</h1>
+        <p>Published on {published_at} - <a href="/archive/{slug}">archived copy</a></p>
+        <p>Delivery status: {status}</p>
+        <table>
+            <tr><td>Recipients</td><td>{n_recipients}</td></tr>
+            <tr><td>Sent</td><td>{n_sent}</td></tr>
+            <tr><td>Failed</td><td>{n_failed}</td></tr>
+            <tr><td>Pending</td><td>{n_pending}</td></tr>
+            <tr><td>Not sent</td><td>{n_not_sent}</td></tr>
+        </table>
+        {subject_test_html}
+        {opens_html}
+        {clicks_html}
+        {actions_html}
+        <form action="/admin/issues/{issue_id}/duplicate" method="post">
+            {csrf_field}
+            <button type="submit">Duplicate</button>
+        </form>
+        {revisions_html}
+        {transitions_html}
+        <p><a href="/admin/issues">&lt;- Back</a></p>
+    </body>
+</html>"#,
+        title = htmlescape::encode_minimal(&report.title),
+        published_at = report.published_at.format("%Y-%m-%d %H:%M UTC"),
+        slug = report.slug,
+        status = status.as_str(),
+        n_recipients = report.n_recipients,
+        n_sent = report.n_sent,
+        n_failed = report.n_failed,
+        n_pending = report.n_pending,
+    )))
+}
+
+fn render_subject_test(subject_test: &SubjectTestSummary) -> String {
+    let mut rows_html = String::new();
+    for (subject, result) in
+        subject_test.subjects.iter().zip(&subject_test.results)
+    {
+        writeln!(
+            rows_html,
+            "<tr><td>{label}</td><td>{subject}</td><td>{n_sent}</td><td>{n_converted}</td><td>{rate:.1}%</td></tr>",
+            label = variant_label(result.variant),
+            subject = htmlescape::encode_minimal(subject),
+            n_sent = result.n_sent,
+            n_converted = result.n_converted,
+            rate = 100.0 * result.rate(),
+        )
+        .unwrap();
+    }
+    let metric = subject_test.winning_metric.as_str();
+    let outcome = match (subject_test.winning_variant, subject_test.decide_after)
+    {
+        (Some(winner), _) => format!(
+            "<p>Winner: variant {}, sent to the remaining {} recipients</p>",
+            variant_label(winner),
+            subject_test.n_held
+        ),
+        (None, Some(decide_after)) => format!(
+            "<p>The winner will be picked after {}, {} recipients are waiting for it</p>",
+            decide_after.format("%Y-%m-%d %H:%M UTC"),
+            subject_test.n_held
+        ),
+        (None, None) => String::new(),
+    };
+    format!(
+        r#"<h2>Subject line test</h2>
+        <p>Sample: {sample_percent}% of the recipients, winner picked by {metric}</p>
+        <table>
+            <tr><th>Variant</th><th>Subject</th><th>Sent</th><th>Unique {metric}</th><th>Rate</th></tr>
+            {rows_html}
+        </table>
+        {outcome}"#,
+        sample_percent = subject_test.sample_percent,
+    )
+}
+
+/// Opens are approximate: images blocked by the reader go unnoticed and
+/// requests that look like prefetches are not counted.
+fn render_open_statistics(opens: &OpenStatistics, n_sent: i64) -> String {
+    format!(
+        r#"<h2>Opens</h2>
+        <p>Unique opens: {n_unique_opens} ({open_rate:.1}% of sent emails)</p>
+        <p>Total opens: {n_opens}</p>"#,
+        n_unique_opens = opens.n_unique_opens,
+        open_rate = percentage(opens.n_unique_opens, n_sent),
+        n_opens = opens.n_opens,
+    )
+}
+
+fn render_click_statistics(
+    links: &[LinkStatistics],
+    n_clickers: i64,
+    n_sent: i64,
+) -> String {
+    let click_rate = percentage(n_clickers, n_sent);
+    let mut rows_html = String::new();
+    for link in links {
+        writeln!(
+            rows_html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+            htmlescape::encode_minimal(&link.url),
+            link.n_clicks,
+            link.n_unique_clicks,
+        )
+        .unwrap();
+    }
+    format!(
+        r#"<h2>Clicks</h2>
+        <p>Unique clickers: {n_clickers} ({click_rate:.1}% of sent emails)</p>
+        <table>
+            <tr><th>Link</th><th>Clicks</th><th>Unique clicks</th></tr>
+            {rows_html}
+        </table>"#
+    )
+}
+
+fn percentage(n: i64, n_sent: i64) -> f64 {
+    if n_sent > 0 {
+        100.0 * n as f64 / n_sent as f64
+    } else {
+        0.0
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_issue_report(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<Option<IssueReport>, anyhow::Error> {
+    let report = sqlx::query_as!(
+        IssueReport,
+        r#"
+        SELECT
+            title,
+            slug,
+            published_at AS "published_at!",
+            delivery_status,
+            n_recipients,
+            (
+                SELECT count(*)
+                FROM issue_delivery_log l
+                WHERE
+                    l.newsletter_issue_id = i.newsletter_issue_id AND
+                    l.outcome = 'sent'
+            ) AS "n_sent!",
+            (
+                SELECT count(*)
+                FROM issue_delivery_log l
+                WHERE
+                    l.newsletter_issue_id = i.newsletter_issue_id AND
+                    l.outcome <> 'sent'
+            ) AS "n_failed!",
+            (
+                SELECT count(*)
+                FROM issue_delivery_queue q
+                WHERE q.newsletter_issue_id = i.newsletter_issue_id
+            ) AS "n_pending!",
+            track_clicks,
+            track_opens
+        FROM newsletter_issues i
+        WHERE
+            newsletter_issue_id = $1 AND
+            state = 'published'
+        "#,
+        issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the issue report")?;
+    Ok(report)
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_open_statistics(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<OpenStatistics, anyhow::Error> {
+    let opens = sqlx::query_as!(
+        OpenStatistics,
+        r#"
+        SELECT
+            count(*) AS "n_unique_opens!",
+            coalesce(sum(n_opens), 0) AS "n_opens!"
+        FROM issue_opens
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to retrieve the open statistics of an issue")?;
+    Ok(opens)
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_link_statistics(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<Vec<LinkStatistics>, anyhow::Error> {
+    let links = sqlx::query_as!(
+        LinkStatistics,
+        r#"
+        SELECT
+            l.url,
+            count(c.link_id) AS "n_clicks!",
+            count(DISTINCT c.subscriber_id) AS "n_unique_clicks!"
+        FROM issue_links l
+        LEFT JOIN link_clicks c ON c.link_id = l.link_id
+        WHERE l.newsletter_issue_id = $1
+        GROUP BY l.link_id, l.url
+        ORDER BY 2 DESC, l.url
+        "#,
+        issue_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the click statistics of an issue")?;
+    Ok(links)
+}
+
+#[tracing::instrument(skip(pool))]
+async fn count_unique_clickers(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<i64, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT count(DISTINCT c.subscriber_id) AS "n_clickers!"
+        FROM link_clicks c
+        JOIN issue_links l ON l.link_id = c.link_id
+        WHERE l.newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to count the subscribers who clicked a link")?;
+    Ok(row.n_clickers)
+}
@@ -0,0 +1,220 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use similar::{ChangeTag, TextDiff};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::utils::{e404, e500};
+
+pub struct Revision {
+    pub revision_number: i32,
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+    pub created_at: DateTime<Utc>,
+    pub author: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct DiffParameters {
+    from: i32,
+    to: i32,
+}
+
+/// Snapshot the current content of the issue as its latest revision.
+/// Nothing is recorded if the content did not change since the previous
+/// revision.
+#[tracing::instrument(skip(transaction))]
+pub async fn record_revision(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    author_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_revisions (
+            newsletter_issue_id,
+            revision_number,
+            title,
+            text_content,
+            html_content,
+            created_by,
+            created_at
+        )
+        SELECT
+            i.newsletter_issue_id,
+            coalesce(latest.revision_number, 0) + 1,
+            i.title,
+            i.text_content,
+            i.html_content,
+            $2,
+            now()
+        FROM newsletter_issues i
+        LEFT JOIN LATERAL (
+            SELECT revision_number, title, text_content, html_content
+            FROM issue_revisions r
+            WHERE r.newsletter_issue_id = i.newsletter_issue_id
+            ORDER BY revision_number DESC
+            LIMIT 1
+        ) latest ON true
+        WHERE
+            i.newsletter_issue_id = $1 AND (
+                latest.revision_number IS NULL OR
+                (latest.title, latest.text_content, latest.html_content)
+                    IS DISTINCT FROM
+                (i.title, i.text_content, i.html_content)
+            )
+        "#,
+        issue_id,
+        author_id
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+/// Revisions of the issue, newest first.
+#[tracing::instrument(skip(pool))]
+pub async fn get_revisions(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<Vec<Revision>, anyhow::Error> {
+    let revisions = sqlx::query_as!(
+        Revision,
+        r#"
+        SELECT
+            revision_number,
+            title,
+            text_content,
+            html_content,
+            issue_revisions.created_at,
+            users.username AS "author?"
+        FROM issue_revisions
+        LEFT JOIN users ON users.user_id = issue_revisions.created_by
+        WHERE newsletter_issue_id = $1
+        ORDER BY revision_number DESC
+        "#,
+        issue_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the revisions of an issue")?;
+    Ok(revisions)
+}
+
+/// Each revision links to its diff with the previous one.
+/// Drafts can be restored to any revision but the latest.
+pub fn render_revisions(
+    issue_id: Uuid,
+    revisions: &[Revision],
+    restorable: bool,
+    csrf_field: &str,
+) -> String {
+    let latest = revisions.first().map(|r| r.revision_number);
+    let mut rows_html = String::new();
+    for revision in revisions {
+        let n = revision.revision_number;
+        let diff_html = if n > 1 {
+            format!(
+                r#"<a href="/admin/issues/{issue_id}/diff?from={}&amp;to={n}">Changes</a>"#,
+                n - 1
+            )
+        } else {
+            String::new()
+        };
+        let restore_html = if restorable && Some(n) != latest {
+            format!(
+                r#"<form action="/admin/drafts/{issue_id}/revisions/{n}/restore" method="post">
+                    {csrf_field}
+                    <button type="submit">Restore</button>
+                </form>"#
+            )
+        } else {
+            String::new()
+        };
+        writeln!(
+            rows_html,
+            "<tr><td>{n}</td><td>{created_at}</td><td>{author}</td><td>{title}</td><td>{diff_html}</td><td>{restore_html}</td></tr>",
+            created_at = revision.created_at.format("%Y-%m-%d %H:%M UTC"),
+            author = htmlescape::encode_minimal(
+                revision.author.as_deref().unwrap_or("-")
+            ),
+            title = htmlescape::encode_minimal(&revision.title),
+        )
+        .unwrap();
+    }
+    format!(
+        r#"<h2>Revisions</h2>
+        <table>
+            <tr><th>#</th><th>Saved at</th><th>Author</th><th>Title</th><th></th><th></th></tr>
+            {rows_html}
+        </table>"#
+    )
+}
+
+#[tracing::instrument(name = "Compare two revisions", skip(pool))]
+pub async fn revision_diff(
+    issue_id: web::Path<Uuid>,
+    parameters: web::Query<DiffParameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let revisions = get_revisions(&pool, issue_id).await.map_err(e500)?;
+    let find = |n: i32| {
+        revisions
+            .iter()
+            .find(|r| r.revision_number == n)
+            .ok_or_else(|| e404(format!("Revision {} does not exist.", n)))
+    };
+    let from = find(parameters.from)?;
+    let to = find(parameters.to)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!doctype html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+        <title>Revision changes</title>
+    </head>
+    <body>
+        <h1>Changes from revision {from_n} to revision {to_n}</h1>
+        <h2>Title</h2>
+        {title_diff}
+        <h2>HTML content</h2>
+        {html_diff}
+        <h2>Text content</h2>
+        {text_diff}
+        <p><a href="/admin/issues">&lt;- Back</a></p>
+    </body>
+</html>"#,
+            from_n = from.revision_number,
+            to_n = to.revision_number,
+            title_diff = render_diff(&from.title, &to.title),
+            html_diff = render_diff(&from.html_content, &to.html_content),
+            text_diff = render_diff(&from.text_content, &to.text_content),
+        )))
+}
+
+/// Line by line diff, removed lines are wrapped in `<del>` and added ones
+/// in `<ins>`.
+fn render_diff(old: &str, new: &str) -> String {
+    if old == new {
+        return "<p>No changes.</p>".to_string();
+    }
+    let mut diff_html = String::new();
+    for change in TextDiff::from_lines(old, new).iter_all_changes() {
+        let line =
+            htmlescape::encode_minimal(change.value().trim_end_matches('\n'));
+        match change.tag() {
+            ChangeTag::Delete => writeln!(diff_html, "<del>- {}</del>", line),
+            ChangeTag::Insert => writeln!(diff_html, "<ins>+ {}</ins>", line),
+            ChangeTag::Equal => writeln!(diff_html, "  {}", line),
+        }
+        .unwrap();
+    }
+    format!("<pre>{}</pre>", diff_html)
+}
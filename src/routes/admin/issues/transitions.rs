@@ -0,0 +1,96 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::domain::IssueState;
+
+pub struct Transition {
+    pub from_state: Option<String>,
+    pub to_state: String,
+    pub transitioned_at: DateTime<Utc>,
+    pub username: Option<String>,
+}
+
+/// Keep track of who moved the issue to `to_state`, and when.
+/// `from_state` is `None` when the issue is created.
+#[tracing::instrument(skip(transaction))]
+pub async fn record_transition(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    from_state: Option<IssueState>,
+    to_state: IssueState,
+    user_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_transitions (
+            newsletter_issue_id,
+            from_state,
+            to_state,
+            user_id,
+            transitioned_at
+        )
+        VALUES ($1, $2, $3, $4, now())
+        "#,
+        issue_id,
+        from_state.map(|s| s.as_str()),
+        to_state.as_str(),
+        user_id
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+/// Transitions of the issue, oldest first.
+#[tracing::instrument(skip(pool))]
+pub async fn get_transitions(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<Vec<Transition>, anyhow::Error> {
+    let transitions = sqlx::query_as!(
+        Transition,
+        r#"
+        SELECT
+            from_state,
+            to_state,
+            transitioned_at,
+            users.username AS "username?"
+        FROM issue_transitions
+        LEFT JOIN users ON users.user_id = issue_transitions.user_id
+        WHERE newsletter_issue_id = $1
+        ORDER BY transitioned_at
+        "#,
+        issue_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the transitions of an issue")?;
+    Ok(transitions)
+}
+
+pub fn render_transitions(transitions: &[Transition]) -> String {
+    let mut rows_html = String::new();
+    for transition in transitions {
+        writeln!(
+            rows_html,
+            "<tr><td>{at}</td><td>{user}</td><td>{from}</td><td>{to}</td></tr>",
+            at = transition.transitioned_at.format("%Y-%m-%d %H:%M UTC"),
+            user = htmlescape::encode_minimal(
+                transition.username.as_deref().unwrap_or("-")
+            ),
+            from = transition.from_state.as_deref().unwrap_or("-"),
+            to = transition.to_state,
+        )
+        .unwrap();
+    }
+    format!(
+        r#"<h2>History</h2>
+        <table>
+            <tr><th>Date</th><th>User</th><th>From</th><th>To</th></tr>
+            {rows_html}
+        </table>"#
+    )
+}
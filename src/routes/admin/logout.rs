@@ -1,24 +1,31 @@
-use actix_web::HttpResponse;
+use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
 
 use crate::{
+    authentication::revoke_session,
     session_state::TypedSession,
     utils::{e500, see_other},
 };
 
 #[tracing::instrument(
     name = "Logout user from current session",
-    skip(session),
+    skip(session, pool),
     fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
 )]
 pub async fn log_out(
     session: TypedSession,
+    pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    if session.get_user_id().map_err(e500)?.is_none() {
-        Ok(see_other("/login"))
-    } else {
-        session.logout();
-        FlashMessage::info("You have succesfully logged out.").send();
-        Ok(see_other("/login"))
+    let Some(user_id) = session.get_user_id().map_err(e500)? else {
+        return Ok(see_other("/login"));
+    };
+    if let Some(session_id) = session.get_session_id().map_err(e500)? {
+        revoke_session(&pool, user_id, session_id)
+            .await
+            .map_err(e500)?;
     }
+    session.logout();
+    FlashMessage::info("You have succesfully logged out.").send();
+    Ok(see_other("/login"))
 }
@@ -1,12 +1,56 @@
+mod api_tokens;
 mod dashboard;
+mod drafts;
+mod issues;
 mod logout;
 mod newsletter;
 mod password;
+mod sessions;
+mod two_factor;
+mod users;
 
+pub use api_tokens::api_tokens_list;
+pub use api_tokens::create_user_api_token;
+pub use api_tokens::revoke_user_api_token;
 pub use dashboard::admin_dashboard;
 pub use dashboard::get_username;
+pub use drafts::edit_draft_form;
+pub use drafts::publish_draft;
+pub use drafts::publish_issue;
+pub use drafts::restore_revision;
+pub use drafts::review_issue;
+pub use drafts::save_draft;
+pub use drafts::PublishOutcome;
+pub use issues::change_delivery_status;
+pub use issues::duplicate_issue;
+pub use issues::issue_report;
+pub use issues::issues_list;
+pub use issues::revision_diff;
+pub use issues::set_archive_visibility;
 pub use logout::log_out;
+pub use newsletter::create_draft;
+pub use newsletter::delete_attachment;
 pub use newsletter::publish_newsletter;
 pub use newsletter::publish_newsletter_form;
+pub use newsletter::store_draft;
+pub use newsletter::upload_attachments;
+pub use newsletter::FormData as IssueFormData;
+pub use newsletter::NewDraftOutcome;
 pub use password::change_password;
 pub use password::change_password_form;
+pub use sessions::log_out_everywhere;
+pub use sessions::revoke_user_session;
+pub use sessions::sessions_list;
+pub use two_factor::confirm_two_factor_setup;
+pub use two_factor::new_recovery_codes;
+pub use two_factor::start_two_factor_setup;
+pub use two_factor::turn_off_two_factor;
+pub use two_factor::two_factor_settings;
+pub use users::change_user_email;
+pub use users::change_user_role;
+pub use users::change_user_status;
+pub use users::create_user;
+pub use users::delete_user;
+pub use users::set_two_factor_policy;
+pub use users::unlock_login;
+pub use users::users_list;
@@ -0,0 +1,278 @@
+use actix_multipart::{Field, Multipart};
+use actix_web::{
+    web::{self, ReqData},
+    HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use futures_util::TryStreamExt;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    authentication::UserId,
+    configuration::AttachmentSettings,
+    domain::AttachmentName,
+    utils::{e400, e500, see_other},
+};
+
+pub struct StagedAttachment {
+    pub attachment_id: Uuid,
+    pub file_name: String,
+    pub content_id: Option<String>,
+    pub size: i32,
+}
+
+struct Upload {
+    file_name: AttachmentName,
+    content_type: String,
+    inline: bool,
+    content: Vec<u8>,
+}
+
+/// Files are staged for the next issue published by the uploader.
+/// The `attachment` field takes regular attachments, `inline_image` takes
+/// images referenced from the HTML content as `cid:<file name>`.
+#[tracing::instrument(
+    name = "Upload newsletter attachments",
+    skip_all,
+    fields(user_id=%*user_id)
+)]
+pub async fn upload_attachments(
+    mut payload: Multipart,
+    pool: web::Data<PgPool>,
+    user_id: ReqData<UserId>,
+    settings: web::Data<AttachmentSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let staged = get_staged_attachments(&pool, *user_id)
+        .await
+        .map_err(e500)?;
+    let mut total_size: usize = staged.iter().map(|a| a.size as usize).sum();
+    let mut inline_names: Vec<String> =
+        staged.into_iter().filter_map(|a| a.content_id).collect();
+
+    let mut uploads = vec![];
+    let mut errors = vec![];
+    while let Some(field) = payload.try_next().await.map_err(e400)? {
+        let upload = match read_upload(field, &settings).await? {
+            Some(Ok(upload)) => upload,
+            Some(Err(e)) => {
+                errors.push(e);
+                continue;
+            }
+            None => continue,
+        };
+        let name = upload.file_name.as_ref();
+        if total_size + upload.content.len() > settings.max_issue_size {
+            errors.push(format!(
+                "{} was not attached: the attachments of an issue can't exceed {} bytes.",
+                name, settings.max_issue_size
+            ));
+        } else if upload.inline && inline_names.iter().any(|n| n == name) {
+            errors.push(format!(
+                "An inline image named {} is already attached.",
+                name
+            ));
+        } else {
+            total_size += upload.content.len();
+            if upload.inline {
+                inline_names.push(name.to_string());
+            }
+            uploads.push(upload);
+        }
+    }
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    for upload in &uploads {
+        insert_attachment(&mut transaction, *user_id, upload)
+            .await
+            .map_err(e500)?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to store the uploaded attachments")
+        .map_err(e500)?;
+
+    for e in errors {
+        FlashMessage::error(htmlescape::encode_minimal(&e)).send();
+    }
+    if !uploads.is_empty() {
+        FlashMessage::info(format!(
+            "{} file(s) will be attached to your next issue.",
+            uploads.len()
+        ))
+        .send();
+    }
+    Ok(see_other("/admin/newsletters"))
+}
+
+/// `None` for fields that don't carry a file, e.g. an empty file input.
+async fn read_upload(
+    mut field: Field,
+    settings: &AttachmentSettings,
+) -> Result<Option<Result<Upload, String>>, actix_web::Error> {
+    let inline = match field.name() {
+        Some("attachment") => false,
+        Some("inline_image") => true,
+        _ => return Ok(None),
+    };
+    let file_name = match field
+        .content_disposition()
+        .and_then(|disposition| disposition.get_filename())
+    {
+        Some(file_name) if !file_name.is_empty() => file_name.to_string(),
+        _ => return Ok(None),
+    };
+    let file_name = match AttachmentName::parse(&file_name) {
+        Ok(file_name) => file_name,
+        Err(e) => return Ok(Some(Err(e))),
+    };
+    let content_type = field
+        .content_type()
+        .map(|mime| mime.essence_str().to_string())
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    if inline && !content_type.starts_with("image/") {
+        return Ok(Some(Err(format!(
+            "{} is not an image and can't be inlined.",
+            file_name.as_ref()
+        ))));
+    }
+
+    // The rest of an oversized file is skipped when reading the next field
+    let mut content = vec![];
+    while let Some(chunk) = field.try_next().await.map_err(e400)? {
+        if content.len() + chunk.len() > settings.max_file_size {
+            return Ok(Some(Err(format!(
+                "{} was not attached: files can't exceed {} bytes.",
+                file_name.as_ref(),
+                settings.max_file_size
+            ))));
+        }
+        content.extend_from_slice(&chunk);
+    }
+    if content.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Ok(Upload {
+        file_name,
+        content_type,
+        inline,
+        content,
+    })))
+}
+
+#[tracing::instrument(
+    name = "Remove a staged attachment",
+    skip(pool, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn delete_attachment(
+    attachment_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_attachments
+        WHERE
+            attachment_id = $1 AND
+            uploaded_by = $2 AND
+            newsletter_issue_id IS NULL
+        "#,
+        attachment_id.into_inner(),
+        **user_id
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to remove a staged attachment")
+    .map_err(e500)?;
+    FlashMessage::info("The attachment has been removed.").send();
+    Ok(see_other("/admin/newsletters"))
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_staged_attachments(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<StagedAttachment>, anyhow::Error> {
+    let attachments = sqlx::query_as!(
+        StagedAttachment,
+        r#"
+        SELECT attachment_id, file_name, content_id, size
+        FROM issue_attachments
+        WHERE
+            uploaded_by = $1 AND
+            newsletter_issue_id IS NULL
+        ORDER BY uploaded_at, file_name
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the staged attachments")?;
+    Ok(attachments)
+}
+
+#[tracing::instrument(skip(transaction, upload))]
+async fn insert_attachment(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    upload: &Upload,
+) -> Result<(), anyhow::Error> {
+    let content_id = upload.inline.then(|| upload.file_name.as_ref());
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_attachments (
+            attachment_id,
+            uploaded_by,
+            file_name,
+            content_type,
+            content_id,
+            content,
+            size,
+            uploaded_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, now())
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        upload.file_name.as_ref(),
+        upload.content_type,
+        content_id,
+        upload.content,
+        upload.content.len() as i32,
+    )
+    .execute(transaction)
+    .await
+    .context("Failed to store an attachment")?;
+    Ok(())
+}
+
+/// Hand the staged attachments of the publisher over to their new issue.
+#[tracing::instrument(skip(transaction))]
+pub async fn claim_staged_attachments(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    newsletter_issue_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE issue_attachments
+        SET newsletter_issue_id = $1
+        WHERE
+            uploaded_by = $2 AND
+            newsletter_issue_id IS NULL
+        "#,
+        newsletter_issue_id,
+        user_id
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
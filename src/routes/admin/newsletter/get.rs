@@ -1,14 +1,63 @@
-use actix_web::{http::header::ContentType, HttpResponse};
+use actix_web::{
+    http::header::ContentType,
+    web::{self, ReqData},
+    HttpResponse,
+};
 use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
 use std::fmt::Write;
 
+use super::attachments::get_staged_attachments;
+use crate::{
+    authentication::{CsrfToken, UserId},
+    configuration::PublishingSettings,
+    utils::e500,
+};
+
 pub async fn publish_newsletter_form(
     flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    publishing: web::Data<PublishingSettings>,
+    user_id: ReqData<UserId>,
+    csrf_token: CsrfToken,
 ) -> Result<HttpResponse, actix_web::Error> {
     let mut msg_html = String::new();
     for m in flash_messages.iter() {
         writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
     }
+    let csrf_field = csrf_token.form_field();
+    let attachments = get_staged_attachments(&pool, **user_id)
+        .await
+        .map_err(e500)?;
+    // Issues needing approval can only start as drafts
+    let publish_html = if publishing.require_approval {
+        ""
+    } else {
+        r#"<button type="submit">Publish</button>"#
+    };
+    let mut attachments_html = String::new();
+    for attachment in attachments {
+        let inline_html = match attachment.content_id {
+            Some(content_id) => format!(
+                " - inline as <code>cid:{}</code>",
+                htmlescape::encode_minimal(&content_id)
+            ),
+            None => String::new(),
+        };
+        writeln!(
+            attachments_html,
+            r#"<li>{file_name} ({size} bytes){inline_html}
+                <form action="/admin/newsletters/attachments/{attachment_id}/delete" method="post">
+                    {csrf_field}
+                    <button type="submit">Remove</button>
+                </form>
+            </li>"#,
+            file_name = htmlescape::encode_minimal(&attachment.file_name),
+            size = attachment.size,
+            attachment_id = attachment.attachment_id,
+        )
+        .unwrap();
+    }
     let idempotency_key = uuid::Uuid::new_v4().to_string();
     Ok(HttpResponse::Ok().content_type(ContentType::html()).body(format!(
         r#"
@@ -21,6 +70,7 @@ pub async fn publish_newsletter_form(
             <body>
                 {msg_html}
                 <form action="/admin/newsletters" method="post">
+                    {csrf_field}
                     <label
                         >Title
                         <input
@@ -33,23 +83,123 @@ pub async fn publish_newsletter_form(
                         >HTML Content
                         <textarea
                             placeholder="Enter your content in HTML format"
-                            name="content_html"
+                            name="html_content"
                         ></textarea>
                     </label>
                     <label
                         >Text Content
                         <textarea
                             placeholder="Enter your content in text format"
-                            name="content_text"
+                            name="text_content"
+                        ></textarea>
+                    </label>
+                    <label
+                        >Hide from the public archive
+                        <input
+                            type="checkbox"
+                            name="hide_from_archive"
+                            value="true"
+                        />
+                    </label>
+                    <label
+                        >Track link clicks
+                        <input
+                            type="checkbox"
+                            name="track_clicks"
+                            value="true"
+                        />
+                    </label>
+                    <label
+                        >Track opens
+                        <input
+                            type="checkbox"
+                            name="track_opens"
+                            value="true"
+                        />
+                    </label>
+                    <label
+                        >Add UTM parameters to outbound links
+                        <input
+                            type="checkbox"
+                            name="tag_links"
+                            value="true"
+                        />
+                    </label>
+                    <label
+                        >UTM source
+                        <input
+                            type="text"
+                            placeholder="newsletter"
+                            name="utm_source"
+                        />
+                    </label>
+                    <label
+                        >UTM medium
+                        <input
+                            type="text"
+                            placeholder="email"
+                            name="utm_medium"
+                        />
+                    </label>
+                    <label
+                        >UTM campaign
+                        <input
+                            type="text"
+                            placeholder="Defaults to the issue slug"
+                            name="utm_campaign"
+                        />
+                    </label>
+
+                    <label
+                        >Deliver at, in the time zone of each subscriber
+                        <input type="time" name="deliver_at" />
+                    </label>
+                    <label
+                        >Alternative subject lines to test, one per line
+                        <textarea
+                            placeholder="The title is always tested"
+                            name="subject_alternatives"
                         ></textarea>
                     </label>
+                    <label
+                        >Sample size, in percent of the recipients
+                        <input type="number" name="ab_sample_percent" value="20" min="1" max="100" />
+                    </label>
+                    <label
+                        >Pick the winner by
+                        <select name="ab_winning_metric">
+                            <option value="opens">Open rate</option>
+                            <option value="clicks">Click rate</option>
+                        </select>
+                    </label>
+                    <label
+                        >Waiting period, in minutes
+                        <input type="number" name="ab_wait_minutes" value="240" min="1" />
+                    </label>
 
                     <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
-                    <button type="submit">Publish</button>
+                    {publish_html}
+                    <button type="submit" formaction="/admin/drafts">Save as draft</button>
+                </form>
+                <h2>Attachments</h2>
+                <ul>
+                    {attachments_html}
+                </ul>
+                <form action="/admin/newsletters/attachments?{csrf_query}" method="post" enctype="multipart/form-data">
+                    <label
+                        >Attachments
+                        <input type="file" name="attachment" multiple />
+                    </label>
+                    <label
+                        >Inline images
+                        <input type="file" name="inline_image" accept="image/*" multiple />
+                    </label>
+                    <button type="submit">Upload</button>
                 </form>
                 <p><a href="/admin/dashboard">&lt;- Back</a></p>
             </body>
         </html>
         "#,
+        csrf_query = csrf_token.query(),
     )))
 }
@@ -1,4 +1,10 @@
+mod attachments;
+pub use attachments::{delete_attachment, upload_attachments};
 mod get;
 pub use get::publish_newsletter_form;
 mod post;
-pub use post::publish_newsletter;
+pub use post::{
+    create_draft, enqueue_delivery_tasks, publish_newsletter, send_warnings,
+    store_draft, success_message, validate_newsletter_issue, FormData,
+    NewDraftOutcome,
+};
@@ -4,13 +4,27 @@ use actix_web::{
 };
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
+use chrono::NaiveTime;
 use sqlx::{PgPool, Postgres, Transaction};
 use tracing::instrument;
 use uuid::Uuid;
 
+use super::attachments::claim_staged_attachments;
+use crate::routes::admin::issues::{record_revision, record_transition};
 use crate::{
     authentication::UserId,
+    configuration::{PublishingSettings, ScheduleSettings},
+    domain::{
+        parse_time_of_day, IssueSlug, IssueState, QuietHours, SubjectTest,
+        WinningMetric,
+    },
     idempotency::{save_response, try_processing, IdempotencyKey, NextAction},
+    issue_delivery_worker::schedule_deliveries,
+    lint::lint_issue,
+    subject_testing::{
+        enqueue_subject_test, insert_subject_test, start_waiting_period,
+    },
+    tracking::UtmParameters,
     utils::{e400, e500, see_other},
 };
 
@@ -19,39 +33,93 @@ pub struct FormData {
     title: String,
     html_content: String,
     text_content: String,
+    /// Only used when publishing, drafts can be saved more than once.
+    #[serde(default)]
     idempotency_key: String,
+    #[serde(default)]
+    hide_from_archive: bool,
+    #[serde(default)]
+    track_clicks: bool,
+    #[serde(default)]
+    track_opens: bool,
+    #[serde(default)]
+    tag_links: bool,
+    #[serde(default)]
+    utm_source: String,
+    #[serde(default)]
+    utm_medium: String,
+    #[serde(default)]
+    utm_campaign: String,
+    /// Alternative subject lines to test, one per line.
+    #[serde(default)]
+    subject_alternatives: String,
+    #[serde(default)]
+    ab_sample_percent: String,
+    #[serde(default)]
+    ab_winning_metric: String,
+    #[serde(default)]
+    ab_wait_minutes: String,
+    /// Deliver at this time of day (`HH:MM`) in each subscriber's time zone.
+    #[serde(default)]
+    deliver_at: String,
+}
+
+/// Options of the form that need to be parsed before storing the issue.
+struct IssueOptions {
+    subject_test: Option<SubjectTest>,
+    deliver_at: Option<NaiveTime>,
 }
 
 #[tracing::instrument(
     name = "Publish a newsletter issue",
-    skip(form, pool),
+    skip(form, pool, publishing, schedule),
     fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
 )]
 pub async fn publish_newsletter(
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
+    publishing: web::Data<PublishingSettings>,
+    schedule: web::Data<ScheduleSettings>,
     user_id: ReqData<UserId>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = user_id.into_inner();
     tracing::Span::current()
-        .record("user_id", &tracing::field::display(user_id));
+        .record("user_id", tracing::field::display(user_id));
 
-    if let Err(violation_messages) = validate_newsletter_issue(&form.0) {
-        for message in violation_messages {
-            FlashMessage::error(message).send();
-        }
+    // A new issue can't have been approved yet
+    if publishing.require_approval {
+        FlashMessage::error(
+            "Issues must be approved before being published. \
+            Save it as a draft and submit it for review.",
+        )
+        .send();
         return Ok(see_other("/admin/newsletters"));
     }
 
-    // Destructure formData to avoid borrow checker issues
-    let FormData {
-        title,
-        text_content,
-        html_content,
-        idempotency_key,
-    } = form.0;
+    let warnings = match validate_newsletter_issue(
+        &form.title,
+        &form.html_content,
+        &form.text_content,
+    ) {
+        Ok(warnings) => warnings,
+        Err(violation_messages) => {
+            for message in violation_messages {
+                FlashMessage::error(message).send();
+            }
+            return Ok(see_other("/admin/newsletters"));
+        }
+    };
+    let options = match issue_options(&form) {
+        Ok(options) => options,
+        Err(message) => {
+            FlashMessage::error(message).send();
+            return Ok(see_other("/admin/newsletters"));
+        }
+    };
+
+    let form = form.into_inner();
     let idempotency_key: IdempotencyKey =
-        idempotency_key.try_into().map_err(e400)?;
+        form.idempotency_key.clone().try_into().map_err(e400)?;
 
     let mut transaction =
         match try_processing(&pool, &idempotency_key, *user_id)
@@ -61,25 +129,28 @@ pub async fn publish_newsletter(
             NextAction::StartProcessing(transaction) => transaction,
             NextAction::ReturnSavedResponse(saved_response) => {
                 success_message().send();
+                send_warnings(warnings);
                 return Ok(saved_response);
             }
         };
 
-    let issue_id = insert_newsletter_issue(
+    let issue_id = create_issue(
         &mut transaction,
-        &title,
-        &text_content,
-        &html_content,
+        *user_id,
+        &form,
+        &options,
+        IssueState::Published,
     )
     .await
-    .context("Failed to store newsletter issue details")
     .map_err(e500)?;
-    enqueue_delivery_tasks(&mut transaction, issue_id)
+    let quiet_hours = schedule.quiet_hours().map_err(e500)?;
+    enqueue_delivery_tasks(&mut transaction, issue_id, quiet_hours)
         .await
         .context("Failed to enqueue delivery tasks")
         .map_err(e500)?;
 
     success_message().send();
+    send_warnings(warnings);
     let response = see_other("/admin/newsletters");
     let response =
         save_response(transaction, &idempotency_key, *user_id, response)
@@ -88,17 +159,135 @@ pub async fn publish_newsletter(
     Ok(response)
 }
 
-fn success_message() -> FlashMessage {
+/// Drafts go through the same form as published issues, they are only
+/// stored until someone publishes them.
+#[tracing::instrument(
+    name = "Save a newsletter draft",
+    skip(form, pool),
+    fields(user_id=%*user_id)
+)]
+pub async fn create_draft(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    match store_draft(&pool, **user_id, &form).await.map_err(e500)? {
+        NewDraftOutcome::Saved { issue_id, warnings } => {
+            FlashMessage::info("The draft has been saved.").send();
+            send_warnings(warnings);
+            Ok(see_other(&format!("/admin/drafts/{}", issue_id)))
+        }
+        NewDraftOutcome::Invalid(violation_messages) => {
+            for message in violation_messages {
+                FlashMessage::error(message).send();
+            }
+            Ok(see_other("/admin/newsletters"))
+        }
+    }
+}
+
+/// What came of an attempt to store a new draft.
+pub enum NewDraftOutcome {
+    /// The lint warnings of the draft come along with it.
+    Saved {
+        issue_id: Uuid,
+        warnings: Vec<String>,
+    },
+    Invalid(Vec<String>),
+}
+
+#[tracing::instrument(skip(pool, form))]
+pub async fn store_draft(
+    pool: &PgPool,
+    author_id: Uuid,
+    form: &FormData,
+) -> Result<NewDraftOutcome, anyhow::Error> {
+    let warnings = match validate_newsletter_issue(
+        &form.title,
+        &form.html_content,
+        &form.text_content,
+    ) {
+        Ok(warnings) => warnings,
+        Err(violation_messages) => {
+            return Ok(NewDraftOutcome::Invalid(violation_messages));
+        }
+    };
+    let options = match issue_options(form) {
+        Ok(options) => options,
+        Err(message) => return Ok(NewDraftOutcome::Invalid(vec![message])),
+    };
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let issue_id = create_issue(
+        &mut transaction,
+        author_id,
+        form,
+        &options,
+        IssueState::Draft,
+    )
+    .await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to store the new draft")?;
+    Ok(NewDraftOutcome::Saved { issue_id, warnings })
+}
+
+/// Store the issue with its first revision and the files staged by its
+/// author.
+async fn create_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    author_id: Uuid,
+    form: &FormData,
+    options: &IssueOptions,
+    state: IssueState,
+) -> Result<Uuid, anyhow::Error> {
+    let issue_id = insert_newsletter_issue(
+        transaction,
+        author_id,
+        form,
+        options.deliver_at,
+        state,
+    )
+    .await
+    .context("Failed to store newsletter issue details")?;
+    if let Some(subject_test) = &options.subject_test {
+        insert_subject_test(transaction, issue_id, subject_test)
+            .await
+            .context("Failed to store the subject test of the issue")?;
+    }
+    claim_staged_attachments(transaction, author_id, issue_id)
+        .await
+        .context("Failed to attach the uploaded files to the issue")?;
+    record_revision(transaction, issue_id, author_id)
+        .await
+        .context("Failed to record the first revision of the issue")?;
+    record_transition(transaction, issue_id, None, state, author_id)
+        .await
+        .context("Failed to record the creation of the issue")?;
+    Ok(issue_id)
+}
+
+pub fn success_message() -> FlashMessage {
     FlashMessage::info(
         "The newsletter issue has been accepted - emails will go out shortly.",
     )
 }
 
-fn validate_newsletter_issue(form: &FormData) -> Result<(), Vec<String>> {
+/// Empty fields and lint errors block the issue. Lint warnings are returned
+/// so that they can be shown to its author.
+pub fn validate_newsletter_issue(
+    title: &str,
+    html_content: &str,
+    text_content: &str,
+) -> Result<Vec<String>, Vec<String>> {
     let violation_messages: Vec<String> = [
-        ("title", &form.title),
-        ("HTML content", &form.html_content),
-        ("text content", &form.text_content),
+        ("title", title),
+        ("HTML content", html_content),
+        ("text content", text_content),
     ]
     .iter()
     .filter_map(|field| {
@@ -113,18 +302,66 @@ fn validate_newsletter_issue(form: &FormData) -> Result<(), Vec<String>> {
     if !violation_messages.is_empty() {
         return Err(violation_messages);
     }
-    Ok(())
+    let report = lint_issue(title, html_content, text_content);
+    if !report.errors.is_empty() {
+        return Err(report.errors);
+    }
+    Ok(report.warnings)
 }
 
-#[tracing::instrument(name = "Insert newsletter issue", skip(transaction))]
+fn issue_options(form: &FormData) -> Result<IssueOptions, String> {
+    let deliver_at = match form.deliver_at.trim() {
+        "" => None,
+        deliver_at => Some(parse_time_of_day(deliver_at)?),
+    };
+    Ok(IssueOptions {
+        subject_test: subject_test(form)?,
+        deliver_at,
+    })
+}
+
+/// The winning metric can only be measured if it is tracked.
+fn subject_test(form: &FormData) -> Result<Option<SubjectTest>, String> {
+    let subject_test = SubjectTest::parse(
+        &form.subject_alternatives,
+        &form.ab_sample_percent,
+        &form.ab_winning_metric,
+        &form.ab_wait_minutes,
+    )?;
+    match subject_test.as_ref().map(|t| t.winning_metric) {
+        Some(WinningMetric::Opens) if !form.track_opens => Err(
+            "Open tracking must be enabled to pick a subject by open rate"
+                .into(),
+        ),
+        Some(WinningMetric::Clicks) if !form.track_clicks => Err(
+            "Click tracking must be enabled to pick a subject by click rate"
+                .into(),
+        ),
+        _ => Ok(subject_test),
+    }
+}
+
+pub fn send_warnings(warnings: Vec<String>) {
+    for warning in warnings {
+        FlashMessage::warning(format!("Warning: {}", warning)).send();
+    }
+}
+
+#[tracing::instrument(
+    name = "Insert newsletter issue",
+    skip(transaction, form)
+)]
 #[instrument(skip_all)]
 pub async fn insert_newsletter_issue(
     transaction: &mut Transaction<'_, Postgres>,
-    title: &str,
-    text_content: &str,
-    html_content: &str,
+    author_id: Uuid,
+    form: &FormData,
+    deliver_at: Option<NaiveTime>,
+    state: IssueState,
 ) -> Result<Uuid, sqlx::Error> {
     let newsletter_issue_id = Uuid::new_v4();
+    let slug = IssueSlug::new(&form.title, newsletter_issue_id);
+    let utm = utm_parameters(form, &slug);
     sqlx::query!(
         r#"
         INSERT INTO newsletter_issues (
@@ -132,35 +369,108 @@ pub async fn insert_newsletter_issue(
             title,
             text_content,
             html_content,
-            published_at
+            published_at,
+            updated_at,
+            slug,
+            hidden_from_archive,
+            created_by,
+            track_clicks,
+            track_opens,
+            utm_source,
+            utm_medium,
+            utm_campaign,
+            state,
+            deliver_at_local_time
+        )
+        VALUES (
+            $1,
+            $2,
+            $3,
+            $4,
+            CASE WHEN $13 = 'published' THEN now() END,
+            now(),
+            $5,
+            $6,
+            $7,
+            $8,
+            $9,
+            $10,
+            $11,
+            $12,
+            $13,
+            $14
         )
-        VALUES ($1, $2, $3, $4, now())
         "#,
         newsletter_issue_id,
-        title,
-        text_content,
-        html_content,
+        form.title,
+        form.text_content,
+        form.html_content,
+        slug.as_ref(),
+        form.hide_from_archive,
+        author_id,
+        form.track_clicks,
+        form.track_opens,
+        utm.as_ref().map(|utm| &utm.source),
+        utm.as_ref().map(|utm| &utm.medium),
+        utm.as_ref().map(|utm| &utm.campaign),
+        state.as_str(),
+        deliver_at,
     )
     .execute(transaction)
     .await?;
     Ok(newsletter_issue_id)
 }
 
+/// Blank parameters fall back to their defaults, the campaign to the slug.
+fn utm_parameters(form: &FormData, slug: &IssueSlug) -> Option<UtmParameters> {
+    if !form.tag_links {
+        return None;
+    }
+    let or_default = |value: &str, default: &str| match value.trim() {
+        "" => default.to_string(),
+        value => value.to_string(),
+    };
+    Some(UtmParameters {
+        source: or_default(&form.utm_source, UtmParameters::DEFAULT_SOURCE),
+        medium: or_default(&form.utm_medium, UtmParameters::DEFAULT_MEDIUM),
+        campaign: or_default(&form.utm_campaign, slug.as_ref()),
+    })
+}
+
 #[instrument(skip_all)]
 pub async fn enqueue_delivery_tasks(
     transaction: &mut Transaction<'_, Postgres>,
     newsletter_issue_id: Uuid,
+    quiet_hours: Option<QuietHours>,
 ) -> Result<(), sqlx::Error> {
+    let n_recipients =
+        match enqueue_subject_test(transaction, newsletter_issue_id).await? {
+            Some(n_recipients) => n_recipients,
+            None => sqlx::query!(
+                r#"
+                INSERT INTO issue_delivery_queue (
+                    newsletter_issue_id,
+                    subscriber_email
+                )
+                SELECT $1, email
+                FROM subscriptions
+                WHERE status = 'confirmed'
+                "#,
+                newsletter_issue_id,
+            )
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected(),
+        };
+    schedule_deliveries(transaction, newsletter_issue_id, quiet_hours).await?;
+    start_waiting_period(transaction, newsletter_issue_id).await?;
     sqlx::query!(
         r#"
-        INSERT INTO issue_delivery_queue (
-            newsletter_issue_id,
-            subscriber_email
-        )
-        SELECT $1, email
-        FROM subscriptions
-        WHERE status = 'confirmed'
+        UPDATE newsletter_issues
+        SET n_recipients = $1
+        WHERE newsletter_issue_id = $2
         "#,
+        n_recipients as i32,
         newsletter_issue_id,
     )
     .execute(transaction)
@@ -2,8 +2,11 @@ use actix_web::{http::header::ContentType, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
 use std::fmt::Write;
 
+use crate::authentication::CsrfToken;
+
 pub async fn change_password_form(
     flash_messages: IncomingFlashMessages,
+    csrf_token: CsrfToken,
 ) -> Result<HttpResponse, actix_web::Error> {
     let mut msg_html = String::new();
     for m in flash_messages.iter() {
@@ -20,6 +23,7 @@ pub async fn change_password_form(
             <body>
                 {msg_html}
                 <form action="/admin/password" method="post">
+                    {csrf_field}
                     <label
                         >Current password
                         <input
@@ -33,7 +37,7 @@ pub async fn change_password_form(
                         <input
                             type="password"
                             placeholder="Enter new password"
-                            name="password"
+                            name="new_password"
                         />
                     </label>
                     <label
@@ -51,5 +55,6 @@ pub async fn change_password_form(
             </body>
         </html>
         "#,
+        csrf_field = csrf_token.form_field(),
     )))
 }
@@ -7,9 +7,14 @@ use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
 
 use crate::{
-    authentication::{validate_credentials, AuthError, Credentials, UserId},
-    domain::{Password, PasswordError},
+    authentication::{
+        revoke_other_sessions, validate_credentials, AuthError, Credentials,
+        UserId,
+    },
+    configuration::PasswordHashingSettings,
+    domain::{Password, PasswordPolicy},
     routes::get_username,
+    session_state::TypedSession,
     utils::{e500, see_other},
 };
 
@@ -23,7 +28,10 @@ pub struct FormData {
 pub async fn change_password(
     user_id: ReqData<UserId>,
     pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashingSettings>,
+    password_policy: web::Data<PasswordPolicy>,
     form: web::Form<FormData>,
+    session: TypedSession,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = user_id.into_inner();
     let username = get_username(*user_id, &pool).await.map_err(e500)?;
@@ -35,30 +43,23 @@ pub async fn change_password(
         return Ok(see_other("/admin/password"));
     }
 
-    let new_password =
-        match Password::parse(form.0.new_password.expose_secret().clone()) {
-            Ok(password) => Secret::new(password),
-            Err(PasswordError::TooShort) => {
-                FlashMessage::error(
-                    "The new password must be at least 12 characters long.",
-                )
-                .send();
-                return Ok(see_other("/admin/password"));
-            }
-            Err(PasswordError::TooLong) => {
-                FlashMessage::error(
-                    "The new password must be at most 128 characters long.",
-                )
-                .send();
-                return Ok(see_other("/admin/password"));
-            }
-        };
+    let new_password = match Password::parse(
+        form.0.new_password.expose_secret().clone(),
+        &username,
+        &password_policy,
+    ) {
+        Ok(password) => Secret::new(password),
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other("/admin/password"));
+        }
+    };
 
     let credentials = Credentials {
         username,
         password: form.0.current_password,
     };
-    if let Err(e) = validate_credentials(credentials, &pool).await {
+    if let Err(e) = validate_credentials(credentials, &hashing, &pool).await {
         return match e {
             AuthError::InvalidCredentials(_) => {
                 FlashMessage::error("The current password is incorrect.")
@@ -68,7 +69,17 @@ pub async fn change_password(
             AuthError::UnexpectedError(_) => Err(e500(e)),
         };
     };
-    crate::authentication::change_password(*user_id, new_password, &pool)
+    crate::authentication::change_password(
+        *user_id,
+        new_password,
+        &hashing,
+        &pool,
+    )
+    .await
+    .map_err(e500)?;
+    // Whoever knew the old password is logged out, but not the user
+    let current_session_id = session.get_session_id().map_err(e500)?;
+    revoke_other_sessions(&pool, *user_id, current_session_id)
         .await
         .map_err(e500)?;
     FlashMessage::info("Your password has been changed.").send();
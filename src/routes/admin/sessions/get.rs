@@ -0,0 +1,95 @@
+use actix_web::{
+    http::header::ContentType,
+    web::{self, ReqData},
+    HttpResponse,
+};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::{
+    authentication::{list_sessions, CsrfToken, UserId},
+    session_state::TypedSession,
+    utils::e500,
+};
+
+#[tracing::instrument(
+    name = "List the sessions of a user",
+    skip(user_id, pool, session, flash_messages, csrf_token)
+)]
+pub async fn sessions_list(
+    user_id: ReqData<UserId>,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: CsrfToken,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let csrf_field = csrf_token.form_field();
+    let current_session_id = session.get_session_id().map_err(e500)?;
+    let sessions = list_sessions(&pool, **user_id).await.map_err(e500)?;
+    let mut rows_html = String::new();
+    for s in sessions {
+        let action_html = if Some(s.session_id) == current_session_id {
+            "This session".to_string()
+        } else {
+            format!(
+                r#"<form action="/admin/sessions/{}/revoke" method="post">
+                        {}
+                        <button type="submit">Log out</button>
+                    </form>"#,
+                s.session_id, csrf_field
+            )
+        };
+        writeln!(
+            rows_html,
+            r#"<tr>
+                <td>{user_agent}</td>
+                <td>{ip_address}</td>
+                <td>{created_at}</td>
+                <td>{last_seen_at}</td>
+                <td>{action_html}</td>
+            </tr>"#,
+            user_agent = htmlescape::encode_minimal(&s.user_agent),
+            ip_address = htmlescape::encode_minimal(&s.ip_address),
+            created_at = s.created_at.format("%Y-%m-%d %H:%M UTC"),
+            last_seen_at = s.last_seen_at.format("%Y-%m-%d %H:%M UTC"),
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!doctype html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+        <title>Active sessions</title>
+    </head>
+    <body>
+        {msg_html}
+        <p>You are logged in on these devices:</p>
+        <table>
+            <tr>
+                <th>Device</th>
+                <th>IP address</th>
+                <th>Logged in at</th>
+                <th>Last seen at</th>
+                <th></th>
+            </tr>
+            {rows_html}
+        </table>
+        <form action="/admin/sessions/revoke-all" method="post">
+            {csrf_field}
+            <button type="submit">Log out everywhere</button>
+        </form>
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>"#,
+        )))
+}
@@ -0,0 +1,57 @@
+use actix_web::{
+    web::{self, ReqData},
+    HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    authentication::{revoke_other_sessions, revoke_session, UserId},
+    session_state::TypedSession,
+    utils::{e404, e500, see_other},
+};
+
+#[tracing::instrument(
+    name = "Revoke a session",
+    skip(user_id, pool, session),
+    fields(user_id = %*user_id)
+)]
+pub async fn revoke_user_session(
+    user_id: ReqData<UserId>,
+    session_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let session_id = session_id.into_inner();
+    if !revoke_session(&pool, **user_id, session_id)
+        .await
+        .map_err(e500)?
+    {
+        return Err(e404("The session was not found."));
+    }
+    if session.get_session_id().map_err(e500)? == Some(session_id) {
+        session.logout();
+        return Ok(see_other("/login"));
+    }
+    FlashMessage::info("The session has been logged out.").send();
+    Ok(see_other("/admin/sessions"))
+}
+
+#[tracing::instrument(
+    name = "Log out everywhere",
+    skip(user_id, pool, session),
+    fields(user_id = %*user_id)
+)]
+pub async fn log_out_everywhere(
+    user_id: ReqData<UserId>,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    revoke_other_sessions(&pool, **user_id, None)
+        .await
+        .map_err(e500)?;
+    session.logout();
+    FlashMessage::info("You have been logged out everywhere.").send();
+    Ok(see_other("/login"))
+}
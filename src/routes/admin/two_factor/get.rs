@@ -0,0 +1,169 @@
+use actix_web::{
+    http::header::ContentType,
+    web::{self, ReqData},
+    HttpResponse,
+};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use crate::{
+    authentication::{
+        get_totp_secret, get_two_factor_status, is_two_factor_required,
+        CsrfToken, TotpEncryptionKey, TwoFactorStatus, UserId,
+    },
+    routes::get_username,
+    utils::e500,
+};
+
+/// Shown as the account name in authenticator apps, after the username.
+const TOTP_ISSUER: &str = "zero2prod";
+
+#[tracing::instrument(
+    name = "Show the two-factor settings",
+    skip(user_id, pool, totp_key, flash_messages, csrf_token)
+)]
+pub async fn two_factor_settings(
+    user_id: ReqData<UserId>,
+    pool: web::Data<PgPool>,
+    totp_key: web::Data<TotpEncryptionKey>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: CsrfToken,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = **user_id;
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let csrf_field = csrf_token.form_field();
+    let required = is_two_factor_required(&pool).await.map_err(e500)?;
+    let status = get_two_factor_status(&pool, user_id).await.map_err(e500)?;
+    let body_html = match status {
+        TwoFactorStatus::Disabled => format!(
+            r#"<p>Two-factor authentication is off.{required_html}</p>
+        <form action="/admin/two-factor/setup" method="post">
+            {csrf_field}
+            <button type="submit">Set up two-factor authentication</button>
+        </form>"#,
+            required_html = if required {
+                " It is required for all users: set it up to continue."
+            } else {
+                ""
+            },
+        ),
+        TwoFactorStatus::Pending => {
+            let username = get_username(user_id, &pool).await.map_err(e500)?;
+            let secret = get_totp_secret(&pool, &totp_key, user_id)
+                .await
+                .map_err(e500)?
+                .ok_or_else(|| e500("The pending TOTP secret is missing"))?;
+            let otpauth_uri = secret.otpauth_uri(TOTP_ISSUER, &username);
+            format!(
+                r#"<p>Add this account to your authenticator app, by opening
+        <a href="{otpauth_uri}">this link</a> or scanning a QR code of it:</p>
+        <p><code>{otpauth_uri}</code></p>
+        <p>You can also enter this key by hand: <code>{secret}</code></p>
+        <form action="/admin/two-factor/enable" method="post">
+            {csrf_field}
+            <label>Code from the app
+                <input
+                    type="text"
+                    inputmode="numeric"
+                    autocomplete="one-time-code"
+                    name="code"
+                >
+            </label>
+            <button type="submit">Turn on</button>
+        </form>
+        <form action="/admin/two-factor/setup" method="post">
+            {csrf_field}
+            <button type="submit">Start over with a new key</button>
+        </form>"#,
+                otpauth_uri = htmlescape::encode_minimal(&otpauth_uri),
+                secret = secret.to_base32(),
+            )
+        }
+        TwoFactorStatus::Enabled {
+            remaining_recovery_codes,
+        } => {
+            let turn_off_html = if required {
+                "<p>Two-factor authentication is required for all users, \
+                so it can't be turned off.</p>"
+                    .to_string()
+            } else {
+                code_form("/admin/two-factor/disable", "Turn off", &csrf_field)
+            };
+            format!(
+                r#"<p>Two-factor authentication is on.</p>
+        <p>You have {remaining_recovery_codes} unused recovery codes left.</p>
+        {recovery_codes_form}
+        {turn_off_html}"#,
+                recovery_codes_form = code_form(
+                    "/admin/two-factor/recovery-codes",
+                    "Generate new recovery codes",
+                    &csrf_field
+                ),
+            )
+        }
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!doctype html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+        <title>Two-factor authentication</title>
+    </head>
+    <body>
+        {msg_html}
+        {body_html}
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>"#,
+        )))
+}
+
+/// Changes to an enabled second factor must be confirmed with a code.
+fn code_form(action: &str, label: &str, csrf_field: &str) -> String {
+    format!(
+        r#"<form action="{action}" method="post">
+            {csrf_field}
+            <label>Code from the app, or a recovery code
+                <input type="text" autocomplete="one-time-code" name="code">
+            </label>
+            <button type="submit">{label}</button>
+        </form>"#
+    )
+}
+
+/// Recovery codes are only shown once, right after they are generated.
+pub fn recovery_codes_page(codes: &[String]) -> HttpResponse {
+    let mut codes_html = String::new();
+    for code in codes {
+        writeln!(codes_html, "<li><code>{}</code></li>", code).unwrap();
+    }
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!doctype html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+        <title>Recovery codes</title>
+    </head>
+    <body>
+        <p>Two-factor authentication is on.</p>
+        <p>Keep these recovery codes somewhere safe. Each of them can be used
+        once to log in without your authenticator app.
+        They won't be shown again.</p>
+        <ul>
+            {codes_html}
+        </ul>
+        <p><a href="/admin/dashboard">Continue to the dashboard</a></p>
+    </body>
+</html>"#,
+        ))
+}
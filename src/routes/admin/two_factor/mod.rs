@@ -0,0 +1,8 @@
+mod get;
+mod post;
+
+pub use get::two_factor_settings;
+pub use post::{
+    confirm_two_factor_setup, new_recovery_codes, start_two_factor_setup,
+    turn_off_two_factor,
+};
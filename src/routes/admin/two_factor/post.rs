@@ -0,0 +1,122 @@
+use actix_web::{
+    web::{self, ReqData},
+    HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+
+use super::get::recovery_codes_page;
+use crate::{
+    authentication::{
+        disable_two_factor, enable_two_factor, is_two_factor_enabled,
+        is_two_factor_required, regenerate_recovery_codes, start_enrolment,
+        verify_second_factor, TotpEncryptionKey, UserId,
+    },
+    utils::{e500, see_other},
+};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    code: String,
+}
+
+#[tracing::instrument(
+    name = "Start setting up two-factor authentication",
+    skip(user_id, pool, totp_key)
+)]
+pub async fn start_two_factor_setup(
+    user_id: ReqData<UserId>,
+    pool: web::Data<PgPool>,
+    totp_key: web::Data<TotpEncryptionKey>,
+) -> Result<HttpResponse, actix_web::Error> {
+    start_enrolment(&pool, &totp_key, **user_id)
+        .await
+        .map_err(e500)?;
+    Ok(see_other("/admin/two-factor"))
+}
+
+#[tracing::instrument(
+    name = "Confirm the setup of two-factor authentication",
+    skip(user_id, form, pool, totp_key)
+)]
+pub async fn confirm_two_factor_setup(
+    user_id: ReqData<UserId>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    totp_key: web::Data<TotpEncryptionKey>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if is_two_factor_enabled(&pool, **user_id)
+        .await
+        .map_err(e500)?
+    {
+        return Ok(see_other("/admin/two-factor"));
+    }
+    match enable_two_factor(&pool, &totp_key, **user_id, &form.0.code)
+        .await
+        .map_err(e500)?
+    {
+        Some(recovery_codes) => Ok(recovery_codes_page(&recovery_codes)),
+        None => {
+            FlashMessage::error("The authentication code is not valid.").send();
+            Ok(see_other("/admin/two-factor"))
+        }
+    }
+}
+
+#[tracing::instrument(
+    name = "Generate new recovery codes",
+    skip(user_id, form, pool, totp_key)
+)]
+pub async fn new_recovery_codes(
+    user_id: ReqData<UserId>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    totp_key: web::Data<TotpEncryptionKey>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if !is_two_factor_enabled(&pool, **user_id)
+        .await
+        .map_err(e500)?
+    {
+        return Ok(see_other("/admin/two-factor"));
+    }
+    if !verify_second_factor(&pool, &totp_key, **user_id, &form.0.code)
+        .await
+        .map_err(e500)?
+    {
+        FlashMessage::error("The authentication code is not valid.").send();
+        return Ok(see_other("/admin/two-factor"));
+    }
+    let recovery_codes = regenerate_recovery_codes(&pool, **user_id)
+        .await
+        .map_err(e500)?;
+    Ok(recovery_codes_page(&recovery_codes))
+}
+
+#[tracing::instrument(
+    name = "Turn off two-factor authentication",
+    skip(user_id, form, pool, totp_key)
+)]
+pub async fn turn_off_two_factor(
+    user_id: ReqData<UserId>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    totp_key: web::Data<TotpEncryptionKey>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if is_two_factor_required(&pool).await.map_err(e500)? {
+        FlashMessage::error(
+            "Two-factor authentication is required for all users.",
+        )
+        .send();
+        return Ok(see_other("/admin/two-factor"));
+    }
+    if !verify_second_factor(&pool, &totp_key, **user_id, &form.0.code)
+        .await
+        .map_err(e500)?
+    {
+        FlashMessage::error("The authentication code is not valid.").send();
+        return Ok(see_other("/admin/two-factor"));
+    }
+    disable_two_factor(&pool, **user_id).await.map_err(e500)?;
+    FlashMessage::info("Two-factor authentication has been turned off.").send();
+    Ok(see_other("/admin/two-factor"))
+}
@@ -0,0 +1,65 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::status::{get_username, is_last_active_owner};
+use crate::utils::{e404, e500, see_other};
+
+#[tracing::instrument(name = "Delete an admin user", skip(pool))]
+pub async fn delete_user(
+    user_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let username = get_username(&mut transaction, user_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("This user does not exist."))?;
+    if is_last_active_owner(&mut transaction, user_id)
+        .await
+        .map_err(e500)?
+    {
+        FlashMessage::error("The last active owner can't be deleted.").send();
+        return Ok(see_other("/admin/users"));
+    }
+    // Uploads that were never attached to an issue are only visible to
+    // their uploader
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_attachments
+        WHERE
+            uploaded_by = $1 AND
+            newsletter_issue_id IS NULL
+        "#,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to delete the staged attachments of a user")
+    .map_err(e500)?;
+    sqlx::query!("DELETE FROM users WHERE user_id = $1", user_id)
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to delete a user")
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the deletion of a user")
+        .map_err(e500)?;
+
+    FlashMessage::info(format!(
+        "The user {} has been deleted.",
+        htmlescape::encode_minimal(&username)
+    ))
+    .send();
+    Ok(see_other("/admin/users"))
+}
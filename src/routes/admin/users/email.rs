@@ -0,0 +1,105 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::SubscriberEmail;
+use crate::utils::{e404, e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    email: String,
+}
+
+/// Password reset links are sent to this address.
+/// Leaving it blank removes it.
+#[tracing::instrument(
+    name = "Change the email address of an admin user",
+    skip(form, pool)
+)]
+pub async fn change_user_email(
+    user_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let email = match parse_optional_email(form.0.email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(htmlescape::encode_minimal(&e)).send();
+            return Ok(see_other("/admin/users"));
+        }
+    };
+    if let Some(email) = &email {
+        if is_email_in_use(&pool, email, Some(user_id))
+            .await
+            .map_err(e500)?
+        {
+            FlashMessage::error(
+                "This email address is already used by another user.",
+            )
+            .send();
+            return Ok(see_other("/admin/users"));
+        }
+    }
+
+    let row = sqlx::query!(
+        r#"
+        UPDATE users
+        SET email = $1
+        WHERE user_id = $2
+        RETURNING username
+        "#,
+        email.as_ref().map(|e| e.as_ref()),
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to change the email address of a user")
+    .map_err(e500)?
+    .ok_or_else(|| e404("This user does not exist."))?;
+
+    FlashMessage::info(format!(
+        "The email address of {} has been updated.",
+        htmlescape::encode_minimal(&row.username)
+    ))
+    .send();
+    Ok(see_other("/admin/users"))
+}
+
+pub fn parse_optional_email(
+    email: String,
+) -> Result<Option<SubscriberEmail>, String> {
+    let email = email.trim();
+    if email.is_empty() {
+        Ok(None)
+    } else {
+        SubscriberEmail::parse(email.to_string()).map(Some)
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn is_email_in_use(
+    pool: &PgPool,
+    email: &SubscriberEmail,
+    except_user_id: Option<Uuid>,
+) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM users
+            WHERE
+                email = $1 AND
+                user_id IS DISTINCT FROM $2
+        ) AS "exists!"
+        "#,
+        email.as_ref(),
+        except_user_id
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to check whether an email address is in use")?;
+    Ok(row.exists)
+}
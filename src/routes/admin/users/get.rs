@@ -0,0 +1,273 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::authentication::{get_lockouts, is_two_factor_required, CsrfToken};
+use crate::domain::Role;
+use crate::utils::{capitalize, e500};
+
+struct UserSummary {
+    user_id: Uuid,
+    username: String,
+    email: Option<String>,
+    role: String,
+    created_at: DateTime<Utc>,
+    disabled_at: Option<DateTime<Utc>>,
+    must_change_password: bool,
+    two_factor_enabled: bool,
+}
+
+pub async fn users_list(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: CsrfToken,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let csrf_field = csrf_token.form_field();
+    let users = get_users(&pool).await.map_err(e500)?;
+    let two_factor_required =
+        is_two_factor_required(&pool).await.map_err(e500)?;
+    let mut rows_html = String::new();
+    for user in users {
+        let (status, action, action_label) = match user.disabled_at {
+            Some(disabled_at) => (
+                format!("Disabled on {}", disabled_at.format("%Y-%m-%d")),
+                "enable",
+                "Enable",
+            ),
+            None if user.must_change_password => (
+                "Active (password change pending)".to_string(),
+                "disable",
+                "Disable",
+            ),
+            None => ("Active".to_string(), "disable", "Disable"),
+        };
+        writeln!(
+            rows_html,
+            r#"<tr>
+                <td>{username}</td>
+                <td>
+                    <form action="/admin/users/{user_id}/email" method="post">
+                        {csrf_field}
+                        <input type="email" name="email" value="{email}">
+                        <button type="submit">Save</button>
+                    </form>
+                </td>
+                <td>
+                    <form action="/admin/users/{user_id}/role" method="post">
+                        {csrf_field}
+                        <select name="role">{role_options}</select>
+                        <button type="submit">Change role</button>
+                    </form>
+                </td>
+                <td>{created_at}</td>
+                <td>{status}</td>
+                <td>{two_factor}</td>
+                <td>
+                    <form action="/admin/users/{user_id}/status" method="post">
+                        {csrf_field}
+                        <input hidden type="text" name="action" value="{action}">
+                        <button type="submit">{action_label}</button>
+                    </form>
+                </td>
+                <td>
+                    <form action="/admin/users/{user_id}/delete" method="post">
+                        {csrf_field}
+                        <button type="submit">Delete</button>
+                    </form>
+                </td>
+            </tr>"#,
+            username = htmlescape::encode_minimal(&user.username),
+            email = htmlescape::encode_minimal(
+                user.email.as_deref().unwrap_or_default()
+            ),
+            role_options = role_options(&user.role),
+            two_factor = if user.two_factor_enabled { "On" } else { "Off" },
+            created_at = user.created_at.format("%Y-%m-%d %H:%M UTC"),
+            user_id = user.user_id,
+        )
+        .unwrap();
+    }
+
+    let mut lockouts_html = String::new();
+    for lockout in get_lockouts(&pool).await.map_err(e500)? {
+        writeln!(
+            lockouts_html,
+            r#"<tr>
+                <td>{kind}</td>
+                <td>{key}</td>
+                <td>{failed_attempts}</td>
+                <td>{locked_until}</td>
+                <td>
+                    <form action="/admin/users/lockouts/unlock" method="post">
+                        {csrf_field}
+                        <input hidden type="text" name="kind" value="{kind}">
+                        <input hidden type="text" name="key" value="{key}">
+                        <button type="submit">Unlock</button>
+                    </form>
+                </td>
+            </tr>"#,
+            kind = lockout.key.kind(),
+            key = htmlescape::encode_minimal(lockout.key.key()),
+            failed_attempts = lockout.failed_attempts,
+            locked_until = lockout.locked_until.format("%Y-%m-%d %H:%M UTC"),
+        )
+        .unwrap();
+    }
+    let lockouts_html = if lockouts_html.is_empty() {
+        "<p>Nobody is locked out.</p>".to_string()
+    } else {
+        format!(
+            r#"<table>
+            <tr>
+                <th>Locked out</th>
+                <th></th>
+                <th>Failed logins</th>
+                <th>Until</th>
+                <th></th>
+            </tr>
+            {lockouts_html}
+        </table>"#
+        )
+    };
+
+    let new_user_role_options = role_options(Role::Editor.as_str());
+    let (policy, policy_action, policy_label) = if two_factor_required {
+        ("required for all users", false, "Make it optional")
+    } else {
+        ("optional", true, "Require it for all users")
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!doctype html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+        <title>Admin users</title>
+    </head>
+    <body>
+        {msg_html}
+        <table>
+            <tr>
+                <th>Username</th>
+                <th>Email</th>
+                <th>Role</th>
+                <th>Created at</th>
+                <th>Status</th>
+                <th>Two-factor</th>
+                <th></th>
+                <th></th>
+            </tr>
+            {rows_html}
+        </table>
+        <h2>Failed logins</h2>
+        <p>Usernames and IP addresses with too many failed logins are locked
+        out for a while.</p>
+        {lockouts_html}
+        <h2>Two-factor authentication</h2>
+        <p>Two-factor authentication is {policy}.</p>
+        <form action="/admin/users/two-factor-policy" method="post">
+            {csrf_field}
+            <input hidden type="text" name="required" value="{policy_action}">
+            <button type="submit">{policy_label}</button>
+        </form>
+        <h2>Add a user</h2>
+        <p>New users must choose their own password when they first log in.</p>
+        <form action="/admin/users" method="post">
+            {csrf_field}
+            <label>Username
+                <input
+                    type="text"
+                    placeholder="Enter the username"
+                    name="username"
+                >
+            </label>
+            <br>
+            <label>Email (to reset forgotten passwords)
+                <input
+                    type="email"
+                    placeholder="Enter the email address"
+                    name="email"
+                >
+            </label>
+            <br>
+            <label>Initial password
+                <input
+                    type="password"
+                    placeholder="Enter the initial password"
+                    name="password"
+                >
+            </label>
+            <br>
+            <label>Confirm initial password
+                <input
+                    type="password"
+                    placeholder="Confirm the initial password"
+                    name="password_check"
+                >
+            </label>
+            <br>
+            <label>Role
+                <select name="role">{new_user_role_options}</select>
+            </label>
+            <br>
+            <button type="submit">Add user</button>
+        </form>
+        <p><a href="/admin/dashboard">&lt;- Back</a></p>
+    </body>
+</html>"#,
+        )))
+}
+
+fn role_options(selected: &str) -> String {
+    let mut options_html = String::new();
+    for role in Role::ALL {
+        writeln!(
+            options_html,
+            r#"<option value="{value}"{selected}>{label}</option>"#,
+            value = role.as_str(),
+            selected = if role.as_str() == selected {
+                " selected"
+            } else {
+                ""
+            },
+            label = capitalize(role.as_str()),
+        )
+        .unwrap();
+    }
+    options_html
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_users(pool: &PgPool) -> Result<Vec<UserSummary>, anyhow::Error> {
+    let users = sqlx::query_as!(
+        UserSummary,
+        r#"
+        SELECT
+            user_id,
+            username,
+            email,
+            created_at,
+            disabled_at,
+            must_change_password,
+            role,
+            totp_enabled_at IS NOT NULL AS "two_factor_enabled!"
+        FROM users
+        ORDER BY username
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve users")?;
+    Ok(users)
+}
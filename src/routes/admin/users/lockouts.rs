@@ -0,0 +1,28 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+
+use crate::authentication::{clear_failed_logins, ThrottleKey};
+use crate::utils::{e400, e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    kind: String,
+    key: String,
+}
+
+#[tracing::instrument(
+    name = "Unlock a login",
+    skip(form, pool),
+    fields(kind = %form.kind, key = %form.key)
+)]
+pub async fn unlock_login(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData { kind, key } = form.0;
+    let key = ThrottleKey::parse(&kind, key).map_err(e400)?;
+    clear_failed_logins(&pool, &key).await.map_err(e500)?;
+    FlashMessage::info(format!("{} has been unlocked.", key.key())).send();
+    Ok(see_other("/admin/users"))
+}
@@ -0,0 +1,17 @@
+mod delete;
+mod email;
+mod get;
+mod lockouts;
+mod policy;
+mod post;
+mod role;
+mod status;
+
+pub use delete::delete_user;
+pub use email::change_user_email;
+pub use get::users_list;
+pub use lockouts::unlock_login;
+pub use policy::set_two_factor_policy;
+pub use post::create_user;
+pub use role::change_user_role;
+pub use status::change_user_status;
@@ -0,0 +1,34 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+
+use crate::authentication::set_two_factor_required;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    required: bool,
+}
+
+/// Users without two-factor authentication must set it up the next time
+/// they use the admin pages.
+#[tracing::instrument(
+    name = "Change the two-factor policy",
+    skip(form, pool),
+    fields(required = %form.required)
+)]
+pub async fn set_two_factor_policy(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    set_two_factor_required(&pool, form.0.required)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info(if form.0.required {
+        "Two-factor authentication is now required for all users."
+    } else {
+        "Two-factor authentication is now optional."
+    })
+    .send();
+    Ok(see_other("/admin/users"))
+}
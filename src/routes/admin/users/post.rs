@@ -0,0 +1,130 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use super::email::{is_email_in_use, parse_optional_email};
+use crate::{
+    configuration::PasswordHashingSettings,
+    domain::{Password, PasswordPolicy, Role, Username},
+    utils::{e400, e500, see_other},
+};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    username: String,
+    #[serde(default)]
+    email: String,
+    password: Secret<String>,
+    password_check: Secret<String>,
+    role: String,
+}
+
+#[tracing::instrument(
+    name = "Create an admin user",
+    skip(form, pool, hashing, password_policy),
+    fields(username = %form.username, role = %form.role)
+)]
+pub async fn create_user(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashingSettings>,
+    password_policy: web::Data<PasswordPolicy>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData {
+        username,
+        email,
+        password,
+        password_check,
+        role,
+    } = form.0;
+    let role: Role = role.try_into().map_err(e400)?;
+
+    let username = match Username::parse(username) {
+        Ok(username) => username,
+        Err(e) => {
+            FlashMessage::error(htmlescape::encode_minimal(&e)).send();
+            return Ok(see_other("/admin/users"));
+        }
+    };
+    let email = match parse_optional_email(email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(htmlescape::encode_minimal(&e)).send();
+            return Ok(see_other("/admin/users"));
+        }
+    };
+    if password.expose_secret() != password_check.expose_secret() {
+        FlashMessage::error(
+            "You entered two different passwords - the field values must match.",
+        )
+        .send();
+        return Ok(see_other("/admin/users"));
+    }
+    let password = match Password::parse(
+        password.expose_secret().clone(),
+        username.as_ref(),
+        &password_policy,
+    ) {
+        Ok(password) => Secret::new(password),
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other("/admin/users"));
+        }
+    };
+    if username_exists(&username, &pool).await.map_err(e500)? {
+        FlashMessage::error(format!(
+            "The username {} is already taken.",
+            htmlescape::encode_minimal(username.as_ref())
+        ))
+        .send();
+        return Ok(see_other("/admin/users"));
+    }
+
+    if let Some(email) = &email {
+        if is_email_in_use(&pool, email, None).await.map_err(e500)? {
+            FlashMessage::error(
+                "This email address is already used by another user.",
+            )
+            .send();
+            return Ok(see_other("/admin/users"));
+        }
+    }
+
+    crate::authentication::create_user(
+        &username,
+        email.as_ref(),
+        password,
+        role,
+        &hashing,
+        &pool,
+    )
+    .await
+    .map_err(e500)?;
+    FlashMessage::info(format!(
+        "The user {} has been created.",
+        htmlescape::encode_minimal(username.as_ref())
+    ))
+    .send();
+    Ok(see_other("/admin/users"))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn username_exists(
+    username: &Username,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM users WHERE username = $1
+        ) AS "exists!"
+        "#,
+        username.as_ref()
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to check whether a username is taken")?;
+    Ok(row.exists)
+}
@@ -0,0 +1,69 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::status::{get_username, is_last_active_owner};
+use crate::domain::Role;
+use crate::utils::{e400, e404, e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    role: String,
+}
+
+#[tracing::instrument(
+    name = "Change the role of an admin user",
+    skip(form, pool),
+    fields(role = %form.role)
+)]
+pub async fn change_user_role(
+    user_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let role: Role = form.0.role.try_into().map_err(e400)?;
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let username = get_username(&mut transaction, user_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("This user does not exist."))?;
+    if role != Role::Owner
+        && is_last_active_owner(&mut transaction, user_id)
+            .await
+            .map_err(e500)?
+    {
+        FlashMessage::error("The last active owner must remain an owner.")
+            .send();
+        return Ok(see_other("/admin/users"));
+    }
+    sqlx::query!(
+        "UPDATE users SET role = $1 WHERE user_id = $2",
+        role.as_str(),
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to change the role of a user")
+    .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the user role change")
+        .map_err(e500)?;
+
+    FlashMessage::info(format!(
+        "The role of {} is now {}.",
+        htmlescape::encode_minimal(&username),
+        role.as_str()
+    ))
+    .send();
+    Ok(see_other("/admin/users"))
+}
@@ -0,0 +1,122 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::utils::{e400, e404, e500, see_other};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    action: String,
+}
+
+#[tracing::instrument(
+    name = "Change the status of an admin user",
+    skip(form, pool),
+    fields(action = %form.action)
+)]
+pub async fn change_user_status(
+    user_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let disable = match form.0.action.as_str() {
+        "disable" => true,
+        "enable" => false,
+        other => {
+            return Err(e400(format!("{} is not a valid user action", other)))
+        }
+    };
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let username = get_username(&mut transaction, user_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("This user does not exist."))?;
+    if disable
+        && is_last_active_owner(&mut transaction, user_id)
+            .await
+            .map_err(e500)?
+    {
+        FlashMessage::error("The last active owner can't be disabled.").send();
+        return Ok(see_other("/admin/users"));
+    }
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET disabled_at = CASE WHEN $1 THEN now() END
+        WHERE
+            user_id = $2 AND
+            (disabled_at IS NULL) = $1
+        "#,
+        disable,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to change the status of a user")
+    .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the user status change")
+        .map_err(e500)?;
+
+    FlashMessage::info(format!(
+        "The user {} has been {}d.",
+        htmlescape::encode_minimal(&username),
+        form.0.action
+    ))
+    .send();
+    Ok(see_other("/admin/users"))
+}
+
+#[tracing::instrument(skip(transaction))]
+pub async fn get_username(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+) -> Result<Option<String>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT username
+        FROM users
+        WHERE user_id = $1
+        FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_optional(transaction)
+    .await
+    .context("Failed to retrieve a user")?;
+    Ok(row.map(|r| r.username))
+}
+
+/// Lock the active owners, so that two owners can't concurrently disable,
+/// delete or demote each other and leave nobody able to manage users.
+#[tracing::instrument(skip(transaction))]
+pub async fn is_last_active_owner(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let active_owners = sqlx::query!(
+        r#"
+        SELECT user_id
+        FROM users
+        WHERE
+            disabled_at IS NULL AND
+            role = 'owner'
+        FOR UPDATE
+        "#
+    )
+    .fetch_all(transaction)
+    .await
+    .context("Failed to retrieve the active owners")?;
+    Ok(active_owners.iter().all(|row| row.user_id == user_id)
+        && active_owners.iter().any(|row| row.user_id == user_id))
+}
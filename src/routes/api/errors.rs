@@ -0,0 +1,119 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        header::{HeaderValue, WWW_AUTHENTICATE},
+        StatusCode,
+    },
+    middleware::Next,
+    HttpResponse, ResponseError,
+};
+
+/// An error response of the API, with a body like
+/// `{"error": {"code": "not_found", "message": "..."}}`.
+/// Its status and message are the ones of the error it replaces, be it a
+/// `SubscribeError`, a `ConfirmError` or one of the errors of `utils`.
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+    /// Kept from the original response, for requests without a valid API
+    /// token.
+    www_authenticate: Option<HeaderValue>,
+    /// How the original error was logged.
+    details: String,
+}
+
+impl ApiError {
+    fn new<B>(error: &actix_web::Error, response: &HttpResponse<B>) -> Self {
+        let status = response.status();
+        // The details of unexpected errors are only logged
+        let message = if status.is_server_error() {
+            "Something went wrong on our side, please try again later."
+                .to_string()
+        } else {
+            error.to_string()
+        };
+        Self {
+            status,
+            message,
+            www_authenticate: response.headers().get(WWW_AUTHENTICATE).cloned(),
+            details: format!("{:?}", error),
+        }
+    }
+
+    /// The reason phrase of the status, in snake case: `not_found`,
+    /// `unauthorized`...
+    fn code(&self) -> String {
+        self.status
+            .canonical_reason()
+            .unwrap_or("error")
+            .to_lowercase()
+            .replace(' ', "_")
+    }
+}
+
+impl std::fmt::Debug for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.details)
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ErrorBody<'a> {
+    error: ErrorDetails<'a>,
+}
+
+#[derive(serde::Serialize)]
+struct ErrorDetails<'a> {
+    code: &'a str,
+    message: &'a str,
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        let mut response = HttpResponse::build(self.status);
+        if let Some(www_authenticate) = &self.www_authenticate {
+            response
+                .insert_header((WWW_AUTHENTICATE, www_authenticate.clone()));
+        }
+        response.json(ErrorBody {
+            error: ErrorDetails {
+                code: &self.code(),
+                message: &self.message,
+            },
+        })
+    }
+}
+
+/// Replace every error response of the API with an `ApiError`, including
+/// the ones of extractors and middleware, so that clients always get JSON.
+pub async fn json_errors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    match next.call(req).await {
+        // Errors of handlers and extractors are already responses
+        Ok(response) => {
+            let Some(error) = response.response().error() else {
+                return Ok(response.map_into_boxed_body());
+            };
+            let error = ApiError::new(error, response.response());
+            Ok(response.into_response(HttpResponse::from_error(error)))
+        }
+        // Errors of middleware become responses once they reach the server
+        Err(e) => {
+            let error = ApiError::new(&e, &e.error_response());
+            Err(error.into())
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use actix_web::{
+    http::header::LOCATION,
+    web::{self, ReqData},
+    HttpResponse,
+};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::pagination::{Page, PageParameters};
+use crate::{
+    authentication::UserId,
+    configuration::{PublishingSettings, ScheduleSettings},
+    domain::PageCursor,
+    routes::admin::{
+        publish_issue, store_draft, IssueFormData, NewDraftOutcome,
+        PublishOutcome,
+    },
+    utils::{e400, e404, e409, e500},
+};
+
+#[derive(serde::Serialize)]
+pub struct IssueSummary {
+    id: Uuid,
+    title: String,
+    slug: String,
+    state: String,
+    created_at: DateTime<Utc>,
+    published_at: Option<DateTime<Utc>>,
+}
+
+#[derive(serde::Serialize)]
+pub struct Issue {
+    id: Uuid,
+    title: String,
+    slug: String,
+    state: String,
+    html_content: String,
+    text_content: String,
+    hidden_from_archive: bool,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    published_at: Option<DateTime<Utc>>,
+    delivery_status: String,
+    n_recipients: i32,
+}
+
+/// An issue, with the lint warnings of its content.
+#[derive(serde::Serialize)]
+struct IssueWithWarnings {
+    #[serde(flatten)]
+    issue: Issue,
+    warnings: Vec<String>,
+}
+
+/// Issues from the most recent.
+#[tracing::instrument(skip(parameters, pool))]
+pub async fn api_list_issues(
+    parameters: web::Query<PageParameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let cursor = parameters.cursor()?;
+    let limit = parameters.limit()?;
+    let issues = sqlx::query_as!(
+        IssueSummary,
+        r#"
+        SELECT
+            newsletter_issue_id AS id,
+            title,
+            slug,
+            state,
+            created_at,
+            published_at
+        FROM newsletter_issues
+        WHERE
+            $1::timestamptz IS NULL OR
+            (created_at, newsletter_issue_id) < ($1::timestamptz, $2::uuid)
+        ORDER BY created_at DESC, newsletter_issue_id DESC
+        LIMIT $3
+        "#,
+        cursor.map(|c| c.created_at),
+        cursor.map(|c| c.id),
+        limit + 1
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to retrieve a page of issues")
+    .map_err(e500)?;
+    Ok(
+        HttpResponse::Ok().json(Page::new(issues, limit, |i| PageCursor {
+            created_at: i.created_at,
+            id: i.id,
+        })),
+    )
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn api_get_issue(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue = get_issue(&pool, *issue_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("This newsletter issue does not exist."))?;
+    Ok(HttpResponse::Ok().json(issue))
+}
+
+/// Takes the fields of the form of the admin pages, and stores a draft:
+/// publishing is a separate request, which needs the `publish` scope.
+#[tracing::instrument(skip(body, pool), fields(user_id=%*user_id))]
+pub async fn api_create_issue(
+    body: web::Json<IssueFormData>,
+    pool: web::Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (issue_id, warnings) = match store_draft(&pool, **user_id, &body)
+        .await
+        .map_err(e500)?
+    {
+        NewDraftOutcome::Saved { issue_id, warnings } => (issue_id, warnings),
+        NewDraftOutcome::Invalid(violation_messages) => {
+            return Err(e400(violation_messages.join("; ")));
+        }
+    };
+    let issue = get_issue(&pool, issue_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e500("The new issue was not found"))?;
+    Ok(HttpResponse::Created()
+        .insert_header((LOCATION, format!("/api/v1/issues/{}", issue_id)))
+        .json(IssueWithWarnings { issue, warnings }))
+}
+
+#[tracing::instrument(
+    skip(pool, publishing, schedule, user_id),
+    fields(user_id=%*user_id)
+)]
+pub async fn api_publish_issue(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    publishing: web::Data<PublishingSettings>,
+    schedule: web::Data<ScheduleSettings>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let warnings =
+        match publish_issue(&pool, &publishing, &schedule, issue_id, **user_id)
+            .await
+            .map_err(e500)?
+        {
+            PublishOutcome::Published(warnings) => warnings,
+            PublishOutcome::NotFound => {
+                return Err(e404("This newsletter issue does not exist."));
+            }
+            PublishOutcome::AlreadyPublished => {
+                return Err(e409("This issue has already been published."));
+            }
+            PublishOutcome::NotApproved => {
+                return Err(e409("Only approved issues can be published."));
+            }
+            PublishOutcome::Invalid(violation_messages) => {
+                return Err(e400(violation_messages.join("; ")));
+            }
+        };
+    let issue = get_issue(&pool, issue_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("This newsletter issue does not exist."))?;
+    Ok(HttpResponse::Ok().json(IssueWithWarnings { issue, warnings }))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_issue(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<Option<Issue>, anyhow::Error> {
+    sqlx::query_as!(
+        Issue,
+        r#"
+        SELECT
+            newsletter_issue_id AS id,
+            title,
+            slug,
+            state,
+            html_content,
+            text_content,
+            hidden_from_archive,
+            created_at,
+            updated_at,
+            published_at,
+            delivery_status,
+            n_recipients
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve a newsletter issue")
+}
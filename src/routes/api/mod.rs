@@ -0,0 +1,15 @@
+//! The JSON API, for scripts and integrations. Requests are authenticated
+//! with API tokens, and errors are reported as JSON too.
+mod errors;
+mod issues;
+mod pagination;
+mod subscribers;
+
+pub use errors::{json_errors, ApiError};
+pub use issues::{
+    api_create_issue, api_get_issue, api_list_issues, api_publish_issue,
+};
+pub use subscribers::{
+    api_create_subscriber, api_get_subscriber, api_list_subscribers,
+    api_update_subscriber,
+};
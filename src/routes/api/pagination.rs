@@ -0,0 +1,60 @@
+use crate::{domain::PageCursor, utils::e400};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 100;
+
+#[derive(serde::Deserialize)]
+pub struct PageParameters {
+    /// The `next_cursor` of the previous page, if any.
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+impl PageParameters {
+    pub fn cursor(&self) -> Result<Option<PageCursor>, actix_web::Error> {
+        self.cursor
+            .as_deref()
+            .map(PageCursor::parse)
+            .transpose()
+            .map_err(e400)
+    }
+
+    pub fn limit(&self) -> Result<i64, actix_web::Error> {
+        match self.limit.unwrap_or(DEFAULT_PAGE_SIZE) {
+            limit @ 1..=MAX_PAGE_SIZE => Ok(limit),
+            _ => Err(e400(format!(
+                "The limit must be between 1 and {}.",
+                MAX_PAGE_SIZE
+            ))),
+        }
+    }
+}
+
+/// A page of a list, with the cursor of the next page unless it is the
+/// last one.
+#[derive(serde::Serialize)]
+pub struct Page<T> {
+    data: Vec<T>,
+    next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// `items` must have been fetched with a limit one higher than `limit`,
+    /// to tell whether there is a next page.
+    pub fn new(
+        mut items: Vec<T>,
+        limit: i64,
+        cursor: impl Fn(&T) -> PageCursor,
+    ) -> Self {
+        let next_cursor = if items.len() as i64 > limit {
+            items.truncate(limit as usize);
+            items.last().map(|item| cursor(item).encode())
+        } else {
+            None
+        };
+        Self {
+            data: items,
+            next_cursor,
+        }
+    }
+}
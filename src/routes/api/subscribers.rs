@@ -0,0 +1,307 @@
+use actix_web::{http::header::LOCATION, web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::pagination::{Page, PageParameters};
+use crate::{
+    domain::{
+        NewSubscriber, PageCursor, SubscriberEmail, SubscriberName,
+        SubscriberTimeZone,
+    },
+    email_client::EmailClient,
+    routes::subscriptions::{
+        insert_subscriber, send_confirmation_email, store_token, FormData,
+        SubscribeError,
+    },
+    startup::ApplicationBaseUrl,
+    utils::{e404, e409, e500},
+};
+
+#[derive(serde::Serialize)]
+pub struct Subscriber {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+    do_not_track: bool,
+    time_zone: Option<String>,
+}
+
+/// Subscribers in the order they subscribed.
+#[tracing::instrument(skip(parameters, pool))]
+pub async fn api_list_subscribers(
+    parameters: web::Query<PageParameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let cursor = parameters.cursor()?;
+    let limit = parameters.limit()?;
+    let subscribers = sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT
+            id,
+            email,
+            name,
+            status,
+            subscribed_at,
+            do_not_track,
+            time_zone
+        FROM subscriptions
+        WHERE
+            $1::timestamptz IS NULL OR
+            (subscribed_at, id) > ($1::timestamptz, $2::uuid)
+        ORDER BY subscribed_at, id
+        LIMIT $3
+        "#,
+        cursor.map(|c| c.created_at),
+        cursor.map(|c| c.id),
+        limit + 1
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to retrieve a page of subscribers")
+    .map_err(e500)?;
+    Ok(
+        HttpResponse::Ok().json(Page::new(subscribers, limit, |s| {
+            PageCursor {
+                created_at: s.subscribed_at,
+                id: s.id,
+            }
+        })),
+    )
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn api_get_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber = get_subscriber(&pool, *subscriber_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("The subscriber was not found."))?;
+    Ok(HttpResponse::Ok().json(subscriber))
+}
+
+/// Takes the fields of the subscription form, and sends the same
+/// confirmation email.
+#[tracing::instrument(skip(body, pool, email_client, base_url))]
+pub async fn api_create_subscriber(
+    body: web::Json<FormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let new_subscriber: NewSubscriber = body
+        .into_inner()
+        .try_into()
+        .map_err(SubscribeError::ValidationError)?;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(SubscribeError::UnexpectedError)?;
+    // Subscribing twice through the form is harmless, but API clients
+    // should know that the subscriber was not created
+    let existing = sqlx::query!(
+        "SELECT id FROM subscriptions WHERE email = $1",
+        new_subscriber.email.as_ref()
+    )
+    .fetch_optional(&mut transaction)
+    .await
+    .context("Failed to look for an existing subscriber")
+    .map_err(SubscribeError::UnexpectedError)?;
+    if existing.is_some() {
+        return Err(e409("A subscriber with this email already exists."));
+    }
+    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
+        .await
+        .context("Failed to insert new subscriber in the database")
+        .map_err(SubscribeError::UnexpectedError)?;
+    let subscription_token = store_token(&mut transaction, subscriber_id)
+        .await
+        .context("Failed to store the confirmation token for a new subscriber")
+        .map_err(SubscribeError::UnexpectedError)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to store a new subscriber")
+        .map_err(SubscribeError::UnexpectedError)?;
+
+    send_confirmation_email(
+        &email_client,
+        &new_subscriber.email,
+        &base_url.0,
+        &subscription_token,
+    )
+    .await
+    .context("Failed to send confirmation email")
+    .map_err(SubscribeError::UnexpectedError)?;
+    let subscriber = get_subscriber(&pool, subscriber_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e500("The new subscriber was not found"))?;
+    Ok(HttpResponse::Created()
+        .insert_header((
+            LOCATION,
+            format!("/api/v1/subscribers/{}", subscriber_id),
+        ))
+        .json(subscriber))
+}
+
+/// Fields left out are not changed.
+#[derive(serde::Deserialize)]
+pub struct SubscriberChanges {
+    email: Option<String>,
+    name: Option<String>,
+    do_not_track: Option<bool>,
+    /// An empty string removes the time zone.
+    time_zone: Option<String>,
+}
+
+/// A new email address must be confirmed, like the first one was.
+#[tracing::instrument(skip(changes, pool, email_client, base_url))]
+pub async fn api_update_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    changes: web::Json<SubscriberChanges>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let changes = changes.into_inner();
+    let email = changes
+        .email
+        .map(SubscriberEmail::parse)
+        .transpose()
+        .map_err(SubscribeError::ValidationError)?;
+    let name = changes
+        .name
+        .map(SubscriberName::parse)
+        .transpose()
+        .map_err(SubscribeError::ValidationError)?;
+    let time_zone = match changes.time_zone.as_deref().map(str::trim) {
+        None => None,
+        Some("") => Some(None),
+        Some(time_zone) => Some(Some(
+            SubscriberTimeZone::parse(time_zone)
+                .map_err(SubscribeError::ValidationError)?,
+        )),
+    };
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(SubscribeError::UnexpectedError)?;
+    let current = sqlx::query!(
+        "SELECT email FROM subscriptions WHERE id = $1 FOR UPDATE",
+        subscriber_id
+    )
+    .fetch_optional(&mut transaction)
+    .await
+    .context("Failed to retrieve a subscriber")
+    .map_err(SubscribeError::UnexpectedError)?
+    .ok_or_else(|| e404("The subscriber was not found."))?;
+    let new_email = email.filter(|email| email.as_ref() != current.email);
+    let result = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET
+            email = COALESCE($2, email),
+            name = COALESCE($3, name),
+            do_not_track = COALESCE($4, do_not_track),
+            time_zone = CASE WHEN $5 THEN $6 ELSE time_zone END,
+            status = CASE
+                WHEN $2::text IS NULL THEN status
+                ELSE 'pending_confirmation'
+            END
+        WHERE id = $1
+        "#,
+        subscriber_id,
+        new_email.as_ref().map(|email| email.as_ref()),
+        name.as_ref().map(|name| name.as_ref()),
+        changes.do_not_track,
+        time_zone.is_some(),
+        time_zone
+            .as_ref()
+            .and_then(Option::as_ref)
+            .map(|tz| tz.as_ref()),
+    )
+    .execute(&mut transaction)
+    .await;
+    match result {
+        Err(sqlx::Error::Database(e))
+            if e.constraint() == Some("subscriptions_email_key") =>
+        {
+            return Err(e409("A subscriber with this email already exists."));
+        }
+        result => {
+            result
+                .context("Failed to update a subscriber")
+                .map_err(SubscribeError::UnexpectedError)?;
+        }
+    }
+    let subscription_token = match &new_email {
+        Some(_) => Some(
+            store_token(&mut transaction, subscriber_id)
+                .await
+                .context("Failed to store the confirmation token")
+                .map_err(SubscribeError::UnexpectedError)?,
+        ),
+        None => None,
+    };
+    transaction
+        .commit()
+        .await
+        .context("Failed to update a subscriber")
+        .map_err(SubscribeError::UnexpectedError)?;
+
+    if let (Some(email), Some(subscription_token)) =
+        (&new_email, &subscription_token)
+    {
+        send_confirmation_email(
+            &email_client,
+            email,
+            &base_url.0,
+            subscription_token,
+        )
+        .await
+        .context("Failed to send confirmation email")
+        .map_err(SubscribeError::UnexpectedError)?;
+    }
+    let subscriber = get_subscriber(&pool, subscriber_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("The subscriber was not found."))?;
+    Ok(HttpResponse::Ok().json(subscriber))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_subscriber(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<Subscriber>, anyhow::Error> {
+    sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT
+            id,
+            email,
+            name,
+            status,
+            subscribed_at,
+            do_not_track,
+            time_zone
+        FROM subscriptions
+        WHERE id = $1
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve a subscriber")
+}
@@ -0,0 +1,125 @@
+use actix_web::{
+    http::header::{ContentType, CONTENT_SECURITY_POLICY},
+    web, HttpResponse,
+};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use super::ARCHIVE_CONTENT_SECURITY_POLICY;
+use crate::utils::{e404, e500};
+
+const ISSUES_PER_PAGE: i64 = 10;
+
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    page: Option<i64>,
+}
+
+struct ArchivedIssue {
+    title: String,
+    slug: String,
+    published_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Browse the newsletter archive", skip_all)]
+pub async fn archive(
+    query: web::Query<QueryParams>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let page = query.page.unwrap_or(1).max(1);
+    let offset = (page - 1)
+        .checked_mul(ISSUES_PER_PAGE)
+        .ok_or_else(|| e404("This page of the archive does not exist."))?;
+    let mut issues = get_archived_issues(&pool, offset).await.map_err(e500)?;
+    let has_next_page = issues.len() as i64 > ISSUES_PER_PAGE;
+    issues.truncate(ISSUES_PER_PAGE as usize);
+
+    let mut issues_html = String::new();
+    for issue in &issues {
+        writeln!(
+            issues_html,
+            r#"<li><a href="/archive/{}">{}</a> - {}</li>"#,
+            issue.slug,
+            htmlescape::encode_minimal(&issue.title),
+            issue.published_at.format("%B %-d, %Y"),
+        )
+        .unwrap();
+    }
+    if issues.is_empty() {
+        issues_html.push_str("<li>No issues have been published yet.</li>");
+    }
+
+    let mut pagination_html = String::new();
+    if page > 1 {
+        write!(
+            pagination_html,
+            r#"<a href="/archive?page={}">&lt;- Newer issues</a> "#,
+            page - 1
+        )
+        .unwrap();
+    }
+    if let Some(next_page) = page.checked_add(1).filter(|_| has_next_page) {
+        write!(
+            pagination_html,
+            r#"<a href="/archive?page={}">Older issues -&gt;</a>"#,
+            next_page
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .insert_header((
+            CONTENT_SECURITY_POLICY,
+            ARCHIVE_CONTENT_SECURITY_POLICY,
+        ))
+        .body(format!(
+            r#"<!doctype html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+        <title>Newsletter archive</title>
+    </head>
+    <body>
+        <h1>Newsletter archive</h1>
+        <ul>
+        {issues_html}
+        </ul>
+        <p>{pagination_html}</p>
+    </body>
+</html>"#,
+        )))
+}
+
+/// Fetch one page of archived issues, plus one extra row to know
+/// whether a next page exists.
+#[tracing::instrument(skip(pool))]
+async fn get_archived_issues(
+    pool: &PgPool,
+    offset: i64,
+) -> Result<Vec<ArchivedIssue>, anyhow::Error> {
+    let issues = sqlx::query_as!(
+        ArchivedIssue,
+        r#"
+        SELECT
+            title,
+            slug,
+            published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE
+            state = 'published' AND
+            hidden_from_archive = false
+        ORDER BY published_at DESC
+        LIMIT $1
+        OFFSET $2
+        "#,
+        ISSUES_PER_PAGE + 1,
+        offset,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve archived newsletter issues")?;
+    Ok(issues)
+}
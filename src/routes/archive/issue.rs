@@ -0,0 +1,78 @@
+use actix_web::{
+    http::header::{ContentType, CONTENT_SECURITY_POLICY},
+    web, HttpResponse,
+};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use super::ARCHIVE_CONTENT_SECURITY_POLICY;
+use crate::utils::{e404, e500};
+
+struct ArchivedIssue {
+    title: String,
+    html_content: String,
+    published_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Read an archived newsletter issue", skip(pool))]
+pub async fn archive_issue(
+    slug: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue = get_archived_issue(&pool, &slug)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("This newsletter issue does not exist."))?;
+    let title = htmlescape::encode_minimal(&issue.title);
+    let published_at = issue.published_at.format("%B %-d, %Y");
+    let html_content = issue.html_content;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .insert_header((
+            CONTENT_SECURITY_POLICY,
+            ARCHIVE_CONTENT_SECURITY_POLICY,
+        ))
+        .body(format!(
+            r#"<!doctype html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+        <title>{title}</title>
+    </head>
+    <body>
+        <h1>{title}</h1>
+        <p><i>Published on {published_at}</i></p>
+        {html_content}
+        <p><a href="/archive">&lt;- All issues</a></p>
+    </body>
+</html>"#,
+        )))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_archived_issue(
+    pool: &PgPool,
+    slug: &str,
+) -> Result<Option<ArchivedIssue>, anyhow::Error> {
+    let issue = sqlx::query_as!(
+        ArchivedIssue,
+        r#"
+        SELECT
+            title,
+            html_content,
+            published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE
+            slug = $1 AND
+            state = 'published' AND
+            hidden_from_archive = false
+        "#,
+        slug
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve an archived newsletter issue")?;
+    Ok(issue)
+}
@@ -0,0 +1,9 @@
+mod index;
+mod issue;
+
+pub use index::archive;
+pub use issue::archive_issue;
+
+/// Issues are written by editors, but served from the origin of the admin
+/// pages: archive pages must not run their scripts.
+const ARCHIVE_CONTENT_SECURITY_POLICY: &str = "sandbox; script-src 'none'";
@@ -0,0 +1,70 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{SecondsFormat, Utc};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use super::issues::{get_feed_issues, FeedValidators, FEED_TITLE};
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::e500;
+
+#[tracing::instrument(name = "Serve the Atom feed", skip_all)]
+pub async fn atom_feed(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issues = get_feed_issues(&pool).await.map_err(e500)?;
+    let validators = FeedValidators::new("atom", &issues);
+
+    Ok(validators.response(
+        &request,
+        "application/atom+xml; charset=utf-8",
+        || {
+            let base_url = &base_url.0;
+            let mut entries = String::new();
+            for issue in &issues {
+                let published_at = issue
+                    .published_at
+                    .to_rfc3339_opts(SecondsFormat::Secs, true);
+                let updated_at =
+                    issue.updated_at.to_rfc3339_opts(SecondsFormat::Secs, true);
+                writeln!(
+                    entries,
+                    r#"  <entry>
+    <title>{title}</title>
+    <link rel="alternate" type="text/html" href="{base_url}/archive/{slug}" />
+    <id>urn:uuid:{issue_id}</id>
+    <published>{published_at}</published>
+    <updated>{updated_at}</updated>
+    <content type="html">{content}</content>
+  </entry>"#,
+                    title = encode_minimal(&issue.title),
+                    slug = issue.slug,
+                    issue_id = issue.newsletter_issue_id,
+                    content = encode_minimal(&issue.html_content),
+                )
+                .unwrap();
+            }
+            // Atom requires an `updated` element even for an empty feed
+            let updated = issues
+                .iter()
+                .map(|issue| issue.updated_at)
+                .max()
+                .unwrap_or_else(Utc::now)
+                .to_rfc3339_opts(SecondsFormat::Secs, true);
+
+            format!(
+                r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>{FEED_TITLE}</title>
+  <id>{base_url}/feed.atom</id>
+  <link rel="self" type="application/atom+xml" href="{base_url}/feed.atom" />
+  <link rel="alternate" type="text/html" href="{base_url}/archive" />
+  <updated>{updated}</updated>
+{entries}</feed>
+"#
+            )
+        },
+    ))
+}
@@ -0,0 +1,160 @@
+use std::time::{Duration, SystemTime};
+
+use actix_web::http::header::{
+    ETag, EntityTag, Header, HttpDate, IfModifiedSince, IfNoneMatch,
+    LastModified,
+};
+use actix_web::{HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub const FEED_TITLE: &str = "Our newsletter";
+const FEED_SIZE: i64 = 20;
+
+pub struct FeedIssue {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub slug: String,
+    pub html_content: String,
+    pub published_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The most recent issues visible in the public archive, newest first.
+#[tracing::instrument(skip_all)]
+pub async fn get_feed_issues(
+    pool: &PgPool,
+) -> Result<Vec<FeedIssue>, anyhow::Error> {
+    let issues = sqlx::query_as!(
+        FeedIssue,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            slug,
+            html_content,
+            published_at AS "published_at!",
+            updated_at
+        FROM newsletter_issues
+        WHERE
+            state = 'published' AND
+            hidden_from_archive = false
+        ORDER BY published_at DESC
+        LIMIT $1
+        "#,
+        FEED_SIZE,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the issues to include in the feed")?;
+    Ok(issues)
+}
+
+pub struct FeedValidators {
+    etag: EntityTag,
+    last_modified: Option<HttpDate>,
+}
+
+impl FeedValidators {
+    /// The entity tag changes whenever an issue enters or leaves the feed,
+    /// `Last-Modified` follows the most recent update to any of its issues.
+    pub fn new(kind: &str, issues: &[FeedIssue]) -> Self {
+        // The hash must not change across releases, or every client would
+        // download the feed again after a deployment
+        let mut hasher = Sha256::new();
+        hasher.update(kind.as_bytes());
+        hasher.update([0]);
+        for issue in issues {
+            hasher.update(issue.newsletter_issue_id.as_bytes());
+            hasher.update(issue.updated_at.timestamp_micros().to_be_bytes());
+        }
+        let etag = EntityTag::new_strong(format!("{:x}", hasher.finalize()));
+        let last_modified = issues
+            .iter()
+            .map(|issue| issue.updated_at)
+            .max()
+            .map(to_http_date);
+        Self {
+            etag,
+            last_modified,
+        }
+    }
+
+    /// Whether the client already holds the current representation,
+    /// `If-None-Match` takes precedence over `If-Modified-Since`.
+    fn is_fresh(&self, request: &HttpRequest) -> bool {
+        if request.headers().contains_key(IfNoneMatch::name()) {
+            return match IfNoneMatch::parse(request) {
+                Ok(IfNoneMatch::Any) => true,
+                Ok(IfNoneMatch::Items(tags)) => {
+                    tags.iter().any(|tag| tag.weak_eq(&self.etag))
+                }
+                Err(_) => false,
+            };
+        }
+        match (IfModifiedSince::parse(request), self.last_modified) {
+            (Ok(IfModifiedSince(since)), Some(last_modified)) => {
+                last_modified <= since
+            }
+            _ => false,
+        }
+    }
+
+    pub fn response(
+        &self,
+        request: &HttpRequest,
+        content_type: &'static str,
+        body: impl FnOnce() -> String,
+    ) -> HttpResponse {
+        let is_fresh = self.is_fresh(request);
+        let mut response = if is_fresh {
+            HttpResponse::NotModified()
+        } else {
+            HttpResponse::Ok()
+        };
+        response.insert_header(ETag(self.etag.clone()));
+        if let Some(last_modified) = self.last_modified {
+            response.insert_header(LastModified(last_modified));
+        }
+        if is_fresh {
+            response.finish()
+        } else {
+            response.content_type(content_type).body(body())
+        }
+    }
+}
+
+/// HTTP dates have a one second resolution, sub-second digits are dropped
+/// so that a date sent back by the client compares equal.
+fn to_http_date(date: DateTime<Utc>) -> HttpDate {
+    let seconds = Duration::from_secs(date.timestamp().max(0) as u64);
+    HttpDate::from(SystemTime::UNIX_EPOCH + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use uuid::Uuid;
+
+    use super::{FeedIssue, FeedValidators};
+
+    #[test]
+    fn entity_tags_are_stable() {
+        let issue = FeedIssue {
+            newsletter_issue_id: Uuid::nil(),
+            title: "Title".into(),
+            slug: "title".into(),
+            html_content: "<p>Content</p>".into(),
+            published_at: DateTime::from_timestamp(1_760_000_000, 0).unwrap(),
+            updated_at: DateTime::from_timestamp(1_760_000_000, 0).unwrap(),
+        };
+        let validators = FeedValidators::new("rss", &[issue]);
+        assert_eq!(
+            validators.etag.tag(),
+            "a75ad0d058df2e510a54437673df814afe7d71d368d313647d85cc1a386709cf"
+        );
+    }
+}
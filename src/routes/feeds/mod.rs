@@ -0,0 +1,6 @@
+mod atom;
+mod issues;
+mod rss;
+
+pub use atom::atom_feed;
+pub use rss::rss_feed;
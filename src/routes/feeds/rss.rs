@@ -0,0 +1,67 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use super::issues::{get_feed_issues, FeedValidators, FEED_TITLE};
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::e500;
+
+#[tracing::instrument(name = "Serve the RSS feed", skip_all)]
+pub async fn rss_feed(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issues = get_feed_issues(&pool).await.map_err(e500)?;
+    let validators = FeedValidators::new("rss", &issues);
+
+    Ok(validators.response(
+        &request,
+        "application/rss+xml; charset=utf-8",
+        || {
+            let base_url = &base_url.0;
+            let mut items = String::new();
+            for issue in &issues {
+                writeln!(
+                    items,
+                    r#"    <item>
+      <title>{title}</title>
+      <link>{base_url}/archive/{slug}</link>
+      <guid isPermaLink="false">urn:uuid:{issue_id}</guid>
+      <pubDate>{published_at}</pubDate>
+      <description>{content}</description>
+    </item>"#,
+                    title = encode_minimal(&issue.title),
+                    slug = issue.slug,
+                    issue_id = issue.newsletter_issue_id,
+                    published_at = issue.published_at.to_rfc2822(),
+                    content = encode_minimal(&issue.html_content),
+                )
+                .unwrap();
+            }
+            let last_build_date = issues
+                .first()
+                .map(|issue| {
+                    format!(
+                        "\n    <lastBuildDate>{}</lastBuildDate>",
+                        issue.published_at.to_rfc2822()
+                    )
+                })
+                .unwrap_or_default();
+
+            format!(
+                r#"<?xml version="1.0" encoding="utf-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">
+  <channel>
+    <title>{FEED_TITLE}</title>
+    <link>{base_url}/archive</link>
+    <description>Past issues of {FEED_TITLE}</description>
+    <atom:link href="{base_url}/feed.rss" rel="self" type="application/rss+xml" />{last_build_date}
+{items}  </channel>
+</rss>
+"#
+            )
+        },
+    ))
+}
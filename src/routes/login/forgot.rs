@@ -0,0 +1,239 @@
+use actix_web::{http::header::ContentType, web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::Write;
+use tracing::Instrument;
+use uuid::Uuid;
+
+use super::post::throttle_keys;
+use crate::{
+    authentication::{is_login_allowed, record_failed_login, CsrfToken},
+    configuration::LoginThrottleSettings,
+    domain::{PasswordResetToken, SubscriberEmail},
+    email_client::EmailClient,
+    startup::ApplicationBaseUrl,
+    utils::{e500, see_other},
+};
+
+/// How long a password reset link can be used for.
+const PASSWORD_RESET_TOKEN_LIFETIME_MINUTES: i32 = 60;
+
+pub async fn forgot_password_form(
+    flash_messages: IncomingFlashMessages,
+    csrf_token: CsrfToken,
+) -> HttpResponse {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"
+            <!doctype html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+                    <title>Forgot password</title>
+                </head>
+                <body>
+                    {msg_html}
+                    <p>We will email you a link to choose a new password.</p>
+                    <form action="/login/forgot-password" method="post">
+                        {csrf_field}
+                        <label
+                            >Username
+                            <input
+                                type="text"
+                                placeholder="Enter username"
+                                name="username"
+                            />
+                        </label>
+
+                        <button type="submit">Send reset link</button>
+                    </form>
+                    <p><a href="/login">&lt;- Back</a></p>
+                </body>
+            </html>
+            "#,
+            csrf_field = csrf_token.form_field(),
+        ))
+}
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    username: String,
+}
+
+/// The response is the same whether the account exists or not, so that
+/// the form can't be used to find out usernames: the link is sent in the
+/// background, and requests count as failed logins, whatever the username.
+#[tracing::instrument(
+    name = "Request a password reset",
+    skip(form, pool, email_client, base_url, throttle_settings, request),
+    fields(username = %form.username)
+)]
+pub async fn request_password_reset(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    throttle_settings: web::Data<LoginThrottleSettings>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let username = form.0.username;
+    let throttle_keys = throttle_keys(&request, username.clone());
+    let allowed = is_login_allowed(&pool, &throttle_settings, &throttle_keys)
+        .await
+        .map_err(e500)?;
+    record_failed_login(&pool, &throttle_settings, &throttle_keys)
+        .await
+        .map_err(e500)?;
+    if allowed {
+        tokio::spawn(
+            async move {
+                if let Err(e) = send_reset_link(
+                    &pool,
+                    &email_client,
+                    &base_url.0,
+                    &username,
+                )
+                .await
+                {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error_message = %e,
+                        "Failed to send a password reset link"
+                    );
+                }
+            }
+            .in_current_span(),
+        );
+    }
+    FlashMessage::info(
+        "If this account has an email address, \
+        a link to reset its password has been sent to it.",
+    )
+    .send();
+    Ok(see_other("/login"))
+}
+
+/// Users without an email address, or disabled, get nothing.
+async fn send_reset_link(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+    username: &str,
+) -> Result<(), anyhow::Error> {
+    let Some((user_id, email)) = get_reset_recipient(pool, username).await?
+    else {
+        return Ok(());
+    };
+    let token = PasswordResetToken::generate();
+    store_reset_token(pool, user_id, &token).await?;
+    send_reset_email(email_client, &email, base_url, &token)
+        .await
+        .context("Failed to send a password reset email")
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_reset_recipient(
+    pool: &PgPool,
+    username: &str,
+) -> Result<Option<(Uuid, SubscriberEmail)>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT user_id, email AS "email!"
+        FROM users
+        WHERE
+            username = $1 AND
+            email IS NOT NULL AND
+            disabled_at IS NULL
+        "#,
+        username
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve the email address of a user")?;
+    match row {
+        Some(row) => {
+            let email = SubscriberEmail::parse(row.email)
+                .map_err(anyhow::Error::msg)?;
+            Ok(Some((row.user_id, email)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Issuing a new token invalidates the ones sent before it.
+#[tracing::instrument(skip(pool, token))]
+async fn store_reset_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    token: &PasswordResetToken,
+) -> Result<(), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    sqlx::query!(
+        r#"
+        DELETE FROM password_reset_tokens
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to delete the previous password reset tokens")?;
+    sqlx::query!(
+        r#"
+        INSERT INTO password_reset_tokens (
+            token_hash,
+            user_id,
+            created_at,
+            expires_at
+        )
+        VALUES ($1, $2, now(), now() + make_interval(mins => $3))
+        "#,
+        token.hash(),
+        user_id,
+        PASSWORD_RESET_TOKEN_LIFETIME_MINUTES,
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to store a password reset token")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the password reset token")?;
+    Ok(())
+}
+
+async fn send_reset_email(
+    email_client: &EmailClient,
+    email: &SubscriberEmail,
+    base_url: &str,
+    token: &PasswordResetToken,
+) -> Result<(), reqwest::Error> {
+    let reset_link =
+        format!("{}/login/reset-password?token={}", base_url, token.as_ref());
+    let html_body = format!(
+        "Someone asked to reset the password of your account.<br />\
+        Click <a href=\"{}\">here</a> to choose a new password. \
+        The link expires in {} minutes.<br />\
+        If it wasn't you, you can ignore this email.",
+        reset_link, PASSWORD_RESET_TOKEN_LIFETIME_MINUTES
+    );
+    let text_body = format!(
+        "Someone asked to reset the password of your account.\n\
+        Visit {} to choose a new password. \
+        The link expires in {} minutes.\n\
+        If it wasn't you, you can ignore this email.",
+        reset_link, PASSWORD_RESET_TOKEN_LIFETIME_MINUTES
+    );
+    email_client
+        .send_email(email, "Reset your password", &html_body, &text_body)
+        .await
+}
@@ -2,7 +2,12 @@ use actix_web::{http::header::ContentType, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
 use std::fmt::Write;
 
-pub async fn login_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
+use crate::authentication::CsrfToken;
+
+pub async fn login_form(
+    flash_messages: IncomingFlashMessages,
+    csrf_token: CsrfToken,
+) -> HttpResponse {
     let mut error_html = String::new();
     for m in flash_messages.iter() {
         writeln!(error_html, "<p><i>{}</i></p>", m.content()).unwrap();
@@ -20,6 +25,7 @@ pub async fn login_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
                 <body>
                     {error_html}
                     <form action="/login" method="post">
+                        {csrf_field}
                         <label
                             >Username
                             <input
@@ -39,8 +45,10 @@ pub async fn login_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
 
                         <button type="submit">Login</button>
                     </form>
+                    <p><a href="/login/forgot-password">Forgot your password?</a></p>
                 </body>
             </html>
             "#,
+            csrf_field = csrf_token.form_field(),
         ))
 }
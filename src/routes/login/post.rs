@@ -1,12 +1,20 @@
 use actix_web::{
-    error::InternalError, http::header::LOCATION, web, HttpResponse,
+    error::InternalError,
+    http::header::{LOCATION, USER_AGENT},
+    web, HttpRequest, HttpResponse,
 };
 use actix_web_flash_messages::FlashMessage;
+use chrono::Utc;
 use secrecy::Secret;
 use sqlx::PgPool;
 
 use crate::{
-    authentication::{validate_credentials, AuthError, Credentials},
+    authentication::{
+        clear_failed_logins, create_session, is_login_allowed,
+        is_two_factor_enabled, record_failed_login, validate_credentials,
+        AuthError, Credentials, ThrottleKey,
+    },
+    configuration::{LoginThrottleSettings, PasswordHashingSettings},
     routes::helpers::error_chain_fmt,
     session_state::TypedSession,
     utils::see_other,
@@ -34,13 +42,16 @@ impl std::fmt::Debug for LoginError {
 
 #[tracing::instrument(
     name = "Publish a newsletter issue",
-    skip(form, pool, session),
+    skip(form, pool, hashing, throttle_settings, session, request),
     fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
 )]
 pub async fn login(
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashingSettings>,
+    throttle_settings: web::Data<LoginThrottleSettings>,
     session: TypedSession,
+    request: HttpRequest,
 ) -> Result<HttpResponse, InternalError<LoginError>> {
     let credentials = Credentials {
         username: form.0.username,
@@ -48,22 +59,63 @@ pub async fn login(
     };
 
     tracing::Span::current()
-        .record("username", &tracing::field::display(&credentials.username));
-    match validate_credentials(credentials, &pool).await {
+        .record("username", tracing::field::display(&credentials.username));
+    let throttle_keys = throttle_keys(&request, credentials.username.clone());
+    let allowed = is_login_allowed(&pool, &throttle_settings, &throttle_keys)
+        .await
+        .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+    // The password is checked even when the login is throttled, so that
+    // neither the response nor its timing reveals the lockout
+    let outcome = match validate_credentials(credentials, &hashing, &pool).await
+    {
+        Ok(_) if !allowed => Err(AuthError::InvalidCredentials(
+            anyhow::anyhow!("Too many failed logins"),
+        )),
+        outcome => outcome,
+    };
+    match outcome {
         Ok(user_id) => {
             tracing::Span::current()
-                .record("user_id", &tracing::field::display(&user_id));
+                .record("user_id", tracing::field::display(&user_id));
             session.renew();
-            session.insert_user_id(user_id).map_err(|e| {
-                login_redirect(LoginError::UnexpectedError(e.into()))
-            })?;
+            let two_factor_enabled = is_two_factor_enabled(&pool, user_id)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+            if two_factor_enabled {
+                session
+                    .insert_pending_two_factor(user_id, Utc::now())
+                    .map_err(|e| {
+                        login_redirect(LoginError::UnexpectedError(e.into()))
+                    })?;
+                // Failures are only forgotten after the second factor, so
+                // that knowing the password doesn't allow guessing codes
+                return Ok(see_other("/login/two-factor"));
+            }
+            clear_failed_logins(&pool, &throttle_keys[0])
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+            start_session(&session, user_id, &request, &pool)
+                .await
+                .map_err(login_redirect)?;
             Ok(HttpResponse::SeeOther()
                 .insert_header((LOCATION, "/admin/dashboard"))
                 .finish())
         }
         Err(e) => {
             let e = match e {
+                AuthError::InvalidCredentials(_) if !allowed => {
+                    LoginError::AuthError(e.into())
+                }
                 AuthError::InvalidCredentials(_) => {
+                    record_failed_login(
+                        &pool,
+                        &throttle_settings,
+                        &throttle_keys,
+                    )
+                    .await
+                    .map_err(|e| {
+                        login_redirect(LoginError::UnexpectedError(e))
+                    })?;
                     LoginError::AuthError(e.into())
                 }
                 AuthError::UnexpectedError(_) => {
@@ -80,7 +132,64 @@ pub async fn login(
     }
 }
 
-fn login_redirect(e: LoginError) -> InternalError<LoginError> {
+/// Failed logins count against the username and the client IP address.
+/// The username comes first.
+pub fn throttle_keys(
+    request: &HttpRequest,
+    username: String,
+) -> [ThrottleKey; 2] {
+    [
+        ThrottleKey::Username(username),
+        ThrottleKey::Ip(client_ip(request)),
+    ]
+}
+
+/// Forwarded headers can be forged, so one is only read when it is
+/// configured: the proxy in front of the application must overwrite it.
+fn client_ip(request: &HttpRequest) -> String {
+    let forwarded = request
+        .app_data::<web::Data<LoginThrottleSettings>>()
+        .and_then(|settings| settings.client_ip_header.as_deref())
+        .and_then(|header| request.headers().get(header))
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty());
+    match forwarded {
+        Some(ip) => ip.to_string(),
+        None => request
+            .peer_addr()
+            .map(|address| address.ip().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Log the user in, once they proved who they are.
+pub async fn start_session(
+    session: &TypedSession,
+    user_id: uuid::Uuid,
+    request: &HttpRequest,
+    pool: &PgPool,
+) -> Result<(), LoginError> {
+    let user_agent = request
+        .headers()
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("Unknown device");
+    let (session_id, logged_in_at) =
+        create_session(pool, user_id, user_agent, &client_ip(request)).await?;
+    session
+        .insert_session_id(session_id)
+        .map_err(|e| LoginError::UnexpectedError(e.into()))?;
+    session
+        .insert_user_id(user_id)
+        .map_err(|e| LoginError::UnexpectedError(e.into()))?;
+    session
+        .insert_logged_in_at(logged_in_at)
+        .map_err(|e| LoginError::UnexpectedError(e.into()))?;
+    Ok(())
+}
+
+pub fn login_redirect(e: LoginError) -> InternalError<LoginError> {
     FlashMessage::error(e.to_string()).send();
     InternalError::from_response(e, see_other("/login"))
 }
@@ -0,0 +1,201 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::{
+    authentication::{revoke_other_sessions, CsrfToken},
+    configuration::PasswordHashingSettings,
+    domain::{Password, PasswordPolicy, PasswordResetToken},
+    session_state::TypedSession,
+    utils::{e500, see_other},
+};
+
+const FORM_URL: &str = "/login/reset-password";
+
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    token: Option<String>,
+}
+
+/// The token of the link is moved to the session, and the browser sent to
+/// a URL without it: URLs end up in logs and browser histories.
+pub async fn reset_password_form(
+    query: web::Query<QueryParams>,
+    session: TypedSession,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: CsrfToken,
+) -> Result<HttpResponse, actix_web::Error> {
+    if let Some(token) = &query.token {
+        session.insert_password_reset_token(token).map_err(e500)?;
+        return Ok(see_other(FORM_URL));
+    }
+    if session.get_password_reset_token().map_err(e500)?.is_none() {
+        return Ok(invalid_link());
+    }
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"
+            <!doctype html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+                    <title>Reset password</title>
+                </head>
+                <body>
+                    {msg_html}
+                    <form action="/login/reset-password" method="post">
+                        {csrf_field}
+                        <label
+                            >New password
+                            <input
+                                type="password"
+                                placeholder="Enter new password"
+                                name="new_password"
+                            />
+                        </label>
+                        <label
+                            >Confirm new password
+                            <input
+                                type="password"
+                                placeholder="Confirm new password"
+                                name="new_password_check"
+                            />
+                        </label>
+
+                        <button type="submit">Reset password</button>
+                    </form>
+                </body>
+            </html>
+            "#,
+            csrf_field = csrf_token.form_field(),
+        )))
+}
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    new_password: Secret<String>,
+    new_password_check: Secret<String>,
+}
+
+#[tracing::instrument(
+    name = "Reset a password",
+    skip(form, session, pool, hashing, password_policy)
+)]
+pub async fn reset_password(
+    form: web::Form<FormData>,
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashingSettings>,
+    password_policy: web::Data<PasswordPolicy>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData {
+        new_password,
+        new_password_check,
+    } = form.0;
+    let Some(token) = session.get_password_reset_token().map_err(e500)? else {
+        return Ok(invalid_link());
+    };
+
+    if new_password.expose_secret() != new_password_check.expose_secret() {
+        FlashMessage::error(
+            "You entered two different passwords - the field values must match.",
+        )
+        .send();
+        return Ok(see_other(FORM_URL));
+    }
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    let user_id = match PasswordResetToken::parse(token) {
+        Ok(token) => use_reset_token(&mut transaction, &token)
+            .await
+            .map_err(e500)?,
+        Err(_) => None,
+    };
+    let Some((user_id, username)) = user_id else {
+        session.remove_password_reset_token();
+        return Ok(invalid_link());
+    };
+    // Nothing is committed if the password is rejected, so that the link
+    // can be used again
+    let new_password = match Password::parse(
+        new_password.expose_secret().clone(),
+        &username,
+        &password_policy,
+    ) {
+        Ok(password) => Secret::new(password),
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other(FORM_URL));
+        }
+    };
+    crate::authentication::reset_password(
+        &mut transaction,
+        user_id,
+        new_password,
+        &hashing,
+    )
+    .await
+    .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the password reset")
+        .map_err(e500)?;
+    // The sessions were revoked with the old password, they must not be
+    // listed as active any more
+    revoke_other_sessions(&pool, user_id, None)
+        .await
+        .map_err(e500)?;
+    session.remove_password_reset_token();
+
+    FlashMessage::info("Your password has been reset. You can now log in.")
+        .send();
+    Ok(see_other("/login"))
+}
+
+fn invalid_link() -> HttpResponse {
+    FlashMessage::error("This password reset link is invalid or has expired.")
+        .send();
+    see_other("/login/forgot-password")
+}
+
+/// Mark the token as used, returning the user it was issued to and their
+/// username.
+/// Tokens can only be used once, before they expire, by enabled users.
+#[tracing::instrument(skip(transaction, token))]
+async fn use_reset_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    token: &PasswordResetToken,
+) -> Result<Option<(Uuid, String)>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        UPDATE password_reset_tokens t
+        SET used_at = now()
+        FROM users u
+        WHERE
+            t.token_hash = $1 AND
+            t.used_at IS NULL AND
+            t.expires_at > now() AND
+            u.user_id = t.user_id AND
+            u.disabled_at IS NULL
+        RETURNING t.user_id, u.username
+        "#,
+        token.hash()
+    )
+    .fetch_optional(transaction)
+    .await
+    .context("Failed to use a password reset token")?;
+    Ok(row.map(|r| (r.user_id, r.username)))
+}
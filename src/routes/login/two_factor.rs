@@ -0,0 +1,132 @@
+use actix_web::{http::header::ContentType, web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+use super::post::{start_session, throttle_keys};
+use crate::{
+    authentication::{
+        clear_failed_logins, is_login_allowed, record_failed_login,
+        verify_second_factor, CsrfToken, TotpEncryptionKey,
+    },
+    configuration::LoginThrottleSettings,
+    routes::get_username,
+    session_state::TypedSession,
+    utils::{e500, see_other},
+};
+
+/// How long a user has to enter their second factor after their password.
+const SECOND_FACTOR_TIMEOUT_MINUTES: i64 = 5;
+
+pub async fn two_factor_form(
+    session: TypedSession,
+    flash_messages: IncomingFlashMessages,
+    csrf_token: CsrfToken,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session.get_pending_two_factor().map_err(e500)?.is_none() {
+        return Ok(see_other("/login"));
+    }
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"
+            <!doctype html>
+            <html lang="en">
+                <head>
+                    <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+                    <title>Two-factor authentication</title>
+                </head>
+                <body>
+                    {msg_html}
+                    <form action="/login/two-factor" method="post">
+                        {csrf_field}
+                        <label
+                            >Authentication code
+                            <input
+                                type="text"
+                                inputmode="numeric"
+                                autocomplete="one-time-code"
+                                placeholder="Enter the code from your app, or a recovery code"
+                                name="code"
+                            />
+                        </label>
+
+                        <button type="submit">Verify</button>
+                    </form>
+                    <p><a href="/login">&lt;- Back</a></p>
+                </body>
+            </html>
+            "#,
+            csrf_field = csrf_token.form_field(),
+        )))
+}
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    code: String,
+}
+
+#[tracing::instrument(
+    name = "Verify the second factor of a login",
+    skip(form, pool, totp_key, throttle_settings, session, request),
+    fields(user_id=tracing::field::Empty)
+)]
+pub async fn verify_two_factor(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    totp_key: web::Data<TotpEncryptionKey>,
+    throttle_settings: web::Data<LoginThrottleSettings>,
+    session: TypedSession,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some((user_id, started_at)) =
+        session.get_pending_two_factor().map_err(e500)?
+    else {
+        return Ok(see_other("/login"));
+    };
+    tracing::Span::current()
+        .record("user_id", tracing::field::display(&user_id));
+    if Utc::now() - started_at
+        > Duration::minutes(SECOND_FACTOR_TIMEOUT_MINUTES)
+    {
+        session.remove_pending_two_factor();
+        FlashMessage::error("Your login has expired, please log in again.")
+            .send();
+        return Ok(see_other("/login"));
+    }
+
+    // Wrong codes count as failed logins too, or they could be guessed
+    let username = get_username(user_id, &pool).await.map_err(e500)?;
+    let throttle_keys = throttle_keys(&request, username);
+    if !is_login_allowed(&pool, &throttle_settings, &throttle_keys)
+        .await
+        .map_err(e500)?
+    {
+        FlashMessage::error("The authentication code is not valid.").send();
+        return Ok(see_other("/login/two-factor"));
+    }
+    if !verify_second_factor(&pool, &totp_key, user_id, &form.0.code)
+        .await
+        .map_err(e500)?
+    {
+        record_failed_login(&pool, &throttle_settings, &throttle_keys)
+            .await
+            .map_err(e500)?;
+        FlashMessage::error("The authentication code is not valid.").send();
+        return Ok(see_other("/login/two-factor"));
+    }
+    clear_failed_logins(&pool, &throttle_keys[0])
+        .await
+        .map_err(e500)?;
+    session.remove_pending_two_factor();
+    session.renew();
+    start_session(&session, user_id, &request, &pool)
+        .await
+        .map_err(e500)?;
+    Ok(see_other("/admin/dashboard"))
+}
@@ -1,14 +1,22 @@
 mod admin;
+mod api;
+mod archive;
+mod feeds;
 mod health_check;
 mod helpers;
 mod home;
 mod login;
 mod subscriptions;
 mod subscriptions_confirm;
+mod tracking;
 
 pub use admin::*;
+pub use api::*;
+pub use archive::*;
+pub use feeds::*;
 pub use health_check::*;
 pub use home::*;
 pub use login::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use tracking::*;
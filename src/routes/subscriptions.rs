@@ -11,6 +11,7 @@ use uuid::Uuid;
 use crate::domain::NewSubscriber;
 use crate::domain::SubscriberEmail;
 use crate::domain::SubscriberName;
+use crate::domain::SubscriberTimeZone;
 use crate::domain::SubscriptionToken;
 use crate::email_client::EmailClient;
 use crate::routes::helpers::error_chain_fmt;
@@ -22,6 +23,11 @@ pub struct FormData {
     email: String,
     #[allow(dead_code)]
     name: String,
+    #[serde(default)]
+    do_not_track: bool,
+    /// IANA name of the subscriber's time zone, optional.
+    #[serde(default)]
+    time_zone: String,
 }
 
 impl TryFrom<FormData> for NewSubscriber {
@@ -30,7 +36,16 @@ impl TryFrom<FormData> for NewSubscriber {
     fn try_from(form: FormData) -> Result<Self, Self::Error> {
         let name = SubscriberName::parse(form.name)?;
         let email = SubscriberEmail::parse(form.email)?;
-        Ok(NewSubscriber { name, email })
+        let time_zone = match form.time_zone.trim() {
+            "" => None,
+            time_zone => Some(SubscriberTimeZone::parse(time_zone)?),
+        };
+        Ok(NewSubscriber {
+            name,
+            email,
+            do_not_track: form.do_not_track,
+            time_zone,
+        })
     }
 }
 
@@ -68,7 +83,7 @@ pub async fn subscribe(
 
     send_confirmation_email(
         &email_client,
-        new_subscriber,
+        &new_subscriber.email,
         &base_url.0,
         &subscription_token,
     )
@@ -174,8 +189,16 @@ pub async fn insert_subscriber(
     let subscriber_id = sqlx::query!(
         r#"
         WITH insert_or_select AS (
-            INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-            SELECT $1, $2, $3, $4, 'pending_confirmation'
+            INSERT INTO subscriptions (
+                id,
+                email,
+                name,
+                subscribed_at,
+                status,
+                do_not_track,
+                time_zone
+            )
+            SELECT $1, $2, $3, $4, 'pending_confirmation', $5, $6
             ON CONFLICT (email) DO NOTHING
             RETURNING id
         )
@@ -188,6 +211,8 @@ pub async fn insert_subscriber(
         new_subscriber.email.as_ref(),
         new_subscriber.name.as_ref(),
         Utc::now(),
+        new_subscriber.do_not_track,
+        new_subscriber.time_zone.as_ref().map(|tz| tz.as_ref()),
     )
     .fetch_one(transaction)
     .await?;
@@ -199,11 +224,11 @@ pub async fn insert_subscriber(
 
 #[tracing::instrument(
     name = "Send a confirmation email to a new subscriber",
-    skip(email_client, new_subscriber)
+    skip(email_client, subscriber_email)
 )]
 pub async fn send_confirmation_email(
     email_client: &EmailClient,
-    new_subscriber: NewSubscriber,
+    subscriber_email: &SubscriberEmail,
     base_url: &str,
     subscription_token: &SubscriptionToken,
 ) -> Result<(), reqwest::Error> {
@@ -223,6 +248,6 @@ pub async fn send_confirmation_email(
     );
 
     email_client
-        .send_email(&new_subscriber.email, "Welcome", &html_body, &text_body)
+        .send_email(subscriber_email, "Welcome", &html_body, &text_body)
         .await
 }
@@ -0,0 +1,75 @@
+use actix_web::{http::header::LOCATION, web, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::configuration::TrackingSettings;
+use crate::startup::HmacSecret;
+use crate::tracking::ClickToken;
+use crate::utils::{e404, e500};
+
+#[tracing::instrument(
+    name = "Follow a tracked link",
+    skip_all,
+    fields(link_id = tracing::field::Empty)
+)]
+pub async fn track_click(
+    token: web::Path<String>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+    tracking: web::Data<TrackingSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let token = ClickToken::decode(&token, &hmac_secret.0).map_err(e404)?;
+    tracing::Span::current()
+        .record("link_id", tracing::field::display(token.link_id));
+    let url = get_link_url(&pool, token.link_id)
+        .await
+        .map_err(e500)?
+        .ok_or_else(|| e404("This link does not exist."))?;
+
+    if tracking.click_tracking_enabled {
+        // Readers must reach their destination even if we fail to count
+        if let Err(e) = record_click(&pool, &token).await {
+            tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to record a click");
+        }
+    }
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, url))
+        .finish())
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_link_url(
+    pool: &PgPool,
+    link_id: Uuid,
+) -> Result<Option<String>, anyhow::Error> {
+    let row =
+        sqlx::query!("SELECT url FROM issue_links WHERE link_id = $1", link_id)
+            .fetch_optional(pool)
+            .await
+            .context("Failed to retrieve a tracked link")?;
+    Ok(row.map(|r| r.url))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn record_click(
+    pool: &PgPool,
+    token: &ClickToken,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO link_clicks (link_id, subscriber_id, clicked_at)
+        SELECT $1, id, now()
+        FROM subscriptions
+        WHERE
+            id = $2 AND
+            do_not_track = false
+        "#,
+        token.link_id,
+        token.subscriber_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to record a click")?;
+    Ok(())
+}
@@ -0,0 +1,7 @@
+mod click;
+mod open;
+mod opt_out;
+
+pub use click::track_click;
+pub use open::track_open;
+pub use opt_out::{opt_out_of_tracking, opt_out_of_tracking_form};
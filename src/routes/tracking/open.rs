@@ -0,0 +1,115 @@
+use actix_web::http::header::{CacheControl, CacheDirective, USER_AGENT};
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::PgPool;
+
+use crate::configuration::TrackingSettings;
+use crate::startup::HmacSecret;
+use crate::tracking::{is_probably_prefetch, OpenToken};
+
+/// A transparent 1x1 GIF.
+const PIXEL: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00,
+    0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00,
+    0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+/// The pixel is always served, a broken image would be visible to readers
+/// while a failure to record an open is only ours to care about.
+#[tracing::instrument(
+    name = "Serve the open tracking pixel",
+    skip_all,
+    fields(newsletter_issue_id = tracing::field::Empty)
+)]
+pub async fn track_open(
+    request: HttpRequest,
+    token: web::Path<String>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+    tracking: web::Data<TrackingSettings>,
+) -> HttpResponse {
+    if tracking.open_tracking_enabled {
+        match OpenToken::decode(&token, &hmac_secret.0) {
+            Ok(token) => {
+                tracing::Span::current().record(
+                    "newsletter_issue_id",
+                    tracing::field::display(token.issue_id),
+                );
+                let user_agent = request
+                    .headers()
+                    .get(USER_AGENT)
+                    .and_then(|h| h.to_str().ok());
+                if let Err(e) = record_open(&pool, &token, user_agent).await {
+                    tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to record an open");
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "Invalid open tracking token"),
+        }
+    }
+    HttpResponse::Ok()
+        .content_type("image/gif")
+        .insert_header(CacheControl(vec![
+            CacheDirective::NoStore,
+            CacheDirective::Private,
+        ]))
+        .body(PIXEL)
+}
+
+#[tracing::instrument(skip(pool, token))]
+async fn record_open(
+    pool: &PgPool,
+    token: &OpenToken,
+    user_agent: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    let delivered_at = sqlx::query!(
+        r#"
+        SELECT l.attempted_at
+        FROM issue_delivery_log l
+        JOIN subscriptions s ON s.email = l.subscriber_email
+        WHERE
+            l.newsletter_issue_id = $1 AND
+            s.id = $2
+        "#,
+        token.issue_id,
+        token.subscriber_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve when the issue was delivered")?
+    .map(|r| r.attempted_at);
+    let since_delivery = delivered_at.map(|at| Utc::now() - at);
+    if is_probably_prefetch(user_agent, since_delivery) {
+        tracing::info!("Ignoring an open that looks like a prefetch");
+        return Ok(());
+    }
+
+    // Subscribers who opted out after the email was sent are not counted
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_opens (
+            newsletter_issue_id,
+            subscriber_id,
+            first_opened_at,
+            last_opened_at,
+            n_opens
+        )
+        SELECT $1, id, now(), now(), 1
+        FROM subscriptions
+        WHERE
+            id = $2 AND
+            do_not_track = false
+        ON CONFLICT (newsletter_issue_id, subscriber_id) DO UPDATE
+        SET
+            last_opened_at = now(),
+            n_opens = issue_opens.n_opens + 1
+        "#,
+        token.issue_id,
+        token.subscriber_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to record an open")?;
+    Ok(())
+}
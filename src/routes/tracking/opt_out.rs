@@ -0,0 +1,78 @@
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::startup::HmacSecret;
+use crate::tracking::OptOutToken;
+use crate::utils::{e404, e500};
+
+/// Mail scanners and link prefetchers follow every link of an email: the
+/// link only leads to a form, which the subscriber must submit.
+#[tracing::instrument(name = "Confirm opting out of tracking", skip_all)]
+pub async fn opt_out_of_tracking_form(
+    token: web::Path<String>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    OptOutToken::decode(&token, &hmac_secret.0).map_err(e404)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!doctype html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+        <title>Disable tracking</title>
+    </head>
+    <body>
+        <p>Stop tracking when you open our emails or click their links?</p>
+        <form action="/t/opt-out/{token}" method="post">
+            <button type="submit">Disable tracking</button>
+        </form>
+    </body>
+</html>"#,
+            token = htmlescape::encode_minimal(&token),
+        )))
+}
+
+#[tracing::instrument(name = "Opt out of tracking", skip_all)]
+pub async fn opt_out_of_tracking(
+    token: web::Path<String>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let token = OptOutToken::decode(&token, &hmac_secret.0).map_err(e404)?;
+    let found = set_do_not_track(&pool, token.subscriber_id)
+        .await
+        .map_err(e500)?;
+    if !found {
+        return Err(e404("This subscriber does not exist."));
+    }
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        r#"<!doctype html>
+<html lang="en">
+    <head>
+        <meta http-equiv="content-type" content="text/html; charset=utf-8" />
+        <title>Tracking disabled</title>
+    </head>
+    <body>
+        <p>We will no longer track when you open our emails or click their links.</p>
+    </body>
+</html>"#,
+    ))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn set_do_not_track(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        "UPDATE subscriptions SET do_not_track = true WHERE id = $1",
+        subscriber_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the tracking preference of a subscriber")?;
+    Ok(result.rows_affected() > 0)
+}
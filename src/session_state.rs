@@ -2,6 +2,7 @@ use std::future::{ready, Ready};
 
 use actix_session::{Session, SessionExt, SessionGetError, SessionInsertError};
 use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 pub struct TypedSession(Session);
@@ -9,6 +10,11 @@ pub struct TypedSession(Session);
 impl TypedSession {
     const USER_ID_KEY: &'static str = "user_id";
     const USERNAME_KEY: &'static str = "username";
+    const LOGGED_IN_AT_KEY: &'static str = "logged_in_at";
+    const PENDING_TWO_FACTOR_KEY: &'static str = "pending_two_factor";
+    const SESSION_ID_KEY: &'static str = "session_id";
+    const CSRF_TOKEN_KEY: &'static str = "csrf_token";
+    const PASSWORD_RESET_TOKEN_KEY: &'static str = "password_reset_token";
 
     pub fn renew(&self) {
         self.0.renew();
@@ -36,6 +42,92 @@ impl TypedSession {
         self.0.get(Self::USERNAME_KEY)
     }
 
+    pub fn insert_logged_in_at(
+        &self,
+        logged_in_at: DateTime<Utc>,
+    ) -> Result<(), SessionInsertError> {
+        self.0
+            .insert(Self::LOGGED_IN_AT_KEY, logged_in_at.timestamp_micros())
+    }
+
+    pub fn get_logged_in_at(
+        &self,
+    ) -> Result<Option<DateTime<Utc>>, SessionGetError> {
+        Ok(self
+            .0
+            .get::<i64>(Self::LOGGED_IN_AT_KEY)?
+            .and_then(DateTime::from_timestamp_micros))
+    }
+
+    /// The id of the session in the index of user sessions.
+    pub fn insert_session_id(
+        &self,
+        session_id: Uuid,
+    ) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::SESSION_ID_KEY, session_id)
+    }
+
+    pub fn get_session_id(&self) -> Result<Option<Uuid>, SessionGetError> {
+        self.0.get(Self::SESSION_ID_KEY)
+    }
+
+    pub fn insert_csrf_token(
+        &self,
+        csrf_token: &str,
+    ) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::CSRF_TOKEN_KEY, csrf_token)
+    }
+
+    pub fn get_csrf_token(&self) -> Result<Option<String>, SessionGetError> {
+        self.0.get(Self::CSRF_TOKEN_KEY)
+    }
+
+    /// The token of the reset link that was opened, kept out of URLs.
+    pub fn insert_password_reset_token(
+        &self,
+        token: &str,
+    ) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::PASSWORD_RESET_TOKEN_KEY, token)
+    }
+
+    pub fn get_password_reset_token(
+        &self,
+    ) -> Result<Option<String>, SessionGetError> {
+        self.0.get(Self::PASSWORD_RESET_TOKEN_KEY)
+    }
+
+    pub fn remove_password_reset_token(&self) {
+        self.0.remove(Self::PASSWORD_RESET_TOKEN_KEY);
+    }
+
+    /// A user who entered their password, but not their second factor yet.
+    pub fn insert_pending_two_factor(
+        &self,
+        user_id: Uuid,
+        started_at: DateTime<Utc>,
+    ) -> Result<(), SessionInsertError> {
+        self.0.insert(
+            Self::PENDING_TWO_FACTOR_KEY,
+            (user_id, started_at.timestamp_micros()),
+        )
+    }
+
+    pub fn get_pending_two_factor(
+        &self,
+    ) -> Result<Option<(Uuid, DateTime<Utc>)>, SessionGetError> {
+        Ok(self
+            .0
+            .get::<(Uuid, i64)>(Self::PENDING_TWO_FACTOR_KEY)?
+            .and_then(|(user_id, started_at)| {
+                DateTime::from_timestamp_micros(started_at)
+                    .map(|started_at| (user_id, started_at))
+            }))
+    }
+
+    pub fn remove_pending_two_factor(&self) {
+        self.0.remove(Self::PENDING_TWO_FACTOR_KEY);
+    }
+
     pub fn logout(self) {
         self.0.purge();
     }
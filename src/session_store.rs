@@ -0,0 +1,262 @@
+//! Where the state of sessions is kept between requests: Redis, or a table
+//! of the application database for deployments that don't run Redis.
+use std::collections::HashMap;
+
+use actix_session::storage::{
+    LoadError, RedisSessionStore, SaveError, SessionKey, SessionStore,
+    UpdateError,
+};
+use actix_web::cookie::time::Duration;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+use crate::configuration::{SessionBackend, Settings};
+
+type SessionState = HashMap<String, String>;
+
+/// As long as the keys generated by actix-session.
+const SESSION_KEY_SIZE: usize = 64;
+
+#[derive(Clone)]
+pub enum AppSessionStore {
+    Redis(RedisSessionStore),
+    Postgres(PostgresSessionStore),
+}
+
+impl AppSessionStore {
+    pub async fn build(
+        configuration: &Settings,
+        db_pool: &PgPool,
+    ) -> Result<Self, anyhow::Error> {
+        match configuration.session_store.backend {
+            SessionBackend::Redis => {
+                let redis_uri = configuration.redis_uri.as_ref().context(
+                    "`redis_uri` must be set to store sessions in Redis",
+                )?;
+                let store =
+                    RedisSessionStore::new(redis_uri.expose_secret()).await?;
+                Ok(Self::Redis(store))
+            }
+            SessionBackend::Postgres => {
+                Ok(Self::Postgres(PostgresSessionStore::new(db_pool.clone())))
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl SessionStore for AppSessionStore {
+    async fn load(
+        &self,
+        session_key: &SessionKey,
+    ) -> Result<Option<SessionState>, LoadError> {
+        match self {
+            Self::Redis(store) => store.load(session_key).await,
+            Self::Postgres(store) => store.load(session_key).await,
+        }
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        match self {
+            Self::Redis(store) => store.save(session_state, ttl).await,
+            Self::Postgres(store) => store.save(session_state, ttl).await,
+        }
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        match self {
+            Self::Redis(store) => {
+                store.update(session_key, session_state, ttl).await
+            }
+            Self::Postgres(store) => {
+                store.update(session_key, session_state, ttl).await
+            }
+        }
+    }
+
+    async fn update_ttl(
+        &self,
+        session_key: &SessionKey,
+        ttl: &Duration,
+    ) -> Result<(), anyhow::Error> {
+        match self {
+            Self::Redis(store) => store.update_ttl(session_key, ttl).await,
+            Self::Postgres(store) => store.update_ttl(session_key, ttl).await,
+        }
+    }
+
+    async fn delete(
+        &self,
+        session_key: &SessionKey,
+    ) -> Result<(), anyhow::Error> {
+        match self {
+            Self::Redis(store) => store.delete(session_key).await,
+            Self::Postgres(store) => store.delete(session_key).await,
+        }
+    }
+}
+
+/// Keeps sessions in the `http_sessions` table. Expired sessions are
+/// ignored, and deleted whenever a new session is saved.
+#[derive(Clone)]
+pub struct PostgresSessionStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    async fn delete_expired_sessions(&self) -> Result<(), anyhow::Error> {
+        sqlx::query!("DELETE FROM http_sessions WHERE expires_at < now()")
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete expired sessions")?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl SessionStore for PostgresSessionStore {
+    async fn load(
+        &self,
+        session_key: &SessionKey,
+    ) -> Result<Option<SessionState>, LoadError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT state
+            FROM http_sessions
+            WHERE session_key = $1 AND expires_at > now()
+            "#,
+            session_key.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to retrieve a session")
+        .map_err(LoadError::Other)?;
+        row.map(|row| serde_json::from_str(&row.state))
+            .transpose()
+            .context("Failed to deserialize a session")
+            .map_err(LoadError::Deserialization)
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        let state = serde_json::to_string(&session_state)
+            .context("Failed to serialize a session")
+            .map_err(SaveError::Serialization)?;
+        self.delete_expired_sessions()
+            .await
+            .map_err(SaveError::Other)?;
+        let session_key = generate_session_key();
+        sqlx::query!(
+            r#"
+            INSERT INTO http_sessions (session_key, state, expires_at)
+            VALUES ($1, $2, $3)
+            "#,
+            session_key,
+            state,
+            expires_at(ttl)
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to save a session")
+        .map_err(SaveError::Other)?;
+        session_key
+            .try_into()
+            .map_err(|e| SaveError::Other(anyhow::Error::new(e)))
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        let state = serde_json::to_string(&session_state)
+            .context("Failed to serialize a session")
+            .map_err(UpdateError::Serialization)?;
+        let result = sqlx::query!(
+            r#"
+            UPDATE http_sessions
+            SET state = $2, expires_at = $3
+            WHERE session_key = $1 AND expires_at > now()
+            "#,
+            session_key.as_ref(),
+            state,
+            expires_at(ttl)
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to update a session")
+        .map_err(UpdateError::Other)?;
+        if result.rows_affected() == 1 {
+            return Ok(session_key);
+        }
+        // The session expired in the meantime: start a new one, as the
+        // Redis store does.
+        self.save(session_state, ttl).await.map_err(|e| match e {
+            SaveError::Serialization(e) => UpdateError::Serialization(e),
+            SaveError::Other(e) => UpdateError::Other(e),
+        })
+    }
+
+    async fn update_ttl(
+        &self,
+        session_key: &SessionKey,
+        ttl: &Duration,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "UPDATE http_sessions SET expires_at = $2 WHERE session_key = $1",
+            session_key.as_ref(),
+            expires_at(ttl)
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to update the expiry of a session")?;
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        session_key: &SessionKey,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "DELETE FROM http_sessions WHERE session_key = $1",
+            session_key.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to delete a session")?;
+        Ok(())
+    }
+}
+
+fn generate_session_key() -> String {
+    let mut rng = thread_rng();
+    std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(SESSION_KEY_SIZE)
+        .collect()
+}
+
+fn expires_at(ttl: &Duration) -> DateTime<Utc> {
+    Utc::now() + chrono::Duration::seconds(ttl.whole_seconds())
+}
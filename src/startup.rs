@@ -1,16 +1,37 @@
-use crate::authentication::reject_anonymous_users;
+use crate::authentication::{
+    move_csrf_token_to_header, reject_anonymous_users, require_api_token,
+    require_editor, require_owner, verify_csrf_token, TotpEncryptionKey,
+    SESSION_TTL,
+};
 use crate::configuration::DatabaseSettings;
-use crate::routes::{log_out, publish_newsletter_form, subscribe};
+use crate::routes::{
+    api_create_issue, api_create_subscriber, api_get_issue, api_get_subscriber,
+    api_list_issues, api_list_subscribers, api_publish_issue, api_tokens_list,
+    api_update_subscriber, atom_feed, change_delivery_status,
+    change_user_email, change_user_role, change_user_status,
+    confirm_two_factor_setup, create_draft, create_user, create_user_api_token,
+    delete_attachment, delete_user, duplicate_issue, edit_draft_form,
+    forgot_password_form, issue_report, json_errors, log_out,
+    log_out_everywhere, new_recovery_codes, opt_out_of_tracking,
+    opt_out_of_tracking_form, publish_draft, publish_newsletter_form,
+    request_password_reset, reset_password, reset_password_form,
+    restore_revision, review_issue, revision_diff, revoke_user_api_token,
+    revoke_user_session, rss_feed, save_draft, sessions_list,
+    set_two_factor_policy, start_two_factor_setup, subscribe, track_click,
+    track_open, turn_off_two_factor, two_factor_form, two_factor_settings,
+    unlock_login, upload_attachments, users_list, verify_two_factor,
+};
+use crate::session_store::AppSessionStore;
 use crate::{
     configuration::Settings,
     email_client::EmailClient,
     routes::{
-        admin_dashboard, change_password, change_password_form, confirm,
-        health_check, home, login, login_form, publish_newsletter,
+        admin_dashboard, archive, archive_issue, change_password,
+        change_password_form, confirm, health_check, home, issues_list, login,
+        login_form, publish_newsletter, set_archive_visibility,
     },
 };
-use actix_session::storage::RedisSessionStore;
-use actix_session::SessionMiddleware;
+use actix_session::{config::BrowserSession, SessionMiddleware};
 use actix_web::{
     cookie::Key,
     dev::Server,
@@ -38,7 +59,7 @@ impl Application {
     pub async fn build(configuration: Settings) -> Result<Self, anyhow::Error> {
         let connection_pool = get_connection_pool(&configuration.database);
 
-        let email_client = configuration.email_client.client();
+        let email_client = configuration.email_client.clone().client();
 
         let address = format!(
             "{}:{}",
@@ -46,15 +67,8 @@ impl Application {
         );
         let listener = TcpListener::bind(address)?;
         let port = listener.local_addr().unwrap().port();
-        let server = run(
-            listener,
-            connection_pool,
-            email_client,
-            configuration.application.base_url,
-            configuration.application.hmac_secret,
-            configuration.redis_uri,
-        )
-        .await?;
+        let server =
+            run(listener, connection_pool, email_client, configuration).await?;
 
         Ok(Self { port, server })
     }
@@ -80,49 +94,264 @@ pub async fn run(
     listener: TcpListener,
     db_pool: PgPool,
     email_client: EmailClient,
-    base_url: String,
-    hmac_secret: Secret<String>,
-    redis_uri: Secret<String>,
+    configuration: Settings,
 ) -> Result<Server, anyhow::Error> {
+    let session_store =
+        AppSessionStore::build(&configuration, &db_pool).await?;
+    let Settings {
+        application,
+        tracking,
+        attachments,
+        publishing,
+        schedule,
+        login_throttle,
+        password_hashing,
+        password_policy,
+        ..
+    } = configuration;
+    // Fail fast rather than on the first issue published
+    schedule.quiet_hours().map_err(anyhow::Error::msg)?;
+    password_hashing.params().map_err(anyhow::Error::msg)?;
+    let hmac_secret = application.hmac_secret;
+    let totp_key =
+        Data::new(TotpEncryptionKey::new(&application.totp_encryption_key));
     let db_pool = web::Data::new(db_pool);
     let email_client = web::Data::new(email_client);
-    let base_url = web::Data::new(ApplicationBaseUrl(base_url));
+    let base_url = web::Data::new(ApplicationBaseUrl(application.base_url));
+    let tracking = web::Data::new(tracking);
+    let attachments = web::Data::new(attachments);
+    let publishing = web::Data::new(publishing);
+    let schedule = web::Data::new(schedule);
+    let login_throttle = web::Data::new(login_throttle);
+    let password_hashing = web::Data::new(password_hashing);
+    let password_policy = web::Data::new(password_policy.policy()?);
     let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework =
         FlashMessagesFramework::builder(message_store).build();
-    let redis_store = RedisSessionStore::new(redis_uri.expose_secret()).await?;
     let server = HttpServer::new(move || {
+        // Routes that change issues are reserved to editors and owners
+        let editor = || middleware::from_fn(require_editor);
         App::new()
             .wrap(message_framework.clone())
-            .wrap(SessionMiddleware::new(
-                redis_store.clone(),
-                secret_key.clone(),
-            ))
+            .wrap(
+                SessionMiddleware::builder(
+                    session_store.clone(),
+                    secret_key.clone(),
+                )
+                .session_lifecycle(
+                    BrowserSession::default().state_ttl(SESSION_TTL),
+                )
+                .build(),
+            )
             .wrap(TracingLogger::default())
+            .wrap(middleware::from_fn(move_csrf_token_to_header))
             .route("/", web::get().to(home))
             .service(
                 web::scope("/admin")
+                    // Checked once the user is known to be logged in
+                    .wrap(middleware::from_fn(verify_csrf_token))
                     .wrap(middleware::from_fn(reject_anonymous_users))
                     .route("/dashboard", web::get().to(admin_dashboard))
                     .route("/password", web::get().to(change_password_form))
                     .route("/password", web::post().to(change_password))
                     .route("/logout", web::post().to(log_out))
+                    .route("/sessions", web::get().to(sessions_list))
+                    .route(
+                        "/sessions/revoke-all",
+                        web::post().to(log_out_everywhere),
+                    )
+                    .route(
+                        "/sessions/{session_id}/revoke",
+                        web::post().to(revoke_user_session),
+                    )
+                    .route("/api-tokens", web::get().to(api_tokens_list))
+                    .route(
+                        "/api-tokens",
+                        web::post().to(create_user_api_token),
+                    )
+                    .route(
+                        "/api-tokens/{api_token_id}/revoke",
+                        web::post().to(revoke_user_api_token),
+                    )
+                    .route("/two-factor", web::get().to(two_factor_settings))
+                    .route(
+                        "/two-factor/setup",
+                        web::post().to(start_two_factor_setup),
+                    )
+                    .route(
+                        "/two-factor/enable",
+                        web::post().to(confirm_two_factor_setup),
+                    )
+                    .route(
+                        "/two-factor/recovery-codes",
+                        web::post().to(new_recovery_codes),
+                    )
+                    .route(
+                        "/two-factor/disable",
+                        web::post().to(turn_off_two_factor),
+                    )
+                    .route(
+                        "/newsletters",
+                        web::get().to(publish_newsletter_form).wrap(editor()),
+                    )
                     .route(
                         "/newsletters",
-                        web::get().to(publish_newsletter_form),
+                        web::post().to(publish_newsletter).wrap(editor()),
+                    )
+                    .route(
+                        "/newsletters/attachments",
+                        web::post().to(upload_attachments).wrap(editor()),
+                    )
+                    .route(
+                        "/newsletters/attachments/{attachment_id}/delete",
+                        web::post().to(delete_attachment).wrap(editor()),
+                    )
+                    .route(
+                        "/drafts",
+                        web::post().to(create_draft).wrap(editor()),
+                    )
+                    .route("/drafts/{issue_id}", web::get().to(edit_draft_form))
+                    .route(
+                        "/drafts/{issue_id}",
+                        web::post().to(save_draft).wrap(editor()),
+                    )
+                    .route(
+                        "/drafts/{issue_id}/publish",
+                        web::post().to(publish_draft).wrap(editor()),
                     )
-                    .route("/newsletters", web::post().to(publish_newsletter)),
+                    .route(
+                        "/drafts/{issue_id}/review",
+                        web::post().to(review_issue).wrap(editor()),
+                    )
+                    .route(
+                        "/drafts/{issue_id}/revisions/{revision_number}/restore",
+                        web::post().to(restore_revision).wrap(editor()),
+                    )
+                    .route("/issues", web::get().to(issues_list))
+                    .route("/issues/{issue_id}", web::get().to(issue_report))
+                    .route(
+                        "/issues/{issue_id}/delivery",
+                        web::post().to(change_delivery_status).wrap(editor()),
+                    )
+                    .route(
+                        "/issues/{issue_id}/diff",
+                        web::get().to(revision_diff),
+                    )
+                    .route(
+                        "/issues/{issue_id}/duplicate",
+                        web::post().to(duplicate_issue).wrap(editor()),
+                    )
+                    .route(
+                        "/issues/{issue_id}/archive",
+                        web::post().to(set_archive_visibility).wrap(editor()),
+                    )
+                    .service(
+                        web::scope("/users")
+                            .wrap(middleware::from_fn(require_owner))
+                            .route("", web::get().to(users_list))
+                            .route("", web::post().to(create_user))
+                            .route(
+                                "/lockouts/unlock",
+                                web::post().to(unlock_login),
+                            )
+                            .route(
+                                "/two-factor-policy",
+                                web::post().to(set_two_factor_policy),
+                            )
+                            .route(
+                                "/{user_id}/status",
+                                web::post().to(change_user_status),
+                            )
+                            .route(
+                                "/{user_id}/email",
+                                web::post().to(change_user_email),
+                            )
+                            .route(
+                                "/{user_id}/role",
+                                web::post().to(change_user_role),
+                            )
+                            .route(
+                                "/{user_id}/delete",
+                                web::post().to(delete_user),
+                            ),
+                    ),
+            )
+            .service(
+                web::scope("/login")
+                    .wrap(middleware::from_fn(verify_csrf_token))
+                    .route("", web::get().to(login_form))
+                    .route("", web::post().to(login))
+                    .route(
+                        "/forgot-password",
+                        web::get().to(forgot_password_form),
+                    )
+                    .route(
+                        "/forgot-password",
+                        web::post().to(request_password_reset),
+                    )
+                    .route(
+                        "/reset-password",
+                        web::get().to(reset_password_form),
+                    )
+                    .route("/reset-password", web::post().to(reset_password))
+                    .route("/two-factor", web::get().to(two_factor_form))
+                    .route("/two-factor", web::post().to(verify_two_factor)),
+            )
+            .service(
+                web::scope("/api/v1")
+                    .wrap(middleware::from_fn(require_api_token))
+                    .wrap(middleware::from_fn(json_errors))
+                    .route("/subscribers", web::get().to(api_list_subscribers))
+                    .route(
+                        "/subscribers",
+                        web::post().to(api_create_subscriber).wrap(editor()),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}",
+                        web::get().to(api_get_subscriber),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}",
+                        web::patch().to(api_update_subscriber).wrap(editor()),
+                    )
+                    .route("/issues", web::get().to(api_list_issues))
+                    .route(
+                        "/issues",
+                        web::post().to(api_create_issue).wrap(editor()),
+                    )
+                    .route("/issues/{issue_id}", web::get().to(api_get_issue))
+                    .route(
+                        "/issues/{issue_id}/publish",
+                        web::post().to(api_publish_issue).wrap(editor()),
+                    ),
             )
-            .route("/login", web::get().to(login_form))
-            .route("/login", web::post().to(login))
             .route("/health_check", web::get().to(health_check))
             .route("/subscriptions", web::post().to(subscribe))
             .route("/subscriptions/confirm", web::get().to(confirm))
+            .route("/archive", web::get().to(archive))
+            .route("/archive/{slug}", web::get().to(archive_issue))
+            .route("/feed.rss", web::get().to(rss_feed))
+            .route("/feed.atom", web::get().to(atom_feed))
+            .route("/t/c/{token}", web::get().to(track_click))
+            .route("/t/o/{token}", web::get().to(track_open))
+            .route(
+                "/t/opt-out/{token}",
+                web::get().to(opt_out_of_tracking_form),
+            )
+            .route("/t/opt-out/{token}", web::post().to(opt_out_of_tracking))
             .app_data(db_pool.clone())
             .app_data(email_client.clone())
             .app_data(base_url.clone())
+            .app_data(tracking.clone())
+            .app_data(attachments.clone())
+            .app_data(publishing.clone())
+            .app_data(schedule.clone())
+            .app_data(login_throttle.clone())
+            .app_data(password_hashing.clone())
+            .app_data(password_policy.clone())
             .app_data(Data::new(HmacSecret(hmac_secret.clone())))
+            .app_data(totp_key.clone())
     })
     .listen(listener)?
     .run();
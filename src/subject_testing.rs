@@ -0,0 +1,440 @@
+//! Subject line A/B tests: a sample of the recipients is split across the
+//! subject variants of an issue, the rest of the audience is held back
+//! until the waiting period is over and receives the winning variant.
+
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::{
+    pick_winner, QuietHours, SubjectTest, VariantResult, WinningMetric,
+};
+use crate::issue_delivery_worker::schedule_deliveries;
+
+#[tracing::instrument(skip(transaction))]
+pub async fn insert_subject_test(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    subject_test: &SubjectTest,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO subject_tests (
+            newsletter_issue_id,
+            sample_percent,
+            winning_metric,
+            wait_minutes
+        )
+        VALUES ($1, $2, $3, $4)
+        "#,
+        issue_id,
+        subject_test.sample_percent,
+        subject_test.winning_metric.as_str(),
+        subject_test.wait_minutes
+    )
+    .execute(&mut *transaction)
+    .await?;
+    for (i, subject) in subject_test.alternatives.iter().enumerate() {
+        sqlx::query!(
+            r#"
+            INSERT INTO subject_variants (
+                newsletter_issue_id,
+                variant,
+                subject
+            )
+            VALUES ($1, $2, $3)
+            "#,
+            issue_id,
+            i as i16 + 1,
+            subject
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Copy the test settings of an issue, not its results.
+#[tracing::instrument(skip(transaction))]
+pub async fn copy_subject_test(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    copy_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO subject_tests (
+            newsletter_issue_id,
+            sample_percent,
+            winning_metric,
+            wait_minutes
+        )
+        SELECT $2, sample_percent, winning_metric, wait_minutes
+        FROM subject_tests
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id,
+        copy_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO subject_variants (newsletter_issue_id, variant, subject)
+        SELECT $2, variant, subject
+        FROM subject_variants
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id,
+        copy_id
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+/// Queue the sample of the test, spread evenly across variants, and hold
+/// back everyone else. Returns the number of recipients, `None` if the
+/// issue has no subject test.
+#[tracing::instrument(skip(transaction))]
+pub async fn enqueue_subject_test(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+) -> Result<Option<u64>, sqlx::Error> {
+    let test = sqlx::query!(
+        r#"
+        SELECT
+            sample_percent,
+            (
+                SELECT count(*) + 1
+                FROM subject_variants v
+                WHERE v.newsletter_issue_id = t.newsletter_issue_id
+            ) AS "n_variants!"
+        FROM subject_tests t
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+    let test = match test {
+        Some(test) => test,
+        None => return Ok(None),
+    };
+    let n_sample = sqlx::query!(
+        r#"
+        WITH recipients AS (
+            SELECT
+                email,
+                row_number() OVER (ORDER BY random()) - 1 AS n,
+                count(*) OVER () AS n_recipients
+            FROM subscriptions
+            WHERE status = 'confirmed'
+        )
+        INSERT INTO issue_delivery_queue (
+            newsletter_issue_id,
+            subscriber_email,
+            subject_variant
+        )
+        SELECT $1, email, (n % $3)::smallint
+        FROM recipients
+        WHERE n < ceil(n_recipients * $2::smallint / 100.0)
+        "#,
+        issue_id,
+        test.sample_percent,
+        test.n_variants
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+    let n_held = sqlx::query!(
+        r#"
+        INSERT INTO subject_test_holdouts (
+            newsletter_issue_id,
+            subscriber_email
+        )
+        SELECT $1, email
+        FROM subscriptions
+        WHERE
+            status = 'confirmed' AND
+            email NOT IN (
+                SELECT subscriber_email
+                FROM issue_delivery_queue
+                WHERE newsletter_issue_id = $1
+            )
+        "#,
+        issue_id
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+    Ok(Some(n_sample + n_held))
+}
+
+/// The waiting period starts once the whole sample can be sent, so that
+/// every variant gets the same chance.
+#[tracing::instrument(skip(transaction))]
+pub async fn start_waiting_period(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subject_tests
+        SET decide_after = (
+            SELECT coalesce(max(execute_after), now())
+            FROM issue_delivery_queue
+            WHERE newsletter_issue_id = $1
+        ) + wait_minutes * interval '1 minute'
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+/// The subject line of `variant`, `None` for the title of the issue.
+#[tracing::instrument(skip(pool))]
+pub async fn get_variant_subject(
+    pool: &PgPool,
+    issue_id: Uuid,
+    variant: i16,
+) -> Result<Option<String>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT subject
+        FROM subject_variants
+        WHERE newsletter_issue_id = $1 AND variant = $2
+        "#,
+        issue_id,
+        variant
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve a subject variant")?;
+    Ok(row.map(|r| r.subject))
+}
+
+pub struct SubjectTestSummary {
+    pub sample_percent: i16,
+    pub winning_metric: WinningMetric,
+    pub decide_after: Option<chrono::DateTime<chrono::Utc>>,
+    pub winning_variant: Option<i16>,
+    pub n_held: i64,
+    /// Subject lines, indexed by variant.
+    pub subjects: Vec<String>,
+    /// Results of the sample, indexed by variant.
+    pub results: Vec<VariantResult>,
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_subject_test(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<Option<SubjectTestSummary>, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let test = sqlx::query!(
+        r#"
+        SELECT
+            t.sample_percent,
+            t.winning_metric,
+            t.decide_after,
+            t.winning_variant,
+            i.title,
+            (
+                SELECT count(*)
+                FROM subject_test_holdouts h
+                WHERE h.newsletter_issue_id = t.newsletter_issue_id
+            ) AS "n_held!"
+        FROM subject_tests t
+        JOIN newsletter_issues i
+            ON i.newsletter_issue_id = t.newsletter_issue_id
+        WHERE t.newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_optional(&mut transaction)
+    .await
+    .context("Failed to retrieve a subject test")?;
+    let test = match test {
+        Some(test) => test,
+        None => return Ok(None),
+    };
+    let winning_metric = WinningMetric::try_from(test.winning_metric)
+        .map_err(anyhow::Error::msg)?;
+    let alternatives = sqlx::query!(
+        r#"
+        SELECT subject
+        FROM subject_variants
+        WHERE newsletter_issue_id = $1
+        ORDER BY variant
+        "#,
+        issue_id
+    )
+    .fetch_all(&mut transaction)
+    .await
+    .context("Failed to retrieve the subject variants of an issue")?;
+    let subjects: Vec<String> = std::iter::once(test.title)
+        .chain(alternatives.into_iter().map(|r| r.subject))
+        .collect();
+    let results =
+        get_sample_results(&mut transaction, issue_id, winning_metric).await?;
+    let results = (0..subjects.len() as i16)
+        .map(|variant| {
+            results
+                .iter()
+                .find(|r| r.variant == variant)
+                .copied()
+                .unwrap_or(VariantResult {
+                    variant,
+                    n_sent: 0,
+                    n_converted: 0,
+                })
+        })
+        .collect();
+    Ok(Some(SubjectTestSummary {
+        sample_percent: test.sample_percent,
+        winning_metric,
+        decide_after: test.decide_after,
+        winning_variant: test.winning_variant,
+        n_held: test.n_held,
+        subjects,
+        results,
+    }))
+}
+
+/// Unique opens or clickers among the emails successfully sent to the
+/// sample, by variant.
+#[tracing::instrument(skip(transaction))]
+async fn get_sample_results(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    winning_metric: WinningMetric,
+) -> Result<Vec<VariantResult>, anyhow::Error> {
+    let results = sqlx::query_as!(
+        VariantResult,
+        r#"
+        SELECT
+            l.subject_variant AS "variant!",
+            count(*) AS "n_sent!",
+            count(*) FILTER (
+                WHERE CASE $2
+                    WHEN 'opens' THEN EXISTS (
+                        SELECT 1
+                        FROM issue_opens o
+                        WHERE
+                            o.newsletter_issue_id = $1 AND
+                            o.subscriber_id = s.id
+                    )
+                    ELSE EXISTS (
+                        SELECT 1
+                        FROM link_clicks c
+                        JOIN issue_links il ON il.link_id = c.link_id
+                        WHERE
+                            il.newsletter_issue_id = $1 AND
+                            c.subscriber_id = s.id
+                    )
+                END
+            ) AS "n_converted!"
+        FROM issue_delivery_log l
+        LEFT JOIN subscriptions s ON s.email = l.subscriber_email
+        WHERE
+            l.newsletter_issue_id = $1 AND
+            l.outcome = 'sent' AND
+            l.subject_variant IS NOT NULL AND
+            l.subscriber_email NOT IN (
+                SELECT subscriber_email
+                FROM subject_test_holdouts
+                WHERE newsletter_issue_id = $1
+            )
+        GROUP BY l.subject_variant
+        ORDER BY l.subject_variant
+        "#,
+        issue_id,
+        winning_metric.as_str()
+    )
+    .fetch_all(transaction)
+    .await
+    .context("Failed to compute the results of a subject test")?;
+    Ok(results)
+}
+
+/// Pick the winner of every test whose waiting period is over and queue
+/// the held back recipients with it, unless the delivery was cancelled.
+/// Returns the number of tests decided.
+#[tracing::instrument(skip_all)]
+pub async fn pick_subject_test_winners(
+    pool: &PgPool,
+    quiet_hours: Option<QuietHours>,
+) -> Result<u64, anyhow::Error> {
+    let mut n_decided = 0;
+    loop {
+        let mut transaction = pool.begin().await?;
+        let test = sqlx::query!(
+            r#"
+            SELECT t.newsletter_issue_id, t.winning_metric, i.delivery_status
+            FROM subject_tests t
+            JOIN newsletter_issues i
+                ON i.newsletter_issue_id = t.newsletter_issue_id
+            WHERE
+                t.decided_at IS NULL AND
+                t.decide_after <= now()
+            LIMIT 1
+            FOR UPDATE OF t SKIP LOCKED
+            "#
+        )
+        .fetch_optional(&mut transaction)
+        .await?;
+        let test = match test {
+            Some(test) => test,
+            None => return Ok(n_decided),
+        };
+        let issue_id = test.newsletter_issue_id;
+        let winning_metric = WinningMetric::try_from(test.winning_metric)
+            .map_err(anyhow::Error::msg)?;
+        let results =
+            get_sample_results(&mut transaction, issue_id, winning_metric)
+                .await?;
+        let winner = pick_winner(&results);
+        sqlx::query!(
+            r#"
+            UPDATE subject_tests
+            SET winning_variant = $1, decided_at = now()
+            WHERE newsletter_issue_id = $2
+            "#,
+            winner,
+            issue_id
+        )
+        .execute(&mut transaction)
+        .await?;
+        if test.delivery_status != "cancelled" {
+            sqlx::query!(
+                r#"
+                INSERT INTO issue_delivery_queue (
+                    newsletter_issue_id,
+                    subscriber_email,
+                    subject_variant
+                )
+                SELECT newsletter_issue_id, subscriber_email, $2
+                FROM subject_test_holdouts
+                WHERE newsletter_issue_id = $1
+                ON CONFLICT DO NOTHING
+                "#,
+                issue_id,
+                winner
+            )
+            .execute(&mut transaction)
+            .await?;
+            schedule_deliveries(&mut transaction, issue_id, quiet_hours)
+                .await?;
+        }
+        transaction.commit().await?;
+        tracing::info!(%issue_id, winner, "Picked the winner of a subject test");
+        n_decided += 1;
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use secrecy::Secret;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{rewrite_links, ClickToken};
+
+/// Point every external link of `html_content` to the click tracking
+/// endpoint, using a token unique to the subscriber and the link.
+#[tracing::instrument(skip(pool, html_content, hmac_secret))]
+pub async fn add_click_tracking(
+    pool: &PgPool,
+    issue_id: Uuid,
+    subscriber_id: Uuid,
+    html_content: &str,
+    base_url: &str,
+    hmac_secret: &Secret<String>,
+) -> Result<String, anyhow::Error> {
+    let mut urls = vec![];
+    rewrite_links(html_content, base_url, |url| {
+        urls.push(url.to_string());
+        None
+    });
+    if urls.is_empty() {
+        return Ok(html_content.to_string());
+    }
+    let link_ids = register_links(pool, issue_id, &urls).await?;
+
+    Ok(rewrite_links(html_content, base_url, |url| {
+        let link_id = link_ids.get(url)?;
+        let token = ClickToken {
+            link_id: *link_id,
+            subscriber_id,
+        };
+        Some(format!("{}/t/c/{}", base_url, token.encode(hmac_secret)))
+    }))
+}
+
+/// Links are registered the first time the issue is sent out and shared by
+/// every subsequent delivery.
+#[tracing::instrument(skip(pool, urls))]
+async fn register_links(
+    pool: &PgPool,
+    issue_id: Uuid,
+    urls: &[String],
+) -> Result<HashMap<String, Uuid>, anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_links (link_id, newsletter_issue_id, url)
+        SELECT gen_random_uuid(), $1, url
+        FROM unnest($2::text[]) AS url
+        ON CONFLICT DO NOTHING
+        "#,
+        issue_id,
+        urls,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to register the links of an issue")?;
+    let links = sqlx::query!(
+        r#"
+        SELECT link_id, url
+        FROM issue_links
+        WHERE
+            newsletter_issue_id = $1 AND
+            url = ANY($2)
+        "#,
+        issue_id,
+        urls,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the links of an issue")?;
+    Ok(links.into_iter().map(|l| (l.url, l.link_id)).collect())
+}
@@ -0,0 +1,148 @@
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+
+static HREF: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)(href\s*=\s*)("[^"]*"|'[^']*')"#).unwrap()
+});
+
+static TEXT_URL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)https?://[^\s<>"']+"#).unwrap());
+
+/// Rewrite the target of every `href` attribute in `html` pointing to an
+/// external `http(s)` URL.
+/// `rewrite` receives the decoded URL and returns its replacement, or `None`
+/// to leave the link untouched. Links to `base_url` are never rewritten.
+pub fn rewrite_links<F>(html: &str, base_url: &str, mut rewrite: F) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    HREF.replace_all(html, |caps: &Captures| {
+        let quoted = &caps[2];
+        let quote = &quoted[..1];
+        let url = htmlescape::decode_html(&quoted[1..quoted.len() - 1])
+            .unwrap_or_else(|_| quoted[1..quoted.len() - 1].to_string());
+        let replacement = if is_external_link(&url, base_url) {
+            rewrite(&url)
+        } else {
+            None
+        };
+        match replacement {
+            Some(new_url) => format!(
+                "{}{}{}{}",
+                &caps[1],
+                quote,
+                htmlescape::encode_minimal(&new_url),
+                quote
+            ),
+            None => caps[0].to_string(),
+        }
+    })
+    .into_owned()
+}
+
+/// Rewrite every external `http(s)` URL appearing in plain `text`.
+/// Trailing punctuation is assumed to belong to the sentence, not the URL.
+pub fn rewrite_text_links<F>(
+    text: &str,
+    base_url: &str,
+    mut rewrite: F,
+) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    TEXT_URL
+        .replace_all(text, |caps: &Captures| {
+            let url = caps[0].trim_end_matches(|c| ".,;:!?)".contains(c));
+            let trailing = &caps[0][url.len()..];
+            let replacement = if is_external_link(url, base_url) {
+                rewrite(url)
+            } else {
+                None
+            };
+            match replacement {
+                Some(new_url) => format!("{}{}", new_url, trailing),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+pub fn is_external_link(url: &str, base_url: &str) -> bool {
+    let lowercase_url = url.to_lowercase();
+    (lowercase_url.starts_with("http://")
+        || lowercase_url.starts_with("https://"))
+        && !url.starts_with(base_url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rewrite_links, rewrite_text_links};
+
+    const BASE_URL: &str = "https://newsletter.example.com";
+
+    fn tracked(url: &str) -> Option<String> {
+        Some(format!("{}/t/c/{}", BASE_URL, url.len()))
+    }
+
+    #[test]
+    fn external_links_are_rewritten() {
+        let html = r#"<a href="https://rust-lang.org">Rust</a>"#;
+        assert_eq!(
+            rewrite_links(html, BASE_URL, tracked),
+            r#"<a href="https://newsletter.example.com/t/c/21">Rust</a>"#
+        );
+    }
+
+    #[test]
+    fn single_quoted_and_spaced_attributes_are_rewritten() {
+        let html = r#"<a HREF = 'http://a.com'>A</a>"#;
+        assert_eq!(
+            rewrite_links(html, BASE_URL, tracked),
+            r#"<a HREF = 'https://newsletter.example.com/t/c/12'>A</a>"#
+        );
+    }
+
+    #[test]
+    fn internal_and_non_http_links_are_left_untouched() {
+        let html = r##"<a href="https://newsletter.example.com/archive/x">A</a>
+            <a href="mailto:me@example.com">B</a>
+            <a href="/relative">C</a>
+            <a href="#anchor">D</a>"##;
+        assert_eq!(rewrite_links(html, BASE_URL, tracked), html);
+    }
+
+    #[test]
+    fn urls_are_decoded_before_being_rewritten() {
+        let html = r#"<a href="https://a.com/?x=1&amp;y=2">A</a>"#;
+        let mut seen = vec![];
+        rewrite_links(html, BASE_URL, |url| {
+            seen.push(url.to_string());
+            None
+        });
+        assert_eq!(seen, vec!["https://a.com/?x=1&y=2"]);
+    }
+
+    #[test]
+    fn replacements_are_encoded() {
+        let html = r#"<a href="https://a.com">A</a>"#;
+        let rewritten = rewrite_links(html, BASE_URL, |_| {
+            Some("https://b.com/?x=1&y=\"2\"".into())
+        });
+        assert_eq!(
+            rewritten,
+            r#"<a href="https://b.com/?x=1&amp;y=&quot;2&quot;">A</a>"#
+        );
+    }
+
+    #[test]
+    fn urls_in_text_are_rewritten_without_trailing_punctuation() {
+        let text = "See https://a.com/x. Or (http://b.com), or \
+            https://newsletter.example.com/archive/x!";
+        assert_eq!(
+            rewrite_text_links(text, BASE_URL, tracked),
+            "See https://newsletter.example.com/t/c/15. \
+            Or (https://newsletter.example.com/t/c/12), or \
+            https://newsletter.example.com/archive/x!"
+        );
+    }
+}
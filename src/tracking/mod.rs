@@ -0,0 +1,13 @@
+mod clicks;
+mod links;
+mod opens;
+mod opt_out;
+mod token;
+mod utm;
+
+pub use clicks::add_click_tracking;
+pub use links::{rewrite_links, rewrite_text_links};
+pub use opens::{add_open_pixel, is_probably_prefetch};
+pub use opt_out::add_opt_out_link;
+pub use token::{ClickToken, OpenToken, OptOutToken, TokenError};
+pub use utm::{add_utm_parameters, UtmParameters};
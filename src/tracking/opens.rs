@@ -0,0 +1,141 @@
+use chrono::Duration;
+use secrecy::Secret;
+
+use super::OpenToken;
+
+/// Opens reported this soon after the email left are most likely a
+/// security gateway scanning the message on its way to the inbox.
+const MIN_SECONDS_BEFORE_OPEN: i64 = 5;
+
+/// Fragments of the user agents sent by proxies and scanners which fetch
+/// images whether or not the email is read.
+const PREFETCHING_USER_AGENTS: &[&str] = &[
+    "barracuda",
+    "mimecast",
+    "proofpoint",
+    "symantec",
+    "bot",
+    "crawler",
+    "spider",
+    "preview",
+];
+
+/// Append an invisible image pointing to the open tracking endpoint.
+pub fn add_open_pixel(
+    html_content: &str,
+    base_url: &str,
+    token: &OpenToken,
+    hmac_secret: &Secret<String>,
+) -> String {
+    let pixel = format!(
+        r#"<img src="{}/t/o/{}" width="1" height="1" alt="" style="display:none">"#,
+        base_url,
+        token.encode(hmac_secret)
+    );
+    match html_content.to_ascii_lowercase().rfind("</body>") {
+        Some(i) => {
+            format!("{}{}{}", &html_content[..i], pixel, &html_content[i..])
+        }
+        None => format!("{}\n{}", html_content, pixel),
+    }
+}
+
+/// Best-effort guess at whether a pixel request comes from software
+/// fetching images on the reader's behalf rather than from the reader.
+/// Apple Mail Privacy Protection announces itself as a bare `Mozilla/5.0`.
+pub fn is_probably_prefetch(
+    user_agent: Option<&str>,
+    since_delivery: Option<Duration>,
+) -> bool {
+    let user_agent = match user_agent.map(str::trim) {
+        None | Some("") | Some("Mozilla/5.0") => return true,
+        Some(user_agent) => user_agent.to_ascii_lowercase(),
+    };
+    if PREFETCHING_USER_AGENTS
+        .iter()
+        .any(|fragment| user_agent.contains(fragment))
+    {
+        return true;
+    }
+    match since_delivery {
+        Some(elapsed) => elapsed < Duration::seconds(MIN_SECONDS_BEFORE_OPEN),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    use super::{add_open_pixel, is_probably_prefetch};
+    use crate::tracking::OpenToken;
+
+    fn token() -> OpenToken {
+        OpenToken {
+            issue_id: Uuid::new_v4(),
+            subscriber_id: Uuid::new_v4(),
+        }
+    }
+
+    const BROWSER: &str =
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) Gecko/20100101 Firefox/118.0";
+
+    #[test]
+    fn the_pixel_is_placed_before_the_closing_body_tag() {
+        let token = token();
+        let secret = Secret::new("secret".to_string());
+        let html = add_open_pixel(
+            "<html><BODY><p>Hi</p></BODY></html>",
+            "https://x.com",
+            &token,
+            &secret,
+        );
+        assert!(html
+            .starts_with("<html><BODY><p>Hi</p><img src=\"https://x.com/t/o/"));
+        assert!(html.ends_with("</BODY></html>"));
+    }
+
+    #[test]
+    fn the_pixel_is_appended_to_fragments() {
+        let token = token();
+        let secret = Secret::new("secret".to_string());
+        let html =
+            add_open_pixel("<p>Hi</p>", "https://x.com", &token, &secret);
+        assert!(html.starts_with("<p>Hi</p>\n<img"));
+    }
+
+    #[test]
+    fn a_regular_browser_is_a_real_open() {
+        assert!(!is_probably_prefetch(Some(BROWSER), None));
+        assert!(!is_probably_prefetch(
+            Some(BROWSER),
+            Some(Duration::minutes(3))
+        ));
+    }
+
+    #[test]
+    fn missing_or_bare_user_agents_are_prefetches() {
+        assert!(is_probably_prefetch(None, None));
+        assert!(is_probably_prefetch(Some(""), None));
+        assert!(is_probably_prefetch(Some("Mozilla/5.0"), None));
+    }
+
+    #[test]
+    fn known_scanners_are_prefetches() {
+        assert!(is_probably_prefetch(Some("Barracuda Sentinel (EE)"), None));
+        assert!(is_probably_prefetch(
+            Some("Mozilla/5.0 (compatible; Bot)"),
+            None
+        ));
+    }
+
+    #[test]
+    fn opens_right_after_delivery_are_prefetches() {
+        assert!(is_probably_prefetch(
+            Some(BROWSER),
+            Some(Duration::seconds(1))
+        ));
+    }
+}
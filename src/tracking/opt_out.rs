@@ -0,0 +1,26 @@
+use secrecy::Secret;
+use uuid::Uuid;
+
+use super::OptOutToken;
+
+/// Append a link letting the subscriber turn off tracking to both bodies.
+pub fn add_opt_out_link(
+    html_content: &str,
+    text_content: &str,
+    base_url: &str,
+    subscriber_id: Uuid,
+    hmac_secret: &Secret<String>,
+) -> (String, String) {
+    let token = OptOutToken { subscriber_id };
+    let opt_out_url =
+        format!("{}/t/opt-out/{}", base_url, token.encode(hmac_secret));
+    let html_content = format!(
+        "{}\n<p><a href=\"{}\">Stop tracking my opens and clicks</a></p>",
+        html_content, opt_out_url
+    );
+    let text_content = format!(
+        "{}\n\nStop tracking my opens and clicks: {}",
+        text_content, opt_out_url
+    );
+    (html_content, text_content)
+}
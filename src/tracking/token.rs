@@ -0,0 +1,229 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const MAC_SIZE: usize = 32;
+
+#[derive(thiserror::Error, Debug)]
+pub enum TokenError {
+    #[error("The tracking token is malformed")]
+    Malformed,
+    #[error("The tracking token signature is invalid")]
+    InvalidSignature,
+}
+
+/// Identifies the link a subscriber clicked in a newsletter issue.
+/// Tokens are signed so that statistics can't be forged.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ClickToken {
+    pub link_id: Uuid,
+    pub subscriber_id: Uuid,
+}
+
+impl ClickToken {
+    const PURPOSE: &'static [u8] = b"click";
+
+    pub fn encode(&self, secret: &Secret<String>) -> String {
+        encode_ids(Self::PURPOSE, &[self.link_id, self.subscriber_id], secret)
+    }
+
+    pub fn decode(
+        token: &str,
+        secret: &Secret<String>,
+    ) -> Result<Self, TokenError> {
+        let [link_id, subscriber_id] =
+            decode_ids(Self::PURPOSE, token, secret)?;
+        Ok(Self {
+            link_id,
+            subscriber_id,
+        })
+    }
+}
+
+/// Identifies the subscriber who opened a newsletter issue.
+#[derive(Debug, PartialEq, Eq)]
+pub struct OpenToken {
+    pub issue_id: Uuid,
+    pub subscriber_id: Uuid,
+}
+
+impl OpenToken {
+    const PURPOSE: &'static [u8] = b"open";
+
+    pub fn encode(&self, secret: &Secret<String>) -> String {
+        encode_ids(Self::PURPOSE, &[self.issue_id, self.subscriber_id], secret)
+    }
+
+    pub fn decode(
+        token: &str,
+        secret: &Secret<String>,
+    ) -> Result<Self, TokenError> {
+        let [issue_id, subscriber_id] =
+            decode_ids(Self::PURPOSE, token, secret)?;
+        Ok(Self {
+            issue_id,
+            subscriber_id,
+        })
+    }
+}
+
+/// Lets a subscriber opt out of tracking from the link in our emails.
+#[derive(Debug, PartialEq, Eq)]
+pub struct OptOutToken {
+    pub subscriber_id: Uuid,
+}
+
+impl OptOutToken {
+    const PURPOSE: &'static [u8] = b"opt-out";
+
+    pub fn encode(&self, secret: &Secret<String>) -> String {
+        encode_ids(Self::PURPOSE, &[self.subscriber_id], secret)
+    }
+
+    pub fn decode(
+        token: &str,
+        secret: &Secret<String>,
+    ) -> Result<Self, TokenError> {
+        let [subscriber_id] = decode_ids(Self::PURPOSE, token, secret)?;
+        Ok(Self { subscriber_id })
+    }
+}
+
+fn encode_ids(purpose: &[u8], ids: &[Uuid], secret: &Secret<String>) -> String {
+    let payload = ids.iter().flat_map(|id| *id.as_bytes()).collect();
+    sign(purpose, payload, secret)
+}
+
+fn decode_ids<const N: usize>(
+    purpose: &[u8],
+    token: &str,
+    secret: &Secret<String>,
+) -> Result<[Uuid; N], TokenError> {
+    let payload = verify(purpose, token, secret)?;
+    if payload.len() != N * 16 {
+        return Err(TokenError::Malformed);
+    }
+    let mut ids = [Uuid::nil(); N];
+    for (id, bytes) in ids.iter_mut().zip(payload.chunks_exact(16)) {
+        *id = Uuid::from_slice(bytes).map_err(|_| TokenError::Malformed)?;
+    }
+    Ok(ids)
+}
+
+fn mac(purpose: &[u8], secret: &Secret<String>) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size");
+    mac.update(purpose);
+    mac
+}
+
+fn sign(
+    purpose: &[u8],
+    mut payload: Vec<u8>,
+    secret: &Secret<String>,
+) -> String {
+    let mut mac = mac(purpose, secret);
+    mac.update(&payload);
+    payload.extend_from_slice(&mac.finalize().into_bytes());
+    URL_SAFE_NO_PAD.encode(payload)
+}
+
+fn verify(
+    purpose: &[u8],
+    token: &str,
+    secret: &Secret<String>,
+) -> Result<Vec<u8>, TokenError> {
+    let mut bytes = URL_SAFE_NO_PAD
+        .decode(token)
+        .map_err(|_| TokenError::Malformed)?;
+    if bytes.len() < MAC_SIZE {
+        return Err(TokenError::Malformed);
+    }
+    let signature = bytes.split_off(bytes.len() - MAC_SIZE);
+    let mut mac = mac(purpose, secret);
+    mac.update(&bytes);
+    mac.verify_slice(&signature)
+        .map_err(|_| TokenError::InvalidSignature)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok_eq};
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    use super::{ClickToken, OpenToken, OptOutToken};
+
+    fn secret() -> Secret<String> {
+        Secret::new("a-very-secret-key".to_string())
+    }
+
+    fn token() -> ClickToken {
+        ClickToken {
+            link_id: Uuid::new_v4(),
+            subscriber_id: Uuid::new_v4(),
+        }
+    }
+
+    #[test]
+    fn an_encoded_token_can_be_decoded() {
+        let token = token();
+        let encoded = token.encode(&secret());
+        assert_ok_eq!(ClickToken::decode(&encoded, &secret()), token);
+    }
+
+    #[test]
+    fn encoded_tokens_are_url_safe() {
+        let encoded = token().encode(&secret());
+        assert!(encoded
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    }
+
+    #[test]
+    fn a_token_signed_with_another_secret_is_rejected() {
+        let encoded = token().encode(&Secret::new("another-key".to_string()));
+        assert_err!(ClickToken::decode(&encoded, &secret()));
+    }
+
+    #[test]
+    fn a_tampered_token_is_rejected() {
+        let mut encoded = token().encode(&secret()).into_bytes();
+        encoded[3] = if encoded[3] == b'A' { b'B' } else { b'A' };
+        let encoded = String::from_utf8(encoded).unwrap();
+        assert_err!(ClickToken::decode(&encoded, &secret()));
+    }
+
+    #[test]
+    fn a_token_issued_for_another_purpose_is_rejected() {
+        let token = token();
+        let encoded = OpenToken {
+            issue_id: token.link_id,
+            subscriber_id: token.subscriber_id,
+        }
+        .encode(&secret());
+        assert_err!(ClickToken::decode(&encoded, &secret()));
+    }
+
+    #[test]
+    fn a_token_with_the_wrong_number_of_ids_is_rejected() {
+        let encoded = OptOutToken {
+            subscriber_id: Uuid::new_v4(),
+        }
+        .encode(&secret());
+        assert_err!(OpenToken::decode(&encoded, &secret()));
+        assert_ok_eq!(OptOutToken::decode(&encoded, &secret()).map(|_| ()), ());
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert_err!(ClickToken::decode("not a token", &secret()));
+        assert_err!(ClickToken::decode("", &secret()));
+    }
+}
@@ -0,0 +1,95 @@
+use reqwest::Url;
+
+use super::{rewrite_links, rewrite_text_links};
+
+/// Campaign attribution parameters appended to outbound links.
+#[derive(Debug, Clone)]
+pub struct UtmParameters {
+    pub source: String,
+    pub medium: String,
+    pub campaign: String,
+}
+
+impl UtmParameters {
+    pub const DEFAULT_SOURCE: &'static str = "newsletter";
+    pub const DEFAULT_MEDIUM: &'static str = "email";
+
+    /// Append the parameters to `url`.
+    /// Returns `None` if the URL can't be parsed or already carries UTM
+    /// parameters, which were then chosen on purpose by the author.
+    pub fn tag(&self, url: &str) -> Option<String> {
+        let mut url = Url::parse(url).ok()?;
+        if url.query_pairs().any(|(key, _)| key.starts_with("utm_")) {
+            return None;
+        }
+        url.query_pairs_mut()
+            .append_pair("utm_source", &self.source)
+            .append_pair("utm_medium", &self.medium)
+            .append_pair("utm_campaign", &self.campaign);
+        Some(url.into())
+    }
+}
+
+/// Tag every outbound link of both bodies.
+/// Links to our own pages (archive, tracking, opt-out) are left untouched.
+pub fn add_utm_parameters(
+    html_content: &str,
+    text_content: &str,
+    base_url: &str,
+    parameters: &UtmParameters,
+) -> (String, String) {
+    let html_content =
+        rewrite_links(html_content, base_url, |url| parameters.tag(url));
+    let text_content =
+        rewrite_text_links(text_content, base_url, |url| parameters.tag(url));
+    (html_content, text_content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_utm_parameters, UtmParameters};
+
+    const BASE_URL: &str = "https://newsletter.example.com";
+
+    fn parameters() -> UtmParameters {
+        UtmParameters {
+            source: "newsletter".into(),
+            medium: "email".into(),
+            campaign: "october update".into(),
+        }
+    }
+
+    #[test]
+    fn parameters_are_appended_to_the_existing_query() {
+        assert_eq!(
+            parameters().tag("https://a.com/page?x=1#top").unwrap(),
+            "https://a.com/page?x=1&utm_source=newsletter&utm_medium=email\
+            &utm_campaign=october+update#top"
+        );
+    }
+
+    #[test]
+    fn links_with_utm_parameters_are_left_untouched() {
+        assert!(parameters().tag("https://a.com/?utm_source=blog").is_none());
+    }
+
+    #[test]
+    fn both_bodies_are_tagged_except_internal_links() {
+        let (html, text) = add_utm_parameters(
+            r#"<a href="https://a.com/">A</a> <a href="https://newsletter.example.com/archive/x">B</a>"#,
+            "Read https://a.com/. Archive: https://newsletter.example.com/archive/x",
+            BASE_URL,
+            &parameters(),
+        );
+        assert_eq!(
+            html,
+            r#"<a href="https://a.com/?utm_source=newsletter&amp;utm_medium=email&amp;utm_campaign=october+update">A</a> <a href="https://newsletter.example.com/archive/x">B</a>"#
+        );
+        assert_eq!(
+            text,
+            "Read https://a.com/?utm_source=newsletter&utm_medium=email\
+            &utm_campaign=october+update. \
+            Archive: https://newsletter.example.com/archive/x"
+        );
+    }
+}
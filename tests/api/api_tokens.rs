@@ -0,0 +1,240 @@
+use uuid::Uuid;
+
+use crate::helpers::{
+    assert_is_redirect_to, build_api_client, spawn_app, TestApp,
+};
+
+/// Create a token for the logged in test user, with the given scopes.
+async fn create_token(app: &TestApp, scopes: &[&str]) -> String {
+    let mut body = serde_json::json!({
+        "name": "CI pipeline",
+        "expires_in_days": "30",
+    });
+    for scope in scopes {
+        body[format!("scope_{}", scope)] = "on".into();
+    }
+    let response = app.post_api_tokens(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    html_page
+        .split("<code>")
+        .nth(1)
+        .and_then(|s| s.split("</code>").next())
+        .unwrap()
+        .to_string()
+}
+
+/// A client without any session, like a CI job.
+fn bearer_request(
+    app: &TestApp,
+    method: reqwest::Method,
+    path: &str,
+    token: &str,
+) -> reqwest::RequestBuilder {
+    build_api_client(reqwest::Client::builder())
+        .request(method, format!("{}{}", &app.address, path))
+        .bearer_auth(token)
+}
+
+#[tokio::test]
+async fn tokens_are_shown_once_and_stored_hashed() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    let token = create_token(&app, &["read"]).await;
+
+    assert!(token.starts_with("z2p_"));
+    let row = sqlx::query!("SELECT name, token_hash, scopes FROM api_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(row.name, "CI pipeline");
+    assert_eq!(row.scopes, vec!["read".to_string()]);
+    assert_ne!(row.token_hash, token);
+    let html_page = app.get_api_tokens_html().await;
+    assert!(html_page.contains("<td>CI pipeline</td>"));
+    assert!(!html_page.contains(&token));
+}
+
+#[tokio::test]
+async fn a_token_authenticates_requests_as_its_owner() {
+    let app = spawn_app().await;
+    app.login().await;
+    let token = create_token(&app, &["read"]).await;
+
+    let response =
+        bearer_request(&app, reqwest::Method::GET, "/admin/dashboard", &token)
+            .send()
+            .await
+            .unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
+}
+
+#[tokio::test]
+async fn a_publish_token_can_publish_without_a_csrf_token() {
+    let app = spawn_app().await;
+    app.login().await;
+    let token = create_token(&app, &["publish"]).await;
+
+    let response = bearer_request(
+        &app,
+        reqwest::Method::POST,
+        "/admin/newsletters",
+        &token,
+    )
+    .form(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    }))
+    .send()
+    .await
+    .unwrap();
+
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let n_issues =
+        sqlx::query!(r#"SELECT count(*) AS "count!" FROM newsletter_issues"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .count;
+    assert_eq!(n_issues, 1);
+}
+
+#[tokio::test]
+async fn requests_outside_the_scopes_of_a_token_are_rejected() {
+    let app = spawn_app().await;
+    app.login().await;
+    let token = create_token(&app, &["read", "write"]).await;
+
+    let response = bearer_request(
+        &app,
+        reqwest::Method::POST,
+        "/admin/newsletters",
+        &token,
+    )
+    .form(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    }))
+    .send()
+    .await
+    .unwrap();
+
+    assert_eq!(response.status().as_u16(), 403);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("This request needs an API token with the publish scope."));
+}
+
+#[tokio::test]
+async fn tokens_can_not_manage_credentials() {
+    let app = spawn_app().await;
+    app.login().await;
+    let token = create_token(&app, &["read", "write", "publish"]).await;
+
+    for (method, path) in [
+        (reqwest::Method::GET, "/admin/password"),
+        (reqwest::Method::POST, "/admin/api-tokens"),
+        (reqwest::Method::GET, "/admin/users"),
+        (reqwest::Method::POST, "/admin/sessions/revoke-all"),
+    ] {
+        let response = bearer_request(&app, method, path, &token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 403, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn percent_encoded_paths_need_the_same_scope() {
+    let app = spawn_app().await;
+    app.login().await;
+    let token = create_token(&app, &["read", "write"]).await;
+    let issue_id = Uuid::new_v4();
+
+    for (method, path) in [
+        (reqwest::Method::GET, "/admin/p%61ssword".to_string()),
+        (reqwest::Method::GET, "/admin/%75sers".to_string()),
+        (
+            reqwest::Method::POST,
+            format!("/admin/drafts/{}/p%75blish", issue_id),
+        ),
+    ] {
+        let response = bearer_request(&app, method, &path, &token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 403, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn invalid_expired_and_revoked_tokens_are_rejected() {
+    let app = spawn_app().await;
+    app.login().await;
+    let expired = create_token(&app, &["read"]).await;
+    sqlx::query!("UPDATE api_tokens SET expires_at = now() - interval '1 day'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let revoked = create_token(&app, &["read"]).await;
+    let api_token_id = sqlx::query!(
+        "SELECT api_token_id FROM api_tokens WHERE expires_at > now()"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .api_token_id;
+    let response = app.post_revoke_api_token(api_token_id).await;
+    assert_is_redirect_to(&response, "/admin/api-tokens");
+
+    for token in [&format!("z2p_{}", "a".repeat(40)), &expired, &revoked] {
+        let response =
+            bearer_request(&app, reqwest::Method::GET, "/admin/issues", token)
+                .send()
+                .await
+                .unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+        assert_eq!(
+            response.headers()["WWW-Authenticate"],
+            r#"Bearer realm="admin""#
+        );
+    }
+}
+
+#[tokio::test]
+async fn tokens_need_a_name_and_a_scope() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    let response = app
+        .post_api_tokens(&serde_json::json!({
+            "name": "No scope",
+            "expires_in_days": "never",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/api-tokens");
+    let html_page = app.get_api_tokens_html().await;
+    assert!(html_page.contains("Choose at least one scope for the token."));
+
+    let response = app
+        .post_api_tokens(&serde_json::json!({
+            "name": " ",
+            "expires_in_days": "never",
+            "scope_read": "on",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/api-tokens");
+    let html_page = app.get_api_tokens_html().await;
+    assert!(html_page.contains("You have no API tokens."));
+}
@@ -0,0 +1,359 @@
+use reqwest::Method;
+use uuid::Uuid;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+use zero2prod::domain::ApiScope;
+
+use crate::helpers::{spawn_app, TestApp, TestUser};
+
+async fn mock_email_delivery(app: &TestApp, n_emails: u64) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(n_emails)
+        .mount(&app.email_server)
+        .await;
+}
+
+async fn create_subscriber(
+    app: &TestApp,
+    token: &str,
+    email: &str,
+) -> serde_json::Value {
+    let response = app
+        .api_v1_request(Method::POST, "/subscribers", token)
+        .json(&serde_json::json!({
+            "name": "Ursula Le Guin",
+            "email": email,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 201);
+    response.json().await.unwrap()
+}
+
+async fn assert_json_error(
+    response: reqwest::Response,
+    status: u16,
+    code: &str,
+) -> String {
+    assert_eq!(response.status().as_u16(), status);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], code);
+    body["error"]["message"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn requests_without_an_api_token_get_a_json_error() {
+    let app = spawn_app().await;
+
+    // A session is not enough either
+    app.post_login(&serde_json::json!({
+        "username": &app.test_user.username,
+        "password": &app.test_user.password,
+    }))
+    .await;
+    let response = app
+        .api_client
+        .get(format!("{}/api/v1/subscribers", &app.address))
+        .send()
+        .await
+        .unwrap();
+
+    assert!(response.headers().contains_key("WWW-Authenticate"));
+    let message = assert_json_error(response, 401, "unauthorized").await;
+    assert_eq!(message, "An API token is required.");
+    let response = app
+        .api_v1_request(Method::GET, "/subscribers", "z2p_not-a-token")
+        .send()
+        .await
+        .unwrap();
+    assert_json_error(response, 401, "unauthorized").await;
+}
+
+#[tokio::test]
+async fn subscribers_can_be_created_and_inspected() {
+    let app = spawn_app().await;
+    mock_email_delivery(&app, 1).await;
+    let token = app.api_token(&[ApiScope::Read, ApiScope::Write]).await;
+
+    let subscriber =
+        create_subscriber(&app, &token, "ursula_le_guin@gmail.com").await;
+
+    assert_eq!(subscriber["email"], "ursula_le_guin@gmail.com");
+    assert_eq!(subscriber["status"], "pending_confirmation");
+    let path = format!("/subscribers/{}", subscriber["id"].as_str().unwrap());
+    let response = app
+        .api_v1_request(Method::GET, &path, &token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let fetched: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(fetched, subscriber);
+}
+
+#[tokio::test]
+async fn invalid_and_duplicate_subscribers_are_rejected() {
+    let app = spawn_app().await;
+    mock_email_delivery(&app, 1).await;
+    let token = app.api_token(&[ApiScope::Write]).await;
+    create_subscriber(&app, &token, "ursula_le_guin@gmail.com").await;
+
+    let response = app
+        .api_v1_request(Method::POST, "/subscribers", &token)
+        .json(&serde_json::json!({"name": "", "email": "ursula@gmail.com"}))
+        .send()
+        .await
+        .unwrap();
+    assert_json_error(response, 400, "bad_request").await;
+
+    let response = app
+        .api_v1_request(Method::POST, "/subscribers", &token)
+        .json(&serde_json::json!({
+            "name": "Ursula",
+            "email": "ursula_le_guin@gmail.com",
+        }))
+        .send()
+        .await
+        .unwrap();
+    let message = assert_json_error(response, 409, "conflict").await;
+    assert_eq!(message, "A subscriber with this email already exists.");
+
+    // Errors of the extractors are reported as JSON too
+    let response = app
+        .api_v1_request(Method::POST, "/subscribers", &token)
+        .header("Content-Type", "application/json")
+        .body("{not json")
+        .send()
+        .await
+        .unwrap();
+    assert_json_error(response, 400, "bad_request").await;
+}
+
+#[tokio::test]
+async fn subscribers_are_listed_page_by_page() {
+    let app = spawn_app().await;
+    mock_email_delivery(&app, 5).await;
+    let token = app.api_token(&[ApiScope::Read, ApiScope::Write]).await;
+    let mut created = Vec::new();
+    for i in 0..5 {
+        let email = format!("subscriber{}@gmail.com", i);
+        created.push(create_subscriber(&app, &token, &email).await);
+    }
+
+    let mut listed = Vec::new();
+    let mut path = "/subscribers?limit=2".to_string();
+    loop {
+        let response = app
+            .api_v1_request(Method::GET, &path, &token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        let page: serde_json::Value = response.json().await.unwrap();
+        listed.extend(page["data"].as_array().unwrap().clone());
+        match page["next_cursor"].as_str() {
+            Some(cursor) => {
+                path = format!("/subscribers?limit=2&cursor={}", cursor)
+            }
+            None => break,
+        }
+    }
+
+    assert_eq!(listed, created);
+}
+
+#[tokio::test]
+async fn invalid_page_parameters_are_rejected() {
+    let app = spawn_app().await;
+    let token = app.api_token(&[ApiScope::Read]).await;
+
+    for path in ["/subscribers?cursor=garbage", "/issues?limit=1000"] {
+        let response = app
+            .api_v1_request(Method::GET, path, &token)
+            .send()
+            .await
+            .unwrap();
+        assert_json_error(response, 400, "bad_request").await;
+    }
+}
+
+#[tokio::test]
+async fn updating_a_subscriber_only_changes_the_given_fields() {
+    let app = spawn_app().await;
+    mock_email_delivery(&app, 2).await;
+    let token = app.api_token(&[ApiScope::Read, ApiScope::Write]).await;
+    let subscriber =
+        create_subscriber(&app, &token, "ursula_le_guin@gmail.com").await;
+    let path = format!("/subscribers/{}", subscriber["id"].as_str().unwrap());
+    sqlx::query!("UPDATE subscriptions SET status = 'confirmed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .api_v1_request(Method::PATCH, &path, &token)
+        .json(&serde_json::json!({"name": "Ursula K. Le Guin"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let updated: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(updated["name"], "Ursula K. Le Guin");
+    assert_eq!(updated["email"], "ursula_le_guin@gmail.com");
+    assert_eq!(updated["status"], "confirmed");
+
+    // A new address must be confirmed
+    let response = app
+        .api_v1_request(Method::PATCH, &path, &token)
+        .json(&serde_json::json!({"email": "ursula@gmail.com"}))
+        .send()
+        .await
+        .unwrap();
+    let updated: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(updated["email"], "ursula@gmail.com");
+    assert_eq!(updated["status"], "pending_confirmation");
+
+    let response = app
+        .api_v1_request(
+            Method::PATCH,
+            &format!("/subscribers/{}", Uuid::new_v4()),
+            &token,
+        )
+        .json(&serde_json::json!({"name": "Nobody"}))
+        .send()
+        .await
+        .unwrap();
+    assert_json_error(response, 404, "not_found").await;
+}
+
+#[tokio::test]
+async fn tokens_need_the_scope_of_the_request() {
+    let app = spawn_app().await;
+    let token = app.api_token(&[ApiScope::Read]).await;
+
+    let response = app
+        .api_v1_request(Method::POST, "/subscribers", &token)
+        .json(&serde_json::json!({
+            "name": "Ursula",
+            "email": "ursula_le_guin@gmail.com",
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    let message = assert_json_error(response, 403, "forbidden").await;
+    assert_eq!(
+        message,
+        "This request needs an API token with the write scope."
+    );
+}
+
+#[tokio::test]
+async fn viewers_cant_create_issues() {
+    let app = spawn_app().await;
+    let viewer = TestUser::with_role("viewer");
+    viewer.store(&app.db_pool).await;
+    let token = zero2prod::authentication::create_api_token(
+        &app.db_pool,
+        viewer.user_id,
+        "Viewer token",
+        &ApiScope::ALL,
+        None,
+    )
+    .await
+    .unwrap();
+
+    let response = app
+        .api_v1_request(Method::POST, "/issues", token.as_ref())
+        .json(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_json_error(response, 403, "forbidden").await;
+}
+
+#[tokio::test]
+async fn issues_can_be_created_published_and_inspected() {
+    let app = spawn_app().await;
+    let token = app.api_token(&[ApiScope::Read, ApiScope::Write]).await;
+    let response = app
+        .api_v1_request(Method::POST, "/issues", &token)
+        .json(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 201);
+    let issue: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(issue["state"], "draft");
+    assert!(issue["published_at"].is_null());
+    let path = format!("/issues/{}/publish", issue["id"].as_str().unwrap());
+
+    // Publishing needs its own scope
+    let response = app
+        .api_v1_request(Method::POST, &path, &token)
+        .send()
+        .await
+        .unwrap();
+    assert_json_error(response, 403, "forbidden").await;
+
+    let publish_token = app.api_token(&[ApiScope::Publish]).await;
+    let response = app
+        .api_v1_request(Method::POST, &path, &publish_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let published: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(published["state"], "published");
+    assert!(!published["published_at"].is_null());
+
+    let response = app
+        .api_v1_request(Method::POST, &path, &publish_token)
+        .send()
+        .await
+        .unwrap();
+    assert_json_error(response, 409, "conflict").await;
+    let response = app
+        .api_v1_request(Method::GET, "/issues", &token)
+        .send()
+        .await
+        .unwrap();
+    let page: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(page["data"][0]["id"], issue["id"]);
+    assert_eq!(page["data"][0]["state"], "published");
+    assert!(page["next_cursor"].is_null());
+}
+
+#[tokio::test]
+async fn invalid_issues_are_rejected() {
+    let app = spawn_app().await;
+    let token = app.api_token(&[ApiScope::Write]).await;
+
+    let response = app
+        .api_v1_request(Method::POST, "/issues", &token)
+        .json(&serde_json::json!({
+            "title": "",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    let message = assert_json_error(response, 400, "bad_request").await;
+    assert_eq!(message, "Field title can't be empty");
+}
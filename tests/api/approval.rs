@@ -0,0 +1,223 @@
+use uuid::Uuid;
+use wiremock::{matchers::any, Mock, ResponseTemplate};
+
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, spawn_app_with,
+    TestApp, TestUser,
+};
+
+async fn spawn_app_requiring_approval() -> TestApp {
+    spawn_app_with(|c| c.publishing.require_approval = true).await
+}
+
+fn issue_content() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    })
+}
+
+async fn login_as(app: &TestApp, user: &TestUser) {
+    app.post_logout().await;
+    app.post_login(&serde_json::json!({
+        "username": &user.username,
+        "password": &user.password,
+    }))
+    .await;
+}
+
+async fn create_reviewer(app: &TestApp) -> TestUser {
+    let reviewer = TestUser::generate();
+    reviewer.store(&app.db_pool).await;
+    reviewer
+}
+
+async fn create_draft(app: &TestApp) -> Uuid {
+    app.post_drafts(&issue_content()).await;
+    sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id
+}
+
+async fn issue_state(app: &TestApp, issue_id: Uuid) -> String {
+    sqlx::query!(
+        "SELECT state FROM newsletter_issues WHERE newsletter_issue_id = $1",
+        issue_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .state
+}
+
+fn no_emails_are_sent() -> Mock {
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+}
+
+#[tokio::test]
+async fn issues_can_not_be_published_in_a_single_step() {
+    let app = spawn_app_requiring_approval().await;
+    create_confirmed_subscriber(&app).await;
+    login_as(&app, &app.test_user).await;
+    no_emails_are_sent().mount(&app.email_server).await;
+
+    let response = app.post_newsletters(&issue_content()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page
+        .contains("<p><i>Issues must be approved before being published."));
+    app.dispatch_all_pending_emails().await;
+    let n_issues =
+        sqlx::query!(r#"SELECT count(*) AS "n!" FROM newsletter_issues"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .n;
+    assert_eq!(n_issues, 0);
+}
+
+#[tokio::test]
+async fn unapproved_issues_can_not_be_published() {
+    let app = spawn_app_requiring_approval().await;
+    create_confirmed_subscriber(&app).await;
+    login_as(&app, &app.test_user).await;
+    no_emails_are_sent().mount(&app.email_server).await;
+    let issue_id = create_draft(&app).await;
+
+    for _ in 0..2 {
+        let response = app.post_publish_draft(issue_id).await;
+        assert_is_redirect_to(
+            &response,
+            &format!("/admin/drafts/{}", issue_id),
+        );
+        let html_page = app.get_draft_html(issue_id).await;
+        assert!(html_page
+            .contains("<p><i>Only approved issues can be published.</i></p>"));
+        app.post_review_issue(issue_id, "submit").await;
+    }
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(issue_state(&app, issue_id).await, "submitted");
+}
+
+#[tokio::test]
+async fn authors_can_not_approve_their_own_issues() {
+    let app = spawn_app_requiring_approval().await;
+    login_as(&app, &app.test_user).await;
+    let issue_id = create_draft(&app).await;
+    app.post_review_issue(issue_id, "submit").await;
+
+    let response = app.post_review_issue(issue_id, "approve").await;
+    assert_is_redirect_to(&response, &format!("/admin/drafts/{}", issue_id));
+
+    let html_page = app.get_draft_html(issue_id).await;
+    assert!(html_page.contains(
+        "<p><i>Issues must be approved by someone who did not write them.</i></p>"
+    ));
+    assert_eq!(issue_state(&app, issue_id).await, "submitted");
+}
+
+#[tokio::test]
+async fn an_approved_issue_is_published_and_every_transition_is_recorded() {
+    let app = spawn_app_requiring_approval().await;
+    create_confirmed_subscriber(&app).await;
+    let reviewer = create_reviewer(&app).await;
+    login_as(&app, &app.test_user).await;
+    let issue_id = create_draft(&app).await;
+    app.post_review_issue(issue_id, "submit").await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    login_as(&app, &reviewer).await;
+    let response = app.post_review_issue(issue_id, "approve").await;
+    assert_is_redirect_to(&response, &format!("/admin/drafts/{}", issue_id));
+    assert!(app
+        .get_draft_html(issue_id)
+        .await
+        .contains("<p><i>The issue has been approved.</i></p>"));
+    let response = app.post_publish_draft(issue_id).await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", issue_id));
+    app.dispatch_all_pending_emails().await;
+
+    let transitions = sqlx::query!(
+        r#"
+        SELECT from_state, to_state, user_id
+        FROM issue_transitions
+        WHERE newsletter_issue_id = $1
+        ORDER BY transitioned_at
+        "#,
+        issue_id
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    let transitions: Vec<_> = transitions
+        .into_iter()
+        .map(|t| (t.from_state, t.to_state, t.user_id.unwrap()))
+        .collect();
+    let author_id = app.test_user.user_id;
+    assert_eq!(
+        transitions,
+        vec![
+            (None, "draft".into(), author_id),
+            (Some("draft".into()), "submitted".into(), author_id),
+            (
+                Some("submitted".into()),
+                "approved".into(),
+                reviewer.user_id
+            ),
+            (
+                Some("approved".into()),
+                "published".into(),
+                reviewer.user_id
+            ),
+        ]
+    );
+    let report = app.get_issue_report_html(issue_id).await;
+    assert!(report.contains(&reviewer.username));
+}
+
+#[tokio::test]
+async fn issues_under_review_can_not_be_edited() {
+    let app = spawn_app_requiring_approval().await;
+    login_as(&app, &app.test_user).await;
+    let issue_id = create_draft(&app).await;
+    app.post_review_issue(issue_id, "submit").await;
+
+    let mut content = issue_content();
+    content["title"] = "Edited title".into();
+    let response = app.post_save_draft(issue_id, &content).await;
+    assert_is_redirect_to(&response, &format!("/admin/drafts/{}", issue_id));
+
+    let html_page = app.get_draft_html(issue_id).await;
+    assert!(
+        html_page.contains("<p><i>A submitted issue can't be edited.</i></p>")
+    );
+    assert!(html_page.contains(r#"value="Newsletter title" readonly"#));
+}
+
+#[tokio::test]
+async fn rejected_issues_go_back_to_draft() {
+    let app = spawn_app_requiring_approval().await;
+    let reviewer = create_reviewer(&app).await;
+    login_as(&app, &app.test_user).await;
+    let issue_id = create_draft(&app).await;
+    app.post_review_issue(issue_id, "submit").await;
+
+    login_as(&app, &reviewer).await;
+    app.post_review_issue(issue_id, "reject").await;
+
+    assert_eq!(issue_state(&app, issue_id).await, "draft");
+    let html_page = app.get_draft_html(issue_id).await;
+    assert!(html_page.contains("<p><i>The issue has been rejected.</i></p>"));
+    assert!(html_page.contains("Save draft"));
+}
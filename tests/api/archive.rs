@@ -0,0 +1,232 @@
+use uuid::Uuid;
+use wiremock::{matchers::any, Mock, ResponseTemplate};
+
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, spawn_app, TestApp,
+};
+
+async fn get_issue(app: &TestApp, title: &str) -> (Uuid, String) {
+    let r = sqlx::query!(
+        "SELECT newsletter_issue_id, slug FROM newsletter_issues \
+        WHERE title = $1",
+        title
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    (r.newsletter_issue_id, r.slug)
+}
+
+#[tokio::test]
+async fn published_issues_are_listed_in_the_archive() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    app.publish_issue(serde_json::json!({ "title": "Our first issue" }))
+        .await;
+    let (_, slug) = get_issue(&app, "Our first issue").await;
+
+    let html_page = app.get_archive("/archive").await.text().await.unwrap();
+    assert!(html_page.contains(&format!(
+        r#"<a href="/archive/{}">Our first issue</a>"#,
+        slug
+    )));
+
+    let response = app.get_archive(&format!("/archive/{}", slug)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<h1>Our first issue</h1>"));
+    assert!(html_page.contains("<p>Newsletter body as HTML</p>"));
+}
+
+#[tokio::test]
+async fn the_archive_is_public() {
+    let app = spawn_app().await;
+    app.login().await;
+    app.publish_issue(serde_json::json!({ "title": "Our first issue" }))
+        .await;
+    let (_, slug) = get_issue(&app, "Our first issue").await;
+
+    let response = reqwest::get(format!("{}/archive/{}", app.address, slug))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn archived_issues_can_not_run_scripts() {
+    let app = spawn_app().await;
+    app.login().await;
+    app.publish_issue(serde_json::json!({
+        "title": "Our first issue",
+        "html_content": "<p>Hello</p><script>alert(1)</script>",
+    }))
+    .await;
+    let (_, slug) = get_issue(&app, "Our first issue").await;
+
+    for path in ["/archive".to_string(), format!("/archive/{}", slug)] {
+        let response = app.get_archive(&path).await;
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(
+            response.headers()["Content-Security-Policy"],
+            "sandbox; script-src 'none'",
+            "{}",
+            path
+        );
+    }
+}
+
+#[tokio::test]
+async fn hidden_issues_are_not_in_the_archive() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    app.publish_issue(serde_json::json!({
+    "title": "A private issue",
+    "hide_from_archive": true,
+    }))
+    .await;
+    let (_, slug) = get_issue(&app, "A private issue").await;
+
+    let html_page = app.get_archive("/archive").await.text().await.unwrap();
+    assert!(!html_page.contains("A private issue"));
+    let response = app.get_archive(&format!("/archive/{}", slug)).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn unknown_slugs_return_a_404() {
+    let app = spawn_app().await;
+
+    let response = app.get_archive("/archive/this-does-not-exist").await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn the_archive_is_paginated() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    for i in 0..11 {
+        app.publish_issue(
+            serde_json::json!({ "title": &format!("Issue number {}", i) }),
+        )
+        .await;
+    }
+
+    let first_page = app.get_archive("/archive").await.text().await.unwrap();
+    assert_eq!(first_page.matches("<li>").count(), 10);
+    assert!(first_page.contains(r#"<a href="/archive?page=2">"#));
+    assert!(!first_page.contains("Newer issues"));
+
+    let second_page = app
+        .get_archive("/archive?page=2")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(second_page.matches("<li>").count(), 1);
+    assert!(second_page.contains(r#"<a href="/archive?page=1">"#));
+    assert!(!second_page.contains("Older issues"));
+}
+
+#[tokio::test]
+async fn pages_beyond_the_range_of_offsets_return_a_404() {
+    let app = spawn_app().await;
+
+    let response = app
+        .get_archive(&format!("/archive?page={}", i64::MAX))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn delivered_issues_link_to_their_archived_copy() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_issue(serde_json::json!({ "title": "Our first issue" }))
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    let (_, slug) = get_issue(&app, "Our first issue").await;
+    let archive_url = format!("{}/archive/{}", app.address, slug);
+    let email_request = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_request.last().unwrap().body).unwrap();
+    assert!(body["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains(&format!(r#"<a href="{}">"#, archive_url)));
+    assert!(body["TextBody"].as_str().unwrap().contains(&archive_url));
+
+    let response = reqwest::get(archive_url).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn hidden_issues_are_delivered_without_an_archive_link() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_issue(serde_json::json!({
+    "title": "A private issue",
+    "hide_from_archive": true,
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_request.last().unwrap().body).unwrap();
+    assert!(!body["HtmlBody"].as_str().unwrap().contains("/archive/"));
+    assert!(!body["TextBody"].as_str().unwrap().contains("/archive/"));
+}
+
+#[tokio::test]
+async fn admins_can_hide_an_issue_from_the_archive() {
+    let app = spawn_app().await;
+    app.login().await;
+    app.publish_issue(serde_json::json!({ "title": "Our first issue" }))
+        .await;
+    let (issue_id, slug) = get_issue(&app, "Our first issue").await;
+
+    let response = app.post_archive_visibility(issue_id, true).await;
+    assert_is_redirect_to(&response, "/admin/issues");
+
+    let html_page = app.get_admin_issues_html().await;
+    assert!(html_page
+        .contains("<p><i>The issue has been hidden from the archive.</i></p>"));
+    let response = app.get_archive(&format!("/archive/{}", slug)).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    // And make it public again
+    let response = app.post_archive_visibility(issue_id, false).await;
+    assert_is_redirect_to(&response, "/admin/issues");
+    let response = app.get_archive(&format!("/archive/{}", slug)).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_change_the_archive_visibility() {
+    let app = spawn_app().await;
+
+    let response = app.post_archive_visibility(Uuid::new_v4(), true).await;
+
+    assert_is_redirect_to(&response, "/login");
+}
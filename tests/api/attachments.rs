@@ -0,0 +1,168 @@
+use reqwest::multipart::{Form, Part};
+use wiremock::{matchers::any, Mock, ResponseTemplate};
+
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, spawn_app,
+};
+
+fn file(name: &str, content_type: &str, content: &[u8]) -> Part {
+    Part::bytes(content.to_vec())
+        .file_name(name.to_string())
+        .mime_str(content_type)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn uploaded_files_are_sent_with_the_next_issue() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+
+    let form = Form::new()
+        .part("attachment", file("report.txt", "text/plain", b"hello"))
+        .part("inline_image", file("logo.png", "image/png", &[0x89, 0x50]));
+    let response = app.post_attachments(form).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(
+        html_page.contains("2 file(s) will be attached to your next issue.")
+    );
+    assert!(html_page.contains("report.txt (5 bytes)"));
+    assert!(html_page.contains("<code>cid:logo.png</code>"));
+
+    app.publish_issue(serde_json::json!({
+        "html_content": r#"<p><img src="cid:logo.png"></p>"#,
+    }))
+    .await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_requests.last().unwrap().body).unwrap();
+    assert_eq!(
+        body["Attachments"],
+        serde_json::json!([
+            {
+                "Name": "logo.png",
+                "Content": "iVA=",
+                "ContentType": "image/png",
+                "ContentID": "cid:logo.png",
+            },
+            {
+                "Name": "report.txt",
+                "Content": "aGVsbG8=",
+                "ContentType": "text/plain",
+            },
+        ])
+    );
+    // The files now belong to the published issue
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(!html_page.contains("report.txt"));
+}
+
+#[tokio::test]
+async fn issues_without_uploads_are_sent_without_attachments() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+
+    app.publish_issue(serde_json::json!({
+        "html_content": r#"<p><img src="cid:logo.png"></p>"#,
+    }))
+    .await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_requests.last().unwrap().body).unwrap();
+    assert!(body.get("Attachments").is_none());
+}
+
+#[tokio::test]
+async fn files_above_the_size_limit_are_rejected() {
+    let app = spawn_app().await;
+    app.login().await;
+    let too_large = vec![0u8; 2 * 1024 * 1024 + 1];
+
+    let form = Form::new()
+        .part("attachment", file("big.bin", "application/zip", &too_large))
+        .part("attachment", file("small.txt", "text/plain", b"hi"));
+    let response = app.post_attachments(form).await;
+
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "big.bin was not attached: files can&#x27;t exceed 2097152 bytes."
+    ));
+    assert!(html_page.contains("small.txt (2 bytes)"));
+    assert!(!html_page.contains("big.bin ("));
+}
+
+#[tokio::test]
+async fn only_images_can_be_inlined() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    let form = Form::new()
+        .part("inline_image", file("notes.txt", "text/plain", b"hello"));
+    app.post_attachments(form).await;
+
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page
+        .contains("notes.txt is not an image and can&#x27;t be inlined."));
+    assert!(!html_page.contains("notes.txt ("));
+}
+
+#[tokio::test]
+async fn staged_attachments_can_be_removed() {
+    let app = spawn_app().await;
+    app.login().await;
+    let form = Form::new()
+        .part("attachment", file("report.txt", "text/plain", b"hello"));
+    app.post_attachments(form).await;
+    let attachment_id =
+        sqlx::query!("SELECT attachment_id FROM issue_attachments")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .attachment_id;
+
+    let body = app.with_csrf_token(&serde_json::json!({})).await;
+
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/newsletters/attachments/{}/delete",
+            app.address, attachment_id
+        ))
+        .form(&body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("The attachment has been removed."));
+    assert!(!html_page.contains("report.txt"));
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_upload_attachments() {
+    let app = spawn_app().await;
+
+    let form = Form::new()
+        .part("attachment", file("report.txt", "text/plain", b"hello"));
+    let response = app.post_attachments(form).await;
+
+    assert_is_redirect_to(&response, "/login");
+}
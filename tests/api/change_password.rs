@@ -1,6 +1,6 @@
 use uuid::Uuid;
 
-use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
 
 #[tokio::test]
 async fn you_must_be_logged_in_to_see_the_change_password_form() {
@@ -149,7 +149,7 @@ async fn new_password_must_be_at_most_128_characters() {
 #[tokio::test]
 async fn change_password() {
     let app = spawn_app().await;
-    let new_password = "a".repeat(12);
+    let new_password = Uuid::new_v4().to_string();
 
     let login_body = serde_json::json!({
         "username": app.test_user.username,
@@ -194,3 +194,59 @@ async fn change_password() {
     let response = app.post_login(&login_body).await;
     assert_is_redirect_to(&response, "/admin/dashboard");
 }
+
+async fn rejected_password_message(
+    app: &TestApp,
+    new_password: &str,
+) -> String {
+    app.post_login(&serde_json::json!({
+        "username": &app.test_user.username,
+        "password": &app.test_user.password,
+    }))
+    .await;
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": new_password,
+            "new_password_check": new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+    app.get_change_password_html().await
+}
+
+#[tokio::test]
+async fn new_password_must_not_be_easy_to_guess() {
+    let app = spawn_app().await;
+
+    let html_page = rejected_password_message(&app, "abcabcabcabc").await;
+
+    assert!(html_page.contains(
+        "<p><i>The new password is too easy to guess: make it longer, \
+        or mix letters, digits and symbols.</i></p>"
+    ));
+}
+
+#[tokio::test]
+async fn new_password_must_not_be_a_leaked_password() {
+    let app = spawn_app().await;
+
+    let html_page = rejected_password_message(&app, "Password1234!").await;
+
+    assert!(html_page.contains(
+        "<p><i>The new password appears in a list of leaked passwords: \
+        please choose another one.</i></p>"
+    ));
+}
+
+#[tokio::test]
+async fn new_password_must_not_contain_the_username() {
+    let app = spawn_app().await;
+    let new_password = format!("{}-Xy7!", app.test_user.username);
+
+    let html_page = rejected_password_message(&app, &new_password).await;
+
+    assert!(html_page.contains(
+        "<p><i>The new password must not contain the username.</i></p>"
+    ));
+}
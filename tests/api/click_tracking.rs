@@ -0,0 +1,129 @@
+use uuid::Uuid;
+use wiremock::{matchers::any, Mock, ResponseTemplate};
+
+use crate::helpers::{create_confirmed_subscriber, spawn_app, TestApp};
+
+const HTML_CONTENT: &str =
+    r#"<p>Read <a href="https://example.com/page?a=1&amp;b=2">this</a></p>"#;
+
+async fn publish_issue(app: &TestApp, track_clicks: bool) -> Uuid {
+    app.login().await;
+    app.publish_issue(serde_json::json!({
+        "text_content": "Read https://example.com/page",
+        "html_content": HTML_CONTENT,
+        "track_clicks": track_clicks,
+    }))
+    .await
+}
+
+async fn deliver_issue(app: &TestApp) -> serde_json::Value {
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+    let email_request = app.email_server.received_requests().await.unwrap();
+    serde_json::from_slice(&email_request.last().unwrap().body).unwrap()
+}
+
+fn tracked_links(html: &str) -> Vec<String> {
+    linkify::LinkFinder::new()
+        .links(html)
+        .map(|l| l.as_str().to_owned())
+        .filter(|l| l.contains("/t/c/"))
+        .collect()
+}
+
+#[tokio::test]
+async fn links_are_rewritten_when_click_tracking_is_enabled() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    publish_issue(&app, true).await;
+
+    let body = deliver_issue(&app).await;
+
+    let html = body["HtmlBody"].as_str().unwrap();
+    assert_eq!(tracked_links(html).len(), 1);
+    assert!(!html.contains("https://example.com/page"));
+    // The archive link and the text body are left untouched
+    assert!(html.contains(&format!("{}/archive/", app.address)));
+    assert!(body["TextBody"]
+        .as_str()
+        .unwrap()
+        .contains("Read https://example.com/page"));
+}
+
+#[tokio::test]
+async fn tracked_links_redirect_and_record_the_click() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let issue_id = publish_issue(&app, true).await;
+    let body = deliver_issue(&app).await;
+    let link = tracked_links(body["HtmlBody"].as_str().unwrap()).remove(0);
+
+    for _ in 0..2 {
+        let response = app.api_client.get(&link).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 302);
+        assert_eq!(
+            response.headers()["Location"],
+            "https://example.com/page?a=1&b=2"
+        );
+    }
+
+    let html_page = app.get_issue_report_html(issue_id).await;
+    assert!(html_page.contains("Unique clickers: 1 (100.0% of sent emails)"));
+    assert!(html_page.contains(
+        "<tr><td>https://example.com/page?a=1&amp;b=2</td><td>2</td><td>1</td></tr>"
+    ));
+}
+
+#[tokio::test]
+async fn links_are_not_rewritten_if_the_issue_does_not_opt_in() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let issue_id = publish_issue(&app, false).await;
+
+    let body = deliver_issue(&app).await;
+
+    let html = body["HtmlBody"].as_str().unwrap();
+    assert!(tracked_links(html).is_empty());
+    assert!(html.contains(HTML_CONTENT));
+    let html_page = app.get_issue_report_html(issue_id).await;
+    assert!(html_page.contains("Click tracking is disabled for this issue."));
+}
+
+#[tokio::test]
+async fn links_are_not_rewritten_if_click_tracking_is_disabled_globally() {
+    let mut app = spawn_app().await;
+    app.delivery_settings.tracking.click_tracking_enabled = false;
+    create_confirmed_subscriber(&app).await;
+    publish_issue(&app, true).await;
+
+    let body = deliver_issue(&app).await;
+
+    let html = body["HtmlBody"].as_str().unwrap();
+    assert!(tracked_links(html).is_empty());
+    assert!(html.contains(HTML_CONTENT));
+}
+
+#[tokio::test]
+async fn tampered_tracking_tokens_are_rejected() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    publish_issue(&app, true).await;
+    let body = deliver_issue(&app).await;
+    let link = tracked_links(body["HtmlBody"].as_str().unwrap()).remove(0);
+
+    let tampered_link = format!("{}A", link);
+    let response = app.api_client.get(&tampered_link).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app
+        .api_client
+        .get(format!("{}/t/c/not-a-token", app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -0,0 +1,100 @@
+use wiremock::{matchers::any, Mock, ResponseTemplate};
+
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, spawn_app, TestApp,
+};
+
+fn issue(title: &str, html_content: &str) -> serde_json::Value {
+    serde_json::json!({
+        "title": title,
+        "text_content": "Newsletter body. Unsubscribe: https://example.com/u",
+        "html_content": html_content,
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    })
+}
+
+async fn n_issues(app: &TestApp) -> i64 {
+    sqlx::query!(r#"SELECT count(*) AS "n!" FROM newsletter_issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n
+}
+
+#[tokio::test]
+async fn issues_with_relative_links_are_not_published() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let html =
+        r#"<p>Newsletter body. <a href="/unsubscribe">Unsubscribe</a></p>"#;
+    let response = app.post_newsletters(&issue("Monthly news", html)).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "<p><i>The link to /unsubscribe is relative, it won't work in an email."
+    ));
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(n_issues(&app).await, 0);
+}
+
+#[tokio::test]
+async fn warnings_do_not_block_publishing() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let html = r#"<p>Newsletter body. <img src="https://example.com/a.png"> <a href="https://example.com/u">Unsubscribe</a></p>"#;
+    let response = app.post_newsletters(&issue("MONTHLY NEWS", html)).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("The newsletter issue has been accepted"));
+    assert!(html_page.contains(
+        "<p><i>Warning: The title is written in capital letters.</i></p>"
+    ));
+    assert!(html_page.contains("<p><i>Warning: 1 image(s) have no alt text"));
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn drafts_are_linted_when_saved() {
+    let app = spawn_app().await;
+    app.login().await;
+    let html = r#"<p>Newsletter body. <a href="https://example.com/u">Unsubscribe</a></p>"#;
+    app.post_drafts(&issue("Monthly news", html)).await;
+    let issue_id =
+        sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .newsletter_issue_id;
+
+    let html = r#"<p>Newsletter body. <img alt="Logo" src="logo.png"> Unsubscribe</p>"#;
+    let response = app
+        .post_save_draft(issue_id, &issue("Monthly news", html))
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/drafts/{}", issue_id));
+
+    let html_page = app.get_draft_html(issue_id).await;
+    assert!(html_page.contains("<p><i>The link to logo.png is relative"));
+    let html_content =
+        sqlx::query!("SELECT html_content FROM newsletter_issues")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .html_content;
+    assert!(!html_content.contains("logo.png"));
+}
@@ -0,0 +1,134 @@
+use crate::helpers::{
+    assert_is_redirect_to, build_api_client, get_csrf_token, spawn_app,
+};
+
+const REJECTION: &str = "The form has expired or was not sent from this site";
+
+#[tokio::test]
+async fn logging_in_without_a_csrf_token_is_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/login", &app.address))
+        .form(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 403);
+    assert!(response.text().await.unwrap().contains(REJECTION));
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn admin_forms_carry_the_token_of_the_session() {
+    let app = spawn_app().await;
+    app.login().await;
+    let csrf_field = format!(
+        r#"<input type="hidden" name="csrf_token" value="{}">"#,
+        app.csrf_token().await
+    );
+
+    assert!(app.get_admin_dashboard_html().await.contains(&csrf_field));
+    assert!(app.get_change_password_html().await.contains(&csrf_field));
+    assert!(app.get_sessions_html().await.contains(&csrf_field));
+}
+
+#[tokio::test]
+async fn admin_requests_without_a_csrf_token_are_rejected() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/admin/logout", &app.address))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 403);
+    assert!(response.text().await.unwrap().contains(REJECTION));
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn the_token_of_another_session_is_rejected() {
+    let app = spawn_app().await;
+    app.login().await;
+    let attacker = build_api_client(reqwest::Client::builder());
+    let attacker_token = get_csrf_token(&attacker, &app.address).await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/admin/logout", &app.address))
+        .form(&serde_json::json!({ "csrf_token": attacker_token }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn the_token_can_be_sent_in_the_query_string() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/admin/logout", &app.address))
+        .query(&[("csrf_token", app.csrf_token().await)])
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_token_can_be_sent_in_a_header() {
+    let app = spawn_app().await;
+    app.login().await;
+    let attacker = build_api_client(reqwest::Client::builder());
+    let attacker_token = get_csrf_token(&attacker, &app.address).await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/admin/logout", &app.address))
+        .header("X-CSRF-Token", attacker_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app
+        .api_client
+        .post(format!("{}/admin/logout", &app.address))
+        .header("X-CSRF-Token", app.csrf_token().await)
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn anonymous_users_are_still_sent_to_the_login_page() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/admin/password", &app.address))
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&response, "/login");
+}
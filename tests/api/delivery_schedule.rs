@@ -0,0 +1,144 @@
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+use wiremock::{matchers::any, Mock, ResponseTemplate};
+
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, spawn_app,
+    spawn_app_with, TestApp,
+};
+
+fn issue(deliver_at: &str) -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "deliver_at": deliver_at,
+    })
+}
+
+async fn set_time_zones(app: &TestApp, time_zones: &[&str]) {
+    for time_zone in time_zones {
+        create_confirmed_subscriber(app).await;
+        sqlx::query!(
+            "UPDATE subscriptions SET time_zone = $1 WHERE time_zone IS NULL",
+            time_zone
+        )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+}
+
+fn no_emails_are_sent() -> Mock {
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+}
+
+#[tokio::test]
+async fn issues_are_delivered_at_the_local_time_of_each_subscriber() {
+    let app = spawn_app().await;
+    set_time_zones(&app, &["Asia/Tokyo", "America/New_York"]).await;
+    app.login().await;
+
+    let response = app.post_newsletters(&issue("09:00")).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let tasks = sqlx::query!(
+        r#"
+        SELECT
+            s.time_zone AS "time_zone!",
+            (q.execute_after AT TIME ZONE s.time_zone)::time AS "local_time!",
+            q.execute_after
+        FROM issue_delivery_queue q
+        JOIN subscriptions s ON s.email = q.subscriber_email
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(tasks.len(), 2);
+    for task in &tasks {
+        assert_eq!(
+            task.local_time.to_string(),
+            "09:00:00",
+            "{}",
+            task.time_zone
+        );
+        assert!(task.execute_after > Utc::now() - Duration::minutes(1));
+        assert!(task.execute_after <= Utc::now() + Duration::days(1));
+    }
+    assert_ne!(tasks[0].execute_after, tasks[1].execute_after);
+}
+
+#[tokio::test]
+async fn deliveries_wait_for_their_earliest_send_time() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    // A time of day at least one hour away, in UTC
+    let deliver_at = (Utc::now() + Duration::hours(2)).format("%H:00");
+    app.post_newsletters(&issue(&deliver_at.to_string())).await;
+
+    {
+        let _guard = no_emails_are_sent()
+            .named("Before the earliest send time")
+            .mount_as_scoped(&app.email_server)
+            .await;
+        app.dispatch_all_pending_emails().await;
+    }
+
+    sqlx::query!(
+        "UPDATE issue_delivery_queue SET execute_after = now() - interval '1 second'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn nothing_is_sent_during_quiet_hours() {
+    let now = Utc::now();
+    let app = spawn_app_with(|c| {
+        c.schedule.quiet_hours_start =
+            Some((now - Duration::hours(1)).format("%H:%M").to_string());
+        c.schedule.quiet_hours_end =
+            Some((now + Duration::hours(1)).format("%H:%M").to_string());
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    no_emails_are_sent().mount(&app.email_server).await;
+
+    app.post_newsletters(&issue("")).await;
+    app.dispatch_all_pending_emails().await;
+
+    let execute_after =
+        sqlx::query!("SELECT execute_after FROM issue_delivery_queue")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .execute_after;
+    let quiet_hours_end = now + Duration::hours(1);
+    assert!(execute_after > now + Duration::minutes(58));
+    assert!(execute_after <= quiet_hours_end);
+}
+
+#[tokio::test]
+async fn the_delivery_time_must_be_a_time_of_day() {
+    let app = spawn_app().await;
+    app.login().await;
+
+    let response = app.post_newsletters(&issue("9 o'clock")).await;
+
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("is not a valid time of day (HH:MM)"));
+}
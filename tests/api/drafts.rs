@@ -0,0 +1,221 @@
+use uuid::Uuid;
+use wiremock::{matchers::any, Mock, ResponseTemplate};
+
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, spawn_app, TestApp,
+};
+
+fn issue_content(title: &str, text: &str) -> serde_json::Value {
+    serde_json::json!({
+        "title": title,
+        "text_content": text,
+        "html_content": format!("<p>{}</p>", text),
+    })
+}
+
+async fn create_draft(app: &TestApp) -> Uuid {
+    let response = app
+        .post_drafts(&issue_content("First title", "First line\nSecond line"))
+        .await;
+    let issue_id = sqlx::query!(
+        "SELECT newsletter_issue_id FROM newsletter_issues WHERE state = 'draft'"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .newsletter_issue_id;
+    assert_is_redirect_to(&response, &format!("/admin/drafts/{}", issue_id));
+    issue_id
+}
+
+async fn n_revisions(app: &TestApp, issue_id: Uuid) -> i64 {
+    sqlx::query!(
+        r#"SELECT count(*) AS "n!" FROM issue_revisions WHERE newsletter_issue_id = $1"#,
+        issue_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .n
+}
+
+#[tokio::test]
+async fn drafts_are_saved_without_being_sent() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let issue_id = create_draft(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_draft_html(issue_id).await;
+    assert!(html_page.contains("The draft has been saved."));
+    assert!(html_page.contains(r#"value="First title""#));
+    assert!(app.get_admin_issues_html().await.contains("Draft"));
+    let archive = app.get_archive("/archive").await.text().await.unwrap();
+    assert!(!archive.contains("First title"));
+}
+
+#[tokio::test]
+async fn every_change_to_a_draft_is_kept_as_a_revision() {
+    let app = spawn_app().await;
+    app.login().await;
+    let issue_id = create_draft(&app).await;
+
+    let content = issue_content("Second title", "First line\nNew line");
+    let response = app.post_save_draft(issue_id, &content).await;
+    assert_is_redirect_to(&response, &format!("/admin/drafts/{}", issue_id));
+    // Saving the same content again doesn't add a revision
+    app.post_save_draft(issue_id, &content).await;
+
+    assert_eq!(n_revisions(&app, issue_id).await, 2);
+    let html_page = app.get_draft_html(issue_id).await;
+    assert!(html_page.contains(r#"value="Second title""#));
+    assert!(html_page
+        .contains(&format!("/admin/issues/{}/diff?from=1&amp;to=2", issue_id)));
+    let diff_page = app
+        .api_client
+        .get(format!(
+            "{}/admin/issues/{}/diff?from=1&to=2",
+            app.address, issue_id
+        ))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(diff_page.contains("<del>- First title</del>"));
+    assert!(diff_page.contains("<ins>+ Second title</ins>"));
+    assert!(diff_page.contains("  First line"));
+    assert!(diff_page.contains("<del>- Second line</del>"));
+    assert!(diff_page.contains("<ins>+ New line</ins>"));
+}
+
+#[tokio::test]
+async fn a_draft_can_be_restored_to_a_previous_revision() {
+    let app = spawn_app().await;
+    app.login().await;
+    let issue_id = create_draft(&app).await;
+    app.post_save_draft(issue_id, &issue_content("Second title", "Other"))
+        .await;
+
+    let body = app.with_csrf_token(&serde_json::json!({})).await;
+
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/drafts/{}/revisions/1/restore",
+            app.address, issue_id
+        ))
+        .form(&body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&response, &format!("/admin/drafts/{}", issue_id));
+    let html_page = app.get_draft_html(issue_id).await;
+    assert!(html_page.contains("Revision 1 has been restored."));
+    assert!(html_page.contains(r#"value="First title""#));
+    assert_eq!(n_revisions(&app, issue_id).await, 3);
+}
+
+#[tokio::test]
+async fn publishing_a_draft_sends_it_once() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    let issue_id = create_draft(&app).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_publish_draft(issue_id).await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", issue_id));
+    let response = app.post_publish_draft(issue_id).await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", issue_id));
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_issue_report_html(issue_id).await;
+    assert!(html_page.contains("This issue has already been published."));
+    assert!(html_page.contains("<tr><td>Sent</td><td>1</td></tr>"));
+    let archive = app.get_archive("/archive").await.text().await.unwrap();
+    assert!(archive.contains("First title"));
+}
+
+#[tokio::test]
+async fn published_issues_can_not_be_edited() {
+    let app = spawn_app().await;
+    app.login().await;
+    let issue_id = create_draft(&app).await;
+    app.post_publish_draft(issue_id).await;
+
+    let response = app
+        .post_save_draft(issue_id, &issue_content("Edited", "Edited"))
+        .await;
+
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", issue_id));
+    let title = sqlx::query!("SELECT title FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .title;
+    assert_eq!(title, "First title");
+    assert_eq!(n_revisions(&app, issue_id).await, 1);
+}
+
+#[tokio::test]
+async fn any_issue_can_be_duplicated_as_a_new_draft() {
+    let app = spawn_app().await;
+    app.login().await;
+    let mut body = issue_content("Published title", "Published content");
+    body["idempotency_key"] = Uuid::new_v4().to_string().into();
+    app.post_newsletters(&body).await;
+    let issue_id =
+        sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .newsletter_issue_id;
+    assert_eq!(n_revisions(&app, issue_id).await, 1);
+
+    let body = app.with_csrf_token(&serde_json::json!({})).await;
+
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/issues/{}/duplicate",
+            app.address, issue_id
+        ))
+        .form(&body)
+        .send()
+        .await
+        .unwrap();
+
+    let copy = sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id, title, text_content, slug
+        FROM newsletter_issues
+        WHERE state = 'draft'
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/drafts/{}", copy.newsletter_issue_id),
+    );
+    assert_eq!(copy.title, "Published title");
+    assert_eq!(copy.text_content, "Published content");
+    assert_eq!(n_revisions(&app, copy.newsletter_issue_id).await, 1);
+    let html_page = app.get_draft_html(copy.newsletter_issue_id).await;
+    assert!(html_page.contains("A new draft has been created from the issue."));
+}
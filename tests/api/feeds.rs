@@ -0,0 +1,167 @@
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, TestApp};
+
+async fn publish_issue(app: &TestApp, title: &str, hide_from_archive: bool) {
+    app.login().await;
+    app.publish_issue(serde_json::json!({
+        "title": title,
+        "html_content": "<p>Newsletter body & HTML</p>",
+        "hide_from_archive": hide_from_archive,
+    }))
+    .await;
+}
+
+async fn get_issue_id(app: &TestApp, title: &str) -> Uuid {
+    sqlx::query!(
+        "SELECT newsletter_issue_id FROM newsletter_issues WHERE title = $1",
+        title
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .newsletter_issue_id
+}
+
+async fn get_feed(
+    app: &TestApp,
+    feed: &str,
+    headers: &[(&str, &str)],
+) -> reqwest::Response {
+    let mut request =
+        reqwest::Client::new().get(format!("{}{}", app.address, feed));
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    request.send().await.expect("Failed to execute request")
+}
+
+#[tokio::test]
+async fn rss_feed_lists_archived_issues() {
+    let app = spawn_app().await;
+    publish_issue(&app, "Public issue", false).await;
+    publish_issue(&app, "Private issue", true).await;
+    let issue_id = get_issue_id(&app, "Public issue").await;
+
+    let response = get_feed(&app, "/feed.rss", &[]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/rss+xml; charset=utf-8"
+    );
+    let body = response.text().await.unwrap();
+    assert!(body.contains("<title>Public issue</title>"));
+    assert!(body.contains(&format!(
+        r#"<guid isPermaLink="false">urn:uuid:{}</guid>"#,
+        issue_id
+    )));
+    assert!(body.contains(
+        "<description>&lt;p&gt;Newsletter body &amp; HTML&lt;/p&gt;</description>"
+    ));
+    assert!(body.contains("<pubDate>"));
+    assert!(!body.contains("Private issue"));
+}
+
+#[tokio::test]
+async fn atom_feed_lists_archived_issues() {
+    let app = spawn_app().await;
+    publish_issue(&app, "Public issue", false).await;
+    publish_issue(&app, "Private issue", true).await;
+    let issue_id = get_issue_id(&app, "Public issue").await;
+
+    let response = get_feed(&app, "/feed.atom", &[]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/atom+xml; charset=utf-8"
+    );
+    let body = response.text().await.unwrap();
+    assert!(body.contains("<title>Public issue</title>"));
+    assert!(body.contains(&format!("<id>urn:uuid:{}</id>", issue_id)));
+    assert!(body.contains(
+        r#"<content type="html">&lt;p&gt;Newsletter body &amp; HTML&lt;/p&gt;</content>"#
+    ));
+    assert!(!body.contains("Private issue"));
+}
+
+#[tokio::test]
+async fn empty_feeds_are_served() {
+    let app = spawn_app().await;
+
+    for feed in ["/feed.rss", "/feed.atom"] {
+        let response = get_feed(&app, feed, &[]).await;
+        assert_eq!(response.status().as_u16(), 200);
+        assert!(response.headers().get("Last-Modified").is_none());
+    }
+}
+
+#[tokio::test]
+async fn feeds_return_304_when_the_etag_matches() {
+    let app = spawn_app().await;
+    publish_issue(&app, "Public issue", false).await;
+
+    for feed in ["/feed.rss", "/feed.atom"] {
+        let response = get_feed(&app, feed, &[]).await;
+        let etag = response.headers()["ETag"].to_str().unwrap().to_owned();
+
+        let response = get_feed(&app, feed, &[("If-None-Match", &etag)]).await;
+
+        assert_eq!(response.status().as_u16(), 304);
+        assert_eq!(response.headers()["ETag"], etag.as_str());
+        assert!(response.text().await.unwrap().is_empty());
+    }
+}
+
+#[tokio::test]
+async fn feeds_return_304_when_not_modified_since() {
+    let app = spawn_app().await;
+    publish_issue(&app, "Public issue", false).await;
+
+    for feed in ["/feed.rss", "/feed.atom"] {
+        let response = get_feed(&app, feed, &[]).await;
+        let last_modified = response.headers()["Last-Modified"]
+            .to_str()
+            .unwrap()
+            .to_owned();
+
+        let response =
+            get_feed(&app, feed, &[("If-Modified-Since", &last_modified)])
+                .await;
+
+        assert_eq!(response.status().as_u16(), 304);
+    }
+}
+
+#[tokio::test]
+async fn feeds_are_served_again_after_a_new_issue_is_published() {
+    let app = spawn_app().await;
+    publish_issue(&app, "First issue", false).await;
+    let response = get_feed(&app, "/feed.rss", &[]).await;
+    let etag = response.headers()["ETag"].to_str().unwrap().to_owned();
+
+    publish_issue(&app, "Second issue", false).await;
+    let response =
+        get_feed(&app, "/feed.rss", &[("If-None-Match", &etag)]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("Second issue"));
+}
+
+#[tokio::test]
+async fn hiding_an_issue_invalidates_the_feed() {
+    let app = spawn_app().await;
+    publish_issue(&app, "First issue", false).await;
+    publish_issue(&app, "Second issue", false).await;
+    let issue_id = get_issue_id(&app, "First issue").await;
+    let response = get_feed(&app, "/feed.atom", &[]).await;
+    let etag = response.headers()["ETag"].to_str().unwrap().to_owned();
+
+    app.post_archive_visibility(issue_id, true).await;
+    let response =
+        get_feed(&app, "/feed.atom", &[("If-None-Match", &etag)]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(!response.text().await.unwrap().contains("First issue"));
+}
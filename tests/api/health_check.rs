@@ -6,7 +6,7 @@ async fn health_check_works() {
     let client = test_app.api_client;
 
     let response = client
-        .get(&format!("{}/health_check", test_app.address))
+        .get(format!("{}/health_check", test_app.address))
         .send()
         .await
         .expect("Failed to execute request.");
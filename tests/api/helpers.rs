@@ -1,13 +1,24 @@
 use argon2::password_hash::SaltString;
 use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
+use fake::{
+    faker::{internet::en::SafeEmail, name::en::Name},
+    Fake,
+};
 use once_cell::sync::Lazy;
 use reqwest::Url;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use uuid::Uuid;
-use wiremock::MockServer;
-use zero2prod::configuration::{get_configuration, DatabaseSettings};
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+use zero2prod::authentication::{create_api_token, TotpEncryptionKey};
+use zero2prod::configuration::{get_configuration, DatabaseSettings, Settings};
+use zero2prod::domain::ApiScope;
 use zero2prod::email_client::EmailClient;
-use zero2prod::issue_delivery_worker::{try_execute_task, ExecutionOutcome};
+use zero2prod::issue_delivery_worker::{
+    try_execute_task, DeliverySettings, ExecutionOutcome,
+};
 use zero2prod::startup::get_connection_pool;
 use zero2prod::startup::Application;
 use zero2prod::telemetry::{get_subscriber, init_subscriber};
@@ -34,6 +45,7 @@ pub struct TestUser {
     pub user_id: Uuid,
     pub username: String,
     pub password: String,
+    pub role: String,
 }
 
 impl TestUser {
@@ -42,10 +54,18 @@ impl TestUser {
             user_id: uuid::Uuid::new_v4(),
             username: uuid::Uuid::new_v4().to_string(),
             password: uuid::Uuid::new_v4().to_string(),
+            role: "owner".into(),
         }
     }
 
-    async fn store(&self, pool: &PgPool) {
+    pub fn with_role(role: &str) -> Self {
+        Self {
+            role: role.into(),
+            ..Self::generate()
+        }
+    }
+
+    pub async fn store(&self, pool: &PgPool) {
         let salt = SaltString::generate(&mut rand::thread_rng());
         let password_hash = Argon2::new(
             Algorithm::Argon2id,
@@ -57,11 +77,12 @@ impl TestUser {
         .to_string();
 
         sqlx::query!(
-            r#"INSERT INTO users (user_id, username, password_hash)
-            VALUES ($1, $2, $3)"#,
+            r#"INSERT INTO users (user_id, username, password_hash, role)
+            VALUES ($1, $2, $3, $4)"#,
             self.user_id,
             self.username,
-            password_hash
+            password_hash,
+            self.role
         )
         .execute(pool)
         .await
@@ -77,12 +98,14 @@ pub struct TestApp {
     pub test_user: TestUser,
     pub api_client: reqwest::Client,
     pub email_client: EmailClient,
+    pub delivery_settings: DeliverySettings,
+    pub totp_key: TotpEncryptionKey,
 }
 
 impl TestApp {
     pub async fn post_subscriptions(&self, body: String) -> reqwest::Response {
         self.api_client
-            .post(&format!("{}/subscriptions", self.address))
+            .post(format!("{}/subscriptions", self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(body)
             .send()
@@ -92,10 +115,13 @@ impl TestApp {
 
     pub async fn dispatch_all_pending_emails(&self) {
         loop {
-            if let ExecutionOutcome::EmptyQueue =
-                try_execute_task(&self.db_pool, &self.email_client)
-                    .await
-                    .unwrap()
+            if let ExecutionOutcome::EmptyQueue = try_execute_task(
+                &self.db_pool,
+                &self.email_client,
+                &self.delivery_settings,
+            )
+            .await
+            .unwrap()
             {
                 break;
             }
@@ -129,13 +155,68 @@ impl TestApp {
         ConfirmationLinks { html, plain_text }
     }
 
+    /// The CSRF token of the current session, as found in its forms.
+    pub async fn csrf_token(&self) -> String {
+        get_csrf_token(&self.api_client, &self.address).await
+    }
+
+    /// Adds the CSRF token of the current session to a form.
+    pub async fn with_csrf_token<Body>(&self, body: &Body) -> serde_json::Value
+    where
+        Body: serde::Serialize,
+    {
+        let mut body = serde_json::to_value(body).unwrap();
+        body["csrf_token"] = self.csrf_token().await.into();
+        body
+    }
+
+    /// Publish an issue through the admin form, which needs a logged in
+    /// session. `fields` are added to, or replace, the placeholder content.
+    /// Returns the id of the new issue.
+    pub async fn publish_issue(&self, fields: serde_json::Value) -> Uuid {
+        let mut body = serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string(),
+        });
+        body.as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+        let response = self.post_newsletters(&body).await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        sqlx::query!(
+            "SELECT newsletter_issue_id FROM newsletter_issues
+            ORDER BY created_at DESC LIMIT 1"
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id
+    }
+
     pub async fn post_newsletters<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
     {
+        let body = self.with_csrf_token(body).await;
+        self.api_client
+            .post(format!("{}/admin/newsletters", &self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_attachments(
+        &self,
+        form: reqwest::multipart::Form,
+    ) -> reqwest::Response {
+        let csrf_token = self.csrf_token().await;
         self.api_client
-            .post(&format!("{}/admin/newsletters", &self.address))
-            .form(body)
+            .post(format!("{}/admin/newsletters/attachments", &self.address))
+            .multipart(form)
+            .header("X-CSRF-Token", csrf_token)
             .send()
             .await
             .expect("Failed to execute request")
@@ -143,7 +224,7 @@ impl TestApp {
 
     pub async fn get_publish_newsletter_html(&self) -> String {
         self.api_client
-            .get(&format!("{}/admin/newsletters", &self.address))
+            .get(format!("{}/admin/newsletters", &self.address))
             .send()
             .await
             .expect("Failed to execute request")
@@ -156,17 +237,29 @@ impl TestApp {
     where
         Body: serde::Serialize,
     {
+        let body = self.with_csrf_token(body).await;
         self.api_client
-            .post(&format!("{}/login", &self.address))
-            .form(body)
+            .post(format!("{}/login", &self.address))
+            .form(&body)
             .send()
             .await
             .expect("Failed to execute request")
     }
 
+    /// Log the test user in, with the session of `api_client`.
+    pub async fn login(&self) {
+        let response = self
+            .post_login(&serde_json::json!({
+                "username": &self.test_user.username,
+                "password": &self.test_user.password,
+            }))
+            .await;
+        assert_is_redirect_to(&response, "/admin/dashboard");
+    }
+
     pub async fn get_login_html(&self) -> String {
         self.api_client
-            .get(&format!("{}/login", &self.address))
+            .get(format!("{}/login", &self.address))
             .send()
             .await
             .expect("Failed to execute request")
@@ -175,9 +268,40 @@ impl TestApp {
             .unwrap()
     }
 
+    pub async fn post_forgot_password(
+        &self,
+        username: &str,
+    ) -> reqwest::Response {
+        let body = self
+            .with_csrf_token(&serde_json::json!({ "username": username }))
+            .await;
+        self.api_client
+            .post(format!("{}/login/forgot-password", &self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_reset_password<Body>(
+        &self,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let body = self.with_csrf_token(body).await;
+        self.api_client
+            .post(format!("{}/login/reset-password", &self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
     pub async fn get_admin_dashboard_html(&self) -> String {
         self.api_client
-            .get(&format!("{}/admin/dashboard", &self.address))
+            .get(format!("{}/admin/dashboard", &self.address))
             .send()
             .await
             .expect("Failed to execute request")
@@ -188,7 +312,7 @@ impl TestApp {
 
     pub async fn get_admin_dashboard(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/admin/dashboard", &self.address))
+            .get(format!("{}/admin/dashboard", &self.address))
             .send()
             .await
             .expect("Failed to execute request")
@@ -201,9 +325,10 @@ impl TestApp {
     where
         Body: serde::Serialize,
     {
+        let body = self.with_csrf_token(body).await;
         self.api_client
-            .post(&format!("{}/admin/password", &self.address))
-            .form(body)
+            .post(format!("{}/admin/password", &self.address))
+            .form(&body)
             .send()
             .await
             .expect("Failed to execute request")
@@ -211,7 +336,7 @@ impl TestApp {
 
     pub async fn get_change_password(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/admin/password", &self.address))
+            .get(format!("{}/admin/password", &self.address))
             .send()
             .await
             .expect("Failed to execute request")
@@ -221,9 +346,423 @@ impl TestApp {
         self.get_change_password().await.text().await.unwrap()
     }
 
+    pub async fn get_archive(&self, path: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}{}", &self.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_admin_issues_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/issues", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_archive_visibility(
+        &self,
+        issue_id: Uuid,
+        hidden: bool,
+    ) -> reqwest::Response {
+        let body = self
+            .with_csrf_token(&serde_json::json!({ "hidden": hidden }))
+            .await;
+        self.api_client
+            .post(format!(
+                "{}/admin/issues/{}/archive",
+                &self.address, issue_id
+            ))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_issue_report_html(&self, issue_id: Uuid) -> String {
+        self.api_client
+            .get(format!("{}/admin/issues/{}", &self.address, issue_id))
+            .send()
+            .await
+            .expect("Failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_issue_delivery(
+        &self,
+        issue_id: Uuid,
+        action: &str,
+    ) -> reqwest::Response {
+        let body = self
+            .with_csrf_token(&serde_json::json!({ "action": action }))
+            .await;
+        self.api_client
+            .post(format!(
+                "{}/admin/issues/{}/delivery",
+                &self.address, issue_id
+            ))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_users_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/users", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_users<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let body = self.with_csrf_token(body).await;
+        self.api_client
+            .post(format!("{}/admin/users", &self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_user_status(
+        &self,
+        user_id: Uuid,
+        action: &str,
+    ) -> reqwest::Response {
+        let body = self
+            .with_csrf_token(&serde_json::json!({ "action": action }))
+            .await;
+        self.api_client
+            .post(format!("{}/admin/users/{}/status", &self.address, user_id))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_user_role(
+        &self,
+        user_id: Uuid,
+        role: &str,
+    ) -> reqwest::Response {
+        let body = self
+            .with_csrf_token(&serde_json::json!({ "role": role }))
+            .await;
+        self.api_client
+            .post(format!("{}/admin/users/{}/role", &self.address, user_id))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_user_email(
+        &self,
+        user_id: Uuid,
+        email: &str,
+    ) -> reqwest::Response {
+        let body = self
+            .with_csrf_token(&serde_json::json!({ "email": email }))
+            .await;
+        self.api_client
+            .post(format!("{}/admin/users/{}/email", &self.address, user_id))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_delete_user(&self, user_id: Uuid) -> reqwest::Response {
+        let body = self.with_csrf_token(&serde_json::json!({})).await;
+        self.api_client
+            .post(format!("{}/admin/users/{}/delete", &self.address, user_id))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_drafts<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let body = self.with_csrf_token(body).await;
+        self.api_client
+            .post(format!("{}/admin/drafts", &self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_draft_html(&self, issue_id: Uuid) -> String {
+        self.api_client
+            .get(format!("{}/admin/drafts/{}", &self.address, issue_id))
+            .send()
+            .await
+            .expect("Failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_save_draft<Body>(
+        &self,
+        issue_id: Uuid,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let body = self.with_csrf_token(body).await;
+        self.api_client
+            .post(format!("{}/admin/drafts/{}", &self.address, issue_id))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_publish_draft(
+        &self,
+        issue_id: Uuid,
+    ) -> reqwest::Response {
+        let body = self.with_csrf_token(&serde_json::json!({})).await;
+        self.api_client
+            .post(format!(
+                "{}/admin/drafts/{}/publish",
+                &self.address, issue_id
+            ))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_review_issue(
+        &self,
+        issue_id: Uuid,
+        action: &str,
+    ) -> reqwest::Response {
+        let body = self
+            .with_csrf_token(&serde_json::json!({ "action": action }))
+            .await;
+        self.api_client
+            .post(format!(
+                "{}/admin/drafts/{}/review",
+                &self.address, issue_id
+            ))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_api_tokens_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/api-tokens", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_api_tokens<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let body = self.with_csrf_token(body).await;
+        self.api_client
+            .post(format!("{}/admin/api-tokens", &self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_revoke_api_token(
+        &self,
+        api_token_id: Uuid,
+    ) -> reqwest::Response {
+        let body = self.with_csrf_token(&serde_json::json!({})).await;
+        self.api_client
+            .post(format!(
+                "{}/admin/api-tokens/{}/revoke",
+                &self.address, api_token_id
+            ))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    /// An API token of the test user, without going through the admin
+    /// pages.
+    pub async fn api_token(&self, scopes: &[ApiScope]) -> String {
+        create_api_token(
+            &self.db_pool,
+            self.test_user.user_id,
+            "Test token",
+            scopes,
+            None,
+        )
+        .await
+        .unwrap()
+        .as_ref()
+        .to_string()
+    }
+
+    /// A request to the JSON API, without any session.
+    pub fn api_v1_request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        token: &str,
+    ) -> reqwest::RequestBuilder {
+        build_api_client(reqwest::Client::builder())
+            .request(method, format!("{}/api/v1{}", &self.address, path))
+            .bearer_auth(token)
+    }
+
+    pub async fn get_sessions_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_revoke_session(
+        &self,
+        session_id: Uuid,
+    ) -> reqwest::Response {
+        let body = self.with_csrf_token(&serde_json::json!({})).await;
+        self.api_client
+            .post(format!(
+                "{}/admin/sessions/{}/revoke",
+                &self.address, session_id
+            ))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_log_out_everywhere(&self) -> reqwest::Response {
+        let body = self.with_csrf_token(&serde_json::json!({})).await;
+        self.api_client
+            .post(format!("{}/admin/sessions/revoke-all", &self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_two_factor_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/two-factor", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_two_factor_setup(&self) -> reqwest::Response {
+        let body = self.with_csrf_token(&serde_json::json!({})).await;
+        self.api_client
+            .post(format!("{}/admin/two-factor/setup", &self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    /// `action` is one of `enable`, `disable` or `recovery-codes`.
+    pub async fn post_two_factor(
+        &self,
+        action: &str,
+        code: &str,
+    ) -> reqwest::Response {
+        let body = self
+            .with_csrf_token(&serde_json::json!({ "code": code }))
+            .await;
+        self.api_client
+            .post(format!("{}/admin/two-factor/{}", &self.address, action))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_login_two_factor(&self, code: &str) -> reqwest::Response {
+        let body = self
+            .with_csrf_token(&serde_json::json!({ "code": code }))
+            .await;
+        self.api_client
+            .post(format!("{}/login/two-factor", &self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_unlock_login(
+        &self,
+        kind: &str,
+        key: &str,
+    ) -> reqwest::Response {
+        let body = self
+            .with_csrf_token(&serde_json::json!({ "kind": kind, "key": key }))
+            .await;
+        self.api_client
+            .post(format!("{}/admin/users/lockouts/unlock", &self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_two_factor_policy(
+        &self,
+        required: bool,
+    ) -> reqwest::Response {
+        let body = self
+            .with_csrf_token(&serde_json::json!({ "required": required }))
+            .await;
+        self.api_client
+            .post(format!("{}/admin/users/two-factor-policy", &self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
     pub async fn post_logout(&self) -> reqwest::Response {
+        let body = self.with_csrf_token(&serde_json::json!({})).await;
         self.api_client
-            .post(&format!("{}/admin/logout", &self.address))
+            .post(format!("{}/admin/logout", &self.address))
+            .form(&body)
             .send()
             .await
             .expect("Failed to execute request")
@@ -236,6 +775,15 @@ pub struct ConfirmationLinks {
 }
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawn the application after applying test-specific changes to its
+/// configuration.
+pub async fn spawn_app_with<F>(configure: F) -> TestApp
+where
+    F: FnOnce(&mut Settings),
+{
     Lazy::force(&TRACING);
     let email_server = MockServer::start().await;
 
@@ -244,6 +792,9 @@ pub async fn spawn_app() -> TestApp {
         c.application.port = 0;
         c.database.database_name = Uuid::new_v4().to_string();
         c.email_client.base_url = email_server.uri();
+        // Most tests publish issues in a single step
+        c.publishing.require_approval = false;
+        configure(&mut c);
         c
     };
     configure_database(&configuration.database).await;
@@ -253,12 +804,12 @@ pub async fn spawn_app() -> TestApp {
         .expect("Failed to build application");
     let port = application.port();
     let address = format!("http://127.0.0.1:{}", application.port());
-    let _ = tokio::spawn(application.run_until_stopped());
-    let api_client = reqwest::Client::builder()
-        .cookie_store(true)
-        .redirect(reqwest::redirect::Policy::none())
-        .build()
-        .unwrap();
+    tokio::spawn(application.run_until_stopped());
+    let api_client = build_api_client(reqwest::Client::builder());
+    let delivery_settings = DeliverySettings {
+        base_url: address.clone(),
+        ..DeliverySettings::new(&configuration)
+    };
     let email_client = configuration.email_client.client();
 
     let test_app = TestApp {
@@ -269,6 +820,10 @@ pub async fn spawn_app() -> TestApp {
         test_user: TestUser::generate(),
         api_client,
         email_client,
+        delivery_settings,
+        totp_key: TotpEncryptionKey::new(
+            &configuration.application.totp_encryption_key,
+        ),
     };
     test_app.test_user.store(&test_app.db_pool).await;
     test_app
@@ -296,7 +851,76 @@ async fn configure_database(config: &DatabaseSettings) -> PgPool {
     connection_pool
 }
 
+/// The CSRF token of the session of `client`, read from the login form.
+pub async fn get_csrf_token(client: &reqwest::Client, address: &str) -> String {
+    let html_page = client
+        .get(format!("{}/login", address))
+        .send()
+        .await
+        .expect("Failed to execute request")
+        .text()
+        .await
+        .unwrap();
+    html_page
+        .split(r#"name="csrf_token" value=""#)
+        .nth(1)
+        .and_then(|s| s.split('"').next())
+        .expect("The login form has no CSRF token")
+        .to_string()
+}
+
+/// A client with its own cookies, as used by `TestApp::api_client`.
+pub fn build_api_client(builder: reqwest::ClientBuilder) -> reqwest::Client {
+    builder
+        .cookie_store(true)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+}
+
 pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
     assert_eq!(response.status().as_u16(), 303);
     assert_eq!(response.headers().get("Location").unwrap(), location);
 }
+
+pub async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
+    let name: String = Name().fake();
+    let email: String = SafeEmail().fake();
+    let body = serde_urlencoded::to_string(serde_json::json!({
+        "name": name,
+        "email": email,
+    }))
+    .unwrap();
+
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .named("Create unconfirmed subscriber")
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+
+    app.get_confirmation_links(email_request)
+}
+
+pub async fn create_confirmed_subscriber(app: &TestApp) {
+    let confirmation_links = create_unconfirmed_subscriber(app).await;
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
@@ -0,0 +1,139 @@
+use uuid::Uuid;
+use wiremock::{matchers::any, Mock, ResponseTemplate};
+use zero2prod::issue_delivery_worker::{try_execute_task, ExecutionOutcome};
+
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, spawn_app,
+};
+
+#[tokio::test]
+async fn paused_issues_are_not_delivered() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    let issue_id = app.publish_issue(serde_json::json!({})).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_issue_delivery(issue_id, "pause").await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", issue_id));
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_issue_report_html(issue_id).await;
+    assert!(html_page
+        .contains("<p><i>Delivery of the issue has been paused.</i></p>"));
+    assert!(html_page.contains("Delivery status: paused"));
+    assert!(html_page.contains("<tr><td>Pending</td><td>2</td></tr>"));
+    assert!(html_page.contains(
+        r#"<input hidden type="text" name="action" value="resume">"#
+    ));
+}
+
+#[tokio::test]
+async fn resumed_issues_are_delivered() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    let issue_id = app.publish_issue(serde_json::json!({})).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_issue_delivery(issue_id, "pause").await;
+    app.dispatch_all_pending_emails().await;
+    let response = app.post_issue_delivery(issue_id, "resume").await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", issue_id));
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_issue_report_html(issue_id).await;
+    assert!(html_page.contains("Delivery status: active"));
+    assert!(html_page.contains("<tr><td>Sent</td><td>1</td></tr>"));
+    assert!(html_page.contains("<tr><td>Pending</td><td>0</td></tr>"));
+}
+
+#[tokio::test]
+async fn cancelling_an_issue_stops_its_delivery() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    let issue_id = app.publish_issue(serde_json::json!({})).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Deliver a single email before stopping the issue
+    let outcome = try_execute_task(
+        &app.db_pool,
+        &app.email_client,
+        &app.delivery_settings,
+    )
+    .await
+    .unwrap();
+    assert!(matches!(outcome, ExecutionOutcome::TaskCompleted));
+    let response = app.post_issue_delivery(issue_id, "cancel").await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", issue_id));
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_issue_report_html(issue_id).await;
+    assert!(html_page
+        .contains("<p><i>Delivery of the issue has been cancelled.</i></p>"));
+    assert!(html_page.contains("Delivery status: cancelled"));
+    assert!(html_page.contains("<tr><td>Recipients</td><td>3</td></tr>"));
+    assert!(html_page.contains("<tr><td>Sent</td><td>1</td></tr>"));
+    assert!(html_page.contains("<tr><td>Pending</td><td>0</td></tr>"));
+    assert!(html_page.contains("<tr><td>Not sent</td><td>2</td></tr>"));
+}
+
+#[tokio::test]
+async fn cancelled_issues_can_not_be_resumed() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.login().await;
+    let issue_id = app.publish_issue(serde_json::json!({})).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_issue_delivery(issue_id, "cancel").await;
+    app.get_issue_report_html(issue_id).await;
+    let response = app.post_issue_delivery(issue_id, "resume").await;
+    assert_is_redirect_to(&response, &format!("/admin/issues/{}", issue_id));
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_issue_report_html(issue_id).await;
+    assert!(html_page
+        .contains("<p><i>A cancelled delivery can't be resumed.</i></p>"));
+    assert!(html_page.contains("Delivery status: cancelled"));
+}
+
+#[tokio::test]
+async fn unknown_delivery_actions_are_rejected() {
+    let app = spawn_app().await;
+    app.login().await;
+    let issue_id = app.publish_issue(serde_json::json!({})).await;
+
+    let response = app.post_issue_delivery(issue_id, "explode").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_control_a_delivery() {
+    let app = spawn_app().await;
+
+    let response = app.post_issue_delivery(Uuid::new_v4(), "pause").await;
+
+    assert_is_redirect_to(&response, "/login");
+}
@@ -1,4 +1,29 @@
-use crate::helpers::{assert_is_redirect_to, spawn_app};
+use argon2::password_hash::SaltString;
+use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
+use uuid::Uuid;
+
+use crate::helpers::{
+    assert_is_redirect_to, spawn_app, spawn_app_with, TestApp,
+};
+
+async fn stored_password_hash(app: &TestApp) -> String {
+    sqlx::query!(
+        "SELECT password_hash FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .password_hash
+}
+
+async fn login(app: &TestApp, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "username": &app.test_user.username,
+        "password": password,
+    }))
+    .await
+}
 
 #[tokio::test]
 async fn an_error_flash_message_is_set_on_failure() {
@@ -35,3 +60,57 @@ async fn redirect_to_admin_dashboard_after_login_success() {
     let html_page = app.get_admin_dashboard_html().await;
     assert!(html_page.contains(&format!("Welcome {}!", app.test_user.username)));
 }
+
+#[tokio::test]
+async fn weaker_password_hashes_are_upgraded_on_login() {
+    // The test user is stored with m=15000
+    let app = spawn_app_with(|c| c.password_hashing.memory_kib = 19456).await;
+
+    let response = login(&app, &app.test_user.password).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    let password_hash = stored_password_hash(&app).await;
+    assert!(password_hash.starts_with("$argon2id$v=19$m=19456,t=2,p=1$"));
+    app.post_logout().await;
+    let response = login(&app, &app.test_user.password).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn password_hashes_from_older_algorithms_are_upgraded_on_login() {
+    let app = spawn_app().await;
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    let argon2i_hash = Argon2::new(
+        Algorithm::Argon2i,
+        Version::V0x13,
+        Params::new(15000, 2, 1, None).unwrap(),
+    )
+    .hash_password(app.test_user.password.as_bytes(), &salt)
+    .unwrap()
+    .to_string();
+    sqlx::query!(
+        "UPDATE users SET password_hash = $1 WHERE user_id = $2",
+        argon2i_hash,
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = login(&app, &app.test_user.password).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    let password_hash = stored_password_hash(&app).await;
+    assert!(password_hash.starts_with("$argon2id$v=19$m=15000,t=2,p=1$"));
+}
+
+#[tokio::test]
+async fn a_failed_login_does_not_upgrade_the_password_hash() {
+    let app = spawn_app_with(|c| c.password_hashing.memory_kib = 19456).await;
+    let password_hash = stored_password_hash(&app).await;
+
+    let response = login(&app, &Uuid::new_v4().to_string()).await;
+    assert_is_redirect_to(&response, "/login");
+
+    assert_eq!(stored_password_hash(&app).await, password_hash);
+}
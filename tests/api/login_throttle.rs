@@ -0,0 +1,169 @@
+use uuid::Uuid;
+
+use crate::helpers::{
+    assert_is_redirect_to, spawn_app_with, TestApp, TestUser,
+};
+
+async fn login(app: &TestApp, username: &str, password: &str) -> String {
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": username,
+            "password": password,
+        }))
+        .await;
+    response.headers()["Location"].to_str().unwrap().to_string()
+}
+
+async fn fail_logins(app: &TestApp, username: &str, times: usize) {
+    for _ in 0..times {
+        let location = login(app, username, &Uuid::new_v4().to_string()).await;
+        assert_eq!(location, "/login");
+    }
+}
+
+#[tokio::test]
+async fn a_username_is_locked_out_after_too_many_failed_logins() {
+    let app = spawn_app_with(|c| {
+        c.login_throttle.free_attempts = 100;
+        c.login_throttle.max_attempts_per_username = 3;
+    })
+    .await;
+    let user = &app.test_user;
+    fail_logins(&app, &user.username, 3).await;
+
+    // The right password is rejected, with the usual message
+    let location = login(&app, &user.username, &user.password).await;
+    assert_eq!(location, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("<p><i>Authentication failed</i></p>"));
+
+    // Other users are not affected
+    let other_user = TestUser::generate();
+    other_user.store(&app.db_pool).await;
+    let location =
+        login(&app, &other_user.username, &other_user.password).await;
+    assert_eq!(location, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn failed_logins_delay_the_next_attempt() {
+    let app = spawn_app_with(|c| {
+        c.login_throttle.free_attempts = 1;
+        c.login_throttle.base_delay_seconds = 60;
+    })
+    .await;
+    let user = &app.test_user;
+    fail_logins(&app, &user.username, 1).await;
+
+    let location = login(&app, &user.username, &user.password).await;
+
+    assert_eq!(location, "/login");
+}
+
+#[tokio::test]
+async fn a_successful_login_forgets_earlier_failures() {
+    let app = spawn_app_with(|c| {
+        c.login_throttle.free_attempts = 100;
+        c.login_throttle.max_attempts_per_username = 3;
+    })
+    .await;
+    let user = &app.test_user;
+    fail_logins(&app, &user.username, 2).await;
+    let location = login(&app, &user.username, &user.password).await;
+    assert_eq!(location, "/admin/dashboard");
+    app.post_logout().await;
+
+    fail_logins(&app, &user.username, 2).await;
+    let location = login(&app, &user.username, &user.password).await;
+
+    assert_eq!(location, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn an_ip_address_is_locked_out_after_too_many_failed_logins() {
+    let app = spawn_app_with(|c| {
+        c.login_throttle.free_attempts = 100;
+        c.login_throttle.max_attempts_per_ip = 3;
+    })
+    .await;
+    for _ in 0..3 {
+        fail_logins(&app, &Uuid::new_v4().to_string(), 1).await;
+    }
+
+    let user = &app.test_user;
+    let location = login(&app, &user.username, &user.password).await;
+
+    assert_eq!(location, "/login");
+}
+
+async fn login_from(
+    app: &TestApp,
+    client_ip: &str,
+    username: &str,
+    password: &str,
+) -> String {
+    let body = app
+        .with_csrf_token(&serde_json::json!({
+            "username": username,
+            "password": password,
+        }))
+        .await;
+    let response = app
+        .api_client
+        .post(format!("{}/login", &app.address))
+        .header("X-Real-IP", client_ip)
+        .form(&body)
+        .send()
+        .await
+        .expect("Failed to execute request");
+    response.headers()["Location"].to_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn clients_behind_a_proxy_are_throttled_by_their_own_address() {
+    let app = spawn_app_with(|c| {
+        c.login_throttle.free_attempts = 100;
+        c.login_throttle.max_attempts_per_ip = 3;
+        c.login_throttle.client_ip_header = Some("X-Real-IP".into());
+    })
+    .await;
+    for _ in 0..3 {
+        let username = Uuid::new_v4().to_string();
+        let password = Uuid::new_v4().to_string();
+        let location =
+            login_from(&app, "203.0.113.1", &username, &password).await;
+        assert_eq!(location, "/login");
+    }
+
+    let user = &app.test_user;
+    let location =
+        login_from(&app, "203.0.113.1", &user.username, &user.password).await;
+    assert_eq!(location, "/login");
+    let location =
+        login_from(&app, "203.0.113.2", &user.username, &user.password).await;
+    assert_eq!(location, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn owners_can_unlock_a_username() {
+    let app = spawn_app_with(|c| {
+        c.login_throttle.free_attempts = 100;
+        c.login_throttle.max_attempts_per_username = 3;
+    })
+    .await;
+    let editor = TestUser::with_role("editor");
+    editor.store(&app.db_pool).await;
+    fail_logins(&app, &editor.username, 3).await;
+    login(&app, &app.test_user.username, &app.test_user.password).await;
+
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains(&format!("<td>{}</td>", editor.username)));
+    let response = app.post_unlock_login("username", &editor.username).await;
+    assert_is_redirect_to(&response, "/admin/users");
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains("Nobody is locked out."));
+    app.post_logout().await;
+
+    let location = login(&app, &editor.username, &editor.password).await;
+    assert_eq!(location, "/admin/dashboard");
+}
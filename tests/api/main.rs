@@ -1,8 +1,30 @@
 mod admin_dashboard;
+mod api_tokens;
+mod api_v1;
+mod approval;
+mod archive;
+mod attachments;
 mod change_password;
+mod click_tracking;
+mod content_lint;
+mod csrf;
+mod delivery_schedule;
+mod drafts;
+mod feeds;
 mod health_check;
 mod helpers;
+mod issue_delivery;
 mod login;
+mod login_throttle;
 mod newsletter;
+mod open_tracking;
+mod password_reset;
+mod roles;
+mod session_store;
+mod sessions;
+mod subject_testing;
 mod subscriptions;
 mod subscriptions_confirm;
+mod two_factor;
+mod users;
+mod utm_tagging;
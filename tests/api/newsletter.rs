@@ -1,16 +1,13 @@
 use std::time::Duration;
 
-use fake::{
-    faker::{internet::en::SafeEmail, name::en::Name},
-    Fake,
-};
 use wiremock::{
     matchers::{any, method, path},
     Mock, ResponseTemplate,
 };
 
 use crate::helpers::{
-    assert_is_redirect_to, spawn_app, ConfirmationLinks, TestApp,
+    assert_is_redirect_to, create_confirmed_subscriber,
+    create_unconfirmed_subscriber, spawn_app,
 };
 
 #[tokio::test]
@@ -165,48 +162,6 @@ async fn you_must_be_logged_in_to_send_newsletters() {
     assert_is_redirect_to(&response, "/login");
 }
 
-async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
-    let name: String = Name().fake();
-    let email: String = SafeEmail().fake();
-    let body = serde_urlencoded::to_string(&serde_json::json!({
-        "name": name,
-        "email": email,
-    }))
-    .unwrap();
-
-    let _mock_guard = Mock::given(path("/email"))
-        .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
-        .named("Create unconfirmed subscriber")
-        .expect(1)
-        .mount_as_scoped(&app.email_server)
-        .await;
-
-    app.post_subscriptions(body.into())
-        .await
-        .error_for_status()
-        .unwrap();
-
-    let email_request = &app
-        .email_server
-        .received_requests()
-        .await
-        .unwrap()
-        .pop()
-        .unwrap();
-
-    app.get_confirmation_links(email_request)
-}
-
-async fn create_confirmed_subscriber(app: &TestApp) {
-    let confirmation_links = create_unconfirmed_subscriber(app).await;
-    reqwest::get(confirmation_links.html)
-        .await
-        .unwrap()
-        .error_for_status()
-        .unwrap();
-}
-
 #[tokio::test]
 async fn newsletter_creation_is_idempotent() {
     let app = spawn_app().await;
@@ -291,3 +246,41 @@ async fn concurrent_form_submission_is_handled_gracefully() {
     );
     app.dispatch_all_pending_emails().await;
 }
+
+#[tokio::test]
+async fn published_issues_record_their_author_and_timestamps() {
+    let app = spawn_app().await;
+
+    // Login
+    app.post_login(&serde_json::json!({
+        "username": &app.test_user.username,
+        "password": &app.test_user.password,
+    }))
+    .await;
+
+    let before_publishing = chrono::Utc::now();
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_newsletters(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let issue = sqlx::query!(
+        "SELECT created_by, published_at, updated_at, slug \
+        FROM newsletter_issues"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(issue.created_by, Some(app.test_user.user_id));
+    assert!(issue.published_at >= Some(before_publishing));
+    assert_eq!(issue.published_at, Some(issue.updated_at));
+    assert!(issue.slug.starts_with("newsletter-title-"));
+
+    // The author is shown in the list of published issues
+    let html_page = app.get_admin_issues_html().await;
+    assert!(html_page.contains(&format!("<td>{}</td>", app.test_user.username)));
+}
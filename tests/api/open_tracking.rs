@@ -0,0 +1,213 @@
+use uuid::Uuid;
+use wiremock::{matchers::any, Mock, ResponseTemplate};
+
+use crate::helpers::{create_confirmed_subscriber, spawn_app, TestApp};
+
+const BROWSER: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) Gecko/20100101 Firefox/118.0";
+
+async fn publish_issue(app: &TestApp, track_opens: bool) -> Uuid {
+    app.login().await;
+    app.publish_issue(serde_json::json!({
+        "text_content": "Read https://example.com/page",
+        "html_content": r#"<p>Read <a href="https://example.com/page">this</a></p>"#,
+        "track_clicks": true,
+        "track_opens": track_opens,
+    }))
+    .await
+}
+
+async fn deliver_issue(app: &TestApp) -> serde_json::Value {
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+    // Opens right after delivery are treated as prefetches
+    sqlx::query!(
+        "UPDATE issue_delivery_log SET attempted_at = now() - interval '1 hour'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    let email_request = app.email_server.received_requests().await.unwrap();
+    serde_json::from_slice(&email_request.last().unwrap().body).unwrap()
+}
+
+fn find_link(html: &str, path: &str) -> Option<String> {
+    linkify::LinkFinder::new()
+        .links(html)
+        .map(|l| l.as_str().to_owned())
+        .find(|l| l.contains(path))
+}
+
+async fn open_pixel(
+    app: &TestApp,
+    pixel_url: &str,
+    user_agent: &str,
+) -> reqwest::Response {
+    app.api_client
+        .get(pixel_url)
+        .header("User-Agent", user_agent)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn a_pixel_and_an_opt_out_link_are_added_when_open_tracking_is_enabled() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    publish_issue(&app, true).await;
+
+    let body = deliver_issue(&app).await;
+
+    let html = body["HtmlBody"].as_str().unwrap();
+    assert!(find_link(html, "/t/o/").is_some());
+    assert!(find_link(html, "/t/opt-out/").is_some());
+    assert!(
+        find_link(body["TextBody"].as_str().unwrap(), "/t/opt-out/").is_some()
+    );
+}
+
+#[tokio::test]
+async fn opens_are_recorded_and_reported() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let issue_id = publish_issue(&app, true).await;
+    let body = deliver_issue(&app).await;
+    let pixel_url =
+        find_link(body["HtmlBody"].as_str().unwrap(), "/t/o/").unwrap();
+
+    for _ in 0..2 {
+        let response = open_pixel(&app, &pixel_url, BROWSER).await;
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers()["Content-Type"], "image/gif");
+    }
+
+    let opens = sqlx::query!(
+        "SELECT n_opens, first_opened_at, last_opened_at FROM issue_opens"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(opens.n_opens, 2);
+    assert!(opens.first_opened_at <= opens.last_opened_at);
+    let html_page = app.get_issue_report_html(issue_id).await;
+    assert!(html_page.contains("Unique opens: 1 (100.0% of sent emails)"));
+    assert!(html_page.contains("Total opens: 2"));
+}
+
+#[tokio::test]
+async fn prefetches_are_not_counted_as_opens() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    publish_issue(&app, true).await;
+    let body = deliver_issue(&app).await;
+    let pixel_url =
+        find_link(body["HtmlBody"].as_str().unwrap(), "/t/o/").unwrap();
+
+    open_pixel(&app, &pixel_url, "Mozilla/5.0").await;
+    open_pixel(&app, &pixel_url, "Barracuda Sentinel (EE)").await;
+    sqlx::query!("UPDATE issue_delivery_log SET attempted_at = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    open_pixel(&app, &pixel_url, BROWSER).await;
+
+    let n_opens = sqlx::query!("SELECT count(*) AS n FROM issue_opens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_opens, Some(0));
+}
+
+#[tokio::test]
+async fn no_pixel_is_added_if_the_issue_does_not_opt_in() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let issue_id = publish_issue(&app, false).await;
+
+    let body = deliver_issue(&app).await;
+
+    assert!(find_link(body["HtmlBody"].as_str().unwrap(), "/t/o/").is_none());
+    let html_page = app.get_issue_report_html(issue_id).await;
+    assert!(html_page.contains("Open tracking is disabled for this issue."));
+}
+
+#[tokio::test]
+async fn subscribers_who_do_not_want_to_be_tracked_get_untracked_emails() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE subscriptions SET do_not_track = true")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    publish_issue(&app, true).await;
+
+    let body = deliver_issue(&app).await;
+
+    let html = body["HtmlBody"].as_str().unwrap();
+    assert!(find_link(html, "/t/o/").is_none());
+    assert!(find_link(html, "/t/c/").is_none());
+    assert!(find_link(html, "/t/opt-out/").is_none());
+}
+
+#[tokio::test]
+async fn the_opt_out_link_stops_tracking() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    publish_issue(&app, true).await;
+    let body = deliver_issue(&app).await;
+    let html = body["HtmlBody"].as_str().unwrap();
+    let opt_out_url = find_link(html, "/t/opt-out/").unwrap();
+    let pixel_url = find_link(html, "/t/o/").unwrap();
+    let click_url = find_link(html, "/t/c/").unwrap();
+
+    // Following the link only asks for a confirmation
+    let response = app.api_client.get(&opt_out_url).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains(r#"method="post""#));
+    let do_not_track = sqlx::query!("SELECT do_not_track FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .do_not_track;
+    assert!(!do_not_track);
+    let response = app.api_client.post(&opt_out_url).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    open_pixel(&app, &pixel_url, BROWSER).await;
+    let response = app.api_client.get(&click_url).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 302);
+    let do_not_track = sqlx::query!("SELECT do_not_track FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .do_not_track;
+    assert!(do_not_track);
+    let n_tracked = sqlx::query!(
+        r#"SELECT
+            (SELECT count(*) FROM issue_opens) +
+            (SELECT count(*) FROM link_clicks) AS "n!""#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .n;
+    assert_eq!(n_tracked, 0);
+}
+
+#[tokio::test]
+async fn invalid_open_tokens_still_get_a_pixel() {
+    let app = spawn_app().await;
+
+    let response =
+        open_pixel(&app, &format!("{}/t/o/not-a-token", app.address), BROWSER)
+            .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Type"], "image/gif");
+}
@@ -0,0 +1,281 @@
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    assert_is_redirect_to, build_api_client, spawn_app, spawn_app_with, TestApp,
+};
+
+async fn set_email(app: &TestApp) {
+    sqlx::query!(
+        "UPDATE users SET email = 'admin@example.com' WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+/// Reset links are sent in the background, after the response.
+async fn wait_for_emails(app: &TestApp, n: usize) -> Vec<wiremock::Request> {
+    for _ in 0..50 {
+        let requests = app.email_server.received_requests().await.unwrap();
+        if requests.len() >= n {
+            return requests;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("{} emails were not sent in time", n);
+}
+
+/// Open a reset link, which moves its token to the session.
+async fn open_reset_link(app: &TestApp, token: &str) -> reqwest::Response {
+    app.api_client
+        .get(format!("{}/login/reset-password", &app.address))
+        .query(&[("token", token)])
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+/// Request a reset link for the test user, open it and return its token.
+async fn request_reset_token(app: &TestApp) -> String {
+    let _guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_forgot_password(&app.test_user.username).await;
+    let email_request = wait_for_emails(app, 1).await.pop().unwrap();
+    let links = app.get_confirmation_links(&email_request);
+    assert_eq!(links.html.path(), "/login/reset-password");
+    let token = links
+        .html
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .unwrap()
+        .1
+        .into_owned();
+    open_reset_link(app, &token).await;
+    token
+}
+
+#[tokio::test]
+async fn the_login_form_links_to_the_password_reset_form() {
+    let app = spawn_app().await;
+
+    let html_page = app.get_login_html().await;
+
+    assert!(html_page.contains(r#"href="/login/forgot-password""#));
+}
+
+#[tokio::test]
+async fn a_reset_link_lets_the_user_choose_a_new_password() {
+    let app = spawn_app().await;
+    set_email(&app).await;
+    request_reset_token(&app).await;
+    let new_password = Uuid::new_v4().to_string();
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Your password has been reset."));
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn the_token_is_moved_out_of_the_url() {
+    let app = spawn_app().await;
+    set_email(&app).await;
+    let token = request_reset_token(&app).await;
+
+    let response = open_reset_link(&app, &token).await;
+    assert_is_redirect_to(&response, "/login/reset-password");
+    let response = app
+        .api_client
+        .get(format!("{}/login/reset-password", &app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(!response.text().await.unwrap().contains(&token));
+
+    // Without a link, there is nothing to reset
+    let response = build_api_client(reqwest::Client::builder())
+        .get(format!("{}/login/reset-password", &app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/login/forgot-password");
+}
+
+#[tokio::test]
+async fn reset_tokens_can_only_be_used_once() {
+    let app = spawn_app().await;
+    set_email(&app).await;
+    let token = request_reset_token(&app).await;
+    let new_password = Uuid::new_v4().to_string();
+    let body = serde_json::json!({
+        "new_password": &new_password,
+        "new_password_check": &new_password,
+    });
+    app.post_reset_password(&body).await;
+
+    open_reset_link(&app, &token).await;
+    let response = app.post_reset_password(&body).await;
+
+    assert_is_redirect_to(&response, "/login/forgot-password");
+    let stored_hash =
+        sqlx::query!("SELECT token_hash FROM password_reset_tokens")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .token_hash;
+    assert_ne!(stored_hash, token);
+}
+
+#[tokio::test]
+async fn expired_reset_tokens_are_rejected() {
+    let app = spawn_app().await;
+    set_email(&app).await;
+    request_reset_token(&app).await;
+    sqlx::query!(
+        "UPDATE password_reset_tokens SET expires_at = now() - interval '1 minute'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    let new_password = Uuid::new_v4().to_string();
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login/forgot-password");
+    let response = app
+        .api_client
+        .get(format!("{}/login/forgot-password", &app.address))
+        .send()
+        .await
+        .unwrap();
+    let html_page = response.text().await.unwrap();
+    assert!(html_page
+        .contains("This password reset link is invalid or has expired."));
+}
+
+#[tokio::test]
+async fn the_new_password_must_follow_the_password_rules() {
+    let app = spawn_app().await;
+    set_email(&app).await;
+    request_reset_token(&app).await;
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "new_password": "too-short",
+            "new_password_check": "too-short",
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login/reset-password");
+    let used_at = sqlx::query!("SELECT used_at FROM password_reset_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .used_at;
+    assert!(used_at.is_none());
+}
+
+#[tokio::test]
+async fn resetting_the_password_ends_existing_sessions() {
+    let app = spawn_app().await;
+    set_email(&app).await;
+    app.post_login(&serde_json::json!({
+        "username": &app.test_user.username,
+        "password": &app.test_user.password,
+    }))
+    .await;
+    request_reset_token(&app).await;
+    let new_password = Uuid::new_v4().to_string();
+
+    app.post_reset_password(&serde_json::json!({
+        "new_password": &new_password,
+        "new_password_check": &new_password,
+    }))
+    .await;
+
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+    let n_sessions = sqlx::query!(
+        r#"SELECT count(*) AS "n!" FROM user_sessions WHERE user_id = $1"#,
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .n;
+    assert_eq!(n_sessions, 0);
+}
+
+#[tokio::test]
+async fn no_email_is_sent_for_unknown_users() {
+    let app = spawn_app().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_forgot_password("not-a-user").await;
+
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("a link to reset its password has been sent"));
+}
+
+#[tokio::test]
+async fn reset_requests_are_throttled() {
+    let app = spawn_app_with(|c| {
+        c.login_throttle.free_attempts = 100;
+        c.login_throttle.max_attempts_per_username = 2;
+    })
+    .await;
+    set_email(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    for _ in 0..3 {
+        let response = app.post_forgot_password(&app.test_user.username).await;
+        assert_is_redirect_to(&response, "/login");
+    }
+
+    wait_for_emails(&app, 2).await;
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    let n_emails = app.email_server.received_requests().await.unwrap().len();
+    assert_eq!(n_emails, 2);
+}
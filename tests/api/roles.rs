@@ -0,0 +1,160 @@
+use uuid::Uuid;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp, TestUser};
+
+async fn login(app: &TestApp, user: &TestUser) {
+    app.post_login(&serde_json::json!({
+        "username": &user.username,
+        "password": &user.password,
+    }))
+    .await;
+}
+
+fn newsletter_request_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    })
+}
+
+async fn issue_id(app: &TestApp) -> Uuid {
+    sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id
+}
+
+#[tokio::test]
+async fn viewers_can_see_issues_but_not_change_them() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user).await;
+    app.post_newsletters(&newsletter_request_body()).await;
+    let issue_id = issue_id(&app).await;
+    app.post_logout().await;
+    let viewer = TestUser::with_role("viewer");
+    viewer.store(&app.db_pool).await;
+    login(&app, &viewer).await;
+
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(r#"href="/admin/issues""#));
+    assert!(!html_page.contains(r#"href="/admin/newsletters""#));
+    assert!(!html_page.contains(r#"href="/admin/users""#));
+    let html_page = app.get_issue_report_html(issue_id).await;
+    assert!(html_page.contains("Newsletter title"));
+
+    let response = app.post_newsletters(&newsletter_request_body()).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.post_drafts(&newsletter_request_body()).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.post_archive_visibility(issue_id, true).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.post_issue_delivery(issue_id, "pause").await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.post_user_role(viewer.user_id, "owner").await;
+    assert_eq!(response.status().as_u16(), 403);
+    let count =
+        sqlx::query!(r#"SELECT count(*) AS "count!" FROM newsletter_issues"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .count;
+    assert_eq!(count, 1);
+}
+
+#[tokio::test]
+async fn editors_can_publish_issues_but_not_manage_users() {
+    let app = spawn_app().await;
+    let editor = TestUser::with_role("editor");
+    editor.store(&app.db_pool).await;
+    login(&app, &editor).await;
+
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(r#"href="/admin/newsletters""#));
+    assert!(!html_page.contains(r#"href="/admin/users""#));
+
+    let response = app.post_newsletters(&newsletter_request_body()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let response = app
+        .api_client
+        .get(format!("{}/admin/users", &app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.post_delete_user(app.test_user.user_id).await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn role_changes_apply_to_existing_sessions() {
+    let app = spawn_app().await;
+    let user = TestUser::with_role("viewer");
+    user.store(&app.db_pool).await;
+    login(&app, &user).await;
+    let response = app.post_newsletters(&newsletter_request_body()).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    sqlx::query!(
+        "UPDATE users SET role = 'editor' WHERE user_id = $1",
+        user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = app.post_newsletters(&newsletter_request_body()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+}
+
+#[tokio::test]
+async fn owners_can_change_roles_but_the_last_owner_stays_an_owner() {
+    let app = spawn_app().await;
+    // The seeded admin is disabled, so the test user is the only owner
+    sqlx::query!(
+        "UPDATE users SET disabled_at = now() WHERE user_id != $1",
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    let other_user = TestUser::with_role("viewer");
+    other_user.store(&app.db_pool).await;
+    login(&app, &app.test_user).await;
+
+    let response = app.post_user_role(app.test_user.user_id, "editor").await;
+    assert_is_redirect_to(&response, "/admin/users");
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains("The last active owner must remain an owner."));
+
+    app.post_user_role(other_user.user_id, "owner").await;
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains(&format!(
+        "The role of {} is now owner.",
+        other_user.username
+    )));
+
+    // There is another owner now
+    app.post_user_role(app.test_user.user_id, "viewer").await;
+    let role = sqlx::query!(
+        "SELECT role FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .role;
+    assert_eq!(role, "viewer");
+}
+
+#[tokio::test]
+async fn unknown_roles_are_rejected() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user).await;
+
+    let response = app.post_user_role(app.test_user.user_id, "admin").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -0,0 +1,68 @@
+use zero2prod::configuration::SessionBackend;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app_with, TestApp};
+
+async fn spawn_app_without_redis() -> TestApp {
+    spawn_app_with(|c| {
+        c.session_store.backend = SessionBackend::Postgres;
+        c.redis_uri = None;
+    })
+    .await
+}
+
+async fn stored_sessions(app: &TestApp) -> i64 {
+    sqlx::query!(r#"SELECT count(*) AS "count!" FROM http_sessions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count
+}
+
+#[tokio::test]
+async fn sessions_can_be_stored_in_postgres() {
+    let app = spawn_app_without_redis().await;
+
+    app.login().await;
+    assert_eq!(stored_sessions(&app).await, 1);
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
+
+    let response = app.post_logout().await;
+    assert_is_redirect_to(&response, "/login");
+    assert_eq!(stored_sessions(&app).await, 0);
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn expired_sessions_are_ignored() {
+    let app = spawn_app_without_redis().await;
+    app.login().await;
+
+    sqlx::query!(
+        "UPDATE http_sessions SET expires_at = now() - interval '1 minute'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn expired_sessions_are_deleted_when_a_session_is_saved() {
+    let app = spawn_app_without_redis().await;
+    app.login().await;
+    sqlx::query!(
+        "UPDATE http_sessions SET expires_at = now() - interval '1 minute'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    app.post_logout().await;
+
+    app.login().await;
+
+    assert_eq!(stored_sessions(&app).await, 1);
+}
@@ -0,0 +1,178 @@
+use uuid::Uuid;
+
+use crate::helpers::{
+    assert_is_redirect_to, build_api_client, get_csrf_token, spawn_app,
+    TestApp, TestUser,
+};
+
+/// Log the test user in from another device.
+async fn login_from_phone(app: &TestApp) -> reqwest::Client {
+    let client =
+        build_api_client(reqwest::Client::builder().user_agent("Test phone"));
+    let csrf_token = get_csrf_token(&client, &app.address).await;
+    let response = client
+        .post(format!("{}/login", &app.address))
+        .form(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+            "csrf_token": csrf_token,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    client
+}
+
+async fn get_dashboard(app: &TestApp, client: &reqwest::Client) -> u16 {
+    client
+        .get(format!("{}/admin/dashboard", &app.address))
+        .send()
+        .await
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+async fn phone_session_id(app: &TestApp) -> Uuid {
+    sqlx::query!(
+        "SELECT session_id FROM user_sessions WHERE user_agent = 'Test phone'"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .session_id
+}
+
+async fn login(app: &TestApp, user: &TestUser) {
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &user.username,
+            "password": &user.password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn the_sessions_page_lists_every_device() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user).await;
+    login_from_phone(&app).await;
+
+    let html_page = app.get_sessions_html().await;
+
+    assert!(html_page.contains("<td>Test phone</td>"));
+    assert!(html_page.contains("<td>127.0.0.1</td>"));
+    assert!(html_page.contains("This session"));
+    assert!(html_page.contains(&format!(
+        "/admin/sessions/{}/revoke",
+        phone_session_id(&app).await
+    )));
+}
+
+#[tokio::test]
+async fn a_revoked_session_is_logged_out() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user).await;
+    let phone = login_from_phone(&app).await;
+    assert_eq!(get_dashboard(&app, &phone).await, 200);
+
+    let response = app.post_revoke_session(phone_session_id(&app).await).await;
+    assert_is_redirect_to(&response, "/admin/sessions");
+
+    assert_eq!(get_dashboard(&app, &phone).await, 303);
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn users_can_not_revoke_the_sessions_of_others() {
+    let app = spawn_app().await;
+    let phone = login_from_phone(&app).await;
+    let other_user = TestUser::with_role("viewer");
+    other_user.store(&app.db_pool).await;
+    login(&app, &other_user).await;
+
+    let response = app.post_revoke_session(phone_session_id(&app).await).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(get_dashboard(&app, &phone).await, 200);
+}
+
+#[tokio::test]
+async fn logging_out_everywhere_ends_every_session() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user).await;
+    let phone = login_from_phone(&app).await;
+
+    let response = app.post_log_out_everywhere().await;
+    assert_is_redirect_to(&response, "/login");
+
+    assert_eq!(get_dashboard(&app, &phone).await, 303);
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn changing_the_password_logs_other_sessions_out() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user).await;
+    let phone = login_from_phone(&app).await;
+    let new_password = Uuid::new_v4().to_string();
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    assert_eq!(get_dashboard(&app, &phone).await, 303);
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn logging_out_removes_the_session() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user).await;
+
+    app.post_logout().await;
+
+    let count =
+        sqlx::query!(r#"SELECT count(*) AS "count!" FROM user_sessions"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .count;
+    assert_eq!(count, 0);
+}
+
+#[tokio::test]
+async fn expired_sessions_are_not_listed() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user).await;
+    let phone = login_from_phone(&app).await;
+    sqlx::query!(
+        "UPDATE user_sessions SET last_seen_at = now() - interval '2 days' \
+        WHERE user_agent = 'Test phone'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let html_page = app.get_sessions_html().await;
+
+    assert!(!html_page.contains("Test phone"));
+    let n_sessions =
+        sqlx::query!(r#"SELECT count(*) AS "n!" FROM user_sessions"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .n;
+    assert_eq!(n_sessions, 1);
+    assert_eq!(get_dashboard(&app, &phone).await, 303);
+}
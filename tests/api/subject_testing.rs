@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+use wiremock::{matchers::any, Mock, ResponseTemplate};
+use zero2prod::subject_testing::pick_subject_test_winners;
+
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, spawn_app, TestApp,
+};
+
+fn issue_with_subject_test(sample_percent: u8) -> serde_json::Value {
+    serde_json::json!({
+        "title": "Subject A",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "track_opens": true,
+        "subject_alternatives": "Subject B\nSubject C",
+        "ab_sample_percent": sample_percent.to_string(),
+        "ab_winning_metric": "opens",
+        "ab_wait_minutes": "60",
+    })
+}
+
+/// Deliver the queued emails and count them by subject.
+async fn deliver_emails(app: &TestApp) -> HashMap<String, usize> {
+    let n_before = app.email_server.received_requests().await.unwrap().len();
+    app.dispatch_all_pending_emails().await;
+    let mut subjects = HashMap::new();
+    for request in
+        &app.email_server.received_requests().await.unwrap()[n_before..]
+    {
+        let body: serde_json::Value = request.body_json().unwrap();
+        let subject = body["Subject"].as_str().unwrap().to_string();
+        *subjects.entry(subject).or_default() += 1;
+    }
+    subjects
+}
+
+async fn end_waiting_period(app: &TestApp, issue_id: Uuid) {
+    sqlx::query!(
+        r#"
+        UPDATE subject_tests
+        SET decide_after = now() - interval '1 minute'
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+async fn setup(n_subscribers: usize) -> TestApp {
+    let app = spawn_app().await;
+    for _ in 0..n_subscribers {
+        create_confirmed_subscriber(&app).await;
+    }
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.login().await;
+    app
+}
+
+#[tokio::test]
+async fn the_sample_is_split_evenly_across_subject_variants() {
+    let app = setup(10).await;
+
+    app.publish_issue(issue_with_subject_test(60)).await;
+    let subjects = deliver_emails(&app).await;
+
+    assert_eq!(subjects.len(), 3);
+    for subject in ["Subject A", "Subject B", "Subject C"] {
+        assert_eq!(subjects[subject], 2, "{}", subject);
+    }
+    let n_held =
+        sqlx::query!(r#"SELECT count(*) AS "n!" FROM subject_test_holdouts"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .n;
+    assert_eq!(n_held, 4);
+}
+
+#[tokio::test]
+async fn the_winning_subject_is_sent_to_the_rest_of_the_audience() {
+    let app = setup(10).await;
+    let issue_id = app.publish_issue(issue_with_subject_test(60)).await;
+    deliver_emails(&app).await;
+
+    // One of the two recipients of variant B opened the email
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_opens (
+            newsletter_issue_id,
+            subscriber_id,
+            first_opened_at,
+            last_opened_at,
+            n_opens
+        )
+        SELECT l.newsletter_issue_id, s.id, now(), now(), 1
+        FROM issue_delivery_log l
+        JOIN subscriptions s ON s.email = l.subscriber_email
+        WHERE l.subject_variant = 1
+        LIMIT 1
+        "#
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    end_waiting_period(&app, issue_id).await;
+
+    assert_eq!(
+        pick_subject_test_winners(
+            &app.db_pool,
+            app.delivery_settings.quiet_hours
+        )
+        .await
+        .unwrap(),
+        1
+    );
+    let subjects = deliver_emails(&app).await;
+
+    assert_eq!(subjects, HashMap::from([("Subject B".to_string(), 4)]));
+    let report = app.get_issue_report_html(issue_id).await;
+    assert!(report.contains("Winner: variant B"));
+    assert!(report.contains("<tr><td>Sent</td><td>10</td></tr>"));
+}
+
+#[tokio::test]
+async fn the_winner_is_not_picked_before_the_end_of_the_waiting_period() {
+    let app = setup(2).await;
+    let issue_id = app.publish_issue(issue_with_subject_test(50)).await;
+    deliver_emails(&app).await;
+
+    assert_eq!(
+        pick_subject_test_winners(
+            &app.db_pool,
+            app.delivery_settings.quiet_hours
+        )
+        .await
+        .unwrap(),
+        0
+    );
+
+    assert!(deliver_emails(&app).await.is_empty());
+    let report = app.get_issue_report_html(issue_id).await;
+    assert!(report.contains("1 recipients are waiting for it"));
+    assert!(report.contains("<tr><td>Not sent</td><td>0</td></tr>"));
+}
+
+#[tokio::test]
+async fn held_back_recipients_are_dropped_when_the_delivery_is_cancelled() {
+    let app = setup(2).await;
+    let issue_id = app.publish_issue(issue_with_subject_test(50)).await;
+    deliver_emails(&app).await;
+
+    app.post_issue_delivery(issue_id, "cancel").await;
+    end_waiting_period(&app, issue_id).await;
+    pick_subject_test_winners(&app.db_pool, app.delivery_settings.quiet_hours)
+        .await
+        .unwrap();
+
+    assert!(deliver_emails(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn the_winning_metric_must_be_tracked() {
+    let app = setup(1).await;
+    let mut body = issue_with_subject_test(50);
+    body["track_opens"] = false.into();
+
+    let response = app.post_newsletters(&body).await;
+
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "<p><i>Open tracking must be enabled to pick a subject by open rate</i></p>"
+    ));
+    let n_issues =
+        sqlx::query!(r#"SELECT count(*) AS "n!" FROM newsletter_issues"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .n;
+    assert_eq!(n_issues, 0);
+}
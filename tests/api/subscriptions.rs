@@ -45,6 +45,49 @@ async fn subscribe_persists_the_new_subscriber() {
     assert_eq!(saved.status, "pending_confirmation")
 }
 
+#[tokio::test]
+async fn subscribe_persists_the_tracking_preference() {
+    let app = spawn_app().await;
+
+    let body =
+        "name=le%20guin&email=ursula_le_guin%40gmail.com&do_not_track=true";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body.into()).await;
+
+    let saved = sqlx::query!("SELECT do_not_track FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription");
+    assert!(saved.do_not_track);
+}
+
+#[tokio::test]
+async fn subscribe_persists_the_time_zone() {
+    let app = spawn_app().await;
+
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com&time_zone=America%2FLos_Angeles";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body.into()).await;
+
+    let saved = sqlx::query!("SELECT time_zone FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription");
+    assert_eq!(saved.time_zone.as_deref(), Some("America/Los_Angeles"));
+}
+
 #[tokio::test]
 async fn subscribe_returns_a_400_when_data_is_missing() {
     let test_app = spawn_app().await;
@@ -74,6 +117,10 @@ async fn subscribe_returns_a_400_when_fields_are_present_but_empty() {
         ("name=&email=ursula_le_guin%40gmail.com", "empty name"),
         ("name=Ursula&email=", "empty email"),
         ("name=Ursula&email=definitely-not-an-email", "invalid email"),
+        (
+            "name=Ursula&email=ursula_le_guin%40gmail.com&time_zone=Mars%2FOlympus",
+            "invalid time zone",
+        ),
     ];
 
     for (body, description) in test_cases {
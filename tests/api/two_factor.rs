@@ -0,0 +1,224 @@
+use uuid::Uuid;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp, TestUser};
+
+async fn login(app: &TestApp, user: &TestUser) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "username": &user.username,
+        "password": &user.password,
+    }))
+    .await
+}
+
+/// The code shown by an authenticator app, `offset` steps from now.
+async fn totp_code(app: &TestApp, user_id: Uuid, offset: i64) -> String {
+    let encrypted = sqlx::query!(
+        "SELECT totp_secret FROM users WHERE user_id = $1",
+        user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .totp_secret
+    .unwrap();
+    let secret = app.totp_key.decrypt(&encrypted).unwrap();
+    secret.code_at(chrono::Utc::now().timestamp() / 30 + offset)
+}
+
+/// Turn on two-factor authentication for the logged in test user and
+/// return its recovery codes.
+async fn enable_two_factor(app: &TestApp) -> Vec<String> {
+    app.post_two_factor_setup().await;
+    let code = totp_code(app, app.test_user.user_id, 0).await;
+    let response = app.post_two_factor("enable", &code).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    html_page
+        .split("<li><code>")
+        .skip(1)
+        .map(|s| s.split("</code>").next().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn setting_up_shows_an_otpauth_uri_and_encrypts_the_secret() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user).await;
+
+    let response = app.post_two_factor_setup().await;
+    assert_is_redirect_to(&response, "/admin/two-factor");
+
+    let html_page = app.get_two_factor_html().await;
+    let encrypted = sqlx::query!(
+        "SELECT totp_secret, totp_enabled_at FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert!(encrypted.totp_enabled_at.is_none());
+    let secret = app
+        .totp_key
+        .decrypt(&encrypted.totp_secret.unwrap())
+        .unwrap()
+        .to_base32();
+    assert!(html_page.contains(&format!(
+        "otpauth://totp/zero2prod:{}?secret={}",
+        app.test_user.username, secret
+    )));
+}
+
+#[tokio::test]
+async fn a_wrong_code_does_not_turn_on_two_factor_authentication() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user).await;
+    app.post_two_factor_setup().await;
+
+    let response = app.post_two_factor("enable", "000000").await;
+    assert_is_redirect_to(&response, "/admin/two-factor");
+
+    let html_page = app.get_two_factor_html().await;
+    assert!(html_page.contains("The authentication code is not valid."));
+    app.post_logout().await;
+    let response = login(&app, &app.test_user).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn logging_in_requires_a_code_once_two_factor_is_on() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user).await;
+    let recovery_codes = enable_two_factor(&app).await;
+    assert_eq!(recovery_codes.len(), 10);
+    app.post_logout().await;
+
+    let response = login(&app, &app.test_user).await;
+    assert_is_redirect_to(&response, "/login/two-factor");
+
+    // The password alone does not log the user in
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app.post_login_two_factor("000000").await;
+    assert_is_redirect_to(&response, "/login/two-factor");
+    let code = totp_code(&app, app.test_user.user_id, 1).await;
+    let response = app.post_login_two_factor(&code).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
+}
+
+#[tokio::test]
+async fn a_code_can_not_be_used_twice() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user).await;
+    enable_two_factor(&app).await;
+    app.post_logout().await;
+
+    // The code that turned two-factor on
+    let code = totp_code(&app, app.test_user.user_id, 0).await;
+    login(&app, &app.test_user).await;
+    let response = app.post_login_two_factor(&code).await;
+
+    assert_is_redirect_to(&response, "/login/two-factor");
+}
+
+#[tokio::test]
+async fn recovery_codes_can_be_used_once() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user).await;
+    let recovery_codes = enable_two_factor(&app).await;
+    app.post_logout().await;
+
+    login(&app, &app.test_user).await;
+    let response = app.post_login_two_factor(&recovery_codes[0]).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let html_page = app.get_two_factor_html().await;
+    assert!(html_page.contains("You have 9 unused recovery codes left."));
+    app.post_logout().await;
+
+    login(&app, &app.test_user).await;
+    let response = app.post_login_two_factor(&recovery_codes[0]).await;
+    assert_is_redirect_to(&response, "/login/two-factor");
+}
+
+#[tokio::test]
+async fn two_factor_can_be_turned_off_with_a_code() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user).await;
+    let recovery_codes = enable_two_factor(&app).await;
+
+    let response = app.post_two_factor("disable", "000000").await;
+    assert_is_redirect_to(&response, "/admin/two-factor");
+    let response = app.post_two_factor("disable", &recovery_codes[0]).await;
+    assert_is_redirect_to(&response, "/admin/two-factor");
+
+    let html_page = app.get_two_factor_html().await;
+    assert!(html_page.contains("Two-factor authentication is off."));
+    app.post_logout().await;
+    let response = login(&app, &app.test_user).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn owners_can_require_two_factor_for_everybody() {
+    let app = spawn_app().await;
+    let editor = TestUser::with_role("editor");
+    editor.store(&app.db_pool).await;
+    login(&app, &editor).await;
+    let response = app.post_two_factor_policy(true).await;
+    assert_eq!(response.status().as_u16(), 403);
+    app.post_logout().await;
+
+    login(&app, &app.test_user).await;
+    let response = app.post_two_factor_policy(true).await;
+    assert_is_redirect_to(&response, "/admin/users");
+    // The owner must set it up too
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/admin/two-factor");
+    let recovery_codes = enable_two_factor(&app).await;
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains("required for all users"));
+    let response = app.post_two_factor("disable", &recovery_codes[0]).await;
+    assert_is_redirect_to(&response, "/admin/two-factor");
+    assert!(app
+        .get_two_factor_html()
+        .await
+        .contains("Two-factor authentication is on."));
+    app.post_logout().await;
+
+    let response = login(&app, &editor).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/admin/two-factor");
+    let html_page = app.get_two_factor_html().await;
+    assert!(html_page.contains(
+        "You must set up two-factor authentication before continuing."
+    ));
+}
+
+#[tokio::test]
+async fn the_two_factor_setup_is_enforced_on_percent_encoded_paths() {
+    let app = spawn_app().await;
+    sqlx::query!("UPDATE security_settings SET require_two_factor = true")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    login(&app, &app.test_user).await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/admin/%64ashboard", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_is_redirect_to(&response, "/admin/two-factor");
+    let response = app
+        .api_client
+        .get(format!("{}/admin/tw%6F-factor", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 200);
+}
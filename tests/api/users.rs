@@ -0,0 +1,243 @@
+use uuid::Uuid;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp, TestUser};
+
+async fn login(
+    app: &TestApp,
+    username: &str,
+    password: &str,
+) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "username": username,
+        "password": password,
+    }))
+    .await
+}
+
+async fn user_id(app: &TestApp, username: &str) -> Uuid {
+    sqlx::query!("SELECT user_id FROM users WHERE username = $1", username)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .user_id
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_users() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_users(&serde_json::json!({
+            "username": "ursula",
+            "password": "a-long-enough-password",
+            "password_check": "a-long-enough-password",
+            "role": "editor",
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn new_users_must_change_their_password_on_first_login() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user.username, &app.test_user.password).await;
+    let initial_password = Uuid::new_v4().to_string();
+
+    let response = app
+        .post_users(&serde_json::json!({
+            "username": "ursula",
+            "password": &initial_password,
+            "password_check": &initial_password,
+            "role": "editor",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/users");
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains("The user ursula has been created."));
+    assert!(html_page.contains("Active (password change pending)"));
+
+    // The new user can log in, but can only change their password
+    app.post_logout().await;
+    login(&app, "ursula", &initial_password).await;
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/admin/password");
+    let html_page = app.get_change_password_html().await;
+    assert!(
+        html_page.contains("You must choose a new password before continuing.")
+    );
+
+    let new_password = Uuid::new_v4().to_string();
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &initial_password,
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains("Welcome ursula"));
+}
+
+#[tokio::test]
+async fn usernames_must_be_unique() {
+    let app = spawn_app().await;
+    login(&app, &app.test_user.username, &app.test_user.password).await;
+    let password = Uuid::new_v4().to_string();
+
+    let response = app
+        .post_users(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &password,
+            "password_check": &password,
+            "role": "editor",
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/admin/users");
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains("is already taken."));
+}
+
+#[tokio::test]
+async fn disabled_users_can_not_log_in_and_lose_their_sessions() {
+    let app = spawn_app().await;
+    let other_user = TestUser::generate();
+    other_user.store(&app.db_pool).await;
+
+    // The user is logged in when another admin disables them
+    login(&app, &app.test_user.username, &app.test_user.password).await;
+    sqlx::query!(
+        "UPDATE users SET disabled_at = now() WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response =
+        login(&app, &app.test_user.username, &app.test_user.password).await;
+    assert_is_redirect_to(&response, "/login");
+
+    // Enabling the user again lets them back in
+    login(&app, &other_user.username, &other_user.password).await;
+    app.post_user_status(app.test_user.user_id, "enable").await;
+    app.post_logout().await;
+    let response =
+        login(&app, &app.test_user.username, &app.test_user.password).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn the_last_active_owner_can_not_be_disabled_or_deleted() {
+    let app = spawn_app().await;
+    // The seeded admin is disabled, so the test user is the only active one
+    sqlx::query!(
+        "UPDATE users SET disabled_at = now() WHERE user_id != $1",
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    login(&app, &app.test_user.username, &app.test_user.password).await;
+
+    let response = app.post_user_status(app.test_user.user_id, "disable").await;
+    assert_is_redirect_to(&response, "/admin/users");
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains("The last active owner can't be disabled."));
+
+    let response = app.post_delete_user(app.test_user.user_id).await;
+    assert_is_redirect_to(&response, "/admin/users");
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains("The last active owner can't be deleted."));
+
+    // Disabled owners can still be deleted
+    let admin_id = user_id(&app, "admin").await;
+    app.post_delete_user(admin_id).await;
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains("The user admin has been deleted."));
+}
+
+#[tokio::test]
+async fn deleting_a_user_keeps_the_issues_they_wrote() {
+    let app = spawn_app().await;
+    let other_user = TestUser::generate();
+    other_user.store(&app.db_pool).await;
+    login(&app, &other_user.username, &other_user.password).await;
+    app.post_newsletters(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    }))
+    .await;
+    app.post_logout().await;
+
+    login(&app, &app.test_user.username, &app.test_user.password).await;
+    let response = app.post_delete_user(other_user.user_id).await;
+
+    assert_is_redirect_to(&response, "/admin/users");
+    let issue = sqlx::query!("SELECT title, created_by FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.title, "Newsletter title");
+    assert_eq!(issue.created_by, None);
+    let html_page = app.get_users_html().await;
+    assert!(!html_page.contains(&format!("<td>{}</td>", other_user.username)));
+}
+
+#[tokio::test]
+async fn email_addresses_can_be_set_but_not_shared() {
+    let app = spawn_app().await;
+    let other_user = TestUser::generate();
+    other_user.store(&app.db_pool).await;
+    login(&app, &app.test_user.username, &app.test_user.password).await;
+
+    let response = app
+        .post_user_email(app.test_user.user_id, "ursula@example.com")
+        .await;
+    assert_is_redirect_to(&response, "/admin/users");
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains(r#"value="ursula@example.com""#));
+
+    app.post_user_email(other_user.user_id, "ursula@example.com")
+        .await;
+    let html_page = app.get_users_html().await;
+    assert!(html_page
+        .contains("This email address is already used by another user."));
+
+    app.post_user_email(app.test_user.user_id, "not-an-email")
+        .await;
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains("is not a valid subscriber email"));
+}
+
+#[tokio::test]
+async fn the_password_change_is_enforced_on_percent_encoded_paths() {
+    let app = spawn_app().await;
+    sqlx::query!("UPDATE users SET must_change_password = true")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    login(&app, &app.test_user.username, &app.test_user.password).await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/admin/%64ashboard", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_is_redirect_to(&response, "/admin/password");
+    let response = app
+        .api_client
+        .get(format!("{}/admin/p%61ssword", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 200);
+}
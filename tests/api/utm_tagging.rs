@@ -0,0 +1,126 @@
+use wiremock::{matchers::any, Mock, ResponseTemplate};
+
+use crate::helpers::{create_confirmed_subscriber, spawn_app, TestApp};
+
+const HTML_CONTENT: &str = r#"<p><a href="https://example.com/page">Read</a> or <a href="https://example.com/?utm_source=blog">this</a></p>"#;
+const TEXT_CONTENT: &str = "Read https://example.com/page.";
+
+async fn publish_issue(app: &TestApp, extra: serde_json::Value) -> String {
+    app.login().await;
+    let mut fields = serde_json::json!({
+        "text_content": TEXT_CONTENT,
+        "html_content": HTML_CONTENT,
+    });
+    fields
+        .as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    let issue_id = app.publish_issue(fields).await;
+    sqlx::query!(
+        "SELECT slug FROM newsletter_issues WHERE newsletter_issue_id = $1",
+        issue_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .slug
+}
+
+async fn deliver_issue(app: &TestApp) -> serde_json::Value {
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+    let email_request = app.email_server.received_requests().await.unwrap();
+    serde_json::from_slice(&email_request.last().unwrap().body).unwrap()
+}
+
+#[tokio::test]
+async fn outbound_links_are_tagged_with_the_default_parameters() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let slug =
+        publish_issue(&app, serde_json::json!({ "tag_links": true })).await;
+
+    let body = deliver_issue(&app).await;
+
+    let tagged_url = format!(
+        "https://example.com/page?utm_source=newsletter&utm_medium=email&utm_campaign={}",
+        slug
+    );
+    let html = body["HtmlBody"].as_str().unwrap();
+    assert!(html.contains(&htmlescape::encode_minimal(&tagged_url)));
+    // Links already tagged by the author and our own links are left as is
+    assert!(html.contains("https://example.com/?utm_source=blog\""));
+    assert!(html.contains(&format!("{}/archive/{}\"", app.address, slug)));
+    let text = body["TextBody"].as_str().unwrap();
+    assert!(text.contains(&format!("Read {}.", tagged_url)));
+}
+
+#[tokio::test]
+async fn custom_parameters_are_used_when_provided() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    publish_issue(
+        &app,
+        serde_json::json!({
+            "tag_links": true,
+            "utm_source": "weekly",
+            "utm_medium": "mail",
+            "utm_campaign": "spring sale",
+        }),
+    )
+    .await;
+
+    let body = deliver_issue(&app).await;
+
+    assert!(body["TextBody"].as_str().unwrap().contains(
+        "https://example.com/page?utm_source=weekly&utm_medium=mail&utm_campaign=spring+sale"
+    ));
+}
+
+#[tokio::test]
+async fn links_are_not_tagged_if_the_issue_does_not_opt_in() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    publish_issue(
+        &app,
+        serde_json::json!({ "utm_source": "weekly", "tag_links": false }),
+    )
+    .await;
+
+    let body = deliver_issue(&app).await;
+
+    assert!(body["HtmlBody"].as_str().unwrap().contains(HTML_CONTENT));
+    assert!(body["TextBody"].as_str().unwrap().contains(TEXT_CONTENT));
+}
+
+#[tokio::test]
+async fn tracked_links_redirect_to_the_tagged_url() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let slug = publish_issue(
+        &app,
+        serde_json::json!({ "tag_links": true, "track_clicks": true }),
+    )
+    .await;
+    let body = deliver_issue(&app).await;
+    let link = linkify::LinkFinder::new()
+        .links(body["HtmlBody"].as_str().unwrap())
+        .map(|l| l.as_str().to_owned())
+        .find(|l| l.contains("/t/c/"))
+        .unwrap();
+
+    let response = app.api_client.get(&link).send().await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 302);
+    assert_eq!(
+        response.headers()["Location"].to_str().unwrap(),
+        format!(
+            "https://example.com/page?utm_source=newsletter&utm_medium=email&utm_campaign={}",
+            slug
+        )
+    );
+}